@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use agent_core::{telemetry, version};
 use agentgateway::{Config, client, serdes};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use tracing::info;
 
 #[cfg(feature = "jemalloc")]
@@ -46,6 +46,42 @@ struct Args {
 	/// Copy our own binary to a destination.
 	#[arg(long = "copy-self", hide = true)]
 	copy_self: Option<PathBuf>,
+
+	#[command(subcommand)]
+	command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Run a suite of synthetic requests against the configuration, checking the route, backend, and policy
+	/// decision for each.
+	Test {
+		/// YAML file with the test suite
+		suite: PathBuf,
+	},
+	/// Convert Gateway API resources or an Envoy bootstrap config into agentgateway configuration. Anything that
+	/// cannot be converted is reported as a warning.
+	Convert {
+		/// YAML files to convert. Gateway API resources may be split across multiple files.
+		#[arg(required = true)]
+		files: Vec<PathBuf>,
+		/// Where to write the configuration. Defaults to stdout.
+		#[arg(short, long)]
+		output: Option<PathBuf>,
+	},
+	/// Download a support bundle from a running agentgateway, containing its sanitized config, version,
+	/// recent errors, metrics, and readiness state.
+	Diagnostics {
+		/// Address of the admin server
+		#[arg(long, default_value = "http://localhost:15000")]
+		admin_address: String,
+		/// File containing the bearer token for the admin server, if one is required
+		#[arg(long)]
+		token_file: Option<PathBuf>,
+		/// Where to write the bundle. Defaults to a timestamped file in the current directory.
+		#[arg(short, long)]
+		output: Option<PathBuf>,
+	},
 }
 
 fn main() -> anyhow::Result<()> {
@@ -59,6 +95,7 @@ fn main() -> anyhow::Result<()> {
 		version_short,
 		version_long,
 		copy_self,
+		command,
 	} = args;
 
 	if version_short {
@@ -72,6 +109,9 @@ fn main() -> anyhow::Result<()> {
 	if let Some(copy_self) = copy_self {
 		return copy_binary(copy_self);
 	}
+	if validate_only && command.is_some() {
+		anyhow::bail!("--validate-only cannot be used with a subcommand");
+	}
 	tokio::runtime::Builder::new_current_thread()
 		.enable_all()
 		.build()
@@ -88,8 +128,16 @@ fn main() -> anyhow::Result<()> {
 				},
 				(None, None) => ("{}".to_string(), None),
 			};
-			if validate_only {
-				return validate(contents, filename).await;
+			match command {
+				Some(Command::Test { suite }) => return test(contents, filename, suite).await,
+				Some(Command::Convert { files, output }) => return convert(files, output),
+				Some(Command::Diagnostics {
+					admin_address,
+					token_file,
+					output,
+				}) => return diagnostics(admin_address, token_file, output).await,
+				None if validate_only => return validate(contents, filename).await,
+				None => {},
 			}
 			let config = agentgateway::config::parse_config(contents, filename)?;
 			proxy(Arc::new(config)).await
@@ -128,6 +176,64 @@ async fn validate(contents: String, filename: Option<PathBuf>) -> anyhow::Result
 	Ok(())
 }
 
+async fn test(contents: String, filename: Option<PathBuf>, suite: PathBuf) -> anyhow::Result<()> {
+	let config = agentgateway::config::parse_config(contents, filename)?;
+	let client = client::Client::new(&config.dns, None);
+	let Some(cfg) = config.xds.local_config else {
+		anyhow::bail!("test requires a local configuration");
+	};
+	let cs = cfg.read_to_string().await?;
+	let suite = fs_err::read_to_string(suite)?;
+	let report = agentgateway::configtest::run(client, cs.as_str(), suite.as_str()).await?;
+	print!("{report}");
+	let failed = report.failed();
+	if failed > 0 {
+		anyhow::bail!("{failed} test(s) failed");
+	}
+	Ok(())
+}
+
+fn convert(files: Vec<PathBuf>, output: Option<PathBuf>) -> anyhow::Result<()> {
+	let input = files
+		.iter()
+		.map(fs_err::read_to_string)
+		.collect::<Result<Vec<_>, _>>()?
+		.join("\n---\n");
+	let conversion = agentgateway::convert::convert(&input)?;
+	for w in &conversion.warnings {
+		eprintln!("warning: {w}");
+	}
+	let config = serdes::yamlviajson::to_string(&conversion.config)?;
+	match output {
+		Some(output) => fs_err::write(output, config)?,
+		None => print!("{config}"),
+	}
+	Ok(())
+}
+
+async fn diagnostics(
+	admin_address: String,
+	token_file: Option<PathBuf>,
+	output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+	let token = token_file
+		.map(fs_err::read_to_string)
+		.transpose()?
+		.map(|t| t.trim().to_string());
+	let bundle =
+		agentgateway::management::diagnostics::fetch(&admin_address, token.as_deref()).await?;
+	let output = output.unwrap_or_else(|| {
+		let ts = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or_default();
+		PathBuf::from(format!("agentgateway-diagnostics-{ts}.tar.gz"))
+	});
+	fs_err::write(&output, &bundle)?;
+	println!("Wrote diagnostics bundle to {}", output.display());
+	Ok(())
+}
+
 async fn proxy(cfg: Arc<Config>) -> anyhow::Result<()> {
 	info!("version: {}", version::BuildInfo::new());
 	info!(
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use ::http::uri::PathAndQuery;
+use ::http::{Method, Uri, Version};
+
+use crate::client::{Call, Client, Transport};
+use crate::http::auth::{BackendAuth, apply_backend_auth, apply_late_backend_auth};
+use crate::http::{Body, Response, StatusCode, header};
+use crate::json;
+use crate::types::agent::Target;
+use crate::*;
+
+/// How long to wait for an upstream to return its agent card.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// AgentCardCache caches the agent card of each upstream A2A agent, and uses the capabilities it advertises to
+/// reject calls the agent cannot serve, such as `message/stream` to an agent without streaming support.
+/// Cards are fetched on first use, and refreshed in the background once they expire.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "AgentCardCacheSerde"))]
+pub struct AgentCardCache {
+	config: AgentCardCacheSerde,
+	cards: Arc<Mutex<HashMap<Target, Entry>>>,
+}
+
+impl serde::Serialize for AgentCardCache {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for AgentCardCache {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = AgentCardCacheSerde::deserialize(deserializer)?;
+		if !config.path.starts_with('/') {
+			return Err(serde::de::Error::custom("path must start with '/'"));
+		}
+		Ok(AgentCardCache {
+			config,
+			cards: Default::default(),
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct AgentCardCacheSerde {
+	/// How long a fetched agent card is used before it is refreshed. Defaults to 5m.
+	#[serde(default = "default_ttl", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub ttl: Duration,
+	/// Path the agent card is served at on the upstream. Defaults to `/.well-known/agent-card.json`.
+	#[serde(default = "default_path")]
+	pub path: String,
+}
+
+fn default_ttl() -> Duration {
+	Duration::from_secs(5 * 60)
+}
+
+fn default_path() -> String {
+	"/.well-known/agent-card.json".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Capability {
+	Streaming,
+	PushNotifications,
+}
+
+impl Capability {
+	/// for_method returns the capability an agent must advertise to serve the method, if any.
+	fn for_method(method: &str) -> Option<Capability> {
+		match method {
+			"message/stream" | "tasks/resubscribe" | "tasks/sendSubscribe" => Some(Capability::Streaming),
+			m if m.starts_with("tasks/pushNotificationConfig/")
+				|| m.starts_with("tasks/pushNotification/") =>
+			{
+				Some(Capability::PushNotifications)
+			},
+			_ => None,
+		}
+	}
+
+	/// error returns the A2A error code and message for a call that needs an unsupported capability.
+	fn error(&self) -> (i64, &'static str) {
+		match self {
+			// UnsupportedOperationError
+			Capability::Streaming => (-32004, "agent does not support streaming"),
+			// PushNotificationNotSupportedError
+			Capability::PushNotifications => (-32003, "agent does not support push notifications"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Capabilities {
+	streaming: bool,
+	push_notifications: bool,
+}
+
+impl Capabilities {
+	fn supports(&self, c: Capability) -> bool {
+		match c {
+			Capability::Streaming => self.streaming,
+			Capability::PushNotifications => self.push_notifications,
+		}
+	}
+}
+
+impl From<&a2a_sdk::AgentCapabilities> for Capabilities {
+	fn from(value: &a2a_sdk::AgentCapabilities) -> Self {
+		Capabilities {
+			streaming: value.streaming.unwrap_or_default(),
+			push_notifications: value.push_notifications.unwrap_or_default(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+	/// The advertised capabilities, or None if the card could not be fetched. In that case calls are passed
+	/// through, and the upstream decides, until the entry expires.
+	capabilities: Option<Capabilities>,
+	fetched: Instant,
+	refreshing: bool,
+}
+
+/// CardFetch describes how to reach the upstream agent to fetch its card. The card is requested from the same
+/// target and authority as the call being checked.
+pub struct CardFetch {
+	pub client: Client,
+	pub target: Target,
+	pub transport: Transport,
+	pub backend_auth: Option<BackendAuth>,
+	pub uri: Uri,
+	pub version: Version,
+}
+
+impl AgentCardCache {
+	/// check returns a JSON-RPC error response to send instead of forwarding the call, if the upstream agent does
+	/// not advertise a capability the method requires.
+	pub async fn check(
+		&self,
+		method: &str,
+		req: &mut crate::http::Request,
+		fetch: CardFetch,
+	) -> Option<Response> {
+		let required = Capability::for_method(method)?;
+		let capabilities = self.capabilities(fetch).await?;
+		if capabilities.supports(required) {
+			return None;
+		}
+		debug!(method, "rejecting A2A call the agent does not support");
+		let (code, message) = required.error();
+		let id = json::inspect_body::<JsonRpcId>(req.body_mut())
+			.await
+			.ok()
+			.and_then(|r| r.id);
+		Some(error_response(id, code, message))
+	}
+
+	async fn capabilities(&self, fetch: CardFetch) -> Option<Capabilities> {
+		let stale = {
+			let mut cards = self.cards.lock().expect("mutex");
+			match cards.get_mut(&fetch.target) {
+				Some(e) if e.fetched.elapsed() < self.config.ttl => return e.capabilities,
+				Some(e) if e.refreshing => return e.capabilities,
+				Some(e) => {
+					e.refreshing = true;
+					Some(e.capabilities)
+				},
+				None => None,
+			}
+		};
+		match stale {
+			// Serve the expired card while it is refreshed in the background.
+			Some(capabilities) => {
+				let cache = self.clone();
+				tokio::task::spawn(async move {
+					cache.refresh(fetch).await;
+				});
+				capabilities
+			},
+			None => self.refresh(fetch).await,
+		}
+	}
+
+	async fn refresh(&self, fetch: CardFetch) -> Option<Capabilities> {
+		let target = fetch.target.clone();
+		let capabilities = match fetch.fetch(&self.config.path).await {
+			Ok(c) => Some(c),
+			Err(err) => {
+				warn!(%target, ?err, "failed to fetch A2A agent card");
+				None
+			},
+		};
+		self.cards.lock().expect("mutex").insert(
+			target,
+			Entry {
+				capabilities,
+				fetched: Instant::now(),
+				refreshing: false,
+			},
+		);
+		capabilities
+	}
+}
+
+impl CardFetch {
+	async fn fetch(self, path: &str) -> anyhow::Result<Capabilities> {
+		let mut parts = self.uri.into_parts();
+		parts.path_and_query = Some(PathAndQuery::try_from(path)?);
+		let mut req = ::http::Request::builder()
+			.method(Method::GET)
+			.uri(Uri::from_parts(parts)?)
+			.version(self.version)
+			.body(Body::empty())?;
+		apply_backend_auth(self.backend_auth.as_ref(), &mut req).await?;
+		apply_late_backend_auth(self.backend_auth.as_ref(), &mut req).await?;
+		let call = Call {
+			req,
+			target: self.target,
+			transport: self.transport,
+		};
+		let resp = tokio::time::timeout(FETCH_TIMEOUT, self.client.call(call)).await??;
+		if !resp.status().is_success() {
+			anyhow::bail!("agent card request returned {}", resp.status());
+		}
+		let card: a2a_sdk::AgentCard = json::from_body(resp.into_body()).await?;
+		Ok(Capabilities::from(&card.capabilities))
+	}
+}
+
+#[derive(serde::Deserialize)]
+struct JsonRpcId {
+	id: Option<serde_json::Value>,
+}
+
+fn error_response(id: Option<serde_json::Value>, code: i64, message: &str) -> Response {
+	let body = serde_json::json!({
+		"jsonrpc": "2.0",
+		"id": id,
+		"error": {
+			"code": code,
+			"message": message,
+		},
+	});
+	::http::Response::builder()
+		.status(StatusCode::OK)
+		.header(header::CONTENT_TYPE, "application/json")
+		.body(Body::from(body.to_string()))
+		.expect("builder should succeed")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn required_capabilities() {
+		assert_eq!(
+			Capability::for_method("message/stream"),
+			Some(Capability::Streaming)
+		);
+		assert_eq!(
+			Capability::for_method("tasks/pushNotificationConfig/set"),
+			Some(Capability::PushNotifications)
+		);
+		assert_eq!(Capability::for_method("message/send"), None);
+
+		let caps = Capabilities::from(&a2a_sdk::AgentCapabilities {
+			push_notifications: None,
+			state_transition_history: None,
+			streaming: Some(true),
+			extensions: vec![],
+		});
+		assert!(caps.supports(Capability::Streaming));
+		assert!(!caps.supports(Capability::PushNotifications));
+	}
+
+	#[tokio::test]
+	async fn unsupported_error_keeps_id() {
+		let resp = error_response(Some(serde_json::json!(7)), -32004, "unsupported");
+		let body: serde_json::Value = json::from_body(resp.into_body()).await.unwrap();
+		assert_eq!(body["id"], 7);
+		assert_eq!(body["error"]["code"], -32004);
+	}
+}
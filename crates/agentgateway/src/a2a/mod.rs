@@ -4,8 +4,13 @@ use tracing::warn;
 
 use crate::http::{Body, Response, filters};
 use crate::json;
+use crate::telemetry::errors::ErrorType;
 use crate::types::agent::A2aPolicy;
 
+mod card;
+
+pub use card::{AgentCardCache, CardFetch};
+
 pub async fn apply_to_request(pol: Option<&A2aPolicy>, req: &mut Request<Body>) -> RequestType {
 	if pol.is_none() {
 		return RequestType::Unknown;
@@ -58,13 +63,15 @@ pub enum RequestType {
 	Call(&'static str),
 }
 
+/// apply_to_response processes an A2A response, returning the classification of the JSON-RPC error it
+/// carries, if any.
 pub async fn apply_to_response(
 	pol: Option<&A2aPolicy>,
 	a2a_type: RequestType,
 	resp: &mut Response,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<ErrorType>> {
 	if pol.is_none() {
-		return Ok(());
+		return Ok(None);
 	};
 	match a2a_type {
 		RequestType::AgentCard(uri) => {
@@ -90,27 +97,54 @@ pub async fn apply_to_response(
 
 			resp.headers_mut().remove(header::CONTENT_LENGTH);
 			*resp.body_mut() = json::to_body(agent_card)?;
-			Ok(())
+			Ok(None)
 		},
 		RequestType::Call(_) => {
-			// TODO: we don't really do anything with the response... but if we did, we could do this.
-			Ok(())
-			// match crate::http::classify_content_type(resp.headers()) {
-			// 	crate::http::WellKnownContentTypes::Json => {
-			// 		let call = json::inspect_body::<a2a_sdk::JsonRpcMessage>(resp.body_mut()).await?;
-			// 	},
-			// 	crate::http::WellKnownContentTypes::Sse => {
-			// 		let orig = std::mem::replace(resp.body_mut(), crate::http::Body::empty());
-			// 		let new_body = parse::sse::json_parser::<a2a_sdk::JsonRpcMessage>(orig, |f| {
-			// 		});
-			// 		*resp.body_mut() = new_body;
-			// 	},
-			// 	_ => {
-			// 		warn!("unknown content type from A2A");
-			// 	}
-			// }
-			// Ok(())
+			// Only buffered JSON responses are inspected for errors; streamed responses are passed through as is.
+			let small_json = matches!(
+				crate::http::classify_content_type(resp.headers()),
+				crate::http::WellKnownContentTypes::Json
+			) && resp
+				.headers()
+				.get(header::CONTENT_LENGTH)
+				.and_then(|v| v.to_str().ok())
+				.and_then(|v| v.parse::<u64>().ok())
+				.is_some_and(|len| len <= MAX_INSPECTED_RESPONSE);
+			if !small_json {
+				return Ok(None);
+			}
+			let Ok(msg) = json::inspect_body::<JsonRpcResponse>(resp.body_mut()).await else {
+				return Ok(None);
+			};
+			Ok(msg.error.map(|e| error_type(e.code)))
 		},
-		RequestType::Unknown => Ok(()),
+		RequestType::Unknown => Ok(None),
+	}
+}
+
+const MAX_INSPECTED_RESPONSE: u64 = 2_097_152;
+
+#[derive(serde::Deserialize)]
+struct JsonRpcResponse {
+	error: Option<JsonRpcError>,
+}
+
+#[derive(serde::Deserialize)]
+struct JsonRpcError {
+	code: i64,
+}
+
+/// error_type classifies an A2A JSON-RPC error code.
+fn error_type(code: i64) -> ErrorType {
+	match code {
+		// TaskNotFoundError
+		-32001 => ErrorType::NotFound,
+		// TaskNotCancelableError
+		-32002 => ErrorType::InvalidRequest,
+		// PushNotificationNotSupportedError, UnsupportedOperationError, ContentTypeNotSupportedError
+		-32003..=-32005 => ErrorType::Unsupported,
+		// InvalidAgentResponseError
+		-32006 => ErrorType::InvalidResponse,
+		code => ErrorType::from_json_rpc(code),
 	}
 }
@@ -41,6 +41,7 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	)
 	.await
 	.context("readiness server starts")?;
+	let readiness_address = readiness_server.address();
 	// Run the readiness server in the data plane worker pool.
 	data_plane_pool.send(DataPlaneTask {
 		block_shutdown: false,
@@ -68,11 +69,30 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	let pool = ca
 		.clone()
 		.map(|ca| agent_hbone::pool::WorkloadHBONEPool::new(config.hbone.clone(), ca));
-	let client = client::Client::new(&config.dns, pool);
+	let sub_registry = metrics::sub_registry(&mut registry);
+	let mut proxy_metrics = crate::metrics::Metrics::new(sub_registry);
+	let usage_reporter = config
+		.usage_reports
+		.clone()
+		.map(|cfg| Arc::new(crate::telemetry::usage::UsageReporter::new(cfg)));
+	if let Some(usage) = &usage_reporter {
+		proxy_metrics = proxy_metrics.with_usage_reporter(usage.clone());
+	}
+	let proxy_metrics = Arc::new(proxy_metrics);
+	let client = client::Client::new(&config.dns, pool).with_metrics(proxy_metrics.clone());
+	if let Some(usage) = usage_reporter {
+		tokio::spawn(usage.run(client.clone(), drain_rx.clone()));
+	}
 
 	let (xds_tx, xds_rx) = tokio::sync::watch::channel(());
-	let state_mgr =
-		state_manager::StateManager::new(&config.xds, client.clone(), xds_metrics, xds_tx).await?;
+	let state_mgr = state_manager::StateManager::new(
+		&config.xds,
+		client.clone(),
+		xds_metrics,
+		xds_tx,
+		ready.clone(),
+	)
+	.await?;
 	let mut xds_rx_for_task = xds_rx.clone();
 	tokio::spawn(async move {
 		// When we get the initial XDS state, unblock readiness
@@ -82,8 +102,13 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	let stores = state_mgr.stores();
 	// Run the XDS state manager in the current tokio worker pool.
 	tokio::spawn(state_mgr.run());
+	tokio::spawn(
+		stores
+			.version
+			.clone()
+			.run(stores.clone(), proxy_metrics.clone()),
+	);
 
-	#[allow(unused_mut)]
 	let mut admin_server = crate::management::admin::Service::new(
 		config.clone(),
 		stores.clone(),
@@ -93,28 +118,38 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	.await
 	.context("admin server starts")?;
 	#[cfg(feature = "ui")]
-	admin_server.set_admin_handler(Arc::new(crate::ui::UiHandler::new(config.clone())));
-	#[cfg(feature = "ui")]
-	info!("serving UI at http://{}/ui", config.admin_addr);
+	if config.ui.enabled {
+		admin_server.set_admin_handler(Arc::new(crate::ui::UiHandler::new(config.clone())));
+		info!(
+			"serving UI at http://{}{}/ui",
+			admin_server.ui_address(),
+			config.ui.base_path.as_deref().unwrap_or_default()
+		);
+	}
 
-	let sub_registry = metrics::sub_registry(&mut registry);
 	let tracer = trc::Tracer::new(&config.tracing)?;
+	let mcp_state = mcp::sse::App::new(
+		stores.clone(),
+		Arc::new(crate::mcp::relay::metrics::Metrics::new(
+			&mut registry,
+			None, // TODO custom tags
+		)),
+		drain_rx.clone(),
+	);
+	admin_server.add_config_dump_handler(Arc::new(mcp_state.handshakes()));
+	let sessions = mcp_state.sessions();
+	let connections = crate::management::diagnostics::Table::default();
 	let pi = ProxyInputs {
 		cfg: config.clone(),
 		stores: stores.clone(),
 		tracer: tracer.clone(),
-		metrics: Arc::new(crate::metrics::Metrics::new(sub_registry)),
+		metrics: proxy_metrics,
 		upstream: client.clone(),
 		ca,
+		state: config.state_store.build().context("state store")?,
+		connections: connections.clone(),
 
-		mcp_state: mcp::sse::App::new(
-			stores.clone(),
-			Arc::new(crate::mcp::relay::metrics::Metrics::new(
-				&mut registry,
-				None, // TODO custom tags
-			)),
-			drain_rx.clone(),
-		),
+		mcp_state,
 	};
 
 	let gw = proxy::Gateway::new(Arc::new(pi), drain_rx.clone());
@@ -134,16 +169,41 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 
 	drop(proxy_task);
 
+	// Metrics are shared between the stats server and diagnostics bundles.
+	let registry = Arc::new(Mutex::new(registry));
+	admin_server.set_diagnostics(crate::management::diagnostics::Diagnostics {
+		registry: registry.clone(),
+		ready: ready.clone(),
+		connections,
+		sessions,
+	});
+	let admin_address = admin_server.address();
+
 	// Run the admin server in the current tokio worker pool.
 	admin_server.spawn();
 
 	// Create and start the metrics server.
-	let metrics_server =
-		crate::management::metrics_server::Server::new(config.stats_addr, drain_rx.clone(), registry)
-			.await
-			.context("stats server starts")?;
+	let metrics_server = crate::management::metrics_server::Server::new(
+		config.stats_addr,
+		config.stats_access.clone(),
+		drain_rx.clone(),
+		registry,
+	)
+	.await
+	.context("stats server starts")?;
+	let stats_address = metrics_server.address();
 	// Run the metrics sever in the current tokio worker pool.
 	metrics_server.spawn();
+	info!(
+		version = agent_core::version::BuildInfo::new().version,
+		admin = %admin_address,
+		stats = %stats_address,
+		readiness = %readiness_address,
+		xds = config.xds.address.as_deref().unwrap_or("none"),
+		local_config = config.xds.local_config.is_some(),
+		workers = config.num_worker_threads,
+		"agentgateway started",
+	);
 	tokio::task::spawn_blocking(|| {
 		let t0 = std::time::Instant::now();
 		crate::llm::preload_tokenizers();
@@ -1,7 +1,7 @@
 // Portions of this code are heavily inspired from https://github.com/Kuadrant/wasm-shim/
 // Under Apache 2.0 license (https://github.com/Kuadrant/wasm-shim/blob/main/LICENSE)
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::net::IpAddr;
 use std::sync::Arc;
@@ -9,7 +9,7 @@ use std::sync::Arc;
 use agent_core::strng::Strng;
 use bytes::Bytes;
 pub use cel::Value;
-use cel::objects::Key;
+use cel::objects::{Key, Map};
 use cel::{Context, ExecutionError, ParseError, ParseErrors, Program};
 pub use functions::{FLATTEN_LIST, FLATTEN_LIST_RECURSIVE, FLATTEN_MAP, FLATTEN_MAP_RECURSIVE};
 use once_cell::sync::Lazy;
@@ -52,6 +52,7 @@ pub const LLM_COMPLETION_ATTRIBUTE: &str = "llm.completion";
 pub const RESPONSE_ATTRIBUTE: &str = "response";
 pub const JWT_ATTRIBUTE: &str = "jwt";
 pub const MCP_ATTRIBUTE: &str = "mcp";
+pub const VARIABLES_ATTRIBUTE: &str = "vars";
 pub const ALL_ATTRIBUTES: &[&str] = &[
 	SOURCE_ATTRIBUTE,
 	REQUEST_ATTRIBUTE,
@@ -62,6 +63,7 @@ pub const ALL_ATTRIBUTES: &[&str] = &[
 	RESPONSE_ATTRIBUTE,
 	JWT_ATTRIBUTE,
 	MCP_ATTRIBUTE,
+	VARIABLES_ATTRIBUTE,
 ];
 
 pub struct Expression {
@@ -99,6 +101,9 @@ static ROOT_CONTEXT: Lazy<Arc<Context<'static>>> = Lazy::new(root_context);
 pub struct ContextBuilder {
 	pub attributes: HashSet<String>,
 	pub context: ExpressionContext,
+	/// The evaluated route variables, exposed as `vars`. These are kept as CEL values, rather than in the
+	/// context, so they are not converted again for each executor.
+	variables: Option<Value>,
 }
 
 impl Default for ContextBuilder {
@@ -112,6 +117,7 @@ impl ContextBuilder {
 		Self {
 			attributes: Default::default(),
 			context: Default::default(),
+			variables: None,
 		}
 	}
 	/// register_expression registers the given expressions attributes as required attributes.
@@ -147,9 +153,17 @@ impl ContextBuilder {
 		}
 		self.context.response = Some(ResponseContext {
 			code: resp.status(),
+			trailers: Default::default(),
 		})
 	}
 
+	/// with_response_trailers records the trailers of the response, once the body has completed.
+	pub fn with_response_trailers(&mut self, trailers: &::http::HeaderMap) {
+		if let Some(resp) = self.context.response.as_mut() {
+			resp.trailers = trailers.clone();
+		}
+	}
+
 	pub fn with_jwt(&mut self, info: &Claims) {
 		if !self.attributes.contains(JWT_ATTRIBUTE) {
 			return;
@@ -164,6 +178,14 @@ impl ContextBuilder {
 		self.context.source = Some(SourceContext {
 			address: tcp.peer_addr.ip(),
 			port: tcp.peer_addr.port(),
+			rtt: tcp.rtt.current().map(|rtt| rtt.as_secs_f64() * 1000.0),
+			tls: tls.map(|t| TLSContext {
+				version: t.version.clone(),
+				cipher: t.cipher.clone(),
+				alpn: t.negotiated_alpn.map(|a| a.as_str()),
+				resumed: t.resumed,
+				server_name: t.server_name.clone(),
+			}),
 			identity: tls.and_then(|t| t.src_identity.as_ref()).map(|m| match m {
 				Identity::Spiffe {
 					trust_domain,
@@ -223,6 +245,15 @@ impl ContextBuilder {
 		}
 	}
 
+	/// with_variables sets the evaluated route variables.
+	pub fn with_variables(&mut self, vars: impl IntoIterator<Item = (Strng, Value)>) {
+		let map: HashMap<Key, Value> = vars
+			.into_iter()
+			.map(|(k, v)| (Key::from(k.as_str()), v))
+			.collect();
+		self.variables = Some(Value::Map(Map { map: Arc::new(map) }));
+	}
+
 	pub fn needs_llm_completion(&self) -> bool {
 		self.attributes.contains(LLM_COMPLETION_ATTRIBUTE)
 	}
@@ -240,6 +271,7 @@ impl ContextBuilder {
 			llm,
 			source,
 			mcp: _,
+			vars: _,
 		} = &self.context;
 
 		ctx.add_variable_from_value(REQUEST_ATTRIBUTE, opt_to_value(request)?);
@@ -248,6 +280,10 @@ impl ContextBuilder {
 		ctx.add_variable_from_value(MCP_ATTRIBUTE, opt_to_value(&mcp)?);
 		ctx.add_variable_from_value(LLM_ATTRIBUTE, opt_to_value(llm)?);
 		ctx.add_variable_from_value(SOURCE_ATTRIBUTE, opt_to_value(source)?);
+		ctx.add_variable_from_value(
+			VARIABLES_ATTRIBUTE,
+			self.variables.clone().unwrap_or(Value::Null),
+		);
 
 		Ok(Executor { ctx })
 	}
@@ -332,6 +368,9 @@ pub struct ExpressionContext {
 	/// `mcp` contains attributes about the MCP request.
 	// This is only included for schema generation; see build_with_mcp.
 	pub mcp: Option<crate::mcp::rbac::ResourceType>,
+	/// `vars` contains the values of the variables defined on the route, by name.
+	// This is only included for schema generation; see ContextBuilder::with_variables.
+	pub vars: Option<HashMap<String, serde_json::Value>>,
 }
 
 #[apply(schema_ser!)]
@@ -367,6 +406,15 @@ pub struct ResponseContext {
 	#[cfg_attr(feature = "schema", schemars(with = "u16"))]
 	/// The HTTP status code of the response.
 	pub code: ::http::StatusCode,
+
+	#[serde(with = "http_serde::header_map")]
+	#[cfg_attr(
+		feature = "schema",
+		schemars(with = "std::collections::HashMap<String, String>")
+	)]
+	/// The trailers of the response, such as `grpc-status`. These are only available once the response body
+	/// has completed, so are empty when evaluated before then.
+	pub trailers: ::http::HeaderMap,
 }
 
 #[apply(schema_ser!)]
@@ -377,6 +425,24 @@ pub struct SourceContext {
 	port: u16,
 	/// The (Istio SPIFFE) identity of the downstream connection, if available.
 	identity: Option<IdentityContext>,
+	/// The round trip time estimate of the downstream connection, in milliseconds, if available.
+	rtt: Option<f64>,
+	/// The TLS session of the downstream connection, if it uses TLS.
+	tls: Option<TLSContext>,
+}
+
+#[apply(schema_ser!)]
+pub struct TLSContext {
+	/// The negotiated TLS version, such as `TLSv1_3`.
+	version: Option<Strng>,
+	/// The negotiated cipher suite, such as `TLS13_AES_128_GCM_SHA256`.
+	cipher: Option<Strng>,
+	/// The negotiated application protocol: `h2`, `http/1.1`, or `other`.
+	alpn: Option<&'static str>,
+	/// Whether the session was resumed rather than established with a full handshake.
+	resumed: bool,
+	/// The server name the client requested with SNI.
+	server_name: Option<String>,
 }
 
 #[apply(schema_ser!)]
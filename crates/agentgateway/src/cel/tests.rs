@@ -45,6 +45,24 @@ fn expression() {
 	assert_eq!(Value::Bool(true), eval_request(expr, req).unwrap());
 }
 
+#[test]
+fn response_trailers() {
+	let exp =
+		Expression::new(r#"response.code == 200 && response.trailers["grpc-status"] == "0""#).unwrap();
+	let resp = ::http::Response::builder()
+		.status(200)
+		.body(Body::empty())
+		.unwrap();
+	let mut trailers = ::http::HeaderMap::new();
+	trailers.insert("grpc-status", ::http::HeaderValue::from_static("0"));
+	let mut cb = ContextBuilder::new();
+	cb.register_expression(&exp);
+	cb.with_response(&resp);
+	cb.with_response_trailers(&trailers);
+	let exec = cb.build().unwrap();
+	assert_eq!(Value::Bool(true), exec.eval(&exp).unwrap());
+}
+
 #[divan::bench]
 fn bench_native(b: Bencher) {
 	let req = ::http::Request::builder()
@@ -1,17 +1,23 @@
 mod dns;
 mod hyperrustls;
 
+use std::collections::HashMap;
+use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::task;
 
 use ::http::Uri;
 use ::http::uri::{Authority, Scheme};
+use http_body_util::BodyExt;
 use hyper_util_fork::rt::TokioIo;
+use prometheus_client::metrics::gauge::Gauge;
 use rustls_pki_types::{DnsName, ServerName};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::event;
 
 use crate::http::backendtls::BackendTLS;
 use crate::proxy::ProxyError;
+use crate::telemetry::metrics::{Metrics, PoolPartitionLabels};
 use crate::transport::hbone::WorkloadKey;
 use crate::transport::stream::{LoggingMode, Socket};
 use crate::transport::{hbone, stream};
@@ -22,6 +28,8 @@ use crate::*;
 pub struct Client {
 	resolver: Arc<dns::CachedResolver>,
 	client: hyper_util_fork::client::legacy::Client<Connector, http::Body, PoolKey>,
+	partitions: Arc<PartitionLimits>,
+	metrics: Option<Arc<Metrics>>,
 }
 
 impl Debug for Client {
@@ -64,7 +72,66 @@ impl From<Option<BackendTLS>> for Transport {
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct PoolKey(Target, SocketAddr, Transport, ::http::Version);
+struct PoolKey(
+	Target,
+	SocketAddr,
+	Transport,
+	::http::Version,
+	Option<Strng>,
+);
+
+/// PoolPartition, when present in the request extensions, places the request into a dedicated partition of the
+/// connection pool. Connections are never shared across partitions.
+#[derive(Debug, Clone)]
+pub struct PoolPartition {
+	pub key: Strng,
+	/// Route that selected the partition. Metrics are labeled by route rather than by key, since keys are
+	/// request-derived and unbounded.
+	pub route: Option<Strng>,
+	/// Maximum number of concurrent requests for the partition, per upstream.
+	pub max_streams: Option<NonZeroU32>,
+}
+
+/// PartitionLimits tracks the concurrency limits for each (upstream, partition) pair.
+#[derive(Debug, Default)]
+struct PartitionLimits {
+	limits: Mutex<HashMap<(Strng, Strng), (NonZeroU32, Arc<Semaphore>)>>,
+}
+
+impl PartitionLimits {
+	// Once we have this many limiters, drop any that have no requests in flight.
+	const PRUNE_THRESHOLD: usize = 1024;
+
+	fn get(&self, target: &Strng, partition: &Strng, max: NonZeroU32) -> Arc<Semaphore> {
+		let mut limits = self.limits.lock().expect("mutex poisoned");
+		if limits.len() >= Self::PRUNE_THRESHOLD {
+			limits.retain(|_, (_, sem)| Arc::strong_count(sem) > 1);
+		}
+		let (cur, sem) = limits
+			.entry((target.clone(), partition.clone()))
+			.or_insert_with(|| (max, Arc::new(Semaphore::new(max.get() as usize))));
+		if *cur != max {
+			// The limit changed; start fresh. In-flight requests hold permits on the old semaphore.
+			*cur = max;
+			*sem = Arc::new(Semaphore::new(max.get() as usize));
+		}
+		sem.clone()
+	}
+}
+
+/// PartitionGuard is held for the lifetime of a partitioned request, including the response body.
+struct PartitionGuard {
+	_permit: Option<OwnedSemaphorePermit>,
+	active: Option<Gauge>,
+}
+
+impl Drop for PartitionGuard {
+	fn drop(&mut self) {
+		if let Some(active) = &self.active {
+			active.dec();
+		}
+	}
+}
 
 impl Transport {
 	pub fn scheme(&self) -> Scheme {
@@ -102,7 +169,7 @@ impl tower::Service<::http::Extensions> for Connector {
 		let it = self.clone();
 
 		Box::pin(async move {
-			let PoolKey(target, ep, transport, _) =
+			let PoolKey(target, ep, transport, _, _) =
 				dst.remove::<PoolKey>().expect("pool key must be set");
 
 			match transport {
@@ -196,6 +263,54 @@ impl Client {
 		Client {
 			resolver: Arc::new(resolver),
 			client,
+			partitions: Default::default(),
+			metrics: None,
+		}
+	}
+
+	/// with_metrics enables recording metrics about upstream calls, such as pool partition usage.
+	pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Client {
+		self.metrics = Some(metrics);
+		self
+	}
+
+	async fn acquire_partition(&self, target: Strng, partition: &PoolPartition) -> PartitionGuard {
+		let labels = PoolPartitionLabels {
+			target: target.clone().into(),
+			route: partition.route.clone().into(),
+		};
+		let active = self.metrics.as_ref().map(|m| {
+			m.pool_partition_requests.get_or_create(&labels).inc();
+			m.pool_partition_active_requests
+				.get_or_create(&labels)
+				.clone()
+		});
+		let permit = match partition.max_streams {
+			Some(max) => {
+				let sem = self.partitions.get(&target, &partition.key, max);
+				let permit = match sem.clone().try_acquire_owned() {
+					Ok(permit) => permit,
+					Err(_) => {
+						trace!(partition=%partition.key, %target, "pool partition at capacity, waiting");
+						if let Some(m) = &self.metrics {
+							m.pool_partition_throttled.get_or_create(&labels).inc();
+						}
+						sem
+							.acquire_owned()
+							.await
+							.expect("partition semaphore is never closed")
+					},
+				};
+				Some(permit)
+			},
+			None => None,
+		};
+		if let Some(active) = &active {
+			active.inc();
+		}
+		PartitionGuard {
+			_permit: permit,
+			active,
 		}
 	}
 
@@ -229,6 +344,15 @@ impl Client {
 			.await
 	}
 
+	/// resolve looks up a hostname with the gateway's DNS resolver.
+	pub async fn resolve(&self, hostname: Strng) -> Result<IpAddr, ProxyError> {
+		self
+			.resolver
+			.resolve(hostname)
+			.await
+			.map_err(|_| ProxyError::DnsResolution)
+	}
+
 	pub async fn call(&self, call: Call) -> Result<http::Response, ProxyError> {
 		let start = std::time::Instant::now();
 		let Call {
@@ -239,12 +363,7 @@ impl Client {
 		let dest = match &target {
 			Target::Address(addr) => *addr,
 			Target::Hostname(hostname, port) => {
-				let ip = self
-					.resolver
-					.resolve(hostname.clone())
-					.await
-					.map_err(|_| ProxyError::DnsResolution)?;
-				SocketAddr::from((ip, *port))
+				SocketAddr::from((self.resolve(hostname.clone()).await?, *port))
 			},
 		};
 		http::modify_req_uri(&mut req, |uri| {
@@ -264,7 +383,12 @@ impl Client {
 		let version = req.version();
 		let transport_name = transport.name();
 		let target_name = target.to_string();
-		let key = PoolKey(target, dest, transport, version);
+		let partition = req.extensions_mut().remove::<PoolPartition>();
+		let partition_guard = match &partition {
+			Some(p) => Some(self.acquire_partition(strng::new(&target_name), p).await),
+			None => None,
+		};
+		let key = PoolKey(target, dest, transport, version, partition.map(|p| p.key));
 		trace!(?req, ?key, "sending request");
 		req.extensions_mut().insert(key);
 		let method = req.method().clone();
@@ -302,6 +426,97 @@ impl Client {
 
 			duration = dur,
 		);
-		Ok(resp?.map(http::Body::new))
+		let resp = resp?;
+		match partition_guard {
+			// Hold the partition until the response body is complete, as the stream is in use until then.
+			Some(guard) => Ok(resp.map(|body| {
+				http::Body::new(body.map_frame(move |frame| {
+					let _ = &guard;
+					frame
+				}))
+			})),
+			None => Ok(resp.map(http::Body::new)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn partitions_are_isolated() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let mut registry = prometheus_client::registry::Registry::default();
+		let metrics = Arc::new(Metrics::new(&mut registry));
+		let client = Client::new(&cfg.dns, None).with_metrics(metrics.clone());
+		let partition = |key: &str| PoolPartition {
+			key: strng::new(key),
+			route: Some(strng::literal!("route")),
+			max_streams: NonZeroU32::new(1),
+		};
+		let target = strng::literal!("upstream");
+		let wait = Duration::from_millis(50);
+
+		let a = client
+			.acquire_partition(target.clone(), &partition("a"))
+			.await;
+		// Partition "a" is at capacity, which must not block other partitions.
+		let b = tokio::time::timeout(
+			wait,
+			client.acquire_partition(target.clone(), &partition("b")),
+		)
+		.await
+		.expect("partition b should not wait on partition a");
+
+		// Another request in partition "a" waits until the in-flight one completes.
+		let blocked = client.acquire_partition(target.clone(), &partition("a"));
+		tokio::pin!(blocked);
+		assert!(tokio::time::timeout(wait, &mut blocked).await.is_err());
+		drop(a);
+		let a = tokio::time::timeout(wait, blocked)
+			.await
+			.expect("partition a should be released");
+
+		// Metrics are labeled by route, not by partition key.
+		let labels = PoolPartitionLabels {
+			target: target.into(),
+			route: strng::literal!("route").into(),
+		};
+		assert_eq!(
+			metrics.pool_partition_requests.get_or_create(&labels).get(),
+			3
+		);
+		assert_eq!(
+			metrics
+				.pool_partition_throttled
+				.get_or_create(&labels)
+				.get(),
+			1
+		);
+		let active = metrics
+			.pool_partition_active_requests
+			.get_or_create(&labels);
+		assert_eq!(active.get(), 2);
+		drop((a, b));
+		assert_eq!(active.get(), 0);
+	}
+
+	#[test]
+	fn partition_limits_follow_config() {
+		let limits = PartitionLimits::default();
+		let target = strng::literal!("upstream");
+		let (a, b) = (strng::literal!("a"), strng::literal!("b"));
+		let one = NonZeroU32::new(1).unwrap();
+		let sem = limits.get(&target, &a, one);
+		assert!(Arc::ptr_eq(&sem, &limits.get(&target, &a, one)));
+		assert!(!Arc::ptr_eq(&sem, &limits.get(&target, &b, one)));
+		assert!(!Arc::ptr_eq(
+			&sem,
+			&limits.get(&strng::literal!("other"), &a, one)
+		));
+		// Changing the limit replaces the semaphore.
+		let two = NonZeroU32::new(2).unwrap();
+		assert_eq!(limits.get(&target, &a, two).available_permits(), 2);
 	}
 }
@@ -8,12 +8,13 @@ use agent_core::prelude::*;
 use serde::de::DeserializeOwned;
 
 use crate::control::caclient;
-use crate::telemetry::log::{LoggingFields, MetricFields};
+use crate::management::access::Access;
+use crate::telemetry::log::{BodyCapture, LoggingFields, MetricFields};
 use crate::telemetry::trc;
 use crate::types::discovery::Identity;
 use crate::{
-	Address, Config, ConfigSource, NestedRawConfig, StringOrInt, ThreadingMode, XDSConfig, cel,
-	client, serdes, telemetry,
+	Address, Config, ConfigSource, NestedRawConfig, RawManagementAccess, RawUi, StringOrInt,
+	ThreadingMode, XDSConfig, cel, client, serdes, telemetry, transport,
 };
 
 pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Result<Config> {
@@ -66,6 +67,7 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 			namespace,
 			gateway,
 			local_config,
+			unreachable: raw.control_plane_unreachable.unwrap_or_default(),
 		}
 	};
 
@@ -175,6 +177,53 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 		.map(|addr| Address::new(ipv6_localhost_enabled, &addr))
 		.transpose()?
 		.unwrap_or(Address::SocketAddr(SocketAddr::new(bind_wildcard, 15021)));
+	let admin_access = parse_management_access(raw.admin_access).context("adminAccess")?;
+	let stats_access = parse_management_access(raw.stats_access).context("statsAccess")?;
+	let ui = raw
+		.ui
+		.map(|ui| parse_ui(ipv6_localhost_enabled, ui))
+		.transpose()
+		.context("ui")?
+		.unwrap_or_default();
+
+	let usage_reports = raw
+		.usage_reports
+		.map(|u| {
+			let group_by = u
+				.group_by
+				.unwrap_or_else(|| vec!["route".to_string(), "identity".to_string()])
+				.iter()
+				.map(|d| telemetry::usage::Dimension::parse(d))
+				.collect::<Vec<_>>();
+			for d in &group_by {
+				if let telemetry::usage::Dimension::Field(f) = d
+					&& !raw
+						.metrics
+						.as_ref()
+						.and_then(|m| m.fields.as_ref())
+						.is_some_and(|m| m.add.contains_key(f.as_str()))
+				{
+					anyhow::bail!("usage report dimension {f} is not a metric field")
+				}
+			}
+			if u.directory.is_none() && u.webhook.is_none() {
+				anyhow::bail!("usage reports require a directory or webhook")
+			}
+			Ok(telemetry::usage::Config {
+				interval: u.interval.unwrap_or(Duration::from_secs(3600)),
+				group_by,
+				min_group_requests: u.min_group_requests,
+				pricing: u
+					.pricing
+					.into_iter()
+					.map(|(k, v)| (strng::new(k), v))
+					.collect(),
+				directory: u.directory,
+				format: u.format,
+				webhook: u.webhook.map(|w| w.parse()).transpose()?,
+			})
+		})
+		.transpose()?;
 
 	let threading_mode = if parse::<String>("THREADING_MODE")?.as_deref() == Some("thread_per_core") {
 		ThreadingMode::ThreadPerCore
@@ -187,12 +236,21 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 		admin_addr,
 		stats_addr,
 		readiness_addr,
+		admin_access,
+		stats_access,
+		ui,
 		self_addr,
 		xds,
 		ca,
 		num_worker_threads: parse_worker_threads(raw.worker_threads)?,
 		termination_min_deadline,
 		threading_mode,
+		state_store: raw.state_store.unwrap_or_default(),
+		config_version_header: raw
+			.config_version_header
+			.map(|h| h.parse())
+			.transpose()
+			.context("configVersionHeader")?,
 		termination_max_deadline: match termination_max_deadline {
 			Some(period) => period,
 			None => match parse::<u64>("TERMINATION_GRACE_PERIOD_SECONDS")? {
@@ -252,6 +310,29 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 				.map(Arc::new),
 		},
 		logging: telemetry::log::Config {
+			body_capture: raw
+				.logging
+				.as_ref()
+				.and_then(|l| l.body_capture.as_ref())
+				.map(|bc| {
+					Ok::<_, anyhow::Error>(Arc::new(BodyCapture {
+						condition: bc
+							.condition
+							.as_ref()
+							.map(cel::Expression::new)
+							.transpose()?
+							.map(Arc::new),
+						request: bc.request.unwrap_or(true),
+						response: bc.response.unwrap_or(true),
+						max_bytes: bc.max_bytes.unwrap_or(1024),
+						redact: bc
+							.redact
+							.iter()
+							.map(|r| regex::Regex::new(r))
+							.collect::<Result<_, _>>()?,
+					}))
+				})
+				.transpose()?,
 			filter: raw
 				.logging
 				.as_ref()
@@ -293,6 +374,7 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 					.unwrap_or_default(),
 			),
 		},
+		usage_reports,
 		dns: client::Config {
 			// TODO: read from file
 			resolver_cfg,
@@ -348,6 +430,72 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 	})
 }
 
+fn parse_ui(ipv6_localhost_enabled: bool, raw: RawUi) -> anyhow::Result<crate::UiConfig> {
+	let base_path = match raw.base_path.as_deref().map(|p| p.trim_end_matches('/')) {
+		None | Some("") => None,
+		Some(p) if !p.starts_with('/') => anyhow::bail!("basePath must start with '/'"),
+		Some(p) if p.contains(['"', '<', '>', '\\', '?', '#']) => {
+			anyhow::bail!("basePath contains an invalid character")
+		},
+		Some(p) => Some(p.to_string()),
+	};
+	Ok(crate::UiConfig {
+		enabled: raw.enabled.unwrap_or(true),
+		address: raw
+			.address
+			.map(|addr| Address::new(ipv6_localhost_enabled, &addr))
+			.transpose()?,
+		base_path,
+		title: raw.title,
+		logo_url: raw.logo_url,
+	})
+}
+
+fn parse_management_access(raw: Option<RawManagementAccess>) -> anyhow::Result<Access> {
+	let Some(raw) = raw else {
+		return Ok(Access::local());
+	};
+	let token = match (raw.token, raw.token_file) {
+		(Some(_), Some(_)) => anyhow::bail!("only one of token or tokenFile may be set"),
+		(Some(t), None) => Some(t),
+		(None, Some(f)) => Some(fs_err::read_to_string(f)?.trim().to_string()),
+		(None, None) => None,
+	};
+	if token.as_ref().is_some_and(|t| t.is_empty()) {
+		anyhow::bail!("token must not be empty");
+	}
+	let tls = raw
+		.mtls
+		.map(|mtls| {
+			let cert_chain = crate::types::agent::parse_cert(&fs_err::read(mtls.cert)?)?;
+			let private_key = crate::types::agent::parse_key(&fs_err::read(mtls.key)?)?;
+			let mut roots = rustls::RootCertStore::empty();
+			for ca in crate::types::agent::parse_cert(&fs_err::read(mtls.client_ca)?)? {
+				roots.add(ca)?;
+			}
+			let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+				Arc::new(roots),
+				transport::tls::provider(),
+			)
+			.build()?;
+			let sc = rustls::ServerConfig::builder_with_provider(transport::tls::provider())
+				.with_protocol_versions(transport::tls::ALL_TLS_VERSIONS)
+				.expect("server config must be valid")
+				.with_client_cert_verifier(verifier)
+				.with_single_cert(cert_chain, private_key)?;
+			anyhow::Ok(Arc::new(sc))
+		})
+		.transpose()?;
+	if raw.allowed_sources.is_empty() && token.is_none() && tls.is_none() {
+		return Ok(Access::local());
+	}
+	Ok(Access {
+		token: token.map(Into::into),
+		tls,
+		allowed_sources: raw.allowed_sources,
+	})
+}
+
 fn parse<T: FromStr>(env: &str) -> anyhow::Result<Option<T>>
 where
 	<T as FromStr>::Err: ToString,
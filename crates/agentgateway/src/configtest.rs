@@ -0,0 +1,540 @@
+//! configtest evaluates a suite of synthetic requests against a configuration, reporting which route, backend,
+//! and policy decision each would get. This allows configuration changes to be tested without running backends.
+
+use std::fmt::Write;
+
+use ::http::Method;
+use itertools::Itertools;
+use secrecy::SecretString;
+
+use crate::cel::ContextBuilder;
+use crate::http::jwt::{Claims, Mode};
+use crate::http::{Body, Request};
+use crate::store::Stores;
+use crate::store::binds::RequestPolicy;
+use crate::types::agent::{Backend, BackendReference, ListenerProtocol};
+use crate::types::local::NormalizedLocalConfig;
+use crate::*;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Suite {
+	pub tests: Vec<TestCase>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestCase {
+	pub name: String,
+	pub request: TestRequest,
+	pub expect: Expectation,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestRequest {
+	/// The port the request is sent to. May be omitted if only one port is bound.
+	#[serde(default)]
+	pub port: Option<u16>,
+	#[serde(default = "default_method")]
+	pub method: String,
+	#[serde(default = "default_host")]
+	pub host: String,
+	#[serde(default = "default_path")]
+	pub path: String,
+	#[serde(default)]
+	pub headers: IndexMap<String, String>,
+	/// JWT claims for the request. These are treated as already verified.
+	#[serde(default)]
+	pub identity: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+fn default_method() -> String {
+	"GET".to_string()
+}
+
+fn default_host() -> String {
+	"localhost".to_string()
+}
+
+fn default_path() -> String {
+	"/".to_string()
+}
+
+/// Expectation describes the expected outcome. Any field that is unset is not checked.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Expectation {
+	#[serde(default)]
+	pub route: Option<String>,
+	/// The backend, either by name or by its target (such as `127.0.0.1:8080`).
+	#[serde(default)]
+	pub backend: Option<String>,
+	#[serde(default)]
+	pub decision: Option<Decision>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Decision {
+	/// The request is allowed by all policies.
+	Allow,
+	/// The request is rejected by a policy.
+	Deny,
+	/// No listener or route matches the request.
+	NotFound,
+}
+
+impl Display for Decision {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Decision::Allow => write!(f, "allow"),
+			Decision::Deny => write!(f, "deny"),
+			Decision::NotFound => write!(f, "notFound"),
+		}
+	}
+}
+
+/// Outcome is the result of evaluating a single request.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+	pub route: Option<Strng>,
+	/// The route's backends, as (name, target) pairs.
+	pub backends: Vec<(Strng, String)>,
+	pub decision: Decision,
+	pub reason: Option<&'static str>,
+	/// A policy that calls an external service, so an `allow` decision could not be confirmed.
+	pub unchecked: Option<&'static str>,
+}
+
+impl Outcome {
+	fn not_found(reason: &'static str) -> Outcome {
+		Outcome {
+			route: None,
+			backends: vec![],
+			decision: Decision::NotFound,
+			reason: Some(reason),
+			unchecked: None,
+		}
+	}
+
+	fn deny(mut self, reason: &'static str) -> Outcome {
+		self.decision = Decision::Deny;
+		self.reason = Some(reason);
+		self
+	}
+
+	/// failures compares the outcome to the expectation, returning a description of each mismatch.
+	pub fn failures(&self, expect: &Expectation) -> Vec<String> {
+		let mut failures = vec![];
+		if let Some(want) = &expect.route
+			&& self.route.as_deref() != Some(want.as_str())
+		{
+			failures.push(format!(
+				"route: expected {want}, got {}",
+				self.route.as_deref().unwrap_or("<none>")
+			));
+		}
+		if let Some(want) = &expect.backend {
+			let matches = matches!(
+				self.backends.as_slice(),
+				[(name, target)] if name.as_str() == want.as_str() || target == want
+			);
+			if !matches {
+				let got = if self.backends.is_empty() {
+					"<none>".to_string()
+				} else {
+					self.backends.iter().map(|(_, t)| t.as_str()).join(", ")
+				};
+				failures.push(format!("backend: expected {want}, got {got}"));
+			}
+		}
+		if let Some(want) = expect.decision
+			&& self.decision == Decision::Allow
+			&& let Some(policy) = self.unchecked
+		{
+			failures.push(format!(
+				"decision: cannot check {want}, the route uses {policy}, which calls an external service"
+			));
+		} else if let Some(want) = expect.decision
+			&& self.decision != want
+		{
+			let mut msg = format!("decision: expected {want}, got {}", self.decision);
+			if let Some(reason) = self.reason {
+				let _ = write!(msg, " ({reason})");
+			}
+			failures.push(msg);
+		}
+		failures
+	}
+}
+
+/// load builds a set of stores from a local configuration, as the proxy would.
+pub async fn load(client: client::Client, config: &str) -> anyhow::Result<Stores> {
+	let config = NormalizedLocalConfig::from(client, config).await?;
+	let stores = Stores::new();
+	stores.binds.sync_local(
+		config.binds,
+		config.policies,
+		config.backends,
+		Default::default(),
+	);
+	stores
+		.discovery
+		.sync_local(config.services, config.workloads, Default::default())?;
+	Ok(stores)
+}
+
+/// evaluate determines the outcome of a request, applying the route's request policies in the order the proxy
+/// does. Policies that call external services (external authorization and remote rate limits) cannot be evaluated;
+/// if one is configured, only a `deny` from an earlier or later policy is certain. Local rate limits are assumed to
+/// have capacity, and JWT claims in the test request are treated as verified.
+pub fn evaluate(stores: &Stores, tr: &TestRequest) -> anyhow::Result<Outcome> {
+	let binds = stores.read_binds().all();
+	let bind = match tr.port {
+		Some(port) => binds.iter().find(|b| b.address.port() == port),
+		None if binds.len() == 1 => binds.first(),
+		None => anyhow::bail!("'port' is required when multiple ports are bound"),
+	};
+	let Some(bind) = bind else {
+		return Ok(Outcome::not_found("no bind for port"));
+	};
+
+	let mut builder = ::http::Request::builder()
+		.method(Method::from_bytes(tr.method.as_bytes())?)
+		.uri(format!("http://{}{}", tr.host, tr.path));
+	for (k, v) in &tr.headers {
+		builder = builder.header(k, v);
+	}
+	let mut req: Request = builder.body(Body::empty())?;
+
+	let Some(listener) = bind.listeners.best_match(&tr.host) else {
+		return Ok(Outcome::not_found("no matching listener"));
+	};
+	if !matches!(
+		listener.protocol,
+		ListenerProtocol::HTTP | ListenerProtocol::HTTPS(_) | ListenerProtocol::SOCKS5(..)
+	) {
+		anyhow::bail!("listener {} is not an HTTP listener", listener.key);
+	}
+	let Some((route, _)) = http::route::select_best_route(
+		stores.clone(),
+		Default::default(),
+		None,
+		bind.address,
+		listener.clone(),
+		&req,
+	) else {
+		return Ok(Outcome::not_found("no matching route"));
+	};
+
+	let backends = route
+		.backends
+		.iter()
+		.filter(|b| b.weight > 0)
+		.map(|b| describe_backend(stores, &b.backend))
+		.collect_vec();
+	let mut outcome = Outcome {
+		route: Some(route.route_name.clone()),
+		backends,
+		decision: Decision::Allow,
+		reason: None,
+		unchecked: None,
+	};
+
+	let policies = stores.read_binds().route_policies(&listener, &route);
+	let mut ctx = ContextBuilder::new();
+	policies.register_cel_expressions(&mut ctx);
+	ctx.with_request(&req);
+	if let Some(identity) = &tr.identity {
+		ctx.with_jwt(&Claims {
+			inner: identity.clone(),
+			jwt: SecretString::from(""),
+		});
+	}
+	for policy in policies.request_policies.iter() {
+		match policy {
+			RequestPolicy::LoopDetection => {
+				if let Some(ld) = &policies.loop_detection
+					&& ld.apply(req.headers_mut()).is_err()
+				{
+					return Ok(outcome.deny("loop detected"));
+				}
+			},
+			RequestPolicy::Jwt => {
+				if let Some(jwt) = &policies.jwt
+					&& jwt.mode() == Mode::Strict
+					&& tr.identity.is_none()
+				{
+					return Ok(outcome.deny("missing JWT"));
+				}
+			},
+			RequestPolicy::ExtAuthz => {
+				outcome.unchecked.get_or_insert("extAuthz");
+			},
+			RequestPolicy::Variables => {
+				if let Some(vars) = &policies.variables {
+					vars.apply(&mut ctx)?;
+				}
+			},
+			RequestPolicy::Authorization => {
+				if let Some(authz) = &policies.authorization
+					&& authz.apply(&ctx.build()?).is_err()
+				{
+					return Ok(outcome.deny("authorization"));
+				}
+			},
+			RequestPolicy::RemoteRateLimit => {
+				outcome.unchecked.get_or_insert("remoteRateLimit");
+			},
+			// These never reject a single request.
+			RequestPolicy::SecurityHeaders
+			| RequestPolicy::LocalRateLimit
+			| RequestPolicy::Transformation
+			| RequestPolicy::PoolPartition
+			| RequestPolicy::HeaderSizeLimit => {},
+		}
+	}
+	Ok(outcome)
+}
+
+fn describe_backend(stores: &Stores, b: &BackendReference) -> (Strng, String) {
+	let name = b.name();
+	let target = match b {
+		BackendReference::Service { name, port } => format!("{}:{port}", name.hostname),
+		BackendReference::Backend(_) => match stores.read_binds().backend(&name).as_deref() {
+			Some(Backend::Opaque(_, target)) => target.to_string(),
+			Some(Backend::AI(_, ai)) => format!("ai/{}", ai.provider.provider()),
+			Some(Backend::MCP(_, _)) => "mcp".to_string(),
+			_ => name.to_string(),
+		},
+		BackendReference::Invalid => "invalid".to_string(),
+	};
+	(name, target)
+}
+
+/// TestResult is the result of a single test in a suite. The test passed if there are no failures.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+	pub name: String,
+	pub failures: Vec<String>,
+}
+
+/// Report is the result of running a suite.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+	pub results: Vec<TestResult>,
+}
+
+impl Report {
+	pub fn failed(&self) -> usize {
+		self
+			.results
+			.iter()
+			.filter(|r| !r.failures.is_empty())
+			.count()
+	}
+}
+
+impl Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for r in &self.results {
+			if r.failures.is_empty() {
+				writeln!(f, "PASS {}", r.name)?;
+			} else {
+				writeln!(f, "FAIL {}", r.name)?;
+				for failure in &r.failures {
+					writeln!(f, "  {failure}")?;
+				}
+			}
+		}
+		let failed = self.failed();
+		writeln!(f, "{} passed, {failed} failed", self.results.len() - failed)
+	}
+}
+
+/// run evaluates every test in the suite. Failing tests are reported in the result rather than as an error.
+pub async fn run(client: client::Client, config: &str, suite: &str) -> anyhow::Result<Report> {
+	let stores = load(client, config).await?;
+	let suite: Suite = serdes::yamlviajson::from_str(suite)?;
+	let results = suite
+		.tests
+		.into_iter()
+		.map(|test| {
+			let failures = match evaluate(&stores, &test.request) {
+				Ok(outcome) => outcome.failures(&test.expect),
+				Err(e) => vec![format!("invalid request: {e}")],
+			};
+			TestResult {
+				name: test.name,
+				failures,
+			}
+		})
+		.collect();
+	Ok(Report { results })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CONFIG: &str = r#"
+binds:
+- port: 3000
+  listeners:
+  - protocol: HTTP
+    routes:
+    - name: admin
+      matches:
+      - path:
+          pathPrefix: /admin
+      policies:
+        authorization:
+          rules:
+          - 'jwt.sub == "alice"'
+      backends:
+      - host: 127.0.0.1:8081
+    - name: external
+      matches:
+      - path:
+          pathPrefix: /external
+      policies:
+        extAuthz:
+          host: 127.0.0.1:9000
+        authorization:
+          rules:
+          - 'jwt.sub == "alice"'
+      backends:
+      - host: 127.0.0.1:8082
+    - name: default
+      hostnames: [example.com]
+      backends:
+      - host: 127.0.0.1:8080
+"#;
+
+	const SUITE: &str = r#"
+tests:
+- name: admin allowed
+  request:
+    path: /admin/users
+    host: example.com
+    identity:
+      sub: alice
+  expect:
+    route: admin
+    backend: 127.0.0.1:8081
+    decision: allow
+- name: admin denied
+  request:
+    path: /admin/users
+    identity:
+      sub: bob
+  expect:
+    route: admin
+    decision: deny
+- name: default
+  request:
+    host: example.com
+    path: /foo
+  expect:
+    route: default
+    backend: 127.0.0.1:8080
+- name: unknown host
+  request:
+    host: other.com
+    path: /foo
+  expect:
+    decision: notFound
+"#;
+
+	async fn stores() -> Stores {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		load(client, CONFIG).await.unwrap()
+	}
+
+	#[tokio::test]
+	async fn suite() {
+		let stores = stores().await;
+		let suite: Suite = serdes::yamlviajson::from_str(SUITE).unwrap();
+		for test in suite.tests {
+			let outcome = evaluate(&stores, &test.request).unwrap();
+			assert_eq!(
+				outcome.failures(&test.expect),
+				Vec::<String>::new(),
+				"{}",
+				test.name
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn reports_mismatch() {
+		let stores = stores().await;
+		let req: TestRequest =
+			serde_json::from_value(serde_json::json!({"host": "example.com", "path": "/foo"})).unwrap();
+		let expect: Expectation =
+			serde_json::from_value(serde_json::json!({"route": "admin", "decision": "deny"})).unwrap();
+		let outcome = evaluate(&stores, &req).unwrap();
+		assert_eq!(
+			outcome.failures(&expect),
+			vec![
+				"route: expected admin, got default".to_string(),
+				"decision: expected deny, got allow".to_string(),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn external_policies_are_unchecked() {
+		let stores = stores().await;
+		let request = |sub: &str| -> TestRequest {
+			serde_json::from_value(serde_json::json!({"path": "/external", "identity": {"sub": sub}}))
+				.unwrap()
+		};
+		let expect = |decision: &str| -> Expectation {
+			serde_json::from_value(serde_json::json!({"route": "external", "decision": decision}))
+				.unwrap()
+		};
+
+		// Whether external authorization allows the request is unknown.
+		let outcome = evaluate(&stores, &request("alice")).unwrap();
+		assert_eq!(outcome.unchecked, Some("extAuthz"));
+		assert_eq!(
+			outcome.failures(&expect("allow")),
+			vec![
+				"decision: cannot check allow, the route uses extAuthz, which calls an external service"
+					.to_string()
+			]
+		);
+
+		// A denial by a local policy is certain either way.
+		let outcome = evaluate(&stores, &request("bob")).unwrap();
+		assert_eq!(outcome.failures(&expect("deny")), Vec::<String>::new());
+	}
+
+	#[tokio::test]
+	async fn report() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		let suite = format!(
+			"{SUITE}{}",
+			r#"
+- name: wrong route
+  request:
+    host: example.com
+  expect:
+    route: admin
+"#
+		);
+		let report = run(client, CONFIG, &suite).await.unwrap();
+		assert_eq!(report.failed(), 1);
+		let out = report.to_string();
+		assert!(out.contains("PASS admin allowed\n"), "{out}");
+		assert!(
+			out.contains("FAIL wrong route\n  route: expected admin, got default\n"),
+			"{out}"
+		);
+		assert!(out.ends_with("4 passed, 1 failed\n"), "{out}");
+	}
+}
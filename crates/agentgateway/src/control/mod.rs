@@ -16,6 +16,7 @@ use crate::types::agent::Target;
 use crate::*;
 
 pub mod caclient;
+pub mod unreachable;
 
 #[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
 pub enum RootCert {
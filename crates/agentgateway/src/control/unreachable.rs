@@ -0,0 +1,259 @@
+use std::path::PathBuf;
+
+use agent_core::readiness;
+use agent_xds::ConnectionStatus;
+use serde::{Serialize, Serializer};
+use tokio::sync::{oneshot, watch};
+
+use crate::http::{HeaderName, HeaderValue, Response};
+use crate::state_manager::{LocalClient, PreviousState};
+use crate::*;
+
+/// Config controls how the gateway behaves while the XDS control plane is unreachable.
+/// Until the grace period passes, the gateway keeps serving with its current configuration and retries.
+#[apply(schema!)]
+pub struct Config {
+	/// What to do once the control plane has been unreachable for longer than the grace period.
+	#[serde(default)]
+	pub mode: Mode,
+	/// How long the control plane may be unreachable before the gateway is degraded. Defaults to 30s.
+	#[serde(default = "default_grace_period", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub grace_period: Duration,
+	/// Local configuration file to load while degraded in `failStatic` mode, so critical routes keep working
+	/// without the control plane, including when it is unreachable at startup. Its resources are removed once
+	/// the control plane is reachable again, so they should not share names with resources from the control plane.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fallback_config: Option<PathBuf>,
+	/// Response header that reports, while degraded, how many seconds the control plane has been unreachable.
+	#[serde(
+		default,
+		serialize_with = "ser_display_option",
+		deserialize_with = "de_parse_option"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub stale_header: Option<HeaderName>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			mode: Mode::default(),
+			grace_period: default_grace_period(),
+			fallback_config: None,
+			stale_header: None,
+		}
+	}
+}
+
+fn default_grace_period() -> Duration {
+	Duration::from_secs(30)
+}
+
+#[apply(schema!)]
+#[derive(Default, Copy, PartialEq, Eq)]
+pub enum Mode {
+	/// Keep serving with the last received configuration. The gateway reports ready, but degraded.
+	#[default]
+	FailStatic,
+	/// Reject HTTP requests with a 503, close new TCP and TLS connections, and report the gateway as not ready.
+	FailClosed,
+}
+
+impl Config {
+	/// rejects reports whether requests and connections should be rejected, because the gateway is degraded in `failClosed` mode.
+	pub fn rejects(&self, status: &Status) -> bool {
+		self.mode == Mode::FailClosed && status.unreachable_for().is_some()
+	}
+
+	/// apply_stale_header adds the staleness header to a response, if the gateway is degraded.
+	pub fn apply_stale_header(&self, status: &Status, resp: &mut Response) {
+		if let Some(name) = &self.stale_header
+			&& let Some(unreachable) = status.unreachable_for()
+		{
+			resp
+				.headers_mut()
+				.insert(name.clone(), HeaderValue::from(unreachable.as_secs()));
+		}
+	}
+}
+
+/// Status records when the control plane became unreachable, while the gateway is degraded.
+#[derive(Clone, Debug, Default)]
+pub struct Status(Arc<Mutex<Option<Instant>>>);
+
+impl Status {
+	pub fn unreachable_for(&self) -> Option<Duration> {
+		self.0.lock().expect("mutex").map(|since| since.elapsed())
+	}
+
+	fn set(&self, since: Option<Instant>) {
+		*self.0.lock().expect("mutex") = since;
+	}
+}
+
+impl Serialize for Status {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		#[derive(Serialize)]
+		#[serde(rename_all = "camelCase")]
+		struct Dump {
+			degraded: bool,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			unreachable_seconds: Option<u64>,
+		}
+		let unreachable = self.unreachable_for();
+		Dump {
+			degraded: unreachable.is_some(),
+			unreachable_seconds: unreachable.map(|d| d.as_secs()),
+		}
+		.serialize(serializer)
+	}
+}
+
+/// Monitor watches the XDS connection, and degrades the gateway once it has been lost for longer than the
+/// grace period.
+pub struct Monitor {
+	pub cfg: Config,
+	pub status: Status,
+	pub ready: readiness::Ready,
+	/// Loads the fallback configuration, if one is configured.
+	pub fallback: Option<LocalClient>,
+	/// Notified once the fallback configuration is loaded, so startup does not wait on the control plane.
+	pub on_fallback: Option<oneshot::Sender<()>>,
+}
+
+struct Degraded {
+	_block_ready: Option<readiness::BlockReady>,
+	fallback: Option<PreviousState>,
+}
+
+impl Monitor {
+	pub async fn run(mut self, mut conn: watch::Receiver<ConnectionStatus>) {
+		let mut since = Instant::now();
+		let mut degraded = None;
+		loop {
+			if *conn.borrow_and_update() == ConnectionStatus::Connected {
+				if let Some(d) = degraded.take() {
+					self.recover(d).await;
+				}
+				if conn.changed().await.is_err() {
+					return;
+				}
+				since = Instant::now();
+				continue;
+			}
+			if degraded.is_none() {
+				let remaining = self.cfg.grace_period.saturating_sub(since.elapsed());
+				tokio::select! {
+					res = conn.changed() => {
+						if res.is_err() {
+							return;
+						}
+						continue;
+					}
+					_ = tokio::time::sleep(remaining) => {}
+				}
+				degraded = Some(self.degrade(since).await);
+			}
+			if conn.changed().await.is_err() {
+				return;
+			}
+		}
+	}
+
+	async fn degrade(&mut self, since: Instant) -> Degraded {
+		warn!(
+			mode = ?self.cfg.mode,
+			unreachable = ?since.elapsed(),
+			"control plane is unreachable, degrading"
+		);
+		self.status.set(Some(since));
+		let block_ready = match self.cfg.mode {
+			Mode::FailStatic => {
+				self
+					.ready
+					.set_degraded(Some("control plane unreachable".to_string()));
+				None
+			},
+			Mode::FailClosed => Some(self.ready.register_task("control plane")),
+		};
+		let fallback = match (&self.fallback, self.cfg.mode) {
+			(Some(lc), Mode::FailStatic) => match lc.reload_config(PreviousState::default()).await {
+				Ok(prev) => {
+					info!("loaded fallback configuration");
+					if let Some(tx) = self.on_fallback.take() {
+						let _ = tx.send(());
+					}
+					Some(prev)
+				},
+				Err(e) => {
+					error!("failed to load fallback configuration: {e}");
+					None
+				},
+			},
+			_ => None,
+		};
+		Degraded {
+			_block_ready: block_ready,
+			fallback,
+		}
+	}
+
+	async fn recover(&self, degraded: Degraded) {
+		info!("control plane is reachable again");
+		self.status.set(None);
+		self.ready.set_degraded(None);
+		if let (Some(lc), Some(prev)) = (&self.fallback, degraded.fallback)
+			&& let Err(e) = lc.clear(prev)
+		{
+			error!("failed to remove fallback configuration: {e}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test(start_paused = true)]
+	async fn degrade_and_recover() {
+		let (tx, rx) = watch::channel(ConnectionStatus::Connecting);
+		let status = Status::default();
+		let ready = readiness::Ready::new();
+		let cfg = Config {
+			mode: Mode::FailClosed,
+			stale_header: Some(HeaderName::from_static("x-config-stale")),
+			..Default::default()
+		};
+		tokio::spawn(
+			Monitor {
+				cfg: cfg.clone(),
+				status: status.clone(),
+				ready: ready.clone(),
+				fallback: None,
+				on_fallback: None,
+			}
+			.run(rx),
+		);
+
+		tokio::time::sleep(Duration::from_secs(10)).await;
+		assert!(!cfg.rejects(&status));
+
+		tokio::time::sleep(Duration::from_secs(30)).await;
+		assert!(cfg.rejects(&status));
+		assert!(ready.pending().contains("control plane"));
+		let mut resp = Response::default();
+		cfg.apply_stale_header(&status, &mut resp);
+		assert_eq!(resp.headers().get("x-config-stale").unwrap(), "40");
+
+		tx.send_replace(ConnectionStatus::Connected);
+		tokio::time::sleep(Duration::from_millis(1)).await;
+		assert!(!cfg.rejects(&status));
+		assert!(ready.pending().is_empty());
+
+		// A short disconnect is within the grace period
+		tx.send_replace(ConnectionStatus::Disconnected);
+		tokio::time::sleep(Duration::from_secs(10)).await;
+		assert!(status.unreachable_for().is_none());
+	}
+}
@@ -0,0 +1,1000 @@
+//! convert translates Gateway API resources (Gateway, HTTPRoute, TCPRoute, and TLSRoute) and Envoy bootstrap
+//! configuration into an equivalent local configuration, to ease migrating traffic to agentgateway.
+//!
+//! Only a subset of each format can be translated. Anything that is not is reported as a warning, rather than
+//! silently dropped, so the output should be reviewed before it is used.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{Map, Value, json};
+
+use crate::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct Conversion {
+	/// The converted local configuration.
+	pub config: Value,
+	/// Features of the input that could not be converted.
+	pub warnings: Vec<String>,
+}
+
+/// convert translates a YAML stream of Gateway API resources and/or Envoy bootstrap configs.
+pub fn convert(input: &str) -> anyhow::Result<Conversion> {
+	let docs: Vec<Value> = serdes::yamlviajson::from_str_documents(input)?;
+	let mut c = Converter::default();
+	let mut gateways = vec![];
+	let mut routes = vec![];
+	for doc in docs {
+		if doc.is_null() {
+			continue;
+		}
+		if doc.get("static_resources").is_some() || doc.get("dynamic_resources").is_some() {
+			c.envoy(&doc);
+			continue;
+		}
+		let api_version = str_at(&doc, "/apiVersion").unwrap_or_default();
+		let Some(kind) = str_at(&doc, "/kind") else {
+			anyhow::bail!("document is neither a Kubernetes resource nor an Envoy bootstrap config");
+		};
+		if !api_version.starts_with("gateway.networking.k8s.io/") {
+			c.warn(format!(
+				"{kind} {}: not a Gateway API resource",
+				resource_name(&doc)
+			));
+			continue;
+		}
+		match kind {
+			"Gateway" => gateways.push(doc),
+			"HTTPRoute" | "TCPRoute" | "TLSRoute" => routes.push(doc),
+			_ => c.warn(format!("{kind} {}: not supported", resource_name(&doc))),
+		}
+	}
+	// Routes attach to Gateway listeners, so all Gateways are converted first.
+	for gw in &gateways {
+		c.gateway(gw);
+	}
+	for route in &routes {
+		c.route(route);
+	}
+	Ok(c.finish())
+}
+
+#[derive(Default)]
+struct Converter {
+	/// Listeners, by the port they bind to.
+	binds: BTreeMap<u16, Vec<Listener>>,
+	warnings: Vec<String>,
+}
+
+struct Listener {
+	/// The Gateway (as namespace/name) the listener was converted from, if any.
+	gateway: Option<String>,
+	name: String,
+	port: u16,
+	config: Map<String, Value>,
+}
+
+impl Listener {
+	/// routes returns the route list of the listener: `tcpRoutes` for TCP and TLS listeners, else `routes`.
+	fn routes(&mut self) -> &mut Vec<Value> {
+		let key = if self.config.contains_key("tcpRoutes") {
+			"tcpRoutes"
+		} else {
+			"routes"
+		};
+		match self.config.entry(key).or_insert_with(|| json!([])) {
+			Value::Array(routes) => routes,
+			_ => unreachable!("routes are always an array"),
+		}
+	}
+
+	fn is_tcp(&self) -> bool {
+		self.config.contains_key("tcpRoutes")
+	}
+}
+
+impl Converter {
+	fn warn(&mut self, msg: String) {
+		self.warnings.push(msg);
+	}
+
+	fn finish(self) -> Conversion {
+		let binds = self
+			.binds
+			.into_iter()
+			.map(|(port, listeners)| {
+				json!({
+					"port": port,
+					"listeners": listeners.into_iter().map(|l| Value::Object(l.config)).collect::<Vec<_>>(),
+				})
+			})
+			.collect::<Vec<_>>();
+		Conversion {
+			config: json!({ "binds": binds }),
+			warnings: self.warnings,
+		}
+	}
+
+	fn add_listener(&mut self, l: Listener) {
+		self.binds.entry(l.port).or_default().push(l);
+	}
+
+	fn gateway(&mut self, doc: &Value) {
+		let gw = resource_name(doc);
+		if doc.pointer("/spec/addresses").is_some() {
+			self.warn(format!("Gateway {gw}: addresses are not supported"));
+		}
+		for l in array_at(doc, "/spec/listeners") {
+			let name = str_at(l, "/name").unwrap_or_default().to_string();
+			let ctx = format!("Gateway {gw} listener {name}");
+			let Some(port) = u16_at(l, "/port") else {
+				self.warn(format!("{ctx}: invalid port"));
+				continue;
+			};
+			let mut config = Map::new();
+			config.insert("name".into(), json!(name));
+			config.insert("gatewayName".into(), json!(gw));
+			if let Some(hostname) = str_at(l, "/hostname") {
+				config.insert("hostname".into(), json!(hostname));
+			}
+			let protocol = str_at(l, "/protocol").unwrap_or("HTTP");
+			let tls_mode = str_at(l, "/tls/mode").unwrap_or("Terminate");
+			let routes = match (protocol, tls_mode) {
+				("HTTP", _) => "routes",
+				("HTTPS", "Terminate") => "routes",
+				("TLS", "Terminate") => "tcpRoutes",
+				("TCP", _) => "tcpRoutes",
+				(_, "Passthrough") => {
+					self.warn(format!("{ctx}: TLS passthrough is not supported"));
+					continue;
+				},
+				_ => {
+					self.warn(format!("{ctx}: protocol {protocol} is not supported"));
+					continue;
+				},
+			};
+			config.insert("protocol".into(), json!(protocol));
+			if matches!(protocol, "HTTPS" | "TLS") {
+				let Some(tls) = self.certificate(&ctx, l) else {
+					continue;
+				};
+				config.insert("tls".into(), tls);
+			}
+			config.insert(routes.into(), json!([]));
+			if l.get("allowedRoutes").is_some() {
+				self.warn(format!(
+					"{ctx}: allowedRoutes is not enforced; routes are attached by parentRefs only"
+				));
+			}
+			self.add_listener(Listener {
+				gateway: Some(gw.clone()),
+				name,
+				port,
+				config,
+			});
+		}
+	}
+
+	/// certificate converts the certificate reference of a TLS terminating listener. Secrets cannot be read during
+	/// conversion, so the listener instead references files named after the Secret, which must be exported.
+	fn certificate(&mut self, ctx: &str, l: &Value) -> Option<Value> {
+		let refs = array_at(l, "/tls/certificateRefs");
+		let Some(secret) = refs.first().and_then(|r| str_at(r, "/name")) else {
+			self.warn(format!("{ctx}: TLS requires a certificateRef"));
+			return None;
+		};
+		if refs.len() > 1 {
+			self.warn(format!("{ctx}: only the first certificateRef is converted"));
+		}
+		let cert = format!("{secret}/tls.crt");
+		let key = format!("{secret}/tls.key");
+		self.warn(format!(
+			"{ctx}: the certificate from Secret {secret} must be exported to {cert} and {key}"
+		));
+		Some(json!({ "cert": cert, "key": key }))
+	}
+
+	fn route(&mut self, doc: &Value) {
+		let kind = str_at(doc, "/kind").unwrap_or_default();
+		let name = resource_name(doc);
+		let ctx = format!("{kind} {name}");
+		let namespace = namespace(doc);
+		let tcp = kind != "HTTPRoute";
+		let hostnames = array_at(doc, "/spec/hostnames").to_vec();
+
+		let mut converted = vec![];
+		for (idx, rule) in array_at(doc, "/spec/rules").iter().enumerate() {
+			let ctx = format!("{ctx} rule {idx}");
+			let mut route = Map::new();
+			route.insert(
+				"name".into(),
+				json!(str_at(doc, "/metadata/name").unwrap_or_default()),
+			);
+			if let Some(rule_name) = str_at(rule, "/name") {
+				route.insert("ruleName".into(), json!(rule_name));
+			}
+			if !hostnames.is_empty() {
+				route.insert("hostnames".into(), json!(hostnames));
+			}
+			if !tcp {
+				self.http_rule(&ctx, namespace, rule, &mut route);
+			}
+			let backends = array_at(rule, "/backendRefs")
+				.iter()
+				.filter_map(|b| self.backend_ref(&ctx, namespace, b, tcp))
+				.collect::<Vec<_>>();
+			if !backends.is_empty() {
+				route.insert("backends".into(), json!(backends));
+			}
+			converted.push(Value::Object(route));
+		}
+
+		let mut attached = false;
+		for parent in array_at(doc, "/spec/parentRefs") {
+			let gw = format!(
+				"{}/{}",
+				str_at(parent, "/namespace").unwrap_or(namespace),
+				str_at(parent, "/name").unwrap_or_default()
+			);
+			let section = str_at(parent, "/sectionName");
+			let port = u16_at(parent, "/port");
+			for l in self.binds.values_mut().flatten() {
+				if l.gateway.as_deref() != Some(gw.as_str())
+					|| section.is_some_and(|s| s != l.name)
+					|| port.is_some_and(|p| p != l.port)
+					|| l.is_tcp() != tcp
+				{
+					continue;
+				}
+				l.routes().extend(converted.iter().cloned());
+				attached = true;
+			}
+		}
+		if !attached {
+			self.warn(format!(
+				"{ctx}: no matching Gateway listener for its parentRefs"
+			));
+		}
+	}
+
+	fn http_rule(
+		&mut self,
+		ctx: &str,
+		namespace: &str,
+		rule: &Value,
+		route: &mut Map<String, Value>,
+	) {
+		let matches = array_at(rule, "/matches")
+			.iter()
+			.map(http_match)
+			.collect::<Vec<_>>();
+		if !matches.is_empty() {
+			route.insert("matches".into(), json!(matches));
+		}
+
+		let mut policies = Map::new();
+		for f in array_at(rule, "/filters") {
+			let ty = str_at(f, "/type").unwrap_or_default();
+			match ty {
+				"RequestHeaderModifier" => {
+					policies.insert(
+						"requestHeaderModifier".into(),
+						header_modifier(f.get("requestHeaderModifier")),
+					);
+				},
+				"ResponseHeaderModifier" => {
+					policies.insert(
+						"responseHeaderModifier".into(),
+						header_modifier(f.get("responseHeaderModifier")),
+					);
+				},
+				"RequestRedirect" => {
+					let r = f.get("requestRedirect").unwrap_or(&Value::Null);
+					let mut redirect = Map::new();
+					if let Some(scheme) = str_at(r, "/scheme") {
+						redirect.insert("scheme".into(), json!(scheme));
+					}
+					if let Some(authority) = authority(str_at(r, "/hostname"), u16_at(r, "/port")) {
+						redirect.insert("authority".into(), authority);
+					}
+					if let Some(path) = path_modifier(r.get("path")) {
+						redirect.insert("path".into(), path);
+					}
+					if let Some(status) = r.get("statusCode") {
+						redirect.insert("status".into(), status.clone());
+					}
+					policies.insert("requestRedirect".into(), Value::Object(redirect));
+				},
+				"URLRewrite" => {
+					let r = f.get("urlRewrite").unwrap_or(&Value::Null);
+					let mut rewrite = Map::new();
+					if let Some(authority) = authority(str_at(r, "/hostname"), None) {
+						rewrite.insert("authority".into(), authority);
+					}
+					if let Some(path) = path_modifier(r.get("path")) {
+						rewrite.insert("path".into(), path);
+					}
+					policies.insert("urlRewrite".into(), Value::Object(rewrite));
+				},
+				"RequestMirror" => {
+					let m = f.get("requestMirror").unwrap_or(&Value::Null);
+					let percentage = match (m.get("percent"), m.get("fraction")) {
+						(Some(p), _) => p.as_f64().unwrap_or(100.0) / 100.0,
+						(_, Some(f)) => {
+							f.get("numerator").and_then(Value::as_f64).unwrap_or(0.0)
+								/ f
+									.get("denominator")
+									.and_then(Value::as_f64)
+									.unwrap_or(100.0)
+						},
+						_ => 1.0,
+					};
+					let backend = m
+						.get("backendRef")
+						.and_then(|b| self.backend_ref(ctx, namespace, b, true));
+					if let Some(backend) = backend {
+						policies.insert(
+							"requestMirror".into(),
+							json!({ "backend": backend["backend"], "percentage": percentage }),
+						);
+					}
+				},
+				_ => self.warn(format!("{ctx}: filter {ty} is not supported")),
+			}
+		}
+		if let Some(timeouts) = rule.get("timeouts") {
+			let mut timeout = Map::new();
+			if let Some(t) = str_at(timeouts, "/request") {
+				timeout.insert("requestTimeout".into(), json!(t));
+			}
+			if let Some(t) = str_at(timeouts, "/backendRequest") {
+				timeout.insert("backendRequestTimeout".into(), json!(t));
+			}
+			policies.insert("timeout".into(), Value::Object(timeout));
+		}
+		if rule.get("retry").is_some() {
+			self.warn(format!("{ctx}: retry is not supported"));
+		}
+		if rule.get("sessionPersistence").is_some() {
+			self.warn(format!("{ctx}: sessionPersistence is not supported"));
+		}
+		if !policies.is_empty() {
+			route.insert("policies".into(), Value::Object(policies));
+		}
+	}
+
+	/// backend_ref converts a reference to a Service into a backend addressed by its cluster DNS name. `tcp` selects
+	/// the TCP route backend format, which nests the target under `backend`.
+	fn backend_ref(&mut self, ctx: &str, namespace: &str, b: &Value, tcp: bool) -> Option<Value> {
+		let kind = str_at(b, "/kind").unwrap_or("Service");
+		let name = str_at(b, "/name").unwrap_or_default();
+		if kind != "Service" || !matches!(str_at(b, "/group"), None | Some("") | Some("core")) {
+			self.warn(format!("{ctx}: backendRef {kind} {name} is not supported"));
+			return None;
+		}
+		let Some(port) = u16_at(b, "/port") else {
+			self.warn(format!("{ctx}: backendRef {name} requires a port"));
+			return None;
+		};
+		if b.get("filters").is_some() {
+			self.warn(format!(
+				"{ctx}: filters on backendRef {name} are not supported"
+			));
+		}
+		let ns = str_at(b, "/namespace").unwrap_or(namespace);
+		let host = format!("{name}.{ns}.svc.cluster.local:{port}");
+		let weight = b.get("weight").and_then(Value::as_u64).unwrap_or(1);
+		Some(backend(host, weight, tcp))
+	}
+
+	fn envoy(&mut self, doc: &Value) {
+		if doc.get("dynamic_resources").is_some() {
+			self.warn("envoy: dynamic_resources are not supported".to_string());
+		}
+		let mut clusters = HashMap::new();
+		for c in array_at(doc, "/static_resources/clusters") {
+			let name = str_at(c, "/name").unwrap_or_default();
+			if c.get("transport_socket").is_some() {
+				self.warn(format!(
+					"envoy cluster {name}: upstream TLS is not converted; add a backendTLS policy"
+				));
+			}
+			let mut endpoints = vec![];
+			for e in array_at(c, "/load_assignment/endpoints") {
+				for lb in array_at(e, "/lb_endpoints") {
+					if let Some(addr) = socket_address(lb.pointer("/endpoint/address")) {
+						endpoints.push(addr);
+					}
+				}
+			}
+			if endpoints.is_empty() {
+				self.warn(format!(
+					"envoy cluster {name}: only clusters with static load_assignment endpoints are supported"
+				));
+			}
+			clusters.insert(name.to_string(), endpoints);
+		}
+
+		for (idx, l) in array_at(doc, "/static_resources/listeners")
+			.iter()
+			.enumerate()
+		{
+			let name = str_at(l, "/name")
+				.map(ToString::to_string)
+				.unwrap_or_else(|| format!("listener{idx}"));
+			let ctx = format!("envoy listener {name}");
+			let Some(port) = l
+				.pointer("/address/socket_address/port_value")
+				.and_then(Value::as_u64)
+				.and_then(|p| u16::try_from(p).ok())
+			else {
+				self.warn(format!(
+					"{ctx}: only socket_address listeners are supported"
+				));
+				continue;
+			};
+			let chains = array_at(l, "/filter_chains");
+			if chains.len() > 1 {
+				self.warn(format!("{ctx}: only the first filter chain is converted"));
+			}
+			let Some(chain) = chains.first() else {
+				continue;
+			};
+			if chain.get("transport_socket").is_some() {
+				self.warn(format!(
+					"{ctx}: downstream TLS is not converted; set 'tls' on the listener"
+				));
+			}
+			let mut config = Map::new();
+			config.insert("name".into(), json!(name));
+			for f in array_at(chain, "/filters") {
+				let filter = str_at(f, "/name").unwrap_or_default();
+				let typed = f.get("typed_config").unwrap_or(&Value::Null);
+				match filter {
+					"envoy.filters.network.http_connection_manager" | "envoy.http_connection_manager" => {
+						config.insert("protocol".into(), json!("HTTP"));
+						let routes = self.envoy_http(&ctx, typed, &clusters);
+						config.insert("routes".into(), json!(routes));
+					},
+					"envoy.filters.network.tcp_proxy" | "envoy.tcp_proxy" => {
+						config.insert("protocol".into(), json!("TCP"));
+						let backends = self.envoy_backends(&ctx, typed, &clusters, true);
+						config.insert("tcpRoutes".into(), json!([{ "backends": backends }]));
+					},
+					_ => self.warn(format!("{ctx}: network filter {filter} is not supported")),
+				}
+			}
+			if config.get("protocol").is_none() {
+				self.warn(format!("{ctx}: no supported network filter"));
+				continue;
+			}
+			self.add_listener(Listener {
+				gateway: None,
+				name,
+				port,
+				config,
+			});
+		}
+	}
+
+	fn envoy_http(
+		&mut self,
+		ctx: &str,
+		hcm: &Value,
+		clusters: &HashMap<String, Vec<String>>,
+	) -> Vec<Value> {
+		for f in array_at(hcm, "/http_filters") {
+			let name = str_at(f, "/name").unwrap_or_default();
+			if !matches!(name, "envoy.filters.http.router" | "envoy.router") {
+				self.warn(format!("{ctx}: http filter {name} is not supported"));
+			}
+		}
+		if hcm.get("rds").is_some() {
+			self.warn(format!(
+				"{ctx}: rds is not supported; use an inline route_config"
+			));
+		}
+		let mut routes = vec![];
+		for vh in array_at(hcm, "/route_config/virtual_hosts") {
+			let vh_name = str_at(vh, "/name").unwrap_or_default();
+			let hostnames = array_at(vh, "/domains")
+				.iter()
+				.filter_map(Value::as_str)
+				.filter(|d| *d != "*")
+				.collect::<Vec<_>>();
+			for (idx, r) in array_at(vh, "/routes").iter().enumerate() {
+				let ctx = format!("{ctx} virtual host {vh_name} route {idx}");
+				let mut route = Map::new();
+				route.insert(
+					"name".into(),
+					json!(
+						str_at(r, "/name")
+							.map(ToString::to_string)
+							.unwrap_or_else(|| format!("{vh_name}-{idx}"))
+					),
+				);
+				if !hostnames.is_empty() {
+					route.insert("hostnames".into(), json!(hostnames));
+				}
+				route.insert("matches".into(), json!([self.envoy_match(&ctx, r)]));
+
+				let mut policies = Map::new();
+				if let Some(m) = envoy_header_modifier(r, "request") {
+					policies.insert("requestHeaderModifier".into(), m);
+				}
+				if let Some(m) = envoy_header_modifier(r, "response") {
+					policies.insert("responseHeaderModifier".into(), m);
+				}
+				if let Some(action) = r.get("route") {
+					let backends = self.envoy_backends(&ctx, action, clusters, false);
+					route.insert("backends".into(), json!(backends));
+					let mut rewrite = Map::new();
+					if let Some(p) = str_at(action, "/prefix_rewrite") {
+						rewrite.insert("path".into(), json!({ "prefix": p }));
+					}
+					if let Some(h) = str_at(action, "/host_rewrite_literal") {
+						rewrite.insert("authority".into(), json!({ "full": h }));
+					}
+					if !rewrite.is_empty() {
+						policies.insert("urlRewrite".into(), Value::Object(rewrite));
+					}
+					if let Some(t) = str_at(action, "/timeout").filter(|t| *t != "0s") {
+						policies.insert("timeout".into(), json!({ "requestTimeout": t }));
+					}
+					if action.get("retry_policy").is_some() {
+						self.warn(format!("{ctx}: retry_policy is not supported"));
+					}
+				} else if let Some(r) = r.get("redirect") {
+					policies.insert("requestRedirect".into(), envoy_redirect(r));
+				} else if let Some(d) = r.get("direct_response") {
+					policies.insert(
+						"directResponse".into(),
+						json!({
+							"status": d.get("status").cloned().unwrap_or(json!(200)),
+							"body": str_at(d, "/body/inline_string").unwrap_or_default(),
+						}),
+					);
+				} else {
+					self.warn(format!("{ctx}: route action is not supported"));
+				}
+				if !policies.is_empty() {
+					route.insert("policies".into(), Value::Object(policies));
+				}
+				routes.push(Value::Object(route));
+			}
+		}
+		routes
+	}
+
+	fn envoy_match(&mut self, ctx: &str, r: &Value) -> Value {
+		let mut out = Map::new();
+		let path = if let Some(p) = str_at(r, "/match/path") {
+			json!({ "exact": p })
+		} else if let Some(re) = str_at(r, "/match/safe_regex/regex") {
+			json!({ "regex": [re, re.len()] })
+		} else {
+			json!({ "pathPrefix": str_at(r, "/match/prefix").unwrap_or("/") })
+		};
+		out.insert("path".into(), path);
+		let mut headers = vec![];
+		for h in array_at(r, "/match/headers") {
+			let name = str_at(h, "/name").unwrap_or_default();
+			let value =
+				if let Some(v) = str_at(h, "/string_match/exact").or_else(|| str_at(h, "/exact_match")) {
+					json!({ "exact": v })
+				} else if let Some(v) = str_at(h, "/string_match/safe_regex/regex")
+					.or_else(|| str_at(h, "/safe_regex_match/regex"))
+				{
+					json!({ "regex": v })
+				} else {
+					self.warn(format!("{ctx}: header match on {name} is not supported"));
+					continue;
+				};
+			if name == ":method"
+				&& let Some(m) = value.get("exact")
+			{
+				out.insert("method".into(), m.clone());
+				continue;
+			}
+			headers.push(json!({ "name": name, "value": value }));
+		}
+		if !headers.is_empty() {
+			out.insert("headers".into(), json!(headers));
+		}
+		if r.pointer("/match/query_parameters").is_some() {
+			self.warn(format!("{ctx}: query_parameters matches are not supported"));
+		}
+		Value::Object(out)
+	}
+
+	/// envoy_backends converts the cluster, or weighted clusters, of a route or tcp_proxy into backends, with one
+	/// backend per cluster endpoint.
+	fn envoy_backends(
+		&mut self,
+		ctx: &str,
+		action: &Value,
+		clusters: &HashMap<String, Vec<String>>,
+		tcp: bool,
+	) -> Vec<Value> {
+		let targets = if let Some(c) = str_at(action, "/cluster") {
+			vec![(c, 1)]
+		} else {
+			array_at(action, "/weighted_clusters/clusters")
+				.iter()
+				.filter_map(|c| {
+					Some((
+						str_at(c, "/name")?,
+						c.get("weight").and_then(Value::as_u64).unwrap_or(1),
+					))
+				})
+				.collect()
+		};
+		if targets.is_empty() {
+			self.warn(format!("{ctx}: no cluster"));
+		}
+		let mut backends = vec![];
+		for (cluster, weight) in targets {
+			let Some(endpoints) = clusters.get(cluster) else {
+				self.warn(format!("{ctx}: cluster {cluster} not found"));
+				continue;
+			};
+			for e in endpoints {
+				backends.push(backend(e.clone(), weight, tcp));
+			}
+		}
+		backends
+	}
+}
+
+fn backend(host: String, weight: u64, tcp: bool) -> Value {
+	if tcp {
+		json!({ "weight": weight, "backend": { "host": host } })
+	} else {
+		json!({ "weight": weight, "host": host })
+	}
+}
+
+fn http_match(m: &Value) -> Value {
+	let mut out = Map::new();
+	let path = str_at(m, "/path/value").unwrap_or("/");
+	let path = match str_at(m, "/path/type").unwrap_or("PathPrefix") {
+		"Exact" => json!({ "exact": path }),
+		"RegularExpression" => json!({ "regex": [path, path.len()] }),
+		_ => json!({ "pathPrefix": path }),
+	};
+	out.insert("path".into(), path);
+	let headers = array_at(m, "/headers")
+		.iter()
+		.map(value_match)
+		.collect::<Vec<_>>();
+	if !headers.is_empty() {
+		out.insert("headers".into(), json!(headers));
+	}
+	let query = array_at(m, "/queryParams")
+		.iter()
+		.map(value_match)
+		.collect::<Vec<_>>();
+	if !query.is_empty() {
+		out.insert("query".into(), json!(query));
+	}
+	if let Some(method) = str_at(m, "/method") {
+		out.insert("method".into(), json!(method));
+	}
+	Value::Object(out)
+}
+
+/// value_match converts a Gateway API header or query parameter match.
+fn value_match(m: &Value) -> Value {
+	let value = str_at(m, "/value").unwrap_or_default();
+	let value = match str_at(m, "/type").unwrap_or("Exact") {
+		"RegularExpression" => json!({ "regex": value }),
+		_ => json!({ "exact": value }),
+	};
+	json!({ "name": str_at(m, "/name").unwrap_or_default(), "value": value })
+}
+
+fn header_modifier(m: Option<&Value>) -> Value {
+	let m = m.unwrap_or(&Value::Null);
+	let pairs = |key: &str| {
+		array_at(m, key)
+			.iter()
+			.map(|h| {
+				(
+					str_at(h, "/name").unwrap_or_default().to_string(),
+					json!(str_at(h, "/value").unwrap_or_default()),
+				)
+			})
+			.collect::<Map<_, _>>()
+	};
+	json!({
+		"add": pairs("/add"),
+		"set": pairs("/set"),
+		"remove": array_at(m, "/remove"),
+	})
+}
+
+fn authority(hostname: Option<&str>, port: Option<u16>) -> Option<Value> {
+	match (hostname, port) {
+		(Some(h), Some(p)) => Some(json!({ "full": format!("{h}:{p}") })),
+		(Some(h), None) => Some(json!({ "host": h })),
+		(None, Some(p)) => Some(json!({ "port": p })),
+		(None, None) => None,
+	}
+}
+
+fn path_modifier(p: Option<&Value>) -> Option<Value> {
+	let p = p?;
+	match str_at(p, "/type")? {
+		"ReplaceFullPath" => Some(json!({ "full": str_at(p, "/replaceFullPath")? })),
+		"ReplacePrefixMatch" => Some(json!({ "prefix": str_at(p, "/replacePrefixMatch")? })),
+		_ => None,
+	}
+}
+
+fn envoy_header_modifier(r: &Value, direction: &str) -> Option<Value> {
+	let mut add = Map::new();
+	let mut set = Map::new();
+	for h in array_at(r, &format!("/{direction}_headers_to_add")) {
+		let (Some(k), Some(v)) = (str_at(h, "/header/key"), str_at(h, "/header/value")) else {
+			continue;
+		};
+		let append = match str_at(h, "/append_action") {
+			Some(action) => action == "APPEND_IF_EXISTS_OR_ADD",
+			None => h.get("append").and_then(Value::as_bool).unwrap_or(true),
+		};
+		if append {
+			add.insert(k.to_string(), json!(v));
+		} else {
+			set.insert(k.to_string(), json!(v));
+		}
+	}
+	let remove = array_at(r, &format!("/{direction}_headers_to_remove"));
+	if add.is_empty() && set.is_empty() && remove.is_empty() {
+		return None;
+	}
+	Some(json!({ "add": add, "set": set, "remove": remove }))
+}
+
+fn envoy_redirect(r: &Value) -> Value {
+	let mut redirect = Map::new();
+	if r.get("https_redirect").and_then(Value::as_bool) == Some(true) {
+		redirect.insert("scheme".into(), json!("https"));
+	} else if let Some(s) = str_at(r, "/scheme_redirect") {
+		redirect.insert("scheme".into(), json!(s));
+	}
+	let port = r
+		.get("port_redirect")
+		.and_then(Value::as_u64)
+		.and_then(|p| u16::try_from(p).ok());
+	if let Some(a) = authority(str_at(r, "/host_redirect"), port) {
+		redirect.insert("authority".into(), a);
+	}
+	if let Some(p) = str_at(r, "/path_redirect") {
+		redirect.insert("path".into(), json!({ "full": p }));
+	} else if let Some(p) = str_at(r, "/prefix_rewrite") {
+		redirect.insert("path".into(), json!({ "prefix": p }));
+	}
+	let status = match str_at(r, "/response_code") {
+		Some("FOUND") => 302,
+		Some("SEE_OTHER") => 303,
+		Some("TEMPORARY_REDIRECT") => 307,
+		Some("PERMANENT_REDIRECT") => 308,
+		_ => 301,
+	};
+	redirect.insert("status".into(), json!(status));
+	Value::Object(redirect)
+}
+
+fn socket_address(addr: Option<&Value>) -> Option<String> {
+	let addr = addr?.get("socket_address")?;
+	let host = str_at(addr, "/address")?;
+	let port = addr.get("port_value")?.as_u64()?;
+	Some(format!("{host}:{port}"))
+}
+
+fn str_at<'a>(v: &'a Value, pointer: &str) -> Option<&'a str> {
+	v.pointer(pointer).and_then(Value::as_str)
+}
+
+fn u16_at(v: &Value, pointer: &str) -> Option<u16> {
+	v.pointer(pointer)
+		.and_then(Value::as_u64)
+		.and_then(|p| u16::try_from(p).ok())
+}
+
+fn array_at<'a>(v: &'a Value, pointer: &str) -> &'a [Value] {
+	v.pointer(pointer)
+		.and_then(Value::as_array)
+		.map(Vec::as_slice)
+		.unwrap_or_default()
+}
+
+fn namespace(doc: &Value) -> &str {
+	str_at(doc, "/metadata/namespace").unwrap_or("default")
+}
+
+fn resource_name(doc: &Value) -> String {
+	format!(
+		"{}/{}",
+		namespace(doc),
+		str_at(doc, "/metadata/name").unwrap_or_default()
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::local::LocalConfig;
+
+	fn convert_valid(input: &str) -> Conversion {
+		let c = convert(input).unwrap();
+		// The output must be a valid local configuration.
+		serde_json::from_value::<LocalConfig>(c.config.clone()).unwrap();
+		c
+	}
+
+	#[test]
+	fn gateway_api() {
+		let c = convert_valid(
+			r#"
+apiVersion: gateway.networking.k8s.io/v1
+kind: HTTPRoute
+metadata:
+  name: agents
+  namespace: ai
+spec:
+  parentRefs:
+  - name: gw
+    namespace: infra
+    sectionName: http
+  hostnames: [agents.example.com]
+  rules:
+  - matches:
+    - path:
+        type: PathPrefix
+        value: /a2a
+      headers:
+      - name: x-tenant
+        value: acme
+    filters:
+    - type: RequestHeaderModifier
+      requestHeaderModifier:
+        set:
+        - name: x-gateway
+          value: agentgateway
+    - type: ExtensionRef
+      extensionRef:
+        name: custom
+    backendRefs:
+    - name: agent
+      port: 8080
+    timeouts:
+      request: 30s
+---
+apiVersion: gateway.networking.k8s.io/v1
+kind: Gateway
+metadata:
+  name: gw
+  namespace: infra
+spec:
+  listeners:
+  - name: http
+    port: 80
+    protocol: HTTP
+  - name: https
+    port: 443
+    protocol: HTTPS
+    tls:
+      certificateRefs:
+      - name: gw-cert
+"#,
+		);
+		let binds = &c.config["binds"];
+		assert_eq!(binds[0]["port"], 80);
+		let route = &binds[0]["listeners"][0]["routes"][0];
+		assert_eq!(route["hostnames"], json!(["agents.example.com"]));
+		assert_eq!(route["matches"][0]["path"], json!({"pathPrefix": "/a2a"}));
+		assert_eq!(
+			route["backends"],
+			json!([{"weight": 1, "host": "agent.ai.svc.cluster.local:8080"}])
+		);
+		assert_eq!(route["policies"]["timeout"]["requestTimeout"], "30s");
+		// The route only attaches to the listener named by sectionName.
+		assert_eq!(binds[1]["listeners"][0]["routes"], json!([]));
+		assert_eq!(binds[1]["listeners"][0]["tls"]["cert"], "gw-cert/tls.crt");
+		assert!(
+			c.warnings.iter().any(|w| w.contains("filter ExtensionRef")),
+			"{:?}",
+			c.warnings
+		);
+	}
+
+	#[test]
+	fn envoy_bootstrap() {
+		let c = convert_valid(
+			r#"
+static_resources:
+  listeners:
+  - name: ingress
+    address:
+      socket_address: { address: 0.0.0.0, port_value: 10000 }
+    filter_chains:
+    - filters:
+      - name: envoy.filters.network.http_connection_manager
+        typed_config:
+          "@type": type.googleapis.com/envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager
+          http_filters:
+          - name: envoy.filters.http.lua
+          - name: envoy.filters.http.router
+          route_config:
+            virtual_hosts:
+            - name: mcp
+              domains: ["*"]
+              routes:
+              - match: { prefix: /mcp }
+                route:
+                  cluster: mcp
+                  timeout: 10s
+              - match: { path: /old }
+                redirect: { path_redirect: /new, response_code: FOUND }
+  - name: db
+    address:
+      socket_address: { address: 0.0.0.0, port_value: 5432 }
+    filter_chains:
+    - filters:
+      - name: envoy.filters.network.tcp_proxy
+        typed_config:
+          cluster: db
+  clusters:
+  - name: mcp
+    load_assignment:
+      endpoints:
+      - lb_endpoints:
+        - endpoint:
+            address:
+              socket_address: { address: mcp.internal, port_value: 3000 }
+  - name: db
+    load_assignment:
+      endpoints:
+      - lb_endpoints:
+        - endpoint:
+            address:
+              socket_address: { address: 10.0.0.5, port_value: 5432 }
+"#,
+		);
+		let binds = &c.config["binds"];
+		let routes = &binds[0]["listeners"][0]["routes"];
+		assert_eq!(
+			routes[0]["matches"][0]["path"],
+			json!({"pathPrefix": "/mcp"})
+		);
+		assert_eq!(routes[0]["backends"][0]["host"], "mcp.internal:3000");
+		assert_eq!(routes[1]["policies"]["requestRedirect"]["status"], 302);
+		let tcp = &binds[1]["listeners"][0]["tcpRoutes"][0];
+		assert_eq!(tcp["backends"][0]["backend"]["host"], "10.0.0.5:5432");
+		assert_eq!(
+			c.warnings,
+			vec!["envoy listener ingress: http filter envoy.filters.http.lua is not supported"]
+		);
+	}
+
+	#[test]
+	fn unattached_route() {
+		let c = convert_valid(
+			r#"
+apiVersion: gateway.networking.k8s.io/v1
+kind: HTTPRoute
+metadata:
+  name: orphan
+spec:
+  parentRefs:
+  - name: missing
+"#,
+		);
+		assert_eq!(c.config, json!({"binds": []}));
+		assert_eq!(
+			c.warnings,
+			vec!["HTTPRoute default/orphan: no matching Gateway listener for its parentRefs"]
+		);
+	}
+}
@@ -23,6 +23,13 @@ impl HTTPAuthorizationSet {
 		Ok(())
 	}
 
+	/// monitored_denies reports whether rule sets in monitor mode would deny a request that was allowed.
+	pub fn monitored_denies(&self, exec: &cel::Executor<'_>) -> bool {
+		self
+			.0
+			.monitored_denies(|| Ok(agent_core::bow::OwnedOrBorrowed::Borrowed(exec)))
+	}
+
 	pub fn register(&self, cel: &mut ContextBuilder) {
 		self.0.register(cel);
 	}
@@ -33,6 +40,10 @@ pub struct RuleSet {
 	#[serde(serialize_with = "se_policies", deserialize_with = "de_policies")]
 	#[cfg_attr(feature = "schema", schemars(with = "Vec<String>"))]
 	pub rules: PolicySet,
+	/// In monitor mode, the rules do not affect the decision; requests they would deny are only logged and
+	/// counted.
+	#[serde(default, skip_serializing_if = "is_default")]
+	pub mode: http::PolicyMode,
 }
 
 impl RuleSet {
@@ -140,12 +151,34 @@ impl RuleSets {
 			rule_set.register(ctx);
 		}
 	}
+	/// validate evaluates the enforced rule sets. Rule sets in monitor mode are ignored.
 	pub fn validate<'a>(
 		&self,
 		exec: impl FnOnce() -> anyhow::Result<OwnedOrBorrowed<'a, Executor<'a>>>,
 	) -> bool {
-		let rule_sets = &self.0;
-		let has_rules = rule_sets.iter().any(|r| r.has_rules());
+		Self::validate_sets(self.enforced(), exec)
+	}
+
+	/// monitored_denies reports whether including the rule sets in monitor mode would deny a request.
+	pub fn monitored_denies<'a>(
+		&self,
+		exec: impl FnOnce() -> anyhow::Result<OwnedOrBorrowed<'a, Executor<'a>>>,
+	) -> bool {
+		if self.0.iter().all(|r| r.mode.is_enforce()) {
+			return false;
+		}
+		!Self::validate_sets(self.0.iter(), exec)
+	}
+
+	fn enforced(&self) -> impl Iterator<Item = &RuleSet> + Clone {
+		self.0.iter().filter(|r| r.mode.is_enforce())
+	}
+
+	fn validate_sets<'a, 'b>(
+		mut rule_sets: impl Iterator<Item = &'b RuleSet> + Clone,
+		exec: impl FnOnce() -> anyhow::Result<OwnedOrBorrowed<'a, Executor<'a>>>,
+	) -> bool {
+		let has_rules = rule_sets.clone().any(|r| r.has_rules());
 		// If there are no rule sets, everyone has access
 		if !has_rules {
 			return true;
@@ -156,11 +189,11 @@ impl RuleSets {
 		};
 		let exec = exec.as_ref();
 		// If there are any DENY, deny
-		if rule_sets.iter().any(|r| r.denies(exec)) {
+		if rule_sets.clone().any(|r| r.denies(exec)) {
 			return false;
 		}
 		// If there are any ALLOW, allow
-		if rule_sets.iter().any(|r| r.allows(exec)) {
+		if rule_sets.any(|r| r.allows(exec)) {
 			return true;
 		}
 		// Else deny
@@ -174,7 +207,10 @@ impl RuleSets {
 
 impl RuleSet {
 	pub fn new(rules: PolicySet) -> Self {
-		Self { rules }
+		Self {
+			rules,
+			mode: Default::default(),
+		}
 	}
 
 	pub fn has_rules(&self) -> bool {
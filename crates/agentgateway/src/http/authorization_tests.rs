@@ -8,7 +8,9 @@ use serde_json::{Map, Value};
 use super::*;
 use crate::http::authorization::PolicySet;
 use crate::http::jwt::Claims;
-use crate::mcp::rbac::{ResourceId, ResourceType};
+use crate::mcp::rbac::{McpAuthorizationSet, ResourceId, ResourceType};
+use crate::mcp::sse::MCPInfo;
+use crate::telemetry::log::AsyncLog;
 
 fn create_policy_set(policies: Vec<&str>) -> PolicySet {
 	let mut policy_set = PolicySet::default();
@@ -159,6 +161,56 @@ fn test_rbac_check_array_contains_match() {
 	assert_matches!(rs.validate(|| Ok(OwnedOrBorrowed::Borrowed(&exec))), true);
 }
 
+#[test]
+fn test_rbac_monitor_mode() {
+	let allow = RuleSet::new(create_policy_set(vec![r#"jwt.sub == "1234567890""#]));
+	let monitored: RuleSet = serde_json::from_value(serde_json::json!({
+		"rules": [{"deny": r#"jwt.sub == "1234567890""#}],
+		"mode": "monitor",
+	}))
+	.unwrap();
+	let mut ctx = ContextBuilder::new();
+	let rs = RuleSets::from(vec![allow.clone(), monitored]);
+	rs.register(&mut ctx);
+	ctx.with_jwt(&Claims {
+		inner: Map::from_iter([("sub".to_string(), "1234567890".to_string().into())]),
+		jwt: SecretString::new("".into()),
+	});
+	let exec = ctx.build().unwrap();
+
+	// The monitored deny rule does not affect the decision, but is reported
+	assert_matches!(rs.validate(|| Ok(OwnedOrBorrowed::Borrowed(&exec))), true);
+	assert!(rs.monitored_denies(|| Ok(OwnedOrBorrowed::Borrowed(&exec))));
+
+	let rs = RuleSets::from(vec![allow]);
+	assert!(!rs.monitored_denies(|| Ok(OwnedOrBorrowed::Borrowed(&exec))));
+}
+
+#[test]
+fn test_mcp_monitor_mode_recorded() {
+	let monitored: RuleSet = serde_json::from_value(serde_json::json!({
+		"rules": [{"deny": r#"mcp.tool.name == "increment""#}],
+		"mode": "monitor",
+	}))
+	.unwrap();
+	let rs = RuleSets::from(vec![monitored]);
+	let mut ctx = ContextBuilder::new();
+	rs.register(&mut ctx);
+	let set = McpAuthorizationSet::new(rs);
+	let log = AsyncLog::default();
+	log.store(Some(MCPInfo::default()));
+
+	let tool =
+		|name: &str| ResourceType::Tool(ResourceId::new("server".to_string(), name.to_string()));
+	assert!(set.validate(&tool("decrement"), &ctx, &log));
+	assert!(!log.take().unwrap().monitored_denial);
+
+	// The monitored deny rule does not affect the decision, but is recorded for the request log
+	log.store(Some(MCPInfo::default()));
+	assert!(set.validate(&tool("increment"), &ctx, &log));
+	assert!(log.take().unwrap().monitored_denial);
+}
+
 #[divan::bench]
 fn bench(b: Bencher) {
 	let policies = vec![r#"mcp.tool.name == "increment" && jwt.user.role == "admin""#];
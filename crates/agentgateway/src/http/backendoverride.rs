@@ -0,0 +1,120 @@
+use crate::cel::{Executor, Expression};
+use crate::http::{HeaderName, Request};
+use crate::types::agent::RouteBackendReference;
+use crate::*;
+
+/// BackendOverride lets trusted callers send a single request to a backend other than the route's, by naming it in
+/// a header. This is meant for one-off debugging, such as trying a request against a staging model, without
+/// changing the configuration. Only the backends listed in the policy can be selected.
+#[apply(schema_ser!)]
+pub struct BackendOverride {
+	#[serde(serialize_with = "ser_display")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub header: HeaderName,
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub condition: Arc<Expression>,
+	pub backends: IndexMap<Strng, RouteBackendReference>,
+}
+
+/// Override is the outcome of a request naming a backend in the override header.
+#[derive(Debug)]
+pub enum Override<'a> {
+	/// The request is allowed to override the backend, and named an allowed backend.
+	Allowed(&'a RouteBackendReference),
+	/// The request did not match the condition, so the header is ignored.
+	Untrusted,
+	/// The request matched the condition, but named a backend that is not allowed.
+	NotAllowed,
+}
+
+impl BackendOverride {
+	pub fn expressions(&self) -> impl Iterator<Item = &Expression> {
+		std::iter::once(self.condition.as_ref())
+	}
+
+	/// requested returns the backend named in the override header, if any. The header is removed either way, so it
+	/// never reaches a backend.
+	pub fn requested(&self, req: &mut Request) -> Option<Strng> {
+		let name = req.headers_mut().remove(&self.header)?;
+		Some(strng::new(String::from_utf8_lossy(name.as_bytes()).trim()))
+	}
+
+	/// select checks whether the request may send itself to the named backend.
+	pub fn select(&self, name: &str, exec: &Executor) -> Override<'_> {
+		if !exec.eval_bool(&self.condition) {
+			return Override::Untrusted;
+		}
+		match self.backends.get(name) {
+			Some(b) => Override::Allowed(b),
+			None => Override::NotAllowed,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::agent::BackendReference;
+
+	fn policy() -> BackendOverride {
+		BackendOverride {
+			header: HeaderName::from_static("x-backend-override"),
+			condition: Arc::new(Expression::new(r#"request.headers["x-team"] == "eval""#).unwrap()),
+			backends: IndexMap::from([(
+				strng::literal!("staging"),
+				RouteBackendReference {
+					weight: 1,
+					priority: 0,
+					backend: BackendReference::Backend(strng::literal!("staging-model")),
+					filters: vec![],
+				},
+			)]),
+		}
+	}
+
+	fn request(team: &str, target: &str) -> Request {
+		::http::Request::builder()
+			.header("x-team", team)
+			.header("x-backend-override", target)
+			.body(http::Body::empty())
+			.unwrap()
+	}
+
+	fn select(p: &BackendOverride, req: &mut Request) -> Option<Result<Strng, &'static str>> {
+		let name = p.requested(req)?;
+		let mut ctx = cel::ContextBuilder::new();
+		for e in p.expressions() {
+			ctx.register_expression(e);
+		}
+		ctx.with_request(req);
+		let exec = ctx.build().unwrap();
+		Some(match p.select(&name, &exec) {
+			Override::Allowed(b) => Ok(b.backend.name()),
+			Override::Untrusted => Err("untrusted"),
+			Override::NotAllowed => Err("not allowed"),
+		})
+	}
+
+	#[test]
+	fn selects_allowed_backends() {
+		let p = policy();
+
+		let mut req = request("eval", " staging ");
+		assert_eq!(
+			select(&p, &mut req),
+			Some(Ok(strng::literal!("staging-model")))
+		);
+		assert!(!req.headers().contains_key("x-backend-override"));
+
+		let mut req = request("eval", "prod-db");
+		assert_eq!(select(&p, &mut req), Some(Err("not allowed")));
+
+		let mut req = request("other", "staging");
+		assert_eq!(select(&p, &mut req), Some(Err("untrusted")));
+		assert!(!req.headers().contains_key("x-backend-override"));
+
+		let mut req = request("eval", "staging");
+		req.headers_mut().remove("x-backend-override");
+		assert_eq!(select(&p, &mut req), None);
+	}
+}
@@ -6,19 +6,37 @@ use once_cell::sync::Lazy;
 use rustls::ClientConfig;
 use serde::Serializer;
 
+use crate::client::Client;
+use crate::http::revocation::{Revocation, RevocationConfig, RevocationVerifier};
 use crate::transport;
 use crate::transport::tls;
 use crate::types::agent::{parse_cert, parse_key};
+use crate::*;
 
-pub static SYSTEM_TRUST: Lazy<BackendTLS> =
-	Lazy::new(|| LocalBackendTLS::default().try_into().unwrap());
+pub static SYSTEM_TRUST: Lazy<BackendTLS> = Lazy::new(|| {
+	ResolvedBackendTLS {
+		cert: None,
+		key: None,
+		root: None,
+		insecure: false,
+		insecure_host: false,
+		revocation: None,
+		bundles: vec![],
+		system_trust: None,
+	}
+	.try_into()
+	.unwrap()
+});
 pub static INSECURE_TRUST: Lazy<BackendTLS> = Lazy::new(|| {
-	LocalBackendTLS {
+	ResolvedBackendTLS {
 		cert: None,
 		key: None,
 		root: None,
 		insecure: true,
 		insecure_host: false,
+		revocation: None,
+		bundles: vec![],
+		system_trust: None,
 	}
 	.try_into()
 	.unwrap()
@@ -67,23 +85,88 @@ pub struct LocalBackendTLS {
 	insecure: bool,
 	#[serde(default)]
 	insecure_host: bool,
+	/// Check whether the backend's certificate has been revoked, using CRLs and stapled OCSP responses.
+	#[serde(default)]
+	revocation: Option<RevocationConfig>,
+	/// Names of trust bundles, defined in the top-level 'trustBundles', whose CAs are trusted in addition to
+	/// 'root'.
+	#[serde(default)]
+	trust_bundles: Vec<Strng>,
+	/// Whether to trust the system root CAs. Defaults to true, unless 'root' or 'trustBundles' is set.
+	#[serde(default)]
+	system_trust: Option<bool>,
+}
+
+/// LocalTrustBundle is a named set of CA certificates, in PEM format.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LocalTrustBundle {
+	/// A file containing the certificates. The file is watched, and changes are applied without a restart.
+	File(PathBuf),
+	/// The certificates, inline.
+	Pem(String),
 }
+
+/// TrustBundles holds the resolved trust bundles of a configuration, by name.
+#[derive(Debug, Clone, Default)]
+pub struct TrustBundles {
+	bundles: HashMap<Strng, Arc<Vec<u8>>>,
+	/// Files the bundles were read from, which should be watched for changes.
+	pub files: Vec<PathBuf>,
+}
+
+impl TrustBundles {
+	pub fn load(bundles: IndexMap<Strng, LocalTrustBundle>) -> anyhow::Result<TrustBundles> {
+		let mut res = TrustBundles::default();
+		for (name, bundle) in bundles {
+			let pem = match bundle {
+				LocalTrustBundle::File(path) => {
+					let pem = fs_err::read(&path)?;
+					res.files.push(path);
+					pem
+				},
+				LocalTrustBundle::Pem(pem) => pem.into_bytes(),
+			};
+			res.bundles.insert(name, Arc::new(pem));
+		}
+		Ok(res)
+	}
+
+	fn get(&self, name: &Strng) -> anyhow::Result<Vec<u8>> {
+		self
+			.bundles
+			.get(name)
+			.map(|b| b.as_ref().clone())
+			.ok_or_else(|| anyhow::anyhow!("trust bundle '{name}' not found"))
+	}
+}
+
 pub struct ResolvedBackendTLS {
 	pub cert: Option<Vec<u8>>,
 	pub key: Option<Vec<u8>>,
 	pub root: Option<Vec<u8>>,
 	pub insecure: bool,
 	pub insecure_host: bool,
+	pub revocation: Option<(RevocationConfig, Client)>,
+	/// Additional CAs to trust, in PEM format.
+	pub bundles: Vec<Vec<u8>>,
+	/// Whether to trust the system roots. If unset, they are trusted unless 'root' or 'bundles' is set.
+	pub system_trust: Option<bool>,
 }
 
 impl ResolvedBackendTLS {
 	pub fn try_into(self) -> anyhow::Result<BackendTLS> {
 		let mut roots = rustls::RootCertStore::empty();
-		if let Some(root) = self.root {
-			let mut reader = std::io::BufReader::new(Cursor::new(root));
+		let system_trust = self
+			.system_trust
+			.unwrap_or(self.root.is_none() && self.bundles.is_empty());
+		for pem in self.root.into_iter().chain(self.bundles) {
+			let mut reader = std::io::BufReader::new(Cursor::new(pem));
 			let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
 			roots.add_parsable_certificates(certs);
-		} else {
+		}
+		if system_trust {
 			// TODO: we probably should do this once globally!
 			for cert in &crate::http::backendtls::SYSTEM_ROOT.certs {
 				roots.add(cert.clone()).unwrap();
@@ -104,7 +187,22 @@ impl ResolvedBackendTLS {
 			},
 			_ => ccb.with_no_client_auth(),
 		};
-		if self.insecure_host {
+		let revocation = match self.revocation {
+			Some(_) if self.insecure => {
+				anyhow::bail!("revocation checking cannot be used with insecure")
+			},
+			Some((config, client)) => Some(Revocation::new(
+				config,
+				client,
+				roots.clone(),
+				self.insecure_host,
+			)?),
+			None => None,
+		};
+		if let Some(revocation) = revocation {
+			cc.dangerous()
+				.set_certificate_verifier(Arc::new(RevocationVerifier(revocation)));
+		} else if self.insecure_host {
 			let inner = rustls::client::WebPkiServerVerifier::builder_with_provider(
 				roots,
 				transport::tls::provider(),
@@ -126,13 +224,24 @@ impl ResolvedBackendTLS {
 }
 
 impl LocalBackendTLS {
-	pub fn try_into(self) -> anyhow::Result<BackendTLS> {
+	pub fn try_into(
+		self,
+		client: Client,
+		trust_bundles: &TrustBundles,
+	) -> anyhow::Result<BackendTLS> {
 		ResolvedBackendTLS {
 			cert: self.cert.map(fs_err::read).transpose()?,
 			key: self.key.map(fs_err::read).transpose()?,
 			root: self.root.map(fs_err::read).transpose()?,
 			insecure: self.insecure,
 			insecure_host: self.insecure_host,
+			revocation: self.revocation.map(|r| (r, client)),
+			bundles: self
+				.trust_bundles
+				.iter()
+				.map(|name| trust_bundles.get(name))
+				.collect::<Result<_, _>>()?,
+			system_trust: self.system_trust,
 		}
 		.try_into()
 	}
@@ -0,0 +1,405 @@
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::de::Error;
+use tokio::sync::Semaphore;
+
+use crate::client::Client;
+use crate::http::{Body, StatusCode};
+use crate::telemetry::trc::TraceParent;
+use crate::types::agent::{BackendName, RouteBackendReference, RouteName};
+use crate::*;
+
+/// Bandit selects among a route's backends based on the rewards observed from previous requests, rather than
+/// static weights. Rewards are derived from latency, errors, and optionally a quality score from a webhook.
+///
+/// This is experimental.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "BanditSerde"))]
+pub struct Bandit {
+	config: BanditSerde,
+	arms: Arc<Mutex<HashMap<BackendName, Arm>>>,
+	/// Limits the quality webhook calls in flight, so a slow webhook cannot accumulate unbounded tasks.
+	quality_permits: Arc<Semaphore>,
+}
+
+/// Maximum number of quality webhook calls in flight for a bandit policy. Outcomes beyond this are recorded
+/// without a quality score.
+const MAX_PENDING_QUALITY_REQUESTS: usize = 128;
+
+impl serde::Serialize for Bandit {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Bandit {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = BanditSerde::deserialize(deserializer)?;
+		if !(0.0..=1.0).contains(&config.exploration_rate) {
+			return Err(D::Error::custom("explorationRate must be between 0 and 1"));
+		}
+		if !(config.smoothing > 0.0 && config.smoothing <= 1.0) {
+			return Err(D::Error::custom("smoothing must be between 0 and 1"));
+		}
+		Ok(Bandit {
+			config,
+			arms: Default::default(),
+			quality_permits: Arc::new(Semaphore::new(MAX_PENDING_QUALITY_REQUESTS)),
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct BanditSerde {
+	/// Fraction of requests sent to a random backend, so all backends continue to be evaluated.
+	/// Defaults to 0.1.
+	#[serde(default = "default_exploration_rate")]
+	pub exploration_rate: f64,
+	/// Weight of each observation in a backend's average reward; higher values adapt faster.
+	/// Defaults to 0.1.
+	#[serde(default = "default_smoothing")]
+	pub smoothing: f64,
+	/// Penalty applied to the reward for each second of latency. Defaults to 1.
+	#[serde(default = "default_weight")]
+	pub latency_weight: f64,
+	/// Penalty applied to the reward when a request fails. Defaults to 1.
+	#[serde(default = "default_weight")]
+	pub error_weight: f64,
+	/// URL to send the outcome of each request to. The outcome includes the trace and span ids of the request,
+	/// matching the access log, and the completion for LLM backends. If it responds with
+	/// `{"reward": <number>}`, the number is multiplied by `qualityWeight` and added to the reward.
+	#[serde(
+		default,
+		serialize_with = "ser_display_option",
+		deserialize_with = "de_parse_option"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub quality_webhook: Option<::http::Uri>,
+	/// Weight of the quality score from the webhook. Defaults to 1.
+	#[serde(default = "default_weight")]
+	pub quality_weight: f64,
+}
+
+fn default_exploration_rate() -> f64 {
+	0.1
+}
+
+fn default_smoothing() -> f64 {
+	0.1
+}
+
+fn default_weight() -> f64 {
+	1.0
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Arm {
+	requests: u64,
+	reward: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+	/// The backend had no observations yet.
+	Unexplored,
+	/// The backend was picked at random.
+	Explore,
+	/// The backend had the highest average reward.
+	Exploit,
+}
+
+impl Decision {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Decision::Unexplored => "unexplored",
+			Decision::Explore => "explore",
+			Decision::Exploit => "exploit",
+		}
+	}
+}
+
+impl Bandit {
+	/// select picks a backend. Backends with a weight of 0 are never selected.
+	pub fn select<'a>(
+		&self,
+		backends: &'a [RouteBackendReference],
+	) -> Option<(&'a RouteBackendReference, Decision)> {
+		let candidates = backends.iter().filter(|b| b.weight > 0).collect_vec();
+		let arms = self.arms.lock().expect("mutex acquired");
+		let mut rng = rand::rng();
+
+		let unexplored = candidates
+			.iter()
+			.filter(|b| !arms.contains_key(&b.backend.name()))
+			.collect_vec();
+		if let Some(b) = unexplored.choose(&mut rng) {
+			return Some((**b, Decision::Unexplored));
+		}
+		if rng.random_bool(self.config.exploration_rate) {
+			return candidates.choose(&mut rng).map(|b| (*b, Decision::Explore));
+		}
+		candidates
+			.into_iter()
+			.max_by(|a, b| {
+				let a = arms.get(&a.backend.name()).map(|a| a.reward);
+				let b = arms.get(&b.backend.name()).map(|b| b.reward);
+				a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+			})
+			.map(|b| (b, Decision::Exploit))
+	}
+
+	fn reward(&self, latency: Duration, failed: bool) -> f64 {
+		let mut reward = -self.config.latency_weight * latency.as_secs_f64();
+		if failed {
+			reward -= self.config.error_weight;
+		}
+		reward
+	}
+
+	fn record(&self, backend: &BackendName, reward: f64) {
+		let mut arms = self.arms.lock().expect("mutex acquired");
+		let arm = arms.entry(backend.clone()).or_default();
+		arm.reward = if arm.requests == 0 {
+			reward
+		} else {
+			arm.reward + self.config.smoothing * (reward - arm.reward)
+		};
+		arm.requests += 1;
+		debug!(backend=%backend, reward, average=arm.reward, requests=arm.requests, "bandit reward recorded");
+	}
+}
+
+/// BanditOutcome tracks a selection, so its reward can be recorded once the request completes.
+pub struct BanditOutcome {
+	pub bandit: Bandit,
+	pub backend: BackendName,
+	pub decision: Decision,
+	pub route: Option<RouteName>,
+	pub client: Client,
+}
+
+impl fmt::Debug for BanditOutcome {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("BanditOutcome")
+			.field("backend", &self.backend)
+			.field("decision", &self.decision)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QualityRequest<'a> {
+	route: Option<&'a str>,
+	backend: &'a str,
+	status: Option<u16>,
+	latency_ms: u128,
+	failed: bool,
+	trace_id: Option<String>,
+	span_id: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	completion: Option<&'a [String]>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct QualityResponse {
+	reward: f64,
+}
+
+impl BanditOutcome {
+	/// needs_completion reports whether the LLM completion should be captured, to send to the quality webhook.
+	pub fn needs_completion(&self) -> bool {
+		self.bandit.config.quality_webhook.is_some()
+	}
+
+	/// complete records the reward for the request, given the latency of the backend call. If a quality
+	/// webhook is configured, the reward is recorded in the background once it responds.
+	pub fn complete(
+		self,
+		latency: Duration,
+		status: Option<StatusCode>,
+		failed: bool,
+		trace: Option<&TraceParent>,
+		completion: Option<&[String]>,
+	) {
+		let reward = self.bandit.reward(latency, failed);
+		let Some(url) = self.bandit.config.quality_webhook.clone() else {
+			self.bandit.record(&self.backend, reward);
+			return;
+		};
+		let Ok(permit) = self.bandit.quality_permits.clone().try_acquire_owned() else {
+			debug!(%url, "too many pending bandit quality requests; recording reward without quality");
+			self.bandit.record(&self.backend, reward);
+			return;
+		};
+		let body = serde_json::to_vec(&QualityRequest {
+			route: self.route.as_deref(),
+			backend: &self.backend,
+			status: status.map(|s| s.as_u16()),
+			latency_ms: latency.as_millis(),
+			failed,
+			trace_id: trace.map(|t| t.trace_id()),
+			span_id: trace.map(|t| t.span_id()),
+			completion,
+		})
+		.expect("serialization should succeed");
+		tokio::task::spawn(async move {
+			let req = ::http::Request::builder()
+				.method(::http::Method::POST)
+				.uri(&url)
+				.header(::http::header::CONTENT_TYPE, "application/json")
+				.body(Body::from(body))
+				.expect("builder should succeed");
+			let quality = match self.client.simple_call(req).await {
+				Ok(resp) if resp.status().is_success() => {
+					json::from_body::<QualityResponse>(resp.into_body())
+						.await
+						.map_err(|err| warn!(%url, ?err, "invalid bandit quality response"))
+						.ok()
+				},
+				Ok(resp) => {
+					warn!(%url, status=%resp.status(), "bandit quality webhook failed");
+					None
+				},
+				Err(err) => {
+					warn!(%url, ?err, "bandit quality webhook failed");
+					None
+				},
+			};
+			let quality = quality.map(|q| q.reward * self.bandit.config.quality_weight);
+			self
+				.bandit
+				.record(&self.backend, reward + quality.unwrap_or_default());
+			drop(permit);
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::agent::BackendReference;
+
+	fn backends() -> Vec<RouteBackendReference> {
+		["a", "b", "c"]
+			.into_iter()
+			.map(|n| RouteBackendReference {
+				weight: 1,
+				priority: 0,
+				backend: BackendReference::Backend(strng::new(n)),
+				filters: vec![],
+			})
+			.collect()
+	}
+
+	fn bandit(exploration_rate: f64) -> Bandit {
+		serde_json::from_value(serde_json::json!({"explorationRate": exploration_rate})).unwrap()
+	}
+
+	#[test]
+	fn explores_then_exploits() {
+		let b = bandit(0.0);
+		let backends = backends();
+		let mut seen = vec![];
+		for _ in 0..3 {
+			let (be, decision) = b.select(&backends).unwrap();
+			assert_eq!(decision, Decision::Unexplored);
+			let name = be.backend.name();
+			let latency = match name.as_str() {
+				"b" => Duration::from_millis(100),
+				_ => Duration::from_millis(500),
+			};
+			b.record(&name, b.reward(latency, false));
+			seen.push(name.to_string());
+		}
+		seen.sort();
+		assert_eq!(seen, vec!["a", "b", "c"]);
+
+		let (be, decision) = b.select(&backends).unwrap();
+		assert_eq!(decision, Decision::Exploit);
+		assert_eq!(be.backend.name().as_str(), "b");
+
+		// Errors on the best backend shift traffic away from it
+		for _ in 0..20 {
+			b.record(&strng::new("b"), b.reward(Duration::from_millis(100), true));
+		}
+		let (be, _) = b.select(&backends).unwrap();
+		assert_ne!(be.backend.name().as_str(), "b");
+	}
+
+	#[test]
+	fn skips_zero_weight() {
+		let b = bandit(1.0);
+		let mut backends = backends();
+		backends[0].weight = 0;
+		backends[1].weight = 0;
+		for _ in 0..10 {
+			let (be, _) = b.select(&backends).unwrap();
+			assert_eq!(be.backend.name().as_str(), "c");
+		}
+	}
+
+	#[test]
+	fn quality_request() {
+		let trace =
+			TraceParent::try_from("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01").unwrap();
+		let completion = vec!["hello".to_string()];
+		let body = serde_json::to_value(QualityRequest {
+			route: Some("route"),
+			backend: "a",
+			status: Some(200),
+			latency_ms: 10,
+			failed: false,
+			trace_id: Some(trace.trace_id()),
+			span_id: Some(trace.span_id()),
+			completion: Some(&completion),
+		})
+		.unwrap();
+		assert_eq!(
+			body,
+			serde_json::json!({
+				"route": "route",
+				"backend": "a",
+				"status": 200,
+				"latencyMs": 10,
+				"failed": false,
+				"traceId": "0af7651916cd43dd8448eb211c80319c",
+				"spanId": "b7ad6b7169203331",
+				"completion": ["hello"],
+			})
+		);
+	}
+
+	#[tokio::test]
+	async fn quality_requests_bounded() {
+		let b: Bandit = serde_json::from_value(serde_json::json!({
+			"qualityWebhook": "http://127.0.0.1:1/quality",
+		}))
+		.unwrap();
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let _held = b
+			.quality_permits
+			.clone()
+			.try_acquire_many_owned(MAX_PENDING_QUALITY_REQUESTS as u32)
+			.unwrap();
+		BanditOutcome {
+			bandit: b.clone(),
+			backend: strng::new("a"),
+			decision: Decision::Unexplored,
+			route: None,
+			client: Client::new(&cfg.dns, None),
+		}
+		.complete(Duration::from_secs(1), None, false, None, None);
+		// With no permits left, the reward is recorded right away, without a quality score.
+		let arms = b.arms.lock().unwrap();
+		assert_eq!(arms.get(&strng::new("a")).map(|a| a.requests), Some(1));
+		assert_eq!(arms.get(&strng::new("a")).map(|a| a.reward), Some(-1.0));
+	}
+}
@@ -3,7 +3,7 @@ use async_compression::tokio::bufread::{
 	ZstdEncoder,
 };
 use bytes::Bytes;
-use futures_util::TryStreamExt;
+use futures_util::{FutureExt, TryStreamExt};
 use headers::ContentEncoding;
 use http_body::Body;
 use http_body_util::BodyExt;
@@ -44,6 +44,30 @@ pub async fn encode_body(body: &[u8], encoding: &str) -> Result<Bytes, axum_core
 	read_to_bytes(encoder, usize::MAX).await
 }
 
+/// decode_prefix decompresses as much of a possibly truncated body as it can, up to `limit` bytes.
+/// None is returned if the encoding is not known.
+pub fn decode_prefix(body: &[u8], encoding: &str, limit: usize) -> Option<Bytes> {
+	let reader = BufReader::new(body);
+	let mut decoder: Box<dyn AsyncRead + Unpin + Send> = match encoding {
+		e if e.contains(GZIP) => Box::new(GzipDecoder::new(reader)),
+		e if e.contains(DEFLATE) => Box::new(ZlibDecoder::new(reader)),
+		e if e.contains(BR) => Box::new(BrotliDecoder::new(reader)),
+		e if e.contains(ZSTD) => Box::new(ZstdDecoder::new(reader)),
+		_ => return None,
+	};
+	let mut buffer = bytes::BytesMut::new();
+	// Reading from an in-memory slice never blocks, so the future always completes immediately.
+	while buffer.len() < limit {
+		match decoder.read_buf(&mut buffer).now_or_never() {
+			Some(Ok(n)) if n > 0 => {},
+			// The end of the body, or the point the body was truncated
+			_ => break,
+		}
+	}
+	buffer.truncate(limit);
+	Some(buffer.freeze())
+}
+
 async fn decode_body<B>(body: B, encoding: &str, limit: usize) -> Result<Bytes, axum_core::Error>
 where
 	B: Body + Send + Unpin + 'static,
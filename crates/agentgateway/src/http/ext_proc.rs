@@ -263,6 +263,10 @@ impl ExtProc {
 		// The EPP will await for our headers and body. The body is going to be streaming in.
 		// We will spin off a task that is going to pipe the body to the ext_proc server as we read it.
 		let tx = self.tx_req.clone();
+		// Trailers are sent to the ext_proc server, but the body it returns cannot carry them, so we keep the
+		// original trailers to forward downstream once the body is complete.
+		let (trailers_tx, trailers_rx) = tokio::sync::oneshot::channel();
+		let mut trailers_tx = Some(trailers_tx);
 
 		tokio::task::spawn(async move {
 			let mut stream = BodyStream::new(body);
@@ -275,9 +279,11 @@ impl ExtProc {
 					}))
 				} else if frame.is_trailers() {
 					let frame = frame.into_trailers().expect("already checked");
-					processing_request(Request::ResponseTrailers(HttpTrailers {
-						trailers: to_header_map(&frame),
-					}))
+					let trailers = to_header_map(&frame);
+					if let Some(t) = trailers_tx.take() {
+						let _ = t.send(frame);
+					}
+					processing_request(Request::ResponseTrailers(HttpTrailers { trailers }))
 				} else {
 					panic!("unknown type")
 				};
@@ -297,7 +303,9 @@ impl ExtProc {
 		});
 		// Now we need to build the new body. This is going to be streamed in from the ext_proc server.
 		let (mut tx_chunk, rx_chunk) = tokio::sync::mpsc::channel(1);
-		let body = http_body_util::StreamBody::new(ReceiverStream::new(rx_chunk));
+		let trailers = futures_util::stream::once(trailers_rx)
+			.filter_map(|t| t.ok().map(|t| Ok(Frame::trailers(t))));
+		let body = http_body_util::StreamBody::new(ReceiverStream::new(rx_chunk).chain(trailers));
 		let mut req = http::Response::from_parts(parts, http::Body::new(body));
 		loop {
 			// Loop through all the ext_proc responses and process them
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::de::Error;
+
+use crate::types::agent::{RouteBackendReference, RouteKey};
+use crate::*;
+
+/// Failover sends traffic to the route's backends with the lowest priority, until their error rate or latency
+/// exceeds a threshold. Traffic then fails over to the next priority, and returns once the group recovers.
+///
+/// Priority groups are tracked per route, so a policy attached to a listener or gateway judges each route's
+/// backends separately.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "FailoverSerde"))]
+pub struct Failover {
+	config: FailoverSerde,
+	groups: Arc<Mutex<HashMap<(RouteKey, u32), Window>>>,
+}
+
+impl serde::Serialize for Failover {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Failover {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = FailoverSerde::deserialize(deserializer)?;
+		if !(0.0..=1.0).contains(&config.max_error_rate) {
+			return Err(D::Error::custom("maxErrorRate must be between 0 and 1"));
+		}
+		if !(0.0..=1.0).contains(&config.fraction) {
+			return Err(D::Error::custom("fraction must be between 0 and 1"));
+		}
+		if config.window.is_zero() {
+			return Err(D::Error::custom("window must be greater than 0"));
+		}
+		Ok(Failover {
+			config,
+			groups: Default::default(),
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct FailoverSerde {
+	/// Fraction of failed requests above which a priority group is unhealthy. Defaults to 0.5.
+	#[serde(default = "default_max_error_rate")]
+	pub max_error_rate: f64,
+	/// Average latency above which a priority group is unhealthy. If unset, latency is not considered.
+	#[serde(default, with = "serde_dur_option")]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub max_latency: Option<Duration>,
+	/// The period error rate and latency are measured over. Defaults to 30s.
+	#[serde(default = "default_window", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub window: Duration,
+	/// Minimum number of requests in the window before a priority group can be considered unhealthy.
+	/// Defaults to 10.
+	#[serde(default = "default_min_requests")]
+	pub min_requests: u64,
+	/// Fraction of traffic sent to the next priority while a group is unhealthy. The remainder continues to
+	/// the unhealthy group, so its recovery can be observed. Defaults to 1.
+	#[serde(default = "default_fraction")]
+	pub fraction: f64,
+}
+
+fn default_max_error_rate() -> f64 {
+	0.5
+}
+
+fn default_window() -> Duration {
+	Duration::from_secs(30)
+}
+
+fn default_min_requests() -> u64 {
+	10
+}
+
+fn default_fraction() -> f64 {
+	1.0
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+	requests: u64,
+	errors: u64,
+	latency: Duration,
+}
+
+/// Window holds counts for the current and previous period, so the rates do not reset abruptly.
+/// A group that receives no traffic ages out after two periods and is considered healthy again.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+	start: Instant,
+	current: Counts,
+	previous: Counts,
+}
+
+impl Window {
+	fn new(now: Instant) -> Window {
+		Window {
+			start: now,
+			current: Counts::default(),
+			previous: Counts::default(),
+		}
+	}
+
+	fn roll(&mut self, now: Instant, period: Duration) {
+		let elapsed = now.saturating_duration_since(self.start);
+		if elapsed >= period * 2 {
+			*self = Window::new(now);
+		} else if elapsed >= period {
+			self.previous = self.current;
+			self.current = Counts::default();
+			self.start += period;
+		}
+	}
+
+	fn totals(&self) -> Counts {
+		Counts {
+			requests: self.current.requests + self.previous.requests,
+			errors: self.current.errors + self.previous.errors,
+			latency: self.current.latency + self.previous.latency,
+		}
+	}
+}
+
+impl Failover {
+	fn healthy(&self, window: &Window) -> bool {
+		let totals = window.totals();
+		if totals.requests < self.config.min_requests.max(1) {
+			return true;
+		}
+		let error_rate = totals.errors as f64 / totals.requests as f64;
+		if error_rate > self.config.max_error_rate {
+			return false;
+		}
+		if let Some(max) = self.config.max_latency
+			&& totals.latency / totals.requests as u32 > max
+		{
+			return false;
+		}
+		true
+	}
+
+	/// select picks a backend from the route's most preferred priority group that is healthy. Backends with a
+	/// weight of 0 are never selected.
+	pub fn select<'a>(
+		&self,
+		route: &RouteKey,
+		backends: &'a [RouteBackendReference],
+	) -> Option<&'a RouteBackendReference> {
+		let mut groups: BTreeMap<u32, Vec<&RouteBackendReference>> = BTreeMap::new();
+		for b in backends.iter().filter(|b| b.weight > 0) {
+			groups.entry(b.priority).or_default().push(b);
+		}
+		let now = Instant::now();
+		let mut windows = self.groups.lock().expect("mutex acquired");
+		let mut rng = rand::rng();
+		let last = groups.keys().next_back().copied();
+		for (priority, group) in groups {
+			let window = windows
+				.entry((route.clone(), priority))
+				.or_insert_with(|| Window::new(now));
+			window.roll(now, self.config.window);
+			if Some(priority) == last || self.healthy(window) || !rng.random_bool(self.config.fraction) {
+				return group.choose_weighted(&mut rng, |b| b.weight).ok().copied();
+			}
+		}
+		None
+	}
+
+	fn record(&self, route: &RouteKey, priority: u32, latency: Duration, failed: bool) {
+		let now = Instant::now();
+		let mut windows = self.groups.lock().expect("mutex acquired");
+		let window = windows
+			.entry((route.clone(), priority))
+			.or_insert_with(|| Window::new(now));
+		window.roll(now, self.config.window);
+		window.current.requests += 1;
+		window.current.latency += latency;
+		if failed {
+			window.current.errors += 1;
+		}
+	}
+}
+
+/// FailoverOutcome tracks a selection, so its result can be recorded once the request completes.
+#[derive(Debug)]
+pub struct FailoverOutcome {
+	pub failover: Failover,
+	pub route: RouteKey,
+	pub priority: u32,
+}
+
+impl FailoverOutcome {
+	/// complete records the result of the request, given the latency of the backend call.
+	pub fn complete(self, latency: Duration, failed: bool) {
+		self
+			.failover
+			.record(&self.route, self.priority, latency, failed);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::agent::BackendReference;
+
+	fn backends() -> Vec<RouteBackendReference> {
+		[("primary", 0), ("secondary", 1)]
+			.into_iter()
+			.map(|(n, priority)| RouteBackendReference {
+				weight: 1,
+				priority,
+				backend: BackendReference::Backend(strng::new(n)),
+				filters: vec![],
+			})
+			.collect()
+	}
+
+	fn route() -> RouteKey {
+		strng::literal!("route")
+	}
+
+	fn failover(v: serde_json::Value) -> Failover {
+		serde_json::from_value(v).unwrap()
+	}
+
+	fn selected(f: &Failover, backends: &[RouteBackendReference]) -> String {
+		f.select(&route(), backends)
+			.unwrap()
+			.backend
+			.name()
+			.to_string()
+	}
+
+	#[test]
+	fn fails_over_on_errors() {
+		let f = failover(serde_json::json!({"minRequests": 4}));
+		let backends = backends();
+		assert_eq!(selected(&f, &backends), "primary");
+
+		// Not enough requests to judge yet
+		for _ in 0..3 {
+			f.record(&route(), 0, Duration::from_millis(10), true);
+		}
+		assert_eq!(selected(&f, &backends), "primary");
+
+		f.record(&route(), 0, Duration::from_millis(10), true);
+		assert_eq!(selected(&f, &backends), "secondary");
+
+		// Successes bring the error rate back under the threshold
+		for _ in 0..5 {
+			f.record(&route(), 0, Duration::from_millis(10), false);
+		}
+		assert_eq!(selected(&f, &backends), "primary");
+	}
+
+	#[test]
+	fn fails_over_on_latency() {
+		let f = failover(serde_json::json!({"minRequests": 1, "maxLatency": "100ms"}));
+		let backends = backends();
+		f.record(&route(), 0, Duration::from_millis(500), false);
+		assert_eq!(selected(&f, &backends), "secondary");
+	}
+
+	#[test]
+	fn recovers_after_window() {
+		let mut w = Window::new(Instant::now());
+		w.current.requests = 10;
+		let period = Duration::from_secs(1);
+		w.roll(w.start + period, period);
+		assert_eq!(w.totals().requests, 10);
+		w.roll(w.start + period, period);
+		assert_eq!(w.totals().requests, 0);
+	}
+
+	#[test]
+	fn last_group_always_serves() {
+		let f = failover(serde_json::json!({"minRequests": 1}));
+		let backends = backends();
+		f.record(&route(), 0, Duration::from_millis(10), true);
+		f.record(&route(), 1, Duration::from_millis(10), true);
+		assert_eq!(selected(&f, &backends), "secondary");
+	}
+
+	#[test]
+	fn routes_tracked_separately() {
+		let f = failover(serde_json::json!({"minRequests": 1}));
+		let backends = backends();
+		f.record(&route(), 0, Duration::from_millis(10), true);
+		assert_eq!(selected(&f, &backends), "secondary");
+		let other = f.select(&strng::literal!("other"), &backends).unwrap();
+		assert_eq!(other.backend.name().as_str(), "primary");
+	}
+}
@@ -0,0 +1,81 @@
+use itertools::Itertools;
+
+use crate::http::{HeaderMap, HeaderName};
+use crate::*;
+
+/// HeaderSizeLimit removes oversized request headers, such as large cookies, before the request is forwarded.
+/// Some upstreams, notably LLM providers, reject requests with large headers outright.
+#[apply(schema!)]
+pub struct HeaderSizeLimit {
+	/// Maximum size, in bytes, of a header value. Larger values are removed.
+	pub max_size: usize,
+	/// Headers to limit. If empty, all headers are limited.
+	#[serde(default, skip_serializing_if = "is_default")]
+	pub headers: Vec<Strng>,
+}
+
+impl HeaderSizeLimit {
+	fn limits(&self, name: &HeaderName) -> bool {
+		self.headers.is_empty()
+			|| self
+				.headers
+				.iter()
+				.any(|h| h.eq_ignore_ascii_case(name.as_str()))
+	}
+
+	/// apply removes the header values that exceed the limit, returning the names of the affected headers.
+	/// Other values of a repeated header are kept.
+	pub fn apply(&self, headers: &mut HeaderMap) -> Vec<HeaderName> {
+		let oversized = headers
+			.iter()
+			.filter(|(k, v)| v.len() > self.max_size && self.limits(k))
+			.map(|(k, _)| k.clone())
+			.unique()
+			.collect_vec();
+		for name in &oversized {
+			let kept = headers
+				.get_all(name)
+				.iter()
+				.filter(|v| v.len() <= self.max_size)
+				.cloned()
+				.collect_vec();
+			headers.remove(name);
+			for v in kept {
+				headers.append(name.clone(), v);
+			}
+		}
+		oversized
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::http::HeaderValue;
+
+	fn headers(kv: &[(&'static str, usize)]) -> HeaderMap {
+		let mut h = HeaderMap::new();
+		for (k, len) in kv {
+			h.append(*k, HeaderValue::from_str(&"a".repeat(*len)).unwrap());
+		}
+		h
+	}
+
+	#[test]
+	fn removes_oversized() {
+		let p: HeaderSizeLimit =
+			serde_json::from_value(json!({"maxSize": 10, "headers": ["Cookie"]})).unwrap();
+		let mut h = headers(&[("cookie", 5), ("cookie", 20), ("x-other", 20)]);
+		assert_eq!(p.apply(&mut h), vec![HeaderName::from_static("cookie")]);
+		assert_eq!(h.get_all("cookie").iter().count(), 1);
+		assert_eq!(h.get("cookie").unwrap().len(), 5);
+		assert!(h.contains_key("x-other"));
+
+		let p: HeaderSizeLimit = serde_json::from_value(json!({"maxSize": 10})).unwrap();
+		p.apply(&mut h);
+		assert!(!h.contains_key("x-other"));
+		assert!(h.contains_key("cookie"));
+	}
+}
@@ -0,0 +1,830 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::BytesMut;
+use futures_util::StreamExt;
+use http_body::{Body as _, Frame, SizeHint};
+use http_body_util::BodyExt;
+use moka::ops::compute::Op;
+use serde::de::Error;
+use sha2::{Digest as _, Sha256};
+
+use crate::http::jwt::Claims;
+use crate::http::{
+	Body, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+};
+use crate::proxy::{ProxyError, ProxyResponse};
+use crate::store::kv;
+use crate::transport::stream::{TCPConnectionInfo, TLSConnectionInfo};
+use crate::*;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// In a shared store, keys for requests that are still in progress hold this prefix followed by an id for
+/// the request. Completed keys hold the encoded response.
+const IN_FLIGHT: &[u8] = b"in-flight/";
+/// How long a key stays claimed in a shared store, if the gateway handling the request never completes it.
+const IN_FLIGHT_TTL: Duration = Duration::from_secs(5 * 60);
+/// The largest request body that is fingerprinted. Requests with a larger body are not deduplicated.
+const MAX_REQUEST_BODY_SIZE: usize = 2_097_152;
+
+/// Idempotency deduplicates retried POST requests that carry an `Idempotency-Key` header.
+/// The first response for a key is stored and replayed to later requests with the same key, until it expires.
+/// Keys are scoped to the route, host, and caller, so a response is only replayed to the client that caused
+/// it. The caller is the JWT subject if the request was authenticated, otherwise the client's mTLS identity,
+/// otherwise its IP address. Reusing a key with a different request body is rejected.
+/// The key itself is passed to the upstream unmodified.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "IdempotencySerde"))]
+pub struct Idempotency {
+	config: IdempotencySerde,
+	cache: Arc<Cache>,
+}
+
+impl serde::Serialize for Idempotency {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Idempotency {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = IdempotencySerde::deserialize(deserializer)?;
+		if config.max_entries == 0 {
+			return Err(D::Error::custom("maxEntries must be greater than 0"));
+		}
+		Ok(Idempotency {
+			cache: Arc::new(Cache::new(&config)),
+			config,
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct IdempotencySerde {
+	/// How long a stored response is replayed for. Defaults to 24h.
+	#[serde(default = "default_ttl", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub ttl: Duration,
+	/// Maximum number of keys to store. When full, rarely used keys are evicted first.
+	/// Only applies when state is kept in memory; see `stateStore`.
+	#[serde(default = "default_max_entries")]
+	pub max_entries: usize,
+	/// Maximum size of a response body to store. Larger responses are passed through, but not replayed.
+	#[serde(default = "default_max_body_size")]
+	pub max_body_size: usize,
+}
+
+fn default_ttl() -> Duration {
+	Duration::from_secs(24 * 60 * 60)
+}
+
+fn default_max_entries() -> usize {
+	10_000
+}
+
+fn default_max_body_size() -> usize {
+	64 * 1024
+}
+
+#[derive(Debug)]
+pub enum Lookup {
+	/// The request is not subject to deduplication.
+	Skip,
+	/// The request should be sent; its response will be stored once complete.
+	Proceed(IdempotencyGuard),
+	/// A response for this key was already stored, and should be returned.
+	Replay(Response),
+	/// A request with the same key is still in progress.
+	Conflict,
+	/// The key was already used for a request with a different body.
+	Mismatch,
+}
+
+/// A SHA-256 digest.
+type Hash = [u8; 32];
+
+/// Fingerprint identifies an idempotent request.
+#[derive(Debug)]
+struct Fingerprint {
+	/// Hash of the scope of the key: the route, method, host, path, caller, and the key itself.
+	key: Hash,
+	/// Hash of the request body, to detect a key reused for a different request.
+	body: Hash,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+	InFlight { id: u64, body: Hash },
+	Complete(Arc<StoredResponse>),
+}
+
+#[derive(Debug)]
+struct StoredResponse {
+	/// Hash of the body of the request this responded to.
+	request: Hash,
+	status: StatusCode,
+	headers: HeaderMap,
+	body: Bytes,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncodedResponse {
+	request: String,
+	status: u16,
+	headers: Vec<(String, String)>,
+	body: String,
+}
+
+impl StoredResponse {
+	/// encode serializes the response for a shared store. Responses with header values that are not valid
+	/// strings cannot be encoded.
+	fn encode(&self) -> Option<Bytes> {
+		use base64::Engine;
+		let headers = self
+			.headers
+			.iter()
+			.map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+			.collect::<Option<Vec<_>>>()?;
+		let encoded = EncodedResponse {
+			request: hex::encode(self.request),
+			status: self.status.as_u16(),
+			headers,
+			body: base64::prelude::BASE64_STANDARD.encode(&self.body),
+		};
+		serde_json::to_vec(&encoded).ok().map(Bytes::from)
+	}
+
+	fn decode(b: &[u8]) -> anyhow::Result<Self> {
+		use base64::Engine;
+		let encoded: EncodedResponse = serde_json::from_slice(b)?;
+		let mut headers = HeaderMap::new();
+		for (k, v) in encoded.headers {
+			headers.append(HeaderName::try_from(k)?, HeaderValue::try_from(v)?);
+		}
+		Ok(StoredResponse {
+			request: decode_hash(&encoded.request)?,
+			status: StatusCode::from_u16(encoded.status)?,
+			headers,
+			body: base64::prelude::BASE64_STANDARD
+				.decode(encoded.body)?
+				.into(),
+		})
+	}
+
+	fn to_response(&self) -> Response {
+		let mut resp = ::http::Response::builder()
+			.status(self.status)
+			.body(Body::from(self.body.clone()))
+			.expect("builder should succeed");
+		*resp.headers_mut() = self.headers.clone();
+		resp
+			.headers_mut()
+			.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+		resp
+	}
+}
+
+fn decode_hash(s: &str) -> anyhow::Result<Hash> {
+	let mut h = Hash::default();
+	hex::decode_to_slice(s, &mut h)?;
+	Ok(h)
+}
+
+/// Cache is a bounded in-memory cache of keys. Entries expire after the policy's ttl, and rarely used
+/// entries are evicted once it is full.
+struct Cache {
+	entries: moka::sync::Cache<Hash, Entry>,
+	next_id: AtomicU64,
+}
+
+impl std::fmt::Debug for Cache {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Cache")
+			.field("entries", &self.entries.entry_count())
+			.finish()
+	}
+}
+
+impl Cache {
+	fn new(config: &IdempotencySerde) -> Cache {
+		Cache {
+			entries: moka::sync::Cache::builder()
+				.max_capacity(config.max_entries as u64)
+				.time_to_live(config.ttl)
+				.build(),
+			next_id: AtomicU64::new(0),
+		}
+	}
+}
+
+impl Idempotency {
+	/// lookup checks the request against the state store, or against this policy's own cache if the store is
+	/// local to the process. `route` is the key of the route the request matched.
+	/// The request body is buffered, so it can be fingerprinted.
+	pub async fn lookup(
+		&self,
+		req: &mut Request,
+		route: &str,
+		store: &kv::Store,
+	) -> Result<Lookup, ProxyError> {
+		let Some(fingerprint) = fingerprint(req, route).await? else {
+			return Ok(Lookup::Skip);
+		};
+		Ok(if store.is_local() {
+			self.check(fingerprint)
+		} else {
+			self.check_shared(fingerprint, store).await
+		})
+	}
+
+	fn check(&self, fingerprint: Fingerprint) -> Lookup {
+		let Fingerprint { key, body } = fingerprint;
+		let id = self.cache.next_id.fetch_add(1, Ordering::Relaxed);
+		let mut existing = None;
+		self
+			.cache
+			.entries
+			.entry(key)
+			.and_compute_with(|e| match e.map(|e| e.into_value()) {
+				Some(Entry::InFlight { body: b, .. }) => {
+					existing = Some(if b == body {
+						Lookup::Conflict
+					} else {
+						Lookup::Mismatch
+					});
+					Op::Nop
+				},
+				Some(Entry::Complete(response)) => {
+					existing = Some(if response.request == body {
+						Lookup::Replay(response.to_response())
+					} else {
+						Lookup::Mismatch
+					});
+					Op::Nop
+				},
+				None => Op::Put(Entry::InFlight { id, body }),
+			});
+		if let Some(existing) = existing {
+			return existing;
+		}
+		Lookup::Proceed(IdempotencyGuard {
+			key: Some(GuardKey::Local {
+				cache: self.cache.clone(),
+				key,
+			}),
+			id,
+			request: body,
+			ttl: self.config.ttl,
+			max_body_size: self.config.max_body_size,
+		})
+	}
+
+	/// check_shared is like check, but keeps keys in a shared store, so retries sent to different replicas
+	/// are deduplicated. maxEntries does not apply; keys are only removed once they expire.
+	/// If the store is unavailable, requests are not deduplicated.
+	async fn check_shared(&self, fingerprint: Fingerprint, store: &kv::Store) -> Lookup {
+		let Fingerprint { key, body } = fingerprint;
+		let key = format!("idempotency/{}", hex::encode(key));
+		let id = rand::random::<u64>();
+		match store
+			.set_if_absent(&key, in_flight_marker(id, &body), IN_FLIGHT_TTL)
+			.await
+		{
+			Ok(true) => {
+				return Lookup::Proceed(IdempotencyGuard {
+					key: Some(GuardKey::Shared {
+						store: store.clone(),
+						key,
+					}),
+					id,
+					request: body,
+					ttl: self.config.ttl,
+					max_body_size: self.config.max_body_size,
+				});
+			},
+			Ok(false) => {},
+			Err(e) => {
+				warn!("idempotency store unavailable, not deduplicating request: {e}");
+				return Lookup::Skip;
+			},
+		}
+		match store.get(&key).await {
+			Ok(Some(v)) if !v.starts_with(IN_FLIGHT) => match StoredResponse::decode(&v) {
+				Ok(resp) if resp.request != body => Lookup::Mismatch,
+				Ok(resp) => Lookup::Replay(resp.to_response()),
+				Err(e) => {
+					warn!("invalid stored response for idempotency key, not deduplicating request: {e}");
+					Lookup::Skip
+				},
+			},
+			Ok(Some(v)) if !v.ends_with(hex::encode(body).as_bytes()) => Lookup::Mismatch,
+			// Either still in progress, or it expired since we checked; the client can retry either way.
+			Ok(_) => Lookup::Conflict,
+			Err(e) => {
+				warn!("idempotency store unavailable, not deduplicating request: {e}");
+				Lookup::Skip
+			},
+		}
+	}
+}
+
+fn in_flight_marker(id: u64, body: &Hash) -> Bytes {
+	let mut marker = IN_FLIGHT.to_vec();
+	marker.extend_from_slice(format!("{id:016x}/{}", hex::encode(body)).as_bytes());
+	marker.into()
+}
+
+/// fingerprint hashes the scope of the request's idempotency key, and its body. Requests that are not
+/// subject to deduplication return None.
+async fn fingerprint(req: &mut Request, route: &str) -> Result<Option<Fingerprint>, ProxyError> {
+	if req.method() != Method::POST {
+		return Ok(None);
+	}
+	let Some(idempotency_key) = req.headers().get(IDEMPOTENCY_KEY) else {
+		return Ok(None);
+	};
+	if req.body().size_hint().lower() > MAX_REQUEST_BODY_SIZE as u64 {
+		debug!("request body is too large to fingerprint, not deduplicating request");
+		return Ok(None);
+	}
+	let mut key = Sha256::new();
+	for part in [
+		route.as_bytes(),
+		req.method().as_str().as_bytes(),
+		req.uri().host().unwrap_or_default().as_bytes(),
+		req.uri().path().as_bytes(),
+		caller(req).as_bytes(),
+		idempotency_key.as_bytes(),
+	] {
+		// Length prefix each part, so different parts cannot produce the same hash.
+		key.update((part.len() as u64).to_be_bytes());
+		key.update(part);
+	}
+	let Some(body) = buffer_body(req.body_mut()).await? else {
+		debug!("request body is too large to fingerprint, not deduplicating request");
+		return Ok(None);
+	};
+	Ok(Some(Fingerprint {
+		key: key.finalize().into(),
+		body: Sha256::digest(&body).into(),
+	}))
+}
+
+/// buffer_body reads up to MAX_REQUEST_BODY_SIZE of a body whose length may not be known up front. If the
+/// body fits, it is returned and the body is replaced with the buffered copy. Otherwise, the bytes read so far
+/// are put back in front of the rest of the body, so it can still be forwarded, and None is returned.
+async fn buffer_body(body: &mut Body) -> Result<Option<Bytes>, ProxyError> {
+	let mut rest = std::mem::take(body);
+	let mut buf = BytesMut::new();
+	let mut trailers = None;
+	while let Some(frame) = rest.frame().await {
+		let frame = frame
+			.map_err(|e| ProxyError::Processing(anyhow::anyhow!("failed to read request body: {e}")))?;
+		let frame = match frame.into_data() {
+			Ok(data) => data,
+			Err(frame) => {
+				if let Ok(t) = frame.into_trailers() {
+					trailers = Some(t);
+				}
+				continue;
+			},
+		};
+		buf.extend_from_slice(&frame);
+		if buf.len() > MAX_REQUEST_BODY_SIZE {
+			let read = futures_util::stream::once(std::future::ready(Ok(Frame::data(buf.freeze()))));
+			*body = Body::new(http_body_util::StreamBody::new(
+				read.chain(http_body_util::BodyStream::new(rest)),
+			));
+			return Ok(None);
+		}
+	}
+	let buf = buf.freeze();
+	*body = match trailers {
+		Some(trailers) => Body::new(http_body_util::StreamBody::new(futures_util::stream::iter(
+			[
+				Ok::<_, axum_core::Error>(Frame::data(buf.clone())),
+				Ok(Frame::trailers(trailers)),
+			],
+		))),
+		None => Body::from(buf.clone()),
+	};
+	Ok(Some(buf))
+}
+
+/// caller identifies who sent the request: the JWT subject, the mTLS identity, or the client IP address, in
+/// that order of preference.
+fn caller(req: &Request) -> String {
+	if let Some(sub) = req
+		.extensions()
+		.get::<Claims>()
+		.and_then(|c| c.inner.get("sub"))
+		.and_then(|s| s.as_str())
+	{
+		return format!("jwt/{sub}");
+	}
+	if let Some(id) = req
+		.extensions()
+		.get::<TLSConnectionInfo>()
+		.and_then(|t| t.src_identity.as_ref())
+	{
+		return format!("identity/{id}");
+	}
+	match req.extensions().get::<TCPConnectionInfo>() {
+		Some(t) => format!("ip/{}", t.peer_addr.ip()),
+		None => String::new(),
+	}
+}
+
+/// IdempotencyGuard marks a key as in progress. If it is dropped before a response is stored, the key is
+/// released so the client can retry.
+#[derive(Debug)]
+pub struct IdempotencyGuard {
+	key: Option<GuardKey>,
+	id: u64,
+	/// Hash of the request body.
+	request: Hash,
+	ttl: Duration,
+	max_body_size: usize,
+}
+
+#[derive(Debug)]
+enum GuardKey {
+	Local { cache: Arc<Cache>, key: Hash },
+	Shared { store: kv::Store, key: String },
+}
+
+impl IdempotencyGuard {
+	/// complete stores the response once its body has been fully sent. Errors and responses that indicate the
+	/// request may be retried are not stored.
+	pub fn complete(self, res: Result<Response, ProxyResponse>) -> Result<Response, ProxyResponse> {
+		let resp = res?;
+		let status = resp.status();
+		if status.is_server_error()
+			|| status == StatusCode::TOO_MANY_REQUESTS
+			|| status == StatusCode::REQUEST_TIMEOUT
+		{
+			return Ok(resp);
+		}
+		let headers = resp.headers().clone();
+		Ok(resp.map(|body| {
+			Body::new(CaptureBody {
+				body,
+				buf: Some(BytesMut::new()),
+				status,
+				headers,
+				guard: Some(self),
+			})
+		}))
+	}
+
+	fn store(mut self, response: StoredResponse) {
+		let (cache, key) = match self.key.take() {
+			None => return,
+			Some(GuardKey::Local { cache, key }) => (cache, key),
+			Some(GuardKey::Shared { store, key }) => {
+				let Some(value) = response.encode() else {
+					// Release the key on drop instead.
+					self.key = Some(GuardKey::Shared { store, key });
+					return;
+				};
+				let ttl = self.ttl;
+				tokio::spawn(async move {
+					if let Err(e) = store.set(&key, value, ttl).await {
+						warn!("failed to store response for idempotency key: {e}");
+					}
+				});
+				return;
+			},
+		};
+		let id = self.id;
+		cache
+			.entries
+			.entry(key)
+			.and_compute_with(|e| match e.map(|e| e.into_value()) {
+				Some(Entry::InFlight { id: current, .. }) if current == id => {
+					Op::Put(Entry::Complete(Arc::new(response)))
+				},
+				// The key was evicted or replaced since we started; do not resurrect it.
+				_ => Op::Nop,
+			});
+	}
+}
+
+impl Drop for IdempotencyGuard {
+	fn drop(&mut self) {
+		match self.key.take() {
+			None => {},
+			Some(GuardKey::Local { cache, key }) => {
+				let id = self.id;
+				cache
+					.entries
+					.entry(key)
+					.and_compute_with(|e| match e.map(|e| e.into_value()) {
+						Some(Entry::InFlight { id: current, .. }) if current == id => Op::Remove,
+						_ => Op::Nop,
+					});
+			},
+			Some(GuardKey::Shared { store, key }) => {
+				let marker = in_flight_marker(self.id, &self.request);
+				tokio::spawn(async move {
+					// Only release the key if another request has not claimed it since ours expired.
+					if matches!(store.get(&key).await, Ok(Some(v)) if v == marker) {
+						let _ = store.delete(&key).await;
+					}
+				});
+			},
+		}
+	}
+}
+
+/// complete is a helper to call IdempotencyGuard::complete on an optional guard.
+pub fn complete(
+	guard: Option<IdempotencyGuard>,
+	res: Result<Response, ProxyResponse>,
+) -> Result<Response, ProxyResponse> {
+	match guard {
+		Some(g) => g.complete(res),
+		None => res,
+	}
+}
+
+pin_project_lite::pin_project! {
+	/// CaptureBody passes through a response body, storing a copy of it once complete.
+	struct CaptureBody {
+		#[pin]
+		body: Body,
+		buf: Option<BytesMut>,
+		status: StatusCode,
+		headers: HeaderMap,
+		guard: Option<IdempotencyGuard>,
+	}
+}
+
+impl http_body::Body for CaptureBody {
+	type Data = Bytes;
+	type Error = crate::http::Error;
+
+	fn poll_frame(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+		let this = self.project();
+		let result = ready!(this.body.poll_frame(cx));
+		match &result {
+			Some(Ok(frame)) => {
+				if let Some(data) = frame.data_ref()
+					&& let Some(buf) = this.buf
+					&& buf.len() + data.len() <= this.guard.as_ref().map(|g| g.max_body_size).unwrap_or(0)
+				{
+					buf.extend_from_slice(data);
+				} else {
+					// Too large, or has trailers which we cannot replay.
+					*this.buf = None;
+					*this.guard = None;
+				}
+			},
+			Some(Err(_)) => {
+				*this.buf = None;
+				*this.guard = None;
+			},
+			None => {
+				if let Some(buf) = this.buf.take()
+					&& let Some(guard) = this.guard.take()
+				{
+					let request = guard.request;
+					guard.store(StoredResponse {
+						request,
+						status: *this.status,
+						headers: std::mem::take(this.headers),
+						body: buf.freeze(),
+					});
+				}
+			},
+		}
+		Poll::Ready(result)
+	}
+
+	fn is_end_stream(&self) -> bool {
+		self.body.is_end_stream()
+	}
+
+	fn size_hint(&self) -> SizeHint {
+		self.body.size_hint()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn policy(max_entries: usize) -> Idempotency {
+		serde_json::from_value(serde_json::json!({"maxEntries": max_entries})).unwrap()
+	}
+
+	fn request(key: &str) -> Request {
+		request_from(key, "10.0.0.1", "{}")
+	}
+
+	fn request_from(key: &str, ip: &str, body: &'static str) -> Request {
+		let mut req = ::http::Request::builder()
+			.method(Method::POST)
+			.uri("http://example.com/charge")
+			.header(IDEMPOTENCY_KEY, key)
+			.body(Body::from(body))
+			.unwrap();
+		req.extensions_mut().insert(TCPConnectionInfo {
+			peer_addr: format!("{ip}:12345").parse().unwrap(),
+			local_addr: "127.0.0.1:8080".parse().unwrap(),
+			start: Instant::now(),
+			rtt: Default::default(),
+		});
+		req
+	}
+
+	async fn check(p: &Idempotency, mut req: Request) -> Lookup {
+		p.lookup(&mut req, "route", &kv::memory()).await.unwrap()
+	}
+
+	async fn check_shared(p: &Idempotency, mut req: Request, store: &kv::Store) -> Lookup {
+		let fingerprint = fingerprint(&mut req, "route").await.unwrap().unwrap();
+		p.check_shared(fingerprint, store).await
+	}
+
+	async fn send(guard: IdempotencyGuard, status: StatusCode, body: &'static str) -> Bytes {
+		let resp = ::http::Response::builder()
+			.status(status)
+			.body(Body::from(body))
+			.unwrap();
+		let resp = guard.complete(Ok(resp)).unwrap();
+		axum::body::to_bytes(resp.into_body(), usize::MAX)
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn replay() {
+		let p = policy(10);
+		let Lookup::Proceed(guard) = check(&p, request("a")).await else {
+			panic!("expected proceed")
+		};
+		assert!(matches!(check(&p, request("a")).await, Lookup::Conflict));
+		send(guard, StatusCode::CREATED, "charged").await;
+
+		let Lookup::Replay(resp) = check(&p, request("a")).await else {
+			panic!("expected replay")
+		};
+		assert_eq!(resp.status(), StatusCode::CREATED);
+		assert_eq!(resp.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+		let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		assert_eq!(body.as_ref(), b"charged");
+
+		// A different key is not affected
+		assert!(matches!(check(&p, request("b")).await, Lookup::Proceed(_)));
+	}
+
+	#[tokio::test]
+	async fn scoped_to_caller() {
+		let p = policy(10);
+		let Lookup::Proceed(guard) = check(&p, request_from("a", "10.0.0.1", "{}")).await else {
+			panic!("expected proceed")
+		};
+		send(guard, StatusCode::CREATED, "charged").await;
+
+		// Another client using the same key does not see the response.
+		assert!(matches!(
+			check(&p, request_from("a", "10.0.0.2", "{}")).await,
+			Lookup::Proceed(_)
+		));
+		// Nor does the same client on another route.
+		let mut req = request_from("a", "10.0.0.1", "{}");
+		assert!(matches!(
+			p.lookup(&mut req, "other", &kv::memory()).await.unwrap(),
+			Lookup::Proceed(_)
+		));
+		// Reusing the key for a different request is rejected.
+		assert!(matches!(
+			check(&p, request_from("a", "10.0.0.1", "{\"amount\": 2}")).await,
+			Lookup::Mismatch
+		));
+	}
+
+	#[tokio::test]
+	async fn request_body_is_preserved() {
+		let p = policy(10);
+		let mut req = request_from("a", "10.0.0.1", "payload");
+		assert!(matches!(
+			p.lookup(&mut req, "route", &kv::memory()).await.unwrap(),
+			Lookup::Proceed(_)
+		));
+		let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		assert_eq!(body.as_ref(), b"payload");
+	}
+
+	#[tokio::test]
+	async fn large_body_of_unknown_length_is_forwarded() {
+		let p = policy(10);
+		// A chunked body, whose size is only known once it is read.
+		let chunks = (0..3).map(|i| {
+			Ok::<_, axum_core::Error>(Frame::data(Bytes::from(vec![
+				b'a' + i;
+				MAX_REQUEST_BODY_SIZE / 2
+			])))
+		});
+		let mut req = request("a");
+		*req.body_mut() = Body::new(http_body_util::StreamBody::new(futures_util::stream::iter(
+			chunks,
+		)));
+		assert_eq!(req.body().size_hint().upper(), None);
+		assert!(matches!(
+			p.lookup(&mut req, "route", &kv::memory()).await.unwrap(),
+			Lookup::Skip
+		));
+		let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		assert_eq!(body.len(), 3 * (MAX_REQUEST_BODY_SIZE / 2));
+		assert!(body.starts_with(b"aaa"));
+		assert!(body.ends_with(b"ccc"));
+		assert_eq!(body[MAX_REQUEST_BODY_SIZE], b'c');
+	}
+
+	#[tokio::test]
+	async fn retryable_responses_are_not_stored() {
+		let p = policy(10);
+		let Lookup::Proceed(guard) = check(&p, request("a")).await else {
+			panic!("expected proceed")
+		};
+		send(guard, StatusCode::SERVICE_UNAVAILABLE, "try again").await;
+		assert!(matches!(check(&p, request("a")).await, Lookup::Proceed(_)));
+	}
+
+	#[tokio::test]
+	async fn bounded() {
+		let p = policy(2);
+		for k in ["a", "b", "c", "d", "e"] {
+			let Lookup::Proceed(guard) = check(&p, request(k)).await else {
+				panic!("expected proceed")
+			};
+			send(guard, StatusCode::OK, "ok").await;
+		}
+		p.cache.entries.run_pending_tasks();
+		assert!(p.cache.entries.entry_count() <= 2);
+	}
+
+	#[tokio::test]
+	async fn shared_store() {
+		let p = policy(1);
+		let store = kv::memory();
+		let Lookup::Proceed(guard) = check_shared(&p, request("a"), &store).await else {
+			panic!("expected proceed")
+		};
+		assert!(matches!(
+			check_shared(&p, request("a"), &store).await,
+			Lookup::Conflict
+		));
+		send(guard, StatusCode::CREATED, "charged").await;
+		// The response is written in the background
+		tokio::task::yield_now().await;
+
+		let Lookup::Replay(resp) = check_shared(&p, request("a"), &store).await else {
+			panic!("expected replay")
+		};
+		assert_eq!(resp.status(), StatusCode::CREATED);
+		let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		assert_eq!(body.as_ref(), b"charged");
+		assert!(matches!(
+			check_shared(&p, request_from("a", "10.0.0.1", "other"), &store).await,
+			Lookup::Mismatch
+		));
+
+		// maxEntries does not apply to shared stores
+		assert!(matches!(
+			check_shared(&p, request("b"), &store).await,
+			Lookup::Proceed(_)
+		));
+		tokio::task::yield_now().await;
+		// The dropped guard released 'b'
+		assert!(matches!(
+			check_shared(&p, request("b"), &store).await,
+			Lookup::Proceed(_)
+		));
+		assert!(matches!(
+			check_shared(&p, request("a"), &store).await,
+			Lookup::Replay(_)
+		));
+	}
+}
@@ -200,6 +200,10 @@ impl Serialize for Claims {
 }
 
 impl Jwt {
+	pub fn mode(&self) -> Mode {
+		self.mode
+	}
+
 	pub async fn apply(&self, log: &mut RequestLog, req: &mut Request) -> Result<(), TokenError> {
 		let Ok(TypedHeader(Authorization(bearer))) = req
 			.extract_parts::<TypedHeader<Authorization<Bearer>>>()
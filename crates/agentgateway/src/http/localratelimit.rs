@@ -11,6 +11,7 @@ use crate::*;
 pub struct RateLimit {
 	ratelimit: Arc<ratelimit::Ratelimiter>,
 	pub limit_type: RateLimitType,
+	pub mode: http::PolicyMode,
 }
 
 impl serde::Serialize for RateLimit {
@@ -44,6 +45,9 @@ pub struct RateLimitSerde {
 	#[serde(default)]
 	#[serde(rename = "type")]
 	pub limit_type: RateLimitType,
+	/// In monitor mode, requests over the limit are logged and counted, but not rejected.
+	#[serde(default)]
+	pub mode: http::PolicyMode,
 }
 
 #[apply(schema!)]
@@ -66,6 +70,7 @@ impl TryFrom<RateLimitSerde> for RateLimit {
 		Ok(RateLimit {
 			ratelimit: Arc::new(rl),
 			limit_type: value.limit_type,
+			mode: value.mode,
 		})
 	}
 }
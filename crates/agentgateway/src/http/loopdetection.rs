@@ -0,0 +1,122 @@
+use std::sync::OnceLock;
+
+use itertools::Itertools;
+
+use crate::http::{HeaderMap, HeaderName, HeaderValue};
+use crate::*;
+
+/// LoopDetection prevents requests from cycling between agents behind federated gateways. Each gateway a request
+/// passes through appends its name to a hops header. Requests that already passed through this gateway, or through
+/// too many gateways, are rejected.
+#[apply(schema!)]
+pub struct LoopDetection {
+	/// Maximum number of gateways a request may pass through, including this one. Defaults to 10.
+	#[serde(default = "default_max_hops")]
+	pub max_hops: usize,
+	/// Name identifying this gateway in the hops header. Defaults to the `POD_NAME` or `HOSTNAME` environment
+	/// variable; if neither is set, a random name is chosen at startup.
+	#[serde(default = "default_instance")]
+	pub instance: Strng,
+	/// Header the gateways a request passed through are recorded in. Defaults to `x-agentgateway-hops`.
+	#[serde(
+		default = "default_header",
+		serialize_with = "ser_display",
+		deserialize_with = "de_parse"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub header: HeaderName,
+}
+
+fn default_max_hops() -> usize {
+	10
+}
+
+fn default_header() -> HeaderName {
+	HeaderName::from_static("x-agentgateway-hops")
+}
+
+fn default_instance() -> Strng {
+	static INSTANCE: OnceLock<Strng> = OnceLock::new();
+	INSTANCE
+		.get_or_init(|| {
+			["POD_NAME", "HOSTNAME"]
+				.into_iter()
+				.filter_map(|k| std::env::var(k).ok())
+				.find(|v| !v.is_empty())
+				.map(strng::new)
+				.unwrap_or_else(|| strng::format!("agentgateway-{:08x}", rand::random::<u32>()))
+		})
+		.clone()
+}
+
+impl LoopDetection {
+	/// apply checks the gateways the request already passed through, and adds this one. It returns the path
+	/// including this gateway, or, if the request is rejected, the path it arrived with.
+	pub fn apply(&self, headers: &mut HeaderMap) -> Result<Strng, Strng> {
+		let hops = headers
+			.get_all(&self.header)
+			.iter()
+			.filter_map(|v| v.to_str().ok())
+			.flat_map(|v| v.split(','))
+			.map(str::trim)
+			.filter(|h| !h.is_empty())
+			.collect_vec();
+		let path = hops.iter().join(", ");
+		if hops.contains(&self.instance.as_str()) || hops.len() >= self.max_hops {
+			return Err(path.into());
+		}
+		let path = if path.is_empty() {
+			self.instance.clone()
+		} else {
+			strng::format!("{path}, {}", self.instance)
+		};
+		// Names come from header values or the config, so they are valid; an invalid name is left out rather than
+		// failing the request.
+		if let Ok(v) = HeaderValue::from_str(&path) {
+			headers.insert(self.header.clone(), v);
+		}
+		Ok(path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn policy(max_hops: usize) -> LoopDetection {
+		serde_json::from_value(json!({"maxHops": max_hops, "instance": "gw-b"})).unwrap()
+	}
+
+	#[test]
+	fn appends_hop() {
+		let mut h = HeaderMap::new();
+		assert_eq!(policy(3).apply(&mut h).unwrap(), "gw-b");
+		assert_eq!(h.get("x-agentgateway-hops").unwrap(), "gw-b");
+
+		let mut h = HeaderMap::new();
+		h.append("x-agentgateway-hops", HeaderValue::from_static("gw-a"));
+		h.append("x-agentgateway-hops", HeaderValue::from_static(" gw-c ,"));
+		assert_eq!(policy(3).apply(&mut h).unwrap(), "gw-a, gw-c, gw-b");
+		assert_eq!(h.get_all("x-agentgateway-hops").iter().count(), 1);
+	}
+
+	#[test]
+	fn rejects_loops() {
+		let mut h = HeaderMap::new();
+		h.insert(
+			"x-agentgateway-hops",
+			HeaderValue::from_static("gw-a, gw-b, gw-c"),
+		);
+		assert_eq!(policy(10).apply(&mut h).unwrap_err(), "gw-a, gw-b, gw-c");
+
+		let mut h = HeaderMap::new();
+		h.insert(
+			"x-agentgateway-hops",
+			HeaderValue::from_static("gw-a, gw-c"),
+		);
+		assert!(policy(2).apply(&mut h).is_err());
+		assert!(policy(3).apply(&mut h).is_ok());
+	}
+}
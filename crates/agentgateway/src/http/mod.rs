@@ -15,12 +15,24 @@ mod tests_common;
 mod transformation;
 // Do not warn is it is WIP
 pub mod authorization;
+pub mod backendoverride;
 pub mod backendtls;
+pub mod bandit;
 pub mod compression;
 pub mod ext_authz;
 pub mod ext_proc;
+pub mod failover;
+pub mod headersizelimit;
+pub mod idempotency;
+pub mod loopdetection;
+mod ocsp;
+pub mod poolpartition;
 pub mod remoteratelimit;
+pub mod revocation;
+pub mod securityheaders;
 pub mod transformation_cel;
+pub mod upstreamerrors;
+pub mod variables;
 
 pub type Error = axum_core::Error;
 pub type Body = axum_core::body::Body;
@@ -35,6 +47,8 @@ use bytes::Bytes;
 use tower_serve_static::private::mime;
 
 use crate::proxy::{ProxyError, ProxyResponse};
+use crate::serdes::*;
+use crate::telemetry::log::RequestLog;
 
 pub mod x_headers {
 	use http::HeaderName;
@@ -43,6 +57,7 @@ pub mod x_headers {
 	pub const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
 	pub const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
 	pub const X_AMZN_REQUESTID: HeaderName = HeaderName::from_static("x-amzn-requestid");
+	pub const X_AMZN_ERRORTYPE: HeaderName = HeaderName::from_static("x-amzn-errortype");
 }
 
 pub fn modify_req(
@@ -106,7 +121,6 @@ pub fn classify_content_type(h: &HeaderMap) -> WellKnownContentTypes {
 
 pub fn get_host(req: &Request) -> Result<&str, ProxyError> {
 	// We expect a normalized request, so this will always be in the URI
-	// TODO: handle absolute HTTP/1.1 form
 	let host = req.uri().host().ok_or(ProxyError::InvalidRequest)?;
 	let host = strip_port(host);
 	Ok(host)
@@ -186,6 +200,56 @@ impl PolicyResponse {
 	}
 }
 
+/// PolicyMode controls whether a policy's denials take effect. Monitor mode allows new rules to be rolled out
+/// safely: requests the policy would deny are logged and counted, but still proceed.
+#[apply(schema!)]
+#[derive(Default, Copy, PartialEq, Eq)]
+pub enum PolicyMode {
+	#[default]
+	Enforce,
+	Monitor,
+}
+
+impl PolicyMode {
+	pub fn is_enforce(&self) -> bool {
+		*self == PolicyMode::Enforce
+	}
+
+	/// enforce returns a policy's result as-is, or, in monitor mode, records a denial and allows the request.
+	pub fn enforce<E>(
+		self,
+		policy: &'static str,
+		log: Option<&mut RequestLog>,
+		result: Result<(), E>,
+	) -> Result<(), E> {
+		match (self, result) {
+			(PolicyMode::Monitor, Err(_)) => {
+				if let Some(log) = log {
+					log.record_monitored(policy);
+				}
+				Ok(())
+			},
+			(_, result) => result,
+		}
+	}
+
+	/// enforce_response is like enforce, for policies that deny with a direct response.
+	pub fn enforce_response(
+		self,
+		policy: &'static str,
+		log: Option<&mut RequestLog>,
+		mut resp: PolicyResponse,
+	) -> PolicyResponse {
+		if self == PolicyMode::Monitor
+			&& resp.direct_response.take().is_some()
+			&& let Some(log) = log
+		{
+			log.record_monitored(policy);
+		}
+		resp
+	}
+}
+
 pub fn merge_in_headers(additional_headers: Option<HeaderMap>, dest: &mut HeaderMap) {
 	if let Some(rh) = additional_headers {
 		for (k, v) in rh.into_iter() {
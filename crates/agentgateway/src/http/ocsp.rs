@@ -0,0 +1,487 @@
+//! Verification of OCSP responses stapled by backends during the TLS handshake (RFC 6960).
+//! Responses are never fetched from the responder; only the stapled response is checked.
+
+use anyhow::{Context as _, anyhow, bail};
+use rustls::RootCertStore;
+use rustls::pki_types::{CertificateDer, UnixTime};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use x509_parser::asn1_rs::{Any, Class, Enumerated, FromDer, GeneralizedTime, Oid, Tag};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::time::ASN1Time;
+
+use crate::http::revocation::Status;
+use crate::transport;
+
+const INVALID_RESPONSE: &str = "invalid OCSP response";
+const OCSP_BASIC: &str = "1.3.6.1.5.5.7.48.1.1";
+const SHA1: &str = "1.3.14.3.2.26";
+const SHA256: &str = "2.16.840.1.101.3.4.2.1";
+/// Tolerated clock difference between the gateway and the responder, in seconds.
+const CLOCK_SKEW: i64 = 5 * 60;
+
+/// check returns the status the stapled OCSP response reports for the end-entity certificate. The response must
+/// be signed by the certificate's issuer, or by a responder the issuer delegated to, and be current.
+pub fn check(
+	staple: &[u8],
+	end_entity: &CertificateDer<'_>,
+	intermediates: &[CertificateDer<'_>],
+	roots: &RootCertStore,
+	now: UnixTime,
+) -> anyhow::Result<Status> {
+	if staple.is_empty() {
+		bail!("no OCSP response was stapled");
+	}
+	let (_, leaf) =
+		x509_parser::parse_x509_certificate(end_entity).map_err(|_| anyhow!("invalid certificate"))?;
+	let issuer = find_issuer(&leaf, intermediates, roots).context("certificate issuer not found")?;
+	let now = now.as_secs() as i64;
+
+	// OCSPResponse ::= SEQUENCE { responseStatus ENUMERATED, responseBytes [0] EXPLICIT ResponseBytes OPTIONAL }
+	let (resp, _) = expect(staple, Tag::Sequence)?;
+	let (status, rest) = expect(resp.any.data, Tag::Enumerated)?;
+	let Enumerated(status) = Enumerated::try_from(status.any).context(INVALID_RESPONSE)?;
+	if status != 0 {
+		bail!("OCSP responder returned status {status}");
+	}
+	// ResponseBytes ::= SEQUENCE { responseType OBJECT IDENTIFIER, response OCTET STRING }
+	let (bytes, _) = context(rest, 0).context(INVALID_RESPONSE)?;
+	let (bytes, _) = expect(bytes.any.data, Tag::Sequence)?;
+	let (typ, rest) = expect(bytes.any.data, Tag::Oid)?;
+	let typ = Oid::try_from(typ.any).context(INVALID_RESPONSE)?;
+	if typ.to_id_string() != OCSP_BASIC {
+		bail!("unsupported OCSP response type {typ}");
+	}
+	// BasicOCSPResponse ::= SEQUENCE { tbsResponseData, signatureAlgorithm, signature BIT STRING, certs [0] EXPLICIT OPTIONAL }
+	let (basic, _) = expect(rest, Tag::OctetString)?;
+	let (basic, _) = expect(basic.any.data, Tag::Sequence)?;
+	let (tbs, rest) = expect(basic.any.data, Tag::Sequence)?;
+	let (signature_alg, rest) = expect(rest, Tag::Sequence)?;
+	let (signature, rest) = expect(rest, Tag::BitString)?;
+	let signature = signature.any.data.get(1..).context(INVALID_RESPONSE)?;
+	let certs = match context(rest, 0) {
+		Some((certs, _)) => expect(certs.any.data, Tag::Sequence)?.0.any.data,
+		None => &[],
+	};
+	let signed = verify_signature(&issuer, signature_alg.any.data, tbs.raw, signature)
+		|| delegated_responders(certs, &leaf, &issuer, now)
+			.iter()
+			.any(|responder| verify_signature(responder, signature_alg.any.data, tbs.raw, signature));
+	if !signed {
+		bail!("OCSP response is not signed by the certificate issuer");
+	}
+
+	// ResponseData ::= SEQUENCE { version [0] EXPLICIT OPTIONAL, responderID, producedAt, responses SEQUENCE OF SingleResponse, ... }
+	let mut fields = tbs.any.data;
+	if let Some((_, rest)) = context(fields, 0) {
+		fields = rest;
+	}
+	let (_, rest) = element(fields)?;
+	let (_, rest) = expect(rest, Tag::GeneralizedTime)?;
+	let (responses, _) = expect(rest, Tag::Sequence)?;
+	let mut responses = responses.any.data;
+	while !responses.is_empty() {
+		// SingleResponse ::= SEQUENCE { certID, certStatus, thisUpdate, nextUpdate [0] EXPLICIT OPTIONAL, ... }
+		let (single, rest) = expect(responses, Tag::Sequence)?;
+		responses = rest;
+		let (cert_id, rest) = expect(single.any.data, Tag::Sequence)?;
+		if !matches_certificate(cert_id.any.data, &leaf, &issuer)? {
+			continue;
+		}
+		let (cert_status, rest) = element(rest)?;
+		let (this_update, rest) = expect(rest, Tag::GeneralizedTime)?;
+		if time(this_update)? > now + CLOCK_SKEW {
+			bail!("OCSP response is not yet valid");
+		}
+		if let Some((next_update, _)) = context(rest, 0) {
+			let (next_update, _) = expect(next_update.any.data, Tag::GeneralizedTime)?;
+			if time(next_update)? < now - CLOCK_SKEW {
+				bail!("OCSP response has expired");
+			}
+		}
+		// CertStatus ::= CHOICE { good [0] IMPLICIT NULL, revoked [1] IMPLICIT RevokedInfo, unknown [2] IMPLICIT UnknownInfo }
+		return match (cert_status.any.header.class(), cert_status.any.header.tag()) {
+			(Class::ContextSpecific, Tag(0)) => Ok(Status::Good),
+			(Class::ContextSpecific, Tag(1)) => Ok(Status::Revoked),
+			_ => Ok(Status::Unknown),
+		};
+	}
+	bail!("OCSP response does not cover the certificate")
+}
+
+/// Element is a DER element, along with its complete encoding.
+struct Element<'a> {
+	any: Any<'a>,
+	raw: &'a [u8],
+}
+
+fn element(input: &[u8]) -> anyhow::Result<(Element<'_>, &[u8])> {
+	let (rest, any) = Any::from_der(input).map_err(|_| anyhow!(INVALID_RESPONSE))?;
+	let raw = &input[..input.len() - rest.len()];
+	Ok((Element { any, raw }, rest))
+}
+
+/// expect reads the next element, which must have the given universal tag.
+fn expect(input: &[u8], tag: Tag) -> anyhow::Result<(Element<'_>, &[u8])> {
+	let (e, rest) = element(input)?;
+	if e.any.header.class() != Class::Universal || e.any.header.tag() != tag {
+		bail!(INVALID_RESPONSE);
+	}
+	Ok((e, rest))
+}
+
+/// context reads the next element if it has the given context-specific tag.
+fn context(input: &[u8], tag: u32) -> Option<(Element<'_>, &[u8])> {
+	let (e, rest) = element(input).ok()?;
+	(e.any.header.class() == Class::ContextSpecific && e.any.header.tag() == Tag(tag))
+		.then_some((e, rest))
+}
+
+fn time(e: Element<'_>) -> anyhow::Result<i64> {
+	let t = GeneralizedTime::try_from(e.any).context(INVALID_RESPONSE)?;
+	Ok(
+		t.0
+			.to_datetime()
+			.context(INVALID_RESPONSE)?
+			.unix_timestamp(),
+	)
+}
+
+/// PublicKey is a subject public key, along with the contents of its AlgorithmIdentifier.
+struct PublicKey {
+	algorithm: Vec<u8>,
+	key: Vec<u8>,
+}
+
+impl PublicKey {
+	/// from_spki reads the contents of a SubjectPublicKeyInfo.
+	fn from_spki(contents: &[u8]) -> anyhow::Result<PublicKey> {
+		let (algorithm, rest) = expect(contents, Tag::Sequence)?;
+		let (key, _) = expect(rest, Tag::BitString)?;
+		Ok(PublicKey {
+			algorithm: algorithm.any.data.to_vec(),
+			key: key.any.data.get(1..).context(INVALID_RESPONSE)?.to_vec(),
+		})
+	}
+
+	fn from_certificate(cert: &X509Certificate<'_>) -> anyhow::Result<PublicKey> {
+		PublicKey::from_spki(expect(cert.public_key().raw, Tag::Sequence)?.0.any.data)
+	}
+}
+
+/// find_issuer returns the public key of the certificate's issuer, which is either the first intermediate or a
+/// trusted root.
+fn find_issuer(
+	leaf: &X509Certificate<'_>,
+	intermediates: &[CertificateDer<'_>],
+	roots: &RootCertStore,
+) -> Option<PublicKey> {
+	let issuer = leaf.issuer().as_raw();
+	if let Some(intermediate) = intermediates.first()
+		&& let Ok((_, cert)) = x509_parser::parse_x509_certificate(intermediate)
+		&& cert.subject().as_raw() == issuer
+	{
+		return PublicKey::from_certificate(&cert).ok();
+	}
+	// Trust anchors hold the contents of the subject, without its outer SEQUENCE.
+	let (issuer, _) = expect(issuer, Tag::Sequence).ok()?;
+	roots
+		.roots
+		.iter()
+		.find(|root| root.subject.as_ref() == issuer.any.data)
+		.and_then(|root| PublicKey::from_spki(root.subject_public_key_info.as_ref()).ok())
+}
+
+/// delegated_responders returns the keys of the certificates in the response that the issuer authorized to sign
+/// OCSP responses.
+fn delegated_responders(
+	mut certs: &[u8],
+	leaf: &X509Certificate<'_>,
+	issuer: &PublicKey,
+	now: i64,
+) -> Vec<PublicKey> {
+	let Ok(now) = ASN1Time::from_timestamp(now) else {
+		return Vec::new();
+	};
+	let mut responders = Vec::new();
+	while let Ok((cert, rest)) = element(certs) {
+		certs = rest;
+		let Ok((_, parsed)) = x509_parser::parse_x509_certificate(cert.raw) else {
+			continue;
+		};
+		let ocsp_signing = parsed
+			.extended_key_usage()
+			.ok()
+			.flatten()
+			.is_some_and(|eku| eku.value.ocsp_signing);
+		if parsed.issuer().as_raw() != leaf.issuer().as_raw()
+			|| !ocsp_signing
+			|| !parsed.validity().is_valid_at(now)
+		{
+			continue;
+		}
+		// Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue BIT STRING }
+		let signed_by_issuer = (|| {
+			let (tbs, rest) = expect(cert.any.data, Tag::Sequence).ok()?;
+			let (algorithm, rest) = expect(rest, Tag::Sequence).ok()?;
+			let (signature, _) = expect(rest, Tag::BitString).ok()?;
+			let signature = signature.any.data.get(1..)?;
+			Some(verify_signature(
+				issuer,
+				algorithm.any.data,
+				tbs.raw,
+				signature,
+			))
+		})();
+		if signed_by_issuer == Some(true)
+			&& let Ok(key) = PublicKey::from_certificate(&parsed)
+		{
+			responders.push(key);
+		}
+	}
+	responders
+}
+
+/// verify_signature checks the signature over message, using the algorithms supported by the TLS provider.
+fn verify_signature(key: &PublicKey, algorithm: &[u8], message: &[u8], signature: &[u8]) -> bool {
+	transport::tls::provider()
+		.signature_verification_algorithms
+		.all
+		.iter()
+		.filter(|alg| {
+			alg.public_key_alg_id().as_ref() == key.algorithm.as_slice()
+				&& alg.signature_alg_id().as_ref() == algorithm
+		})
+		.any(|alg| alg.verify_signature(&key.key, message, signature).is_ok())
+}
+
+/// matches_certificate reports whether a CertID identifies the certificate.
+fn matches_certificate(
+	cert_id: &[u8],
+	leaf: &X509Certificate<'_>,
+	issuer: &PublicKey,
+) -> anyhow::Result<bool> {
+	// CertID ::= SEQUENCE { hashAlgorithm, issuerNameHash OCTET STRING, issuerKeyHash OCTET STRING, serialNumber INTEGER }
+	let (algorithm, rest) = expect(cert_id, Tag::Sequence)?;
+	let (algorithm, _) = expect(algorithm.any.data, Tag::Oid)?;
+	let algorithm = Oid::try_from(algorithm.any).context(INVALID_RESPONSE)?;
+	let (name_hash, rest) = expect(rest, Tag::OctetString)?;
+	let (key_hash, rest) = expect(rest, Tag::OctetString)?;
+	let (serial, _) = expect(rest, Tag::Integer)?;
+	let hash = |b: &[u8]| -> Option<Vec<u8>> {
+		match algorithm.to_id_string().as_str() {
+			SHA1 => Some(Sha1::digest(b).to_vec()),
+			SHA256 => Some(Sha256::digest(b).to_vec()),
+			_ => None,
+		}
+	};
+	Ok(
+		serial.any.data == leaf.raw_serial()
+			&& hash(leaf.issuer().as_raw()).is_some_and(|h| h == name_hash.any.data)
+			&& hash(&issuer.key).is_some_and(|h| h == key_hash.any.data),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use rcgen::{
+		BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair,
+		SigningKey,
+	};
+
+	use super::*;
+
+	fn der(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+		let contents = parts.concat();
+		let mut out = vec![tag];
+		if contents.len() < 0x80 {
+			out.push(contents.len() as u8);
+		} else {
+			let len = (contents.len() as u32).to_be_bytes();
+			let skip = len.iter().take_while(|b| **b == 0).count();
+			out.push(0x80 | (len.len() - skip) as u8);
+			out.extend_from_slice(&len[skip..]);
+		}
+		out.extend(contents);
+		out
+	}
+
+	fn generalized_time(secs: i64) -> Vec<u8> {
+		let t = chrono::DateTime::from_timestamp(secs, 0).unwrap();
+		der(0x18, &[t.format("%Y%m%d%H%M%SZ").to_string().as_bytes()])
+	}
+
+	struct Pki {
+		ca_params: CertificateParams,
+		ca_key: KeyPair,
+		ca: CertificateDer<'static>,
+		leaf: CertificateDer<'static>,
+		roots: RootCertStore,
+	}
+
+	fn pki() -> Pki {
+		let ca_key = KeyPair::generate().unwrap();
+		let mut ca_params = CertificateParams::default();
+		ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+		ca_params
+			.distinguished_name
+			.push(DnType::CommonName, "test ca");
+		let ca = ca_params.self_signed(&ca_key).unwrap().der().clone();
+		let leaf_key = KeyPair::generate().unwrap();
+		let leaf = CertificateParams::new(vec!["backend.example.com".to_string()])
+			.unwrap()
+			.signed_by(&leaf_key, &Issuer::from_params(&ca_params, &ca_key))
+			.unwrap()
+			.der()
+			.clone();
+		let mut roots = RootCertStore::empty();
+		roots.add(ca.clone()).unwrap();
+		Pki {
+			ca_params,
+			ca_key,
+			ca,
+			leaf,
+			roots,
+		}
+	}
+
+	/// responder issues a certificate for a delegated OCSP responder.
+	fn responder(pki: &Pki, ocsp_signing: bool) -> (KeyPair, CertificateDer<'static>) {
+		let key = KeyPair::generate().unwrap();
+		let mut params = CertificateParams::default();
+		params.distinguished_name.push(DnType::CommonName, "ocsp");
+		if ocsp_signing {
+			params.extended_key_usages = vec![ExtendedKeyUsagePurpose::OcspSigning];
+		}
+		let cert = params
+			.signed_by(&key, &Issuer::from_params(&pki.ca_params, &pki.ca_key))
+			.unwrap()
+			.der()
+			.clone();
+		(key, cert)
+	}
+
+	const GOOD: &[u8] = &[0x80, 0x00];
+
+	/// response builds an OCSP response for the leaf certificate, signed by signer.
+	fn response(
+		pki: &Pki,
+		cert_status: &[u8],
+		next_update: i64,
+		signer: &KeyPair,
+		certs: &[&[u8]],
+	) -> Vec<u8> {
+		let (_, leaf) = x509_parser::parse_x509_certificate(&pki.leaf).unwrap();
+		let (_, ca) = x509_parser::parse_x509_certificate(&pki.ca).unwrap();
+		let now = UnixTime::now().as_secs() as i64;
+		let sha1 = der(
+			0x30,
+			&[
+				&der(0x06, &[&[0x2b, 0x0e, 0x03, 0x02, 0x1a]]),
+				&[0x05, 0x00],
+			],
+		);
+		let cert_id = der(
+			0x30,
+			&[
+				&sha1,
+				&der(0x04, &[Sha1::digest(leaf.issuer().as_raw()).as_slice()]),
+				&der(
+					0x04,
+					&[Sha1::digest(&ca.public_key().subject_public_key.data).as_slice()],
+				),
+				&der(0x02, &[leaf.raw_serial()]),
+			],
+		);
+		let single = der(
+			0x30,
+			&[
+				&cert_id,
+				cert_status,
+				&generalized_time(now - 60),
+				&der(0xa0, &[&generalized_time(next_update)]),
+			],
+		);
+		let responder_id = der(0xa2, &[&der(0x04, &[&[0u8; 20]])]);
+		let tbs = der(
+			0x30,
+			&[
+				&responder_id,
+				&generalized_time(now - 60),
+				&der(0x30, &[&single]),
+			],
+		);
+		let signature = signer.sign(&tbs).unwrap();
+		let algorithm = der(0x30, &[rustls::pki_types::alg_id::ECDSA_SHA256.as_ref()]);
+		let certs = if certs.is_empty() {
+			vec![]
+		} else {
+			der(0xa0, &[&der(0x30, certs)])
+		};
+		let basic = der(
+			0x30,
+			&[&tbs, &algorithm, &der(0x03, &[&[0], &signature]), &certs],
+		);
+		let ocsp_basic = [0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+		let bytes = der(0x30, &[&der(0x06, &[&ocsp_basic]), &der(0x04, &[&basic])]);
+		der(0x30, &[&der(0x0a, &[&[0]]), &der(0xa0, &[&bytes])])
+	}
+
+	fn check_response(pki: &Pki, staple: &[u8]) -> anyhow::Result<Status> {
+		check(staple, &pki.leaf, &[], &pki.roots, UnixTime::now())
+	}
+
+	#[test]
+	fn status() {
+		let pki = pki();
+		let hour = UnixTime::now().as_secs() as i64 + 3600;
+		let good = response(&pki, GOOD, hour, &pki.ca_key, &[]);
+		assert_eq!(check_response(&pki, &good).unwrap(), Status::Good);
+
+		let revoked = der(0xa1, &[&generalized_time(hour - 7200)]);
+		let revoked = response(&pki, &revoked, hour, &pki.ca_key, &[]);
+		assert_eq!(check_response(&pki, &revoked).unwrap(), Status::Revoked);
+
+		let unknown = response(&pki, &[0x82, 0x00], hour, &pki.ca_key, &[]);
+		assert_eq!(check_response(&pki, &unknown).unwrap(), Status::Unknown);
+
+		// The issuer can be the first intermediate rather than a root.
+		let res = check(
+			&good,
+			&pki.leaf,
+			&[pki.ca.clone()],
+			&RootCertStore::empty(),
+			UnixTime::now(),
+		);
+		assert_eq!(res.unwrap(), Status::Good);
+	}
+
+	#[test]
+	fn invalid() {
+		let pki = pki();
+		let hour = UnixTime::now().as_secs() as i64 + 3600;
+		assert!(check_response(&pki, &[]).is_err());
+		assert!(check_response(&pki, b"not ocsp").is_err());
+
+		let expired = response(&pki, GOOD, hour - 7200, &pki.ca_key, &[]);
+		assert!(check_response(&pki, &expired).is_err());
+
+		let other = KeyPair::generate().unwrap();
+		let forged = response(&pki, GOOD, hour, &other, &[]);
+		assert!(check_response(&pki, &forged).is_err());
+	}
+
+	#[test]
+	fn delegated_responder() {
+		let pki = pki();
+		let hour = UnixTime::now().as_secs() as i64 + 3600;
+		let (key, cert) = responder(&pki, true);
+		let good = response(&pki, GOOD, hour, &key, &[&cert]);
+		assert_eq!(check_response(&pki, &good).unwrap(), Status::Good);
+
+		// A certificate from the same issuer is not enough; it must be authorized for OCSP signing.
+		let (key, cert) = responder(&pki, false);
+		let forged = response(&pki, GOOD, hour, &key, &[&cert]);
+		assert!(check_response(&pki, &forged).is_err());
+	}
+}
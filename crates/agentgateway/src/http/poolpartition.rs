@@ -0,0 +1,89 @@
+use std::num::NonZeroU32;
+
+use crate::cel::{Executor, Expression};
+use crate::http::Request;
+use crate::*;
+
+/// PoolPartition splits the upstream connection pool by a key derived from the request (for example, a tenant),
+/// so a burst from one partition cannot consume all pooled connections and streams to a shared upstream.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct PoolPartition {
+	/// CEL expression evaluated against the request to select the partition. The expression must return a string;
+	/// requests where it fails or returns another type use the shared, unpartitioned pool.
+	#[serde(deserialize_with = "de_expression")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub key: Arc<Expression>,
+	/// Maximum number of concurrent requests, per upstream, for each partition. Requests above the limit wait for
+	/// an in-flight request in the same partition to complete.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub max_streams: Option<NonZeroU32>,
+}
+
+impl PoolPartition {
+	pub fn expressions(&self) -> impl Iterator<Item = &Expression> {
+		std::iter::once(self.key.as_ref())
+	}
+
+	/// apply evaluates the partition key and attaches it to the request, to be picked up by the upstream client.
+	/// The route is only used to label metrics.
+	pub fn apply(&self, req: &mut Request, exec: &Executor, route: Option<Strng>) {
+		let key = match exec.eval(&self.key) {
+			Ok(cel::Value::String(s)) => strng::new(s.as_str()),
+			Ok(_) => {
+				trace!("pool partition key did not evaluate to a string, using shared pool");
+				return;
+			},
+			Err(err) => {
+				trace!(
+					?err,
+					"pool partition key failed to evaluate, using shared pool"
+				);
+				return;
+			},
+		};
+		req.extensions_mut().insert(client::PoolPartition {
+			key,
+			route,
+			max_streams: self.max_streams,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn select(key: &str, tenant: Option<&str>) -> Option<client::PoolPartition> {
+		let p = PoolPartition {
+			key: Arc::new(Expression::new(key).unwrap()),
+			max_streams: NonZeroU32::new(4),
+		};
+		let mut builder = ::http::Request::builder();
+		if let Some(tenant) = tenant {
+			builder = builder.header("x-tenant", tenant);
+		}
+		let mut req = builder.body(http::Body::empty()).unwrap();
+		let mut ctx = cel::ContextBuilder::new();
+		for e in p.expressions() {
+			ctx.register_expression(e);
+		}
+		ctx.with_request(&req);
+		let exec = ctx.build().unwrap();
+		p.apply(&mut req, &exec, Some(strng::literal!("route")));
+		req.extensions_mut().remove::<client::PoolPartition>()
+	}
+
+	#[test]
+	fn selects_partition() {
+		let pp = select(r#"request.headers["x-tenant"]"#, Some("acme")).unwrap();
+		assert_eq!(pp.key.as_str(), "acme");
+		assert_eq!(pp.route.as_deref(), Some("route"));
+		assert_eq!(pp.max_streams, NonZeroU32::new(4));
+
+		// Non-string results and evaluation failures fall back to the shared pool.
+		assert!(select(r#"size(request.headers["x-tenant"])"#, Some("acme")).is_none());
+		assert!(select(r#"request.headers["x-tenant"]"#, None).is_none());
+	}
+}
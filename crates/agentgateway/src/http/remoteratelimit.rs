@@ -24,6 +24,8 @@ pub struct RemoteRateLimit {
 	pub domain: String,
 	pub target: Arc<SimpleBackendReference>,
 	pub descriptors: Arc<DescriptorSet>,
+	#[serde(skip_serializing_if = "is_default")]
+	pub mode: http::PolicyMode,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -0,0 +1,458 @@
+use std::sync::Weak;
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use itertools::Itertools;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, CertificateRevocationListDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tracing::event;
+
+use crate::client::Client;
+use crate::http::{Body, ocsp};
+use crate::transport;
+use crate::transport::tls;
+use crate::*;
+
+/// Maximum size of a fetched CRL.
+const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+#[apply(schema!)]
+pub struct RevocationConfig {
+	/// Certificate revocation lists to check the backend certificate against, as file paths or `http(s)://` URLs.
+	/// CRLs may be PEM or DER encoded.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub crls: Vec<String>,
+	/// Check the OCSP response the backend staples to the handshake. The response must be signed by the
+	/// certificate's issuer or a responder it delegated to; responses are not fetched from the responder.
+	/// At least one CRL or `ocsp` is required.
+	#[serde(default)]
+	pub ocsp: bool,
+	/// How often CRLs are reloaded. Defaults to 1h.
+	#[serde(default = "default_refresh_interval", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub refresh_interval: Duration,
+	/// Timeout for fetching a CRL. Defaults to 5s.
+	#[serde(default = "default_timeout", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub timeout: Duration,
+	/// How to handle a certificate whose revocation status cannot be determined. Defaults to `softFail`.
+	#[serde(default)]
+	pub mode: RevocationMode,
+}
+
+fn default_refresh_interval() -> Duration {
+	Duration::from_secs(60 * 60)
+}
+
+fn default_timeout() -> Duration {
+	Duration::from_secs(5)
+}
+
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum RevocationMode {
+	/// Allow the connection if the status cannot be determined, for example because a CRL has not been
+	/// fetched yet, no CRL covers the certificate, or the backend did not staple a valid OCSP response.
+	/// Revoked certificates are always rejected.
+	#[default]
+	SoftFail,
+	/// Reject the connection unless the certificate is known not to be revoked.
+	HardFail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+	Good,
+	Revoked,
+	Unknown,
+}
+
+impl Status {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Status::Good => "good",
+			Status::Revoked => "revoked",
+			Status::Unknown => "unknown",
+		}
+	}
+}
+
+/// Revocation checks the certificate presented by a backend against CRLs and its stapled OCSP response, during
+/// the TLS handshake. Only the end-entity certificate is checked.
+pub struct Revocation {
+	config: RevocationConfig,
+	client: Client,
+	roots: Arc<RootCertStore>,
+	insecure_host: bool,
+	/// The CRLs most recently loaded from each source. A source that fails to reload keeps its previous CRLs.
+	crls: Mutex<IndexMap<String, Vec<CertificateRevocationListDer<'static>>>>,
+	verifiers: ArcSwap<Verifiers>,
+}
+
+impl fmt::Debug for Revocation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Revocation")
+			.field("config", &self.config)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Debug)]
+struct Verifiers {
+	/// Verifies the certificate chain, without checking revocation.
+	chain: Arc<dyn ServerCertVerifier>,
+	/// Checks the end-entity certificate against the loaded CRLs. Until any are loaded, the CRL status of every
+	/// certificate is unknown.
+	crls: Option<Arc<dyn ServerCertVerifier>>,
+}
+
+impl Revocation {
+	/// new creates a revocation checker. CRLs from files are loaded immediately; CRLs from URLs are loaded in the
+	/// background, so are not available for the first connections.
+	pub fn new(
+		config: RevocationConfig,
+		client: Client,
+		roots: Arc<RootCertStore>,
+		insecure_host: bool,
+	) -> anyhow::Result<Arc<Revocation>> {
+		if config.crls.is_empty() && !config.ocsp {
+			anyhow::bail!("revocation checking requires at least one CRL or ocsp");
+		}
+		let mut crls = IndexMap::new();
+		for source in config.crls.iter().filter(|s| !is_url(s)) {
+			let crl =
+				parse_crls(&fs_err::read(source)?).with_context(|| format!("invalid CRL {source}"))?;
+			crls.insert(source.clone(), crl);
+		}
+		let verifiers = build_verifiers(
+			&roots,
+			crls.values().flatten().cloned().collect(),
+			insecure_host,
+		)?;
+		let revocation = Arc::new(Revocation {
+			config,
+			client,
+			roots,
+			insecure_host,
+			crls: Mutex::new(crls),
+			verifiers: ArcSwap::from_pointee(verifiers),
+		});
+		if !revocation.config.crls.is_empty() {
+			match tokio::runtime::Handle::try_current() {
+				Ok(rt) => {
+					rt.spawn(refresh(Arc::downgrade(&revocation)));
+				},
+				Err(_) => warn!("no runtime available, CRLs will not be refreshed"),
+			}
+		}
+		Ok(revocation)
+	}
+
+	async fn reload_crls(&self) {
+		let mut changed = false;
+		for source in &self.config.crls {
+			let res = if is_url(source) {
+				self.fetch(source).await
+			} else {
+				fs_err::tokio::read(source)
+					.await
+					.map(Bytes::from)
+					.map_err(Into::into)
+			};
+			match res.and_then(|b| parse_crls(&b)) {
+				Ok(crls) => {
+					self
+						.crls
+						.lock()
+						.expect("mutex acquired")
+						.insert(source.clone(), crls);
+					changed = true;
+				},
+				Err(e) => warn!(%source, "failed to load CRL: {e}"),
+			}
+		}
+		if !changed {
+			return;
+		}
+		let crls = self
+			.crls
+			.lock()
+			.expect("mutex acquired")
+			.values()
+			.flatten()
+			.cloned()
+			.collect_vec();
+		match build_verifiers(&self.roots, crls, self.insecure_host) {
+			Ok(v) => self.verifiers.store(Arc::new(v)),
+			Err(e) => warn!("failed to load CRLs: {e}"),
+		}
+	}
+
+	async fn fetch(&self, url: &str) -> anyhow::Result<Bytes> {
+		let req = ::http::Request::builder().uri(url).body(Body::empty())?;
+		let fetch = async {
+			let resp = self.client.simple_call(req).await?;
+			if !resp.status().is_success() {
+				anyhow::bail!("unexpected status {}", resp.status());
+			}
+			Ok::<_, anyhow::Error>(axum::body::to_bytes(resp.into_body(), MAX_RESPONSE_SIZE).await?)
+		};
+		tokio::time::timeout(self.config.timeout, fetch)
+			.await
+			.context("timed out")?
+	}
+}
+
+async fn refresh(revocation: Weak<Revocation>) {
+	loop {
+		let Some(r) = revocation.upgrade() else {
+			return;
+		};
+		r.reload_crls().await;
+		let interval = r.config.refresh_interval;
+		drop(r);
+		tokio::time::sleep(interval).await;
+	}
+}
+
+fn is_url(source: &str) -> bool {
+	source.starts_with("http://") || source.starts_with("https://")
+}
+
+fn parse_crls(b: &[u8]) -> anyhow::Result<Vec<CertificateRevocationListDer<'static>>> {
+	if b.starts_with(b"-----BEGIN") {
+		let mut reader = std::io::BufReader::new(std::io::Cursor::new(b));
+		let crls = rustls_pemfile::crls(&mut reader).collect::<Result<Vec<_>, _>>()?;
+		if crls.is_empty() {
+			anyhow::bail!("no CRLs found");
+		}
+		Ok(crls)
+	} else {
+		Ok(vec![CertificateRevocationListDer::from(b.to_vec())])
+	}
+}
+
+fn build_verifiers(
+	roots: &Arc<RootCertStore>,
+	crls: Vec<CertificateRevocationListDer<'static>>,
+	insecure_host: bool,
+) -> anyhow::Result<Verifiers> {
+	let wrap = |verifier: Arc<WebPkiServerVerifier>| -> Arc<dyn ServerCertVerifier> {
+		if insecure_host {
+			Arc::new(tls::insecure::NoServerNameVerification::new(verifier))
+		} else {
+			verifier
+		}
+	};
+	let chain =
+		WebPkiServerVerifier::builder_with_provider(roots.clone(), transport::tls::provider())
+			.build()?;
+	let crls = if crls.is_empty() {
+		None
+	} else {
+		let verifier =
+			WebPkiServerVerifier::builder_with_provider(roots.clone(), transport::tls::provider())
+				.with_crls(crls)
+				.only_check_end_entity_revocation()
+				.build()?;
+		Some(wrap(verifier))
+	};
+	Ok(Verifiers {
+		chain: wrap(chain),
+		crls,
+	})
+}
+
+fn log_result(
+	server_name: &ServerName<'_>,
+	method: &str,
+	status: Status,
+	reason: Option<&str>,
+	allowed: bool,
+) {
+	let server_name = server_name.to_str();
+	if status == Status::Good {
+		event!(
+			target: "upstream connection",
+			parent: None,
+			tracing::Level::DEBUG,
+
+			server_name = %server_name,
+			revocation.method = method,
+			revocation.status = status.as_str(),
+
+			"revocation check"
+		);
+	} else {
+		event!(
+			target: "upstream connection",
+			parent: None,
+			tracing::Level::WARN,
+
+			server_name = %server_name,
+			revocation.method = method,
+			revocation.status = status.as_str(),
+			revocation.reason = reason,
+			revocation.allowed = allowed,
+
+			"revocation check"
+		);
+	}
+}
+
+/// RevocationVerifier verifies the backend certificate, then checks its revocation status against the loaded CRLs
+/// and the stapled OCSP response. A certificate is revoked if any method reports it revoked, and good if any
+/// method reports it good.
+#[derive(Debug)]
+pub struct RevocationVerifier(pub Arc<Revocation>);
+
+impl ServerCertVerifier for RevocationVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &CertificateDer<'_>,
+		intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>,
+		ocsp_response: &[u8],
+		now: UnixTime,
+	) -> Result<ServerCertVerified, rustls::Error> {
+		let revocation = &self.0;
+		let verifiers = revocation.verifiers.load();
+		// The certificate is invalid regardless of its revocation status.
+		let verified = verifiers.chain.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			ocsp_response,
+			now,
+		)?;
+
+		let crl = (!revocation.config.crls.is_empty()).then(|| match &verifiers.crls {
+			Some(crls) => {
+				match crls.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now) {
+					Ok(_) => (Status::Good, None),
+					Err(rustls::Error::InvalidCertificate(CertificateError::Revoked)) => {
+						(Status::Revoked, None)
+					},
+					Err(e) => (Status::Unknown, Some(e.to_string())),
+				}
+			},
+			None => (Status::Unknown, Some("no CRLs loaded".to_string())),
+		});
+		let ocsp = revocation.config.ocsp.then(|| {
+			match ocsp::check(
+				ocsp_response,
+				end_entity,
+				intermediates,
+				&revocation.roots,
+				now,
+			) {
+				Ok(status) => (status, None),
+				Err(e) => (Status::Unknown, Some(e.to_string())),
+			}
+		});
+
+		let results = [("crl", crl), ("ocsp", ocsp)];
+		let statuses = results
+			.iter()
+			.filter_map(|(_, r)| r.as_ref().map(|(s, _)| *s));
+		let status = if statuses.clone().any(|s| s == Status::Revoked) {
+			Status::Revoked
+		} else if statuses.clone().any(|s| s == Status::Good) {
+			Status::Good
+		} else {
+			Status::Unknown
+		};
+		let allowed = match status {
+			Status::Good => true,
+			Status::Revoked => false,
+			Status::Unknown => revocation.config.mode == RevocationMode::SoftFail,
+		};
+		for (method, (status, reason)) in results
+			.iter()
+			.filter_map(|(method, r)| r.as_ref().map(|r| (method, r)))
+		{
+			log_result(server_name, method, *status, reason.as_deref(), allowed);
+		}
+		match status {
+			_ if allowed => Ok(verified),
+			Status::Revoked => Err(rustls::Error::InvalidCertificate(CertificateError::Revoked)),
+			_ => Err(rustls::Error::InvalidCertificate(
+				CertificateError::UnknownRevocationStatus,
+			)),
+		}
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self
+			.0
+			.verifiers
+			.load()
+			.chain
+			.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self
+			.0
+			.verifiers
+			.load()
+			.chain
+			.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.0.verifiers.load().chain.supported_verify_schemes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn crl_formats() {
+		let der = b"\x30\x03\x02\x01\x01";
+		let crls = parse_crls(der).unwrap();
+		assert_eq!(crls.len(), 1);
+		assert_eq!(crls[0].as_ref(), der);
+
+		let pem = b"-----BEGIN X509 CRL-----\nMAMCAQE=\n-----END X509 CRL-----\n";
+		let crls = parse_crls(pem).unwrap();
+		assert_eq!(crls.len(), 1);
+		assert_eq!(crls[0].as_ref(), der);
+
+		let cert = b"-----BEGIN CERTIFICATE-----\nMAMCAQE=\n-----END CERTIFICATE-----\n";
+		assert!(parse_crls(cert).is_err());
+	}
+
+	#[tokio::test]
+	async fn requires_crls() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = Client::new(&cfg.dns, None);
+		let config = RevocationConfig {
+			crls: vec![],
+			ocsp: false,
+			refresh_interval: default_refresh_interval(),
+			timeout: default_timeout(),
+			mode: RevocationMode::SoftFail,
+		};
+		let err = Revocation::new(config, client, Arc::new(RootCertStore::empty()), false).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"revocation checking requires at least one CRL or ocsp"
+		);
+	}
+}
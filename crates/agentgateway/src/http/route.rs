@@ -104,6 +104,7 @@ pub fn select_best_route(
 			policies: None,
 			backends: vec![RouteBackendReference {
 				weight: 1,
+				priority: 0,
 				backend: BackendReference::Service {
 					name: svc.namespaced_hostname(),
 					port: dst.port(), // TODO: get from req
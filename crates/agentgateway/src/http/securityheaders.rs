@@ -0,0 +1,255 @@
+use ::http::{HeaderValue, Method, StatusCode, header};
+use serde::de::Error;
+
+use crate::http::{PolicyResponse, Request, filters};
+use crate::transport::stream::TLSConnectionInfo;
+use crate::*;
+
+/// SecurityHeaders adds common security headers to responses, and optionally redirects plaintext requests
+/// to HTTPS.
+#[apply(schema_ser!)]
+#[cfg_attr(feature = "schema", schemars(with = "SecurityHeadersSerde"))]
+pub struct SecurityHeaders {
+	#[serde(flatten)]
+	config: SecurityHeadersSerde,
+	#[serde(skip)]
+	headers: Vec<(header::HeaderName, HeaderValue)>,
+	#[serde(skip)]
+	hsts: Option<HeaderValue>,
+}
+
+impl<'de> serde::Deserialize<'de> for SecurityHeaders {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		SecurityHeaders::try_from(SecurityHeadersSerde::deserialize(deserializer)?)
+			.map_err(D::Error::custom)
+	}
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct SecurityHeadersSerde {
+	/// Redirect requests received without TLS to the same URL over HTTPS.
+	#[serde(default)]
+	pub https_redirect: bool,
+	/// Send `Strict-Transport-Security` on responses to requests received over TLS.
+	#[serde(default)]
+	pub hsts: Option<Hsts>,
+	/// Value of `X-Content-Type-Options`. Defaults to `nosniff`; set to an empty string to omit it.
+	#[serde(default = "default_content_type_options")]
+	pub content_type_options: String,
+	/// Value of `Referrer-Policy`. Defaults to `strict-origin-when-cross-origin`; set to an empty string to
+	/// omit it.
+	#[serde(default = "default_referrer_policy")]
+	pub referrer_policy: String,
+	/// Value of `Permissions-Policy`, if any.
+	#[serde(default)]
+	pub permissions_policy: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Hsts {
+	/// How long browsers should only connect over HTTPS. Defaults to 1 year.
+	#[serde(default = "default_hsts_max_age", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub max_age: Duration,
+	#[serde(default)]
+	pub include_subdomains: bool,
+	#[serde(default)]
+	pub preload: bool,
+}
+
+fn default_content_type_options() -> String {
+	"nosniff".to_string()
+}
+
+fn default_referrer_policy() -> String {
+	"strict-origin-when-cross-origin".to_string()
+}
+
+fn default_hsts_max_age() -> Duration {
+	Duration::from_secs(365 * 24 * 60 * 60)
+}
+
+const PERMISSIONS_POLICY: header::HeaderName =
+	header::HeaderName::from_static("permissions-policy");
+
+impl TryFrom<SecurityHeadersSerde> for SecurityHeaders {
+	type Error = anyhow::Error;
+	fn try_from(config: SecurityHeadersSerde) -> Result<Self, Self::Error> {
+		let mut headers = vec![];
+		if !config.content_type_options.is_empty() {
+			headers.push((
+				header::X_CONTENT_TYPE_OPTIONS,
+				HeaderValue::from_str(&config.content_type_options)?,
+			));
+		}
+		if !config.referrer_policy.is_empty() {
+			headers.push((
+				header::REFERRER_POLICY,
+				HeaderValue::from_str(&config.referrer_policy)?,
+			));
+		}
+		if let Some(pp) = &config.permissions_policy {
+			headers.push((PERMISSIONS_POLICY, HeaderValue::from_str(pp)?));
+		}
+		let hsts = config
+			.hsts
+			.as_ref()
+			.map(|h| {
+				let mut v = format!("max-age={}", h.max_age.as_secs());
+				if h.include_subdomains {
+					v.push_str("; includeSubDomains");
+				}
+				if h.preload {
+					v.push_str("; preload");
+				}
+				HeaderValue::from_str(&v)
+			})
+			.transpose()?;
+		Ok(SecurityHeaders {
+			config,
+			headers,
+			hsts,
+		})
+	}
+}
+
+impl SecurityHeaders {
+	pub fn apply(&self, req: &Request) -> Result<PolicyResponse, filters::Error> {
+		let tls = req.extensions().get::<TLSConnectionInfo>().is_some();
+		if self.config.https_redirect && !tls {
+			return Ok(PolicyResponse {
+				direct_response: Some(https_redirect(req)?),
+				response_headers: None,
+			});
+		}
+
+		let mut response_headers = http::HeaderMap::with_capacity(self.headers.len() + 1);
+		for (k, v) in &self.headers {
+			response_headers.insert(k.clone(), v.clone());
+		}
+		// HSTS must only be sent over secure transport (RFC 6797 7.2).
+		if tls && let Some(hsts) = &self.hsts {
+			response_headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+		}
+		Ok(PolicyResponse {
+			direct_response: None,
+			response_headers: Some(response_headers),
+		})
+	}
+}
+
+fn https_redirect(req: &Request) -> Result<http::Response, filters::Error> {
+	let host =
+		req
+			.uri()
+			.authority()
+			.map(|a| a.host())
+			.ok_or(filters::Error::InvalidFilterConfiguration(
+				"no host for HTTPS redirect".to_string(),
+			))?;
+	let path = req
+		.uri()
+		.path_and_query()
+		.map(|p| p.as_str())
+		.unwrap_or("/");
+	// Only GET and HEAD may be changed to GET by clients following a 301; otherwise preserve the method.
+	let status = if req.method() == Method::GET || req.method() == Method::HEAD {
+		StatusCode::MOVED_PERMANENTLY
+	} else {
+		StatusCode::PERMANENT_REDIRECT
+	};
+	Ok(
+		::http::Response::builder()
+			.status(status)
+			.header(header::LOCATION, format!("https://{host}{path}"))
+			.body(http::Body::empty())?,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn policy(v: serde_json::Value) -> SecurityHeaders {
+		serde_json::from_value(v).unwrap()
+	}
+
+	fn request(method: Method, tls: bool) -> Request {
+		let mut req = ::http::Request::builder()
+			.method(method)
+			.uri("http://example.com:8080/a?b=c")
+			.body(http::Body::empty())
+			.unwrap();
+		if tls {
+			req.extensions_mut().insert(TLSConnectionInfo {
+				src_identity: None,
+				server_name: None,
+				negotiated_alpn: None,
+				version: None,
+				cipher: None,
+				resumed: false,
+			});
+		}
+		req
+	}
+
+	#[test]
+	fn headers() {
+		let p = policy(json!({"hsts": {"includeSubdomains": true}}));
+		let resp = p.apply(&request(Method::GET, false)).unwrap();
+		let headers = resp.response_headers.unwrap();
+		assert_eq!(
+			headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+			"nosniff"
+		);
+		assert_eq!(
+			headers.get(header::REFERRER_POLICY).unwrap(),
+			"strict-origin-when-cross-origin"
+		);
+		assert!(headers.get(header::STRICT_TRANSPORT_SECURITY).is_none());
+
+		let resp = p.apply(&request(Method::GET, true)).unwrap();
+		assert_eq!(
+			resp
+				.response_headers
+				.unwrap()
+				.get(header::STRICT_TRANSPORT_SECURITY)
+				.unwrap(),
+			"max-age=31536000; includeSubDomains"
+		);
+
+		let p = policy(json!({"contentTypeOptions": "", "referrerPolicy": ""}));
+		let resp = p.apply(&request(Method::GET, false)).unwrap();
+		assert!(resp.response_headers.unwrap().is_empty());
+	}
+
+	#[test]
+	fn redirect() {
+		let p = policy(json!({"httpsRedirect": true}));
+		let resp = p.apply(&request(Method::GET, false)).unwrap();
+		let dr = resp.direct_response.unwrap();
+		assert_eq!(dr.status(), StatusCode::MOVED_PERMANENTLY);
+		assert_eq!(
+			dr.headers().get(header::LOCATION).unwrap(),
+			"https://example.com/a?b=c"
+		);
+
+		let resp = p.apply(&request(Method::POST, false)).unwrap();
+		assert_eq!(
+			resp.direct_response.unwrap().status(),
+			StatusCode::PERMANENT_REDIRECT
+		);
+
+		let resp = p.apply(&request(Method::GET, true)).unwrap();
+		assert!(resp.direct_response.is_none());
+	}
+}
@@ -1,13 +1,17 @@
 use std::cmp;
+use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
 use std::time::Duration;
 
 use http_body::{Body, SizeHint};
+use itertools::Itertools;
 use pin_project_lite::pin_project;
+use serde::de::Error;
 use tokio::time::{Instant, Sleep, sleep_until};
 
+use crate::types::agent::BackendName;
 use crate::*;
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -28,14 +32,39 @@ pub struct Policy {
 	)]
 	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
 	pub backend_request_timeout: Option<Duration>,
+	/// Derive the timeout from the recent latency of each backend. It applies from the start of each
+	/// upstream call; if a fixed timeout is also set, whichever expires first is used.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub adaptive: Option<Adaptive>,
 }
 
 impl Policy {
+	/// deadline returns when a call to the backend times out. The fixed timeout is measured from the start of
+	/// the request, while the adaptive timeout is learned from upstream latency, so is measured from the start
+	/// of the upstream call.
+	pub fn deadline(
+		&self,
+		backend: &BackendName,
+		request_start: std::time::Instant,
+		upstream_start: std::time::Instant,
+	) -> Option<std::time::Instant> {
+		let fixed = self.effective_timeout().map(|t| request_start + t);
+		let adaptive = self
+			.adaptive
+			.as_ref()
+			.map(|a| upstream_start + a.timeout(backend));
+		match (fixed, adaptive) {
+			(Some(fixed), Some(adaptive)) => Some(cmp::min(fixed, adaptive)),
+			(fixed, adaptive) => fixed.or(adaptive),
+		}
+	}
+
 	pub fn effective_timeout(&self) -> Option<Duration> {
 		match self {
 			Policy {
 				request_timeout: Some(request_timeout),
 				backend_request_timeout: Some(backend_request_timeout),
+				..
 			} => {
 				// We do not distinguish these yet, so just take min
 				// TODO: one should apply to per-request attempt
@@ -54,6 +83,129 @@ impl Policy {
 	}
 }
 
+/// Adaptive derives a timeout from the latencies of recent requests to each backend: the latency at
+/// `percentile`, multiplied by `factor`, and clamped between `min` and `max`.
+/// Until `minSamples` requests to a backend have completed, `max` is used.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "AdaptiveSerde"))]
+pub struct Adaptive {
+	config: AdaptiveSerde,
+	latencies: Arc<Mutex<HashMap<BackendName, VecDeque<Duration>>>>,
+}
+
+impl serde::Serialize for Adaptive {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Adaptive {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = AdaptiveSerde::deserialize(deserializer)?;
+		if !(config.percentile > 0.0 && config.percentile <= 1.0) {
+			return Err(D::Error::custom("percentile must be between 0 and 1"));
+		}
+		if config.factor <= 0.0 {
+			return Err(D::Error::custom("factor must be greater than 0"));
+		}
+		if config.min > config.max {
+			return Err(D::Error::custom("min must not be greater than max"));
+		}
+		if config.window == 0 {
+			return Err(D::Error::custom("window must be greater than 0"));
+		}
+		Ok(Adaptive {
+			config,
+			latencies: Default::default(),
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct AdaptiveSerde {
+	/// The latency percentile to base the timeout on, between 0 and 1. Defaults to 0.99.
+	#[serde(default = "default_percentile")]
+	pub percentile: f64,
+	/// Multiplier applied to the percentile latency. Defaults to 2.
+	#[serde(default = "default_factor")]
+	pub factor: f64,
+	/// The lowest timeout to use. Defaults to 1s.
+	#[serde(default = "default_min", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub min: Duration,
+	/// The highest timeout to use.
+	#[serde(with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub max: Duration,
+	/// Number of recent latencies to keep for each backend. Defaults to 100.
+	#[serde(default = "default_window")]
+	pub window: usize,
+	/// Number of latencies required before the adaptive timeout is used. Defaults to 20.
+	#[serde(default = "default_min_samples")]
+	pub min_samples: usize,
+}
+
+fn default_percentile() -> f64 {
+	0.99
+}
+
+fn default_factor() -> f64 {
+	2.0
+}
+
+fn default_min() -> Duration {
+	Duration::from_secs(1)
+}
+
+fn default_window() -> usize {
+	100
+}
+
+fn default_min_samples() -> usize {
+	20
+}
+
+impl Adaptive {
+	/// timeout computes the current timeout for the backend.
+	pub fn timeout(&self, backend: &BackendName) -> Duration {
+		let latencies = self.latencies.lock().expect("mutex acquired");
+		let Some(window) = latencies.get(backend) else {
+			return self.config.max;
+		};
+		if window.len() < self.config.min_samples.max(1) {
+			return self.config.max;
+		}
+		let mut sorted = window.iter().copied().collect_vec();
+		drop(latencies);
+		sorted.sort_unstable();
+		let idx = ((self.config.percentile * sorted.len() as f64).ceil() as usize)
+			.saturating_sub(1)
+			.min(sorted.len() - 1);
+		Duration::try_from_secs_f64(sorted[idx].as_secs_f64() * self.config.factor)
+			.unwrap_or(self.config.max)
+			.clamp(self.config.min, self.config.max)
+	}
+
+	/// record stores the latency of a call to the backend, excluding the time spent processing the request in
+	/// the gateway. Calls that time out should record the time they were given, so the timeout can grow if the
+	/// backend becomes slower.
+	pub fn record(&self, backend: &BackendName, latency: Duration) {
+		let mut latencies = self.latencies.lock().expect("mutex acquired");
+		let window = latencies.entry(backend.clone()).or_default();
+		if window.len() >= self.config.window {
+			window.pop_front();
+		}
+		window.push_back(latency);
+	}
+}
+
 pub enum BodyTimeout {
 	Deadline(Instant),
 	None,
@@ -141,3 +293,84 @@ impl std::fmt::Display for TimeoutError {
 		write!(f, "data was not received within the designated timeout")
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn adaptive() -> Adaptive {
+		serde_json::from_value(serde_json::json!({
+			"percentile": 0.9,
+			"factor": 2,
+			"min": "100ms",
+			"max": "10s",
+			"window": 10,
+			"minSamples": 5,
+		}))
+		.unwrap()
+	}
+
+	#[test]
+	fn adaptive_timeout() {
+		let a = adaptive();
+		let be = strng::new("be");
+		// Not enough samples yet
+		assert_eq!(a.timeout(&be), Duration::from_secs(10));
+		for ms in 1..=10 {
+			a.record(&be, Duration::from_millis(ms * 100));
+		}
+		// p90 of 100ms..1s is 900ms, doubled
+		assert_eq!(a.timeout(&be), Duration::from_millis(1800));
+		// Old samples are dropped as new ones arrive
+		for _ in 0..10 {
+			a.record(&be, Duration::from_secs(20));
+		}
+		assert_eq!(a.timeout(&be), Duration::from_secs(10));
+		for _ in 0..10 {
+			a.record(&be, Duration::from_millis(1));
+		}
+		assert_eq!(a.timeout(&be), Duration::from_millis(100));
+		// Backends are tracked independently
+		assert_eq!(a.timeout(&strng::new("other")), Duration::from_secs(10));
+	}
+
+	#[test]
+	fn deadline() {
+		let p = Policy {
+			request_timeout: Some(Duration::from_secs(5)),
+			backend_request_timeout: None,
+			adaptive: Some(adaptive()),
+		};
+		let be = strng::new("be");
+		let start = std::time::Instant::now();
+		// The fixed timeout caps the adaptive one
+		assert_eq!(
+			p.deadline(&be, start, start),
+			Some(start + Duration::from_secs(5))
+		);
+		// The adaptive timeout is measured from the start of the upstream call
+		for _ in 0..10 {
+			p.adaptive
+				.as_ref()
+				.unwrap()
+				.record(&be, Duration::from_millis(500));
+		}
+		let upstream_start = start + Duration::from_secs(2);
+		assert_eq!(
+			p.deadline(&be, start, upstream_start),
+			Some(upstream_start + Duration::from_secs(1))
+		);
+		// The fixed timeout is measured from the start of the request
+		let upstream_start = start + Duration::from_millis(4500);
+		assert_eq!(
+			p.deadline(&be, start, upstream_start),
+			Some(start + Duration::from_secs(5))
+		);
+	}
+
+	#[test]
+	fn invalid_adaptive() {
+		let res = serde_json::from_value::<Adaptive>(serde_json::json!({"min": "10s", "max": "1s"}));
+		assert!(res.is_err());
+	}
+}
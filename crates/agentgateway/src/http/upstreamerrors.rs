@@ -0,0 +1,216 @@
+use ::http::{HeaderValue, StatusCode, header};
+use once_cell::sync::Lazy;
+
+use crate::http::{Body, Response};
+use crate::types::agent::ListenerName;
+use crate::*;
+
+/// The largest error body that is sanitized. Larger bodies are replaced instead.
+const MAX_SANITIZE_SIZE: usize = 1_048_576;
+
+/// Rules used in sanitize mode when none are configured. They remove stack traces and internal addresses, which
+/// upstreams commonly include in error responses.
+static DEFAULT_RULES: Lazy<Vec<SanitizeRule>> = Lazy::new(|| {
+	[
+		// Python tracebacks
+		r"(?s)Traceback \(most recent call last\):.*",
+		// Java, JavaScript, and .NET stack frames
+		r"(?m)^[ \t]+at .+$",
+		// Go panics
+		r"(?s)goroutine \d+ \[.*",
+		// Cluster-internal hostnames
+		r"\b(?:[a-zA-Z0-9-]+\.)+(?:svc\.cluster\.local|cluster\.local|internal|localdomain)\b",
+		// Private IPv4 addresses
+		r"\b(?:10\.\d{1,3}|172\.(?:1[6-9]|2\d|3[01])|192\.168)\.\d{1,3}\.\d{1,3}\b",
+	]
+	.into_iter()
+	.map(|p| SanitizeRule {
+		pattern: regex::Regex::new(p).expect("default rules are valid"),
+		replacement: default_replacement(),
+	})
+	.collect()
+});
+
+/// UpstreamErrors controls how error responses from the upstream are returned to clients. Upstreams can include
+/// stack traces, internal hostnames, and other details in error bodies that should not reach external clients.
+/// Errors generated by the gateway itself are not affected.
+#[apply(schema!)]
+pub struct UpstreamErrors {
+	/// How error bodies are returned. Defaults to passthrough.
+	#[serde(default)]
+	pub mode: ErrorBodyMode,
+	/// How error bodies are returned on internal listeners. Defaults to passthrough.
+	#[serde(default)]
+	pub internal_mode: ErrorBodyMode,
+	/// Names of the listeners that are internal, such as ones only reachable from inside the cluster.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub internal_listeners: Vec<Strng>,
+	/// Rewrites applied, in order, in sanitize mode. If unset, stack traces, cluster-internal hostnames, and
+	/// private IP addresses are removed.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub sanitize: Vec<SanitizeRule>,
+	/// Body returned in replace mode. Defaults to the reason phrase of the status code, such as `Bad Gateway`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+	/// The lowest status code treated as an error. Defaults to 500.
+	#[serde(default = "default_min_status")]
+	pub min_status: u16,
+}
+
+#[apply(schema!)]
+#[derive(Copy, Default, PartialEq, Eq)]
+pub enum ErrorBodyMode {
+	/// Return the body unmodified.
+	#[default]
+	Passthrough,
+	/// Return the body with the sanitize rules applied.
+	Sanitize,
+	/// Return a generic message instead of the body.
+	Replace,
+}
+
+#[apply(schema!)]
+pub struct SanitizeRule {
+	#[serde(with = "serde_regex")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub pattern: regex::Regex,
+	/// Replacement for each match. May reference capture groups, such as `$1`. Defaults to `[redacted]`.
+	#[serde(default = "default_replacement")]
+	pub replacement: String,
+}
+
+fn default_min_status() -> u16 {
+	500
+}
+
+fn default_replacement() -> String {
+	"[redacted]".to_string()
+}
+
+impl UpstreamErrors {
+	fn mode(&self, listener: Option<&ListenerName>) -> ErrorBodyMode {
+		match listener {
+			Some(l) if self.internal_listeners.contains(l) => self.internal_mode,
+			_ => self.mode,
+		}
+	}
+
+	pub async fn apply(&self, resp: &mut Response, listener: Option<&ListenerName>) {
+		if resp.status().as_u16() < self.min_status {
+			return;
+		}
+		match self.mode(listener) {
+			ErrorBodyMode::Passthrough => {},
+			ErrorBodyMode::Replace => self.replace(resp),
+			// Compressed bodies cannot be sanitized, so they are replaced.
+			ErrorBodyMode::Sanitize if resp.headers().contains_key(header::CONTENT_ENCODING) => {
+				self.replace(resp)
+			},
+			ErrorBodyMode::Sanitize => {
+				let body = std::mem::take(resp.body_mut());
+				match axum::body::to_bytes(body, MAX_SANITIZE_SIZE).await {
+					Ok(body) => {
+						let body = self.sanitize(&String::from_utf8_lossy(&body));
+						resp.headers_mut().remove(header::CONTENT_LENGTH);
+						*resp.body_mut() = Body::from(body);
+					},
+					Err(_) => self.replace(resp),
+				}
+			},
+		}
+	}
+
+	fn sanitize(&self, body: &str) -> String {
+		let rules = if self.sanitize.is_empty() {
+			DEFAULT_RULES.as_slice()
+		} else {
+			self.sanitize.as_slice()
+		};
+		rules.iter().fold(body.to_string(), |body, rule| {
+			rule
+				.pattern
+				.replace_all(&body, rule.replacement.as_str())
+				.into_owned()
+		})
+	}
+
+	fn replace(&self, resp: &mut Response) {
+		let message = self
+			.message
+			.clone()
+			.unwrap_or_else(|| generic_message(resp.status()));
+		let headers = resp.headers_mut();
+		headers.remove(header::CONTENT_LENGTH);
+		headers.remove(header::CONTENT_ENCODING);
+		headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+		*resp.body_mut() = Body::from(message);
+	}
+}
+
+fn generic_message(status: StatusCode) -> String {
+	status
+		.canonical_reason()
+		.unwrap_or("Upstream Error")
+		.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn response(status: u16, body: &str) -> Response {
+		::http::Response::builder()
+			.status(status)
+			.header(header::CONTENT_LENGTH, body.len())
+			.body(Body::from(body.to_string()))
+			.unwrap()
+	}
+
+	async fn body(resp: Response) -> String {
+		let b = axum::body::to_bytes(resp.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		String::from_utf8(b.to_vec()).unwrap()
+	}
+
+	#[tokio::test]
+	async fn sanitize_defaults() {
+		let p: UpstreamErrors = serde_json::from_value(json!({"mode": "sanitize"})).unwrap();
+		let mut resp = response(
+			500,
+			"failed to reach db.prod.svc.cluster.local (10.2.3.4)\n    at Db.connect (db.js:10)\n",
+		);
+		p.apply(&mut resp, None).await;
+		assert!(!resp.headers().contains_key(header::CONTENT_LENGTH));
+		assert_eq!(
+			body(resp).await,
+			"failed to reach [redacted] ([redacted])\n[redacted]\n"
+		);
+	}
+
+	#[tokio::test]
+	async fn per_listener() {
+		let p: UpstreamErrors = serde_json::from_value(json!({
+			"mode": "replace",
+			"internalListeners": ["internal"],
+			"sanitize": [{"pattern": "secret-(\\w+)", "replacement": "secret-$1-hidden"}],
+			"internalMode": "sanitize",
+		}))
+		.unwrap();
+
+		let mut resp = response(503, "token secret-abc leaked");
+		p.apply(&mut resp, None).await;
+		assert_eq!(body(resp).await, "Service Unavailable");
+
+		let mut resp = response(503, "token secret-abc leaked");
+		p.apply(&mut resp, Some(&strng::literal!("internal"))).await;
+		assert_eq!(body(resp).await, "token secret-abc-hidden leaked");
+
+		// Successful responses are left alone.
+		let mut resp = response(200, "secret-abc");
+		p.apply(&mut resp, None).await;
+		assert_eq!(body(resp).await, "secret-abc");
+	}
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::cel::{ContextBuilder, Expression};
+use crate::*;
+
+/// Variables are named CEL expressions, evaluated once per request and exposed to the other expressions on the
+/// route (authorization rules, transformations, rate limit descriptors, logging fields, ...) as `vars.<name>`.
+///
+/// Variables are evaluated after authentication, so they can read `request`, `source`, and `jwt`; they cannot
+/// reference each other, or attributes only known later in the request, such as `llm` or `response`.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(transparent)]
+pub struct Variables(
+	#[cfg_attr(feature = "schema", schemars(with = "BTreeMap<String, String>"))]
+	BTreeMap<Strng, Arc<Expression>>,
+);
+
+impl<'de> Deserialize<'de> for Variables {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let raw = BTreeMap::<Strng, String>::deserialize(deserializer)?;
+		raw
+			.into_iter()
+			.map(|(name, expr)| {
+				let expr = Expression::new(expr)
+					.map_err(|e| serde::de::Error::custom(format!("variable {name}: {e}")))?;
+				Ok((name, Arc::new(expr)))
+			})
+			.collect::<Result<_, _>>()
+			.map(Variables)
+	}
+}
+
+impl Variables {
+	pub fn expressions(&self) -> impl Iterator<Item = &Expression> {
+		self.0.values().map(|e| e.as_ref())
+	}
+
+	/// apply evaluates the variables and stores the results in the context, so every executor built from it
+	/// afterwards can reference them. Variables that fail to evaluate are left unset; expressions can check for
+	/// them with `has(vars.<name>)`.
+	pub fn apply(&self, ctx: &mut ContextBuilder) -> Result<(), cel::Error> {
+		let exec = ctx.build()?;
+		let values = self
+			.0
+			.iter()
+			.filter_map(|(name, expr)| match exec.eval(expr) {
+				Ok(v) => Some((name.clone(), v)),
+				Err(err) => {
+					trace!(%name, ?err, "variable failed to evaluate");
+					None
+				},
+			})
+			.collect::<Vec<_>>();
+		ctx.with_variables(values);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::http::Body;
+
+	#[test]
+	fn evaluated_once_and_shared() {
+		let vars: Variables = serde_json::from_value(json!({
+			"tenant": r#"request.headers["x-tenant"]"#,
+			"missing": r#"request.headers["x-missing"]"#,
+		}))
+		.unwrap();
+		let authz = Expression::new(r#"vars.tenant == "acme" && !has(vars.missing)"#).unwrap();
+		let req = ::http::Request::builder()
+			.header("x-tenant", "acme")
+			.body(Body::empty())
+			.unwrap();
+
+		let mut ctx = ContextBuilder::new();
+		for expr in vars.expressions() {
+			ctx.register_expression(expr);
+		}
+		ctx.register_expression(&authz);
+		ctx.with_request(&req);
+		vars.apply(&mut ctx).unwrap();
+
+		let exec = ctx.build().unwrap();
+		assert!(exec.eval_bool(&authz));
+	}
+
+	#[test]
+	fn invalid_expression() {
+		let err = serde_json::from_value::<Variables>(json!({"tenant": "jwt."})).unwrap_err();
+		assert!(err.to_string().contains("variable tenant"), "{err}");
+	}
+}
@@ -38,6 +38,57 @@ pub fn traverse_mut<'a>(value: &'a mut Value, path: &[&str]) -> Option<&'a mut V
 	})
 }
 
+/// set_pointer sets the value at the JSON pointer (RFC 6901), creating intermediate objects as needed.
+/// The `-` token may be used to append to an array.
+pub fn set_pointer(root: &mut Value, pointer: &str, value: Value) -> anyhow::Result<()> {
+	if pointer.is_empty() {
+		*root = value;
+		return Ok(());
+	}
+	let Some(pointer) = pointer.strip_prefix('/') else {
+		anyhow::bail!("JSON pointer must start with '/'");
+	};
+	let tokens: Vec<String> = pointer
+		.split('/')
+		.map(|t| t.replace("~1", "/").replace("~0", "~"))
+		.collect();
+	let (last, parents) = tokens
+		.split_last()
+		.expect("split always returns one element");
+	let mut target = root;
+	for token in parents {
+		if target.is_null() {
+			*target = Value::Object(Default::default());
+		}
+		target = match target {
+			Value::Object(map) => map
+				.entry(token.as_str())
+				.or_insert_with(|| Value::Object(Default::default())),
+			Value::Array(list) => parse_index(token)
+				.and_then(|x| list.get_mut(x))
+				.ok_or_else(|| anyhow::anyhow!("invalid array index {token}"))?,
+			_ => anyhow::bail!("cannot traverse into non-container at {token}"),
+		};
+	}
+	if target.is_null() {
+		*target = Value::Object(Default::default());
+	}
+	match target {
+		Value::Object(map) => {
+			map.insert(last.clone(), value);
+		},
+		Value::Array(list) if last == "-" => list.push(value),
+		Value::Array(list) => {
+			let slot = parse_index(last)
+				.and_then(|x| list.get_mut(x))
+				.ok_or_else(|| anyhow::anyhow!("invalid array index {last}"))?;
+			*slot = value;
+		},
+		_ => anyhow::bail!("cannot set field {last} on non-container"),
+	}
+	Ok(())
+}
+
 fn parse_index(s: &str) -> Option<usize> {
 	if s.starts_with('+') || (s.starts_with('0') && s.len() != 1) {
 		return None;
@@ -24,7 +24,9 @@ pub mod app;
 pub mod cel;
 pub mod client;
 pub mod config;
+pub mod configtest;
 pub mod control;
+pub mod convert;
 pub mod http;
 pub mod json;
 pub mod llm;
@@ -80,6 +82,12 @@ pub struct RawConfig {
 	stats_addr: Option<String>,
 	/// Readiness probe server address in the format "ip:port"
 	readiness_addr: Option<String>,
+	/// Access control for the admin server. The admin server only listens on localhost by default.
+	admin_access: Option<RawManagementAccess>,
+	/// Access control for the stats/metrics server.
+	stats_access: Option<RawManagementAccess>,
+	/// Settings for the UI, which is served by the admin server by default.
+	ui: Option<RawUi>,
 
 	auth_token: Option<String>,
 
@@ -96,10 +104,60 @@ pub struct RawConfig {
 	tracing: Option<RawTracing>,
 	logging: Option<RawLogging>,
 	metrics: Option<RawMetrics>,
+	usage_reports: Option<RawUsageReports>,
+	/// How to behave while the XDS control plane is unreachable.
+	control_plane_unreachable: Option<control::unreachable::Config>,
+	/// Where state that outlives a single request, such as idempotency records, is kept. Defaults to memory.
+	state_store: Option<store::kv::Config>,
+	/// Header to add to each response with the hash of the active configuration, such as `x-config-version`. The
+	/// hash is also logged and served on the admin `/config_version` endpoint.
+	config_version_header: Option<String>,
 
 	http2: Option<RawHTTP2>,
 }
 
+#[apply(schema_de!)]
+pub struct RawManagementAccess {
+	/// Require requests to send this bearer token. Prefer `tokenFile`, to keep the token out of the configuration.
+	token: Option<String>,
+	/// File containing the bearer token required on requests.
+	token_file: Option<PathBuf>,
+	/// Serve over TLS, and require clients to present a certificate signed by a dedicated CA.
+	mtls: Option<RawManagementMtls>,
+	/// Source networks allowed to connect, such as `10.0.0.0/8`. If neither a token nor mTLS is configured, this
+	/// defaults to loopback addresses only; otherwise all sources are allowed.
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Vec<String>"))]
+	allowed_sources: Vec<ipnet::IpNet>,
+}
+
+#[apply(schema_de!)]
+pub struct RawManagementMtls {
+	/// Server certificate, in PEM format.
+	cert: PathBuf,
+	/// Server private key, in PEM format.
+	key: PathBuf,
+	/// CA used to verify client certificates, in PEM format.
+	client_ca: PathBuf,
+}
+
+#[apply(schema_de!)]
+pub struct RawUi {
+	/// Serve the UI. Defaults to true.
+	enabled: Option<bool>,
+	/// Serve the UI on a dedicated address in the format "ip:port", instead of the admin address.
+	/// Only the UI and the endpoints it uses are served there; it uses the same access control as the admin server.
+	/// Without a token or mTLS configured, configuration changes are rejected there.
+	address: Option<String>,
+	/// Path prefix the UI is reached under, when it is served behind a proxy such as a portal, for example
+	/// `/portal/gateway`. The prefix is accepted whether or not the proxy strips it.
+	base_path: Option<String>,
+	/// Title shown in the browser tab. Defaults to "Agentgateway Dashboard".
+	title: Option<String>,
+	/// URL of an image to show in place of the agentgateway logo.
+	logo_url: Option<String>,
+}
+
 #[apply(schema_de!)]
 pub struct RawHTTP2 {
 	window_size: Option<u32>,
@@ -135,6 +193,26 @@ pub struct RawTracing {
 pub struct RawLogging {
 	filter: Option<String>,
 	fields: Option<RawLoggingFields>,
+	/// Capture the start of request and response bodies into the log, as `http.request.body` and
+	/// `http.response.body`.
+	body_capture: Option<RawBodyCapture>,
+}
+
+#[apply(schema_de!)]
+pub struct RawBodyCapture {
+	/// Expression to determine whether bodies are captured for a request, such as
+	/// `request.path.startsWith("/mcp")`. Defaults to capturing all requests.
+	condition: Option<String>,
+	/// Whether to capture the request body. Defaults to true.
+	request: Option<bool>,
+	/// Whether to capture the response body. Defaults to true.
+	response: Option<bool>,
+	/// Maximum number of bytes of each body to capture. Compressed bodies are decompressed first.
+	/// Defaults to 1024.
+	max_bytes: Option<usize>,
+	/// Regular expressions to redact from captured bodies. Matches are replaced with `<redacted>`.
+	#[serde(default)]
+	redact: Vec<String>,
 }
 
 #[apply(schema_de!)]
@@ -142,6 +220,29 @@ pub struct RawMetrics {
 	fields: Option<RawMetricFields>,
 }
 
+#[apply(schema_de!)]
+pub struct RawUsageReports {
+	/// How often a usage report is produced. Defaults to 1h.
+	#[serde(default, with = "serde_dur_option")]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	interval: Option<Duration>,
+	/// Dimensions to group usage by. Each entry is one of 'route', 'identity' (the JWT subject), 'model', or the
+	/// name of a custom metric field from `metrics.fields.add`. Defaults to route and identity.
+	group_by: Option<Vec<String>>,
+	/// Groups with fewer requests than this in a report are merged into a single group labeled 'other'.
+	#[serde(default)]
+	min_group_requests: u64,
+	/// Cost of each model, per million input and output tokens, used to compute the cost of each group.
+	#[serde(default)]
+	pricing: HashMap<String, telemetry::usage::ModelPricing>,
+	/// Directory to write each report to.
+	directory: Option<PathBuf>,
+	#[serde(default)]
+	format: telemetry::usage::Format,
+	/// URL to POST each report to.
+	webhook: Option<String>,
+}
+
 #[apply(schema_de!)]
 pub struct RawMetricFields {
 	#[serde(default)]
@@ -265,6 +366,9 @@ pub struct Config {
 	pub admin_addr: Address,
 	pub stats_addr: Address,
 	pub readiness_addr: Address,
+	pub admin_access: management::access::Access,
+	pub stats_access: management::access::Access,
+	pub ui: UiConfig,
 	// For waypoint identification
 	pub self_addr: Option<Strng>,
 	pub hbone: Arc<agent_hbone::Config>,
@@ -273,9 +377,37 @@ pub struct Config {
 	pub ca: Option<caclient::Config>,
 	pub tracing: trc::Config,
 	pub logging: crate::telemetry::log::Config,
+	pub usage_reports: Option<crate::telemetry::usage::Config>,
 	pub dns: client::Config,
 	pub proxy_metadata: ProxyMetadata,
 	pub threading_mode: ThreadingMode,
+	pub state_store: store::kv::Config,
+	#[serde(serialize_with = "ser_display_option")]
+	pub config_version_header: Option<::http::HeaderName>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UiConfig {
+	pub enabled: bool,
+	/// Dedicated address to serve the UI on. If unset, the UI is served by the admin server.
+	pub address: Option<Address>,
+	/// Normalized path prefix, starting with a '/' and without a trailing one.
+	pub base_path: Option<String>,
+	pub title: Option<String>,
+	pub logo_url: Option<String>,
+}
+
+impl Default for UiConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			address: None,
+			base_path: None,
+			title: None,
+			logo_url: None,
+		}
+	}
 }
 
 #[derive(serde::Serialize, Copy, PartialOrd, PartialEq, Eq, Clone, Debug, Default)]
@@ -296,6 +428,8 @@ pub struct XDSConfig {
 	pub gateway: String,
 
 	pub local_config: Option<ConfigSource>,
+	/// How to behave while the control plane is unreachable.
+	pub unreachable: control::unreachable::Config,
 }
 
 #[derive(Clone, Debug)]
@@ -349,6 +483,9 @@ pub struct ProxyInputs {
 
 	mcp_state: mcp::sse::App,
 	ca: Option<Arc<CaClient>>,
+	state: store::kv::Store,
+	/// Open downstream connections, for diagnostics bundles.
+	connections: management::diagnostics::Table<proxy::DownstreamConnection>,
 }
 
 #[derive(Debug, Clone, Copy, serde::Serialize)]
@@ -410,6 +547,14 @@ impl Address {
 		}
 	}
 
+	/// is_loopback reports whether the address is only reachable from the local host.
+	pub fn is_loopback(&self) -> bool {
+		match self {
+			Address::Localhost(_, _) => true,
+			Address::SocketAddr(s) => s.ip().is_loopback(),
+		}
+	}
+
 	// with_ipv6 unconditionally overrides the IPv6 setting for the address
 	pub fn with_ipv6(self, ipv6: bool) -> Self {
 		match self {
@@ -6,8 +6,8 @@ use chrono;
 
 use crate::http::Response;
 use crate::llm::anthropic::types::{
-	ContentBlock, ContentBlockDelta, MessagesErrorResponse, MessagesRequest, MessagesResponse,
-	MessagesStreamEvent, StopReason,
+	ContentBlock, ContentBlockDelta, MessagesError, MessagesErrorResponse, MessagesRequest,
+	MessagesResponse, MessagesStreamEvent, StopReason,
 };
 use crate::llm::{AIError, LLMResponse, universal};
 use crate::telemetry::log::AsyncLog;
@@ -54,116 +54,147 @@ impl Provider {
 			let mut input_tokens = 0;
 			let mut saw_token = false;
 			// https://docs.anthropic.com/en/docs/build-with-claude/streaming
-			parse::sse::json_transform::<MessagesStreamEvent, universal::StreamResponse>(b, move |f| {
-				let mk = |choices: Vec<universal::ChatChoiceStream>, usage: Option<universal::Usage>| {
-					Some(universal::StreamResponse {
-						id: message_id.clone().unwrap_or_else(|| "unknown".to_string()),
-						model: model.clone(),
-						object: "chat.completion.chunk".to_string(),
-						system_fingerprint: None,
-						service_tier: None,
-						created,
-						choices,
-						usage,
-					})
-				};
-				// ignore errors... what else can we do?
-				let f = f.ok()?;
-
-				// Extract info we need
-				match f {
-					MessagesStreamEvent::MessageStart { message } => {
-						message_id = Some(message.id);
-						model = message.model.clone();
-						input_tokens = message.usage.input_tokens;
-						log.non_atomic_mutate(|r| {
-							r.output_tokens = Some(message.usage.output_tokens as u64);
-							r.input_tokens_from_response = Some(message.usage.input_tokens as u64);
-							r.provider_model = Some(strng::new(&message.model))
-						});
-						// no need to respond with anything yet
-						None
-					},
-
-					MessagesStreamEvent::ContentBlockStart { .. } => {
-						// There is never(?) any content here
-						None
-					},
-					MessagesStreamEvent::ContentBlockDelta { delta, .. } => {
-						if !saw_token {
-							saw_token = true;
+			parse::sse::json_transform_terminated::<MessagesStreamEvent, universal::StreamEvent>(
+				b,
+				move |f| {
+					let mk = |choices: Vec<universal::ChatChoiceStream>, usage: Option<universal::Usage>| {
+						Some(universal::StreamEvent::Chunk(universal::StreamResponse {
+							id: message_id.clone().unwrap_or_else(|| "unknown".to_string()),
+							model: model.clone(),
+							object: "chat.completion.chunk".to_string(),
+							system_fingerprint: None,
+							service_tier: None,
+							created,
+							choices,
+							usage,
+						}))
+					};
+					// ignore errors... what else can we do?
+					let f = f.ok()?;
+
+					// Extract info we need
+					match f {
+						MessagesStreamEvent::MessageStart { message } => {
+							message_id = Some(message.id);
+							model = message.model.clone();
+							input_tokens = message.usage.input_tokens;
 							log.non_atomic_mutate(|r| {
-								r.first_token = Some(Instant::now());
+								r.output_tokens = Some(message.usage.output_tokens as u64);
+								r.input_tokens_from_response = Some(message.usage.input_tokens as u64);
+								r.provider_model = Some(strng::new(&message.model))
 							});
-						}
-						let ContentBlockDelta::TextDelta { text } = delta;
-						let choice = universal::ChatChoiceStream {
-							index: 0,
-							logprobs: None,
-							delta: universal::StreamResponseDelta {
-								role: None,
-								content: Some(text),
-								refusal: None,
-								#[allow(deprecated)]
-								function_call: None,
-								tool_calls: None,
-							},
-							finish_reason: None,
-						};
-						mk(vec![choice], None)
-					},
-					MessagesStreamEvent::MessageDelta { usage, delta: _ } => {
-						// TODO
-						// finish_reason = delta.stop_reason.as_ref().map(translate_stop_reason);
-						log.non_atomic_mutate(|r| {
-							r.output_tokens = Some(usage.output_tokens as u64);
-							if let Some(inp) = r.input_tokens_from_response {
-								r.total_tokens = Some(inp + usage.output_tokens as u64)
+							// no need to respond with anything yet
+							None
+						},
+
+						MessagesStreamEvent::ContentBlockStart { .. } => {
+							// There is never(?) any content here
+							None
+						},
+						MessagesStreamEvent::ContentBlockDelta { delta, .. } => {
+							if !saw_token {
+								saw_token = true;
+								log.non_atomic_mutate(|r| {
+									r.first_token = Some(Instant::now());
+								});
 							}
-						});
-						mk(
-							vec![],
-							Some(universal::Usage {
-								prompt_tokens: usage.output_tokens as u32,
-								completion_tokens: input_tokens as u32,
-								total_tokens: (input_tokens + usage.output_tokens) as u32,
-
-								prompt_tokens_details: None,
-								completion_tokens_details: None,
-							}),
-						)
-					},
-					MessagesStreamEvent::ContentBlockStop { .. } => None,
-					MessagesStreamEvent::MessageStop => None,
-					MessagesStreamEvent::Ping => None,
-				}
-			})
+							let ContentBlockDelta::TextDelta { text } = delta;
+							let choice = universal::ChatChoiceStream {
+								index: 0,
+								logprobs: None,
+								delta: universal::StreamResponseDelta {
+									role: None,
+									content: Some(text),
+									refusal: None,
+									#[allow(deprecated)]
+									function_call: None,
+									tool_calls: None,
+								},
+								finish_reason: None,
+							};
+							mk(vec![choice], None)
+						},
+						MessagesStreamEvent::MessageDelta { usage, delta: _ } => {
+							// TODO
+							// finish_reason = delta.stop_reason.as_ref().map(translate_stop_reason);
+							log.non_atomic_mutate(|r| {
+								r.output_tokens = Some(usage.output_tokens as u64);
+								if let Some(inp) = r.input_tokens_from_response {
+									r.total_tokens = Some(inp + usage.output_tokens as u64)
+								}
+							});
+							mk(
+								vec![],
+								Some(universal::Usage {
+									prompt_tokens: usage.output_tokens as u32,
+									completion_tokens: input_tokens as u32,
+									total_tokens: (input_tokens + usage.output_tokens) as u32,
+
+									prompt_tokens_details: None,
+									completion_tokens_details: None,
+								}),
+							)
+						},
+						MessagesStreamEvent::ContentBlockStop { .. } => None,
+						MessagesStreamEvent::MessageStop => None,
+						MessagesStreamEvent::Ping => None,
+						MessagesStreamEvent::Error { error } => Some(universal::StreamEvent::Error(
+							translate_error_body(error, universal::ErrorKind::SERVER),
+						)),
+					}
+				},
+				// The upstream connection failed mid-stream; let the client know rather than silently truncating.
+				|_| {
+					Some(universal::StreamEvent::Error(
+						universal::stream_interrupted(),
+					))
+				},
+			)
 		})
 	}
 
 	pub async fn process_error(
 		&self,
+		status: ::http::StatusCode,
 		bytes: &Bytes,
 	) -> Result<universal::ChatCompletionErrorResponse, AIError> {
 		let resp =
 			serde_json::from_slice::<MessagesErrorResponse>(bytes).map_err(AIError::ResponseParsing)?;
-		translate_error(resp)
+		translate_error(resp, universal::ErrorKind::from_status(status))
 	}
 }
 
+/// ERROR_TYPES maps Anthropic error types to their OpenAI equivalent.
+/// https://docs.anthropic.com/en/api/errors
+pub(super) const ERROR_TYPES: &[(&str, universal::ErrorKind)] = &[
+	(
+		"invalid_request_error",
+		universal::ErrorKind::INVALID_REQUEST,
+	),
+	("authentication_error", universal::ErrorKind::AUTHENTICATION),
+	("permission_error", universal::ErrorKind::PERMISSION),
+	("not_found_error", universal::ErrorKind::NOT_FOUND),
+	("request_too_large", universal::ErrorKind::REQUEST_TOO_LARGE),
+	("rate_limit_error", universal::ErrorKind::RATE_LIMIT),
+	("api_error", universal::ErrorKind::SERVER),
+	("overloaded_error", universal::ErrorKind::OVERLOADED),
+];
+
+/// translate_error converts an Anthropic error, using `fallback` if the error type is not recognized.
 pub(super) fn translate_error(
 	resp: MessagesErrorResponse,
+	fallback: universal::ErrorKind,
 ) -> Result<universal::ChatCompletionErrorResponse, AIError> {
-	Ok(universal::ChatCompletionErrorResponse {
-		event_id: None,
-		error: universal::ChatCompletionError {
-			r#type: "invalid_request_error".to_string(),
-			message: resp.error.message,
-			param: None,
-			code: None,
-			event_id: None,
-		},
-	})
+	Ok(translate_error_body(resp.error, fallback))
+}
+
+fn translate_error_body(
+	error: MessagesError,
+	fallback: universal::ErrorKind,
+) -> universal::ChatCompletionErrorResponse {
+	universal::ErrorKind::lookup(ERROR_TYPES, &error.r#type)
+		.unwrap_or(fallback)
+		.into_error(error.message)
 }
 
 pub(super) fn translate_response(resp: MessagesResponse) -> universal::Response {
@@ -508,6 +539,10 @@ pub(super) mod types {
 		},
 		MessageStop,
 		Ping,
+		/// Errors may be sent mid-stream, for example when the API is overloaded.
+		Error {
+			error: MessagesError,
+		},
 	}
 
 	#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -547,7 +582,7 @@ pub(super) mod types {
 		pub error: MessagesError,
 	}
 
-	#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+	#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 	pub struct MessagesError {
 		pub r#type: String,
 		pub message: String,
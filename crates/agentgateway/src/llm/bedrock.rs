@@ -67,11 +67,18 @@ impl Provider {
 
 	pub async fn process_error(
 		&self,
+		status: ::http::StatusCode,
+		headers: &::http::HeaderMap,
 		bytes: &Bytes,
 	) -> Result<universal::ChatCompletionErrorResponse, AIError> {
 		let resp =
 			serde_json::from_slice::<ConverseErrorResponse>(bytes).map_err(AIError::ResponseParsing)?;
-		translate_error(resp)
+		// The error type is sent as a header, in the form `ValidationException:<link>`
+		let error_type = headers
+			.get(http::x_headers::X_AMZN_ERRORTYPE)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| v.split(':').next());
+		translate_error(resp, error_type, universal::ErrorKind::from_status(status))
 	}
 
 	pub(super) async fn process_streaming(
@@ -91,120 +98,137 @@ impl Provider {
 		let created = chrono::Utc::now().timestamp() as u32;
 		resp.map(move |b| {
 			let mut saw_token = false;
-			parse::aws_sse::transform::<universal::StreamResponse>(b, move |f| {
-				let res = types::ConverseStreamOutput::deserialize(f).ok()?;
-				let mk = |choices: Vec<universal::ChatChoiceStream>, usage: Option<universal::Usage>| {
-					Some(universal::StreamResponse {
-						id: message_id.clone(),
-						model: model.clone(),
-						object: "chat.completion.chunk".to_string(),
-						system_fingerprint: None,
-						service_tier: None,
-						created,
-						choices,
-						usage,
-					})
-				};
-
-				match res {
-					ConverseStreamOutput::ContentBlockDelta(d) => {
-						if !saw_token {
-							saw_token = true;
-							log.non_atomic_mutate(|r| {
-								r.first_token = Some(Instant::now());
-							});
-						}
-						match d.delta {
-							Some(ContentBlockDelta::Text(s)) => {
-								let choice = universal::ChatChoiceStream {
-									index: 0,
-									logprobs: None,
-									delta: universal::StreamResponseDelta {
-										role: None,
-										content: Some(s),
-										refusal: None,
-										#[allow(deprecated)]
-										function_call: None,
-										tool_calls: None,
-									},
-									finish_reason: None,
-								};
-								mk(vec![choice], None)
-							},
-							_ => None,
-						}
-					},
-					ConverseStreamOutput::ContentBlockStart(_) => {
-						// TODO support tool calls
-						None
-					},
-					ConverseStreamOutput::ContentBlockStop(_) => {
-						// No need to send anything here
-						None
-					},
-					ConverseStreamOutput::MessageStart(start) => {
-						// Just send a blob with the role
-						let choice = universal::ChatChoiceStream {
-							index: 0,
-							logprobs: None,
-							delta: universal::StreamResponseDelta {
-								role: Some(match start.role {
-									types::Role::Assistant => universal::Role::Assistant,
-									types::Role::User => universal::Role::User,
-								}),
-								content: None,
-								refusal: None,
-								#[allow(deprecated)]
-								function_call: None,
-								tool_calls: None,
-							},
-							finish_reason: None,
-						};
-						mk(vec![choice], None)
-					},
-					ConverseStreamOutput::MessageStop(stop) => {
-						let finish_reason = Some(translate_stop_reason(&stop.stop_reason));
-
-						// Just send a blob with the finish reason
-						let choice = universal::ChatChoiceStream {
-							index: 0,
-							logprobs: None,
-							delta: universal::StreamResponseDelta {
-								role: None,
-								content: None,
-								refusal: None,
-								#[allow(deprecated)]
-								function_call: None,
-								tool_calls: None,
-							},
-							finish_reason,
-						};
-						mk(vec![choice], None)
-					},
-					ConverseStreamOutput::Metadata(metadata) => {
-						if let Some(usage) = metadata.usage {
-							log.non_atomic_mutate(|r| {
-								r.output_tokens = Some(usage.output_tokens as u64);
-								r.input_tokens_from_response = Some(usage.input_tokens as u64);
-								r.total_tokens = Some(usage.total_tokens as u64);
-							});
-
-							mk(
-								vec![],
-								Some(universal::Usage {
-									prompt_tokens: usage.input_tokens as u32,
-									completion_tokens: usage.output_tokens as u32,
-									total_tokens: usage.total_tokens as u32,
-									prompt_tokens_details: None,
-									completion_tokens_details: None,
-								}),
-							)
-						} else {
+			parse::aws_sse::transform::<universal::StreamEvent>(
+				b,
+				move |f| {
+					let res = types::ConverseStreamOutput::deserialize(f).ok()?;
+					let mk = |choices: Vec<universal::ChatChoiceStream>, usage: Option<universal::Usage>| {
+						Some(universal::StreamEvent::Chunk(universal::StreamResponse {
+							id: message_id.clone(),
+							model: model.clone(),
+							object: "chat.completion.chunk".to_string(),
+							system_fingerprint: None,
+							service_tier: None,
+							created,
+							choices,
+							usage,
+						}))
+					};
+
+					match res {
+						ConverseStreamOutput::ContentBlockDelta(d) => {
+							if !saw_token {
+								saw_token = true;
+								log.non_atomic_mutate(|r| {
+									r.first_token = Some(Instant::now());
+								});
+							}
+							match d.delta {
+								Some(ContentBlockDelta::Text(s)) => {
+									let choice = universal::ChatChoiceStream {
+										index: 0,
+										logprobs: None,
+										delta: universal::StreamResponseDelta {
+											role: None,
+											content: Some(s),
+											refusal: None,
+											#[allow(deprecated)]
+											function_call: None,
+											tool_calls: None,
+										},
+										finish_reason: None,
+									};
+									mk(vec![choice], None)
+								},
+								_ => None,
+							}
+						},
+						ConverseStreamOutput::ContentBlockStart(_) => {
+							// TODO support tool calls
 							None
-						}
-					},
-				}
-			})
+						},
+						ConverseStreamOutput::ContentBlockStop(_) => {
+							// No need to send anything here
+							None
+						},
+						ConverseStreamOutput::MessageStart(start) => {
+							// Just send a blob with the role
+							let choice = universal::ChatChoiceStream {
+								index: 0,
+								logprobs: None,
+								delta: universal::StreamResponseDelta {
+									role: Some(match start.role {
+										types::Role::Assistant => universal::Role::Assistant,
+										types::Role::User => universal::Role::User,
+									}),
+									content: None,
+									refusal: None,
+									#[allow(deprecated)]
+									function_call: None,
+									tool_calls: None,
+								},
+								finish_reason: None,
+							};
+							mk(vec![choice], None)
+						},
+						ConverseStreamOutput::MessageStop(stop) => {
+							let finish_reason = Some(translate_stop_reason(&stop.stop_reason));
+
+							// Just send a blob with the finish reason
+							let choice = universal::ChatChoiceStream {
+								index: 0,
+								logprobs: None,
+								delta: universal::StreamResponseDelta {
+									role: None,
+									content: None,
+									refusal: None,
+									#[allow(deprecated)]
+									function_call: None,
+									tool_calls: None,
+								},
+								finish_reason,
+							};
+							mk(vec![choice], None)
+						},
+						ConverseStreamOutput::Metadata(metadata) => {
+							if let Some(usage) = metadata.usage {
+								log.non_atomic_mutate(|r| {
+									r.output_tokens = Some(usage.output_tokens as u64);
+									r.input_tokens_from_response = Some(usage.input_tokens as u64);
+									r.total_tokens = Some(usage.total_tokens as u64);
+								});
+
+								mk(
+									vec![],
+									Some(universal::Usage {
+										prompt_tokens: usage.input_tokens as u32,
+										completion_tokens: usage.output_tokens as u32,
+										total_tokens: usage.total_tokens as u32,
+										prompt_tokens_details: None,
+										completion_tokens_details: None,
+									}),
+								)
+							} else {
+								None
+							}
+						},
+						ConverseStreamOutput::Exception {
+							exception_type,
+							message,
+						} => {
+							let kind = universal::ErrorKind::lookup(ERROR_TYPES, &exception_type)
+								.unwrap_or(universal::ErrorKind::SERVER);
+							Some(universal::StreamEvent::Error(kind.into_error(message)))
+						},
+					}
+				},
+				// The upstream connection failed mid-stream; let the client know rather than silently truncating.
+				|_| {
+					Some(universal::StreamEvent::Error(
+						universal::stream_interrupted(),
+					))
+				},
+			)
 		})
 	}
 
@@ -222,19 +246,40 @@ impl Provider {
 	}
 }
 
+/// ERROR_TYPES maps Bedrock exceptions to their OpenAI equivalent.
+/// https://docs.aws.amazon.com/bedrock/latest/APIReference/API_runtime_ConverseStream.html#API_runtime_ConverseStream_Errors
+pub(super) const ERROR_TYPES: &[(&str, universal::ErrorKind)] = &[
+	("validationException", universal::ErrorKind::INVALID_REQUEST),
+	("accessDeniedException", universal::ErrorKind::PERMISSION),
+	("resourceNotFoundException", universal::ErrorKind::NOT_FOUND),
+	("throttlingException", universal::ErrorKind::RATE_LIMIT),
+	(
+		"serviceQuotaExceededException",
+		universal::ErrorKind::RATE_LIMIT,
+	),
+	("modelNotReadyException", universal::ErrorKind::OVERLOADED),
+	(
+		"serviceUnavailableException",
+		universal::ErrorKind::OVERLOADED,
+	),
+	("modelTimeoutException", universal::ErrorKind::TIMEOUT),
+	("internalServerException", universal::ErrorKind::SERVER),
+	("modelStreamErrorException", universal::ErrorKind::SERVER),
+	("modelErrorException", universal::ErrorKind::SERVER),
+];
+
+/// translate_error converts a Bedrock error, using `fallback` if the error type is missing or not recognized.
 pub(super) fn translate_error(
 	resp: ConverseErrorResponse,
+	error_type: Option<&str>,
+	fallback: universal::ErrorKind,
 ) -> Result<universal::ChatCompletionErrorResponse, AIError> {
-	Ok(universal::ChatCompletionErrorResponse {
-		event_id: None,
-		error: universal::ChatCompletionError {
-			r#type: "invalid_request_error".to_string(),
-			message: resp.message,
-			param: None,
-			code: None,
-			event_id: None,
-		},
-	})
+	Ok(
+		error_type
+			.and_then(|t| universal::ErrorKind::lookup(ERROR_TYPES, t))
+			.unwrap_or(fallback)
+			.into_error(resp.message),
+	)
 }
 
 pub(super) fn translate_response(
@@ -790,20 +835,37 @@ pub(super) mod types {
 		MessageStop(MessageStopEvent),
 		/// Metadata for the converse output stream.
 		Metadata(ConverseStreamMetadataEvent),
+		/// An error sent mid-stream, such as a throttlingException.
+		Exception {
+			exception_type: String,
+			message: String,
+		},
 	}
 
 	impl ConverseStreamOutput {
 		pub fn deserialize(m: aws_event_stream_parser::Message) -> anyhow::Result<Self> {
-			let Some(v) = m
-				.headers
-				.headers
-				.iter()
-				.find(|h| h.key.as_str() == ":event-type")
-				.and_then(|v| match &v.value {
-					aws_event_stream_parser::HeaderValue::String(s) => Some(s.to_string()),
-					_ => None,
-				})
-			else {
+			let header = |name: &str| {
+				m.headers
+					.headers
+					.iter()
+					.find(|h| h.key.as_str() == name)
+					.and_then(|v| match &v.value {
+						aws_event_stream_parser::HeaderValue::String(s) => Some(s.to_string()),
+						_ => None,
+					})
+			};
+			if header(":message-type").as_deref() == Some("exception") {
+				let Some(exception_type) = header(":exception-type") else {
+					anyhow::bail!("no exception type header")
+				};
+				let ConverseErrorResponse { message } =
+					serde_json::from_slice::<ConverseErrorResponse>(&m.body)?;
+				return Ok(ConverseStreamOutput::Exception {
+					exception_type,
+					message,
+				});
+			}
+			let Some(v) = header(":event-type") else {
 				anyhow::bail!("no event type header")
 			};
 			Ok(match v.as_str() {
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use ::http::uri::{Authority, PathAndQuery};
-use ::http::{HeaderValue, StatusCode, header};
+use ::http::{HeaderMap, HeaderValue, StatusCode, header};
 use agent_core::prelude::Strng;
 use agent_core::strng;
 use axum_extra::headers::authorization::Bearer;
@@ -16,6 +16,7 @@ use crate::http::jwt::Claims;
 use crate::http::{Body, Request, Response};
 use crate::llm::universal::{ChatCompletionError, ChatCompletionErrorResponse};
 use crate::store::{BackendPolicies, LLMResponsePolicies};
+use crate::telemetry::errors::ErrorType;
 use crate::telemetry::log::{AsyncLog, RequestLog};
 use crate::types::agent::Target;
 use crate::{client, *};
@@ -26,6 +27,8 @@ pub mod gemini;
 pub mod openai;
 mod pii;
 pub mod policy;
+pub mod prompthash;
+pub mod sampling;
 #[cfg(test)]
 mod tests;
 pub mod universal;
@@ -96,6 +99,12 @@ pub struct LLMResponse {
 	pub completion: Option<Vec<String>>,
 	// Time to get the first token. Only used for streaming.
 	pub first_token: Option<Instant>,
+	/// Classification of the error returned by the provider, if any.
+	pub error_type: Option<ErrorType>,
+	/// Events in the response stream that were skipped, as they were larger than the maximum event size.
+	pub oversized_events: u64,
+	/// Events in the response stream that were skipped, as they could not be decoded.
+	pub invalid_events: u64,
 }
 
 impl LLMResponse {
@@ -251,18 +260,47 @@ impl AIProvider {
 			p.apply_prompt_enrichment(&mut req);
 			let http_headers = &parts.headers;
 			let claims = parts.extensions.get::<Claims>().cloned();
-			if let Some(dr) = p
-				.apply_prompt_guard(client, &mut req, http_headers, claims)
-				.await
-				.map_err(|e| {
-					warn!("failed to call prompt guard webhook: {e}");
-					AIError::PromptWebhookError
-				})? {
-				return Ok(RequestResult::Rejected(dr));
+			let mode = p.prompt_guard_mode();
+			if mode.is_enforce() {
+				if let Some(dr) = p
+					.apply_prompt_guard(client.clone(), &mut req, http_headers, claims)
+					.await
+					.map_err(|e| {
+						warn!("failed to call prompt guard webhook: {e}");
+						AIError::PromptWebhookError
+					})? {
+					return Ok(RequestResult::Rejected(dr));
+				}
+			} else {
+				// Guard a copy, so masking does not modify the request either.
+				match p
+					.apply_prompt_guard(client.clone(), &mut req.clone(), http_headers, claims)
+					.await
+				{
+					Ok(Some(_)) => {
+						if let Some(log) = log {
+							log.record_monitored("promptGuard");
+						}
+					},
+					Ok(None) => {},
+					Err(e) => warn!("failed to call prompt guard webhook: {e}"),
+				}
 			}
 		}
 		let llm_info = self.to_llm_request(&req, tokenize).await?;
 		if let Some(log) = log {
+			if let Some(hashing) = policies.and_then(|p| p.prompt_hashing.as_ref()) {
+				let hash = hashing.record(&req.messages.iter().map(Into::into).collect_vec());
+				if hash.is_duplicate() {
+					log.record_duplicate_prompt();
+				}
+				log.llm_prompt_hash = Some(hash);
+			}
+			if let Some(sampling) = policies.and_then(|p| p.sampling.as_ref()) {
+				log.llm_sample = sampling.sample(client, log.jwt_sub.as_deref(), || {
+					req.messages.iter().map(Into::into).collect_vec()
+				});
+			}
 			let needs_prompt = log.cel.cel_context.with_llm_request(&llm_info);
 			if needs_prompt {
 				log
@@ -290,7 +328,16 @@ impl AIProvider {
 			AIProvider::Anthropic(p) => serde_json::to_vec(&p.process_request(req).await?),
 			AIProvider::Bedrock(p) => serde_json::to_vec(&p.process_request(req).await?),
 		};
-		let body = resp_json.map_err(AIError::RequestMarshal)?;
+		let mut body = resp_json.map_err(AIError::RequestMarshal)?;
+		if let Some(p) = policies
+			&& !p.request_fields.is_empty()
+		{
+			let exec = log.as_mut().and_then(|l| l.cel.ctx().build().ok());
+			match exec {
+				Some(exec) => body = p.apply_request_fields(&exec, body)?,
+				None => warn!("failed to build CEL context; skipping request fields"),
+			}
+		}
 		let resp = Body::from(body);
 		parts.headers.remove(header::CONTENT_LENGTH);
 		let req = Request::from_parts(parts, resp);
@@ -305,7 +352,8 @@ impl AIProvider {
 		include_completion_in_log: bool,
 		resp: Response,
 	) -> Result<Response, AIError> {
-		if req.streaming {
+		// Errors are returned as a normal JSON body, even for streaming requests
+		if req.streaming && resp.status().is_success() {
 			return self
 				.process_streaming(req, rate_limit, log, include_completion_in_log, resp)
 				.await;
@@ -320,7 +368,7 @@ impl AIProvider {
 		};
 		// 3 cases: success, error properly handled, and unexpected error we need to synthesize
 		let openai_response = self
-			.process_response_status(&req, parts.status, &bytes)
+			.process_response_status(&req, parts.status, &parts.headers, &bytes)
 			.await
 			.unwrap_or_else(|err| {
 				Err(ChatCompletionErrorResponse {
@@ -358,6 +406,9 @@ impl AIProvider {
 						None
 					},
 					first_token: Default::default(),
+					error_type: None,
+					oversized_events: 0,
+					invalid_events: 0,
 				};
 				let body = serde_json::to_vec(&success).map_err(AIError::ResponseMarshal)?;
 				(llm_resp, body)
@@ -371,6 +422,9 @@ impl AIProvider {
 					provider_model: None,
 					completion: None,
 					first_token: None,
+					error_type: Some(err.error.error_type()),
+					oversized_events: 0,
+					invalid_events: 0,
 				};
 				let body = serde_json::to_vec(&err).map_err(AIError::ResponseMarshal)?;
 				(llm_resp, body)
@@ -399,6 +453,7 @@ impl AIProvider {
 		&self,
 		req: &LLMRequest,
 		status: StatusCode,
+		headers: &HeaderMap,
 		bytes: &Bytes,
 	) -> Result<Result<universal::Response, ChatCompletionErrorResponse>, AIError> {
 		if status.is_success() {
@@ -418,8 +473,8 @@ impl AIProvider {
 				AIProvider::OpenAI(p) => p.process_error(bytes).await?,
 				AIProvider::Gemini(p) => p.process_error(bytes).await?,
 				AIProvider::Vertex(p) => p.process_error(bytes).await?,
-				AIProvider::Anthropic(p) => p.process_error(bytes).await?,
-				AIProvider::Bedrock(p) => p.process_error(bytes).await?,
+				AIProvider::Anthropic(p) => p.process_error(status, bytes).await?,
+				AIProvider::Bedrock(p) => p.process_error(status, headers, bytes).await?,
 			};
 			Ok(Err(openai_response))
 		}
@@ -443,6 +498,9 @@ impl AIProvider {
 			provider_model: Default::default(),
 			completion: Default::default(),
 			first_token: Default::default(),
+			error_type: Default::default(),
+			oversized_events: Default::default(),
+			invalid_events: Default::default(),
 		};
 		log.store(Some(llmresp));
 		let resp = match self {
@@ -473,7 +531,14 @@ impl AIProvider {
 			let mut seen_provider = false;
 			let mut saw_token = false;
 			let mut rate_limit = Some(rate_limit);
-			parse::sse::json_passthrough::<universal::StreamResponse>(b, move |f| {
+			let malformed_log = log.clone();
+			let on_malformed = move |reason| {
+				malformed_log.non_atomic_mutate(|r: &mut LLMResponse| match reason {
+					parse::sse::MalformedEvent::Oversized => r.oversized_events += 1,
+					parse::sse::MalformedEvent::Invalid => r.invalid_events += 1,
+				})
+			};
+			parse::sse::json_passthrough::<universal::StreamResponse>(b, on_malformed, move |f| {
 				match f {
 					Some(Ok(f)) => {
 						if let Some(c) = completion.as_mut()
@@ -651,6 +716,28 @@ pub enum AIError {
 	JoinError(#[from] tokio::task::JoinError),
 }
 
+impl AIError {
+	pub fn error_type(&self) -> ErrorType {
+		match self {
+			AIError::MissingField(_)
+			| AIError::ModelNotFound
+			| AIError::MessageNotFound
+			| AIError::UnknownModel
+			| AIError::RequestParsing(_) => ErrorType::InvalidRequest,
+			AIError::RequestTooLarge => ErrorType::RequestTooLarge,
+			AIError::StreamingUnsupported | AIError::UnsupportedModel | AIError::UnsupportedContent => {
+				ErrorType::Unsupported
+			},
+			AIError::IncompleteResponse | AIError::ResponseParsing(_) => ErrorType::InvalidResponse,
+			AIError::PromptWebhookError
+			| AIError::RequestMarshal(_)
+			| AIError::ResponseMarshal(_)
+			| AIError::Encoding(_)
+			| AIError::JoinError(_) => ErrorType::Processing,
+		}
+	}
+}
+
 fn amend_tokens(rate_limit: store::LLMResponsePolicies, llm_resp: &LLMResponse) {
 	let input_mismatch = match (
 		llm_resp.request.input_tokens,
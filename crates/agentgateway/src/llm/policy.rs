@@ -22,6 +22,61 @@ pub struct Policy {
 	pub overrides: Option<HashMap<String, serde_json::Value>>,
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub prompts: Option<PromptEnrichment>,
+	/// Fields to set on the request after it has been translated to the provider format.
+	/// Keys are JSON pointers into the request body (for example, `/metadata/user_id`); values are
+	/// CEL expressions. This allows setting provider-specific extensions, such as OpenRouter `provider`
+	/// preferences. Fields whose expression fails to evaluate are skipped.
+	#[serde(
+		default,
+		skip_serializing_if = "Vec::is_empty",
+		serialize_with = "ser_request_fields",
+		deserialize_with = "de_request_fields"
+	)]
+	#[cfg_attr(
+		feature = "schema",
+		schemars(with = "std::collections::BTreeMap<String, String>")
+	)]
+	pub request_fields: Vec<(Strng, Arc<cel::Expression>)>,
+	/// Send a sample of responses to a webhook for quality evaluation.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sampling: Option<crate::llm::sampling::ResponseSampling>,
+	/// Log a salted hash of each prompt, and count prompts repeated within a window.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub prompt_hashing: Option<crate::llm::prompthash::PromptHashing>,
+}
+
+fn ser_request_fields<S: Serializer>(
+	fields: &[(Strng, Arc<cel::Expression>)],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	use serde::ser::SerializeMap;
+	let mut m = serializer.serialize_map(Some(fields.len()))?;
+	for (k, v) in fields {
+		m.serialize_entry(k, v.as_ref())?;
+	}
+	m.end()
+}
+
+fn de_request_fields<'de, D>(
+	deserializer: D,
+) -> Result<Vec<(Strng, Arc<cel::Expression>)>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let raw = IndexMap::<String, String>::deserialize(deserializer)?;
+	raw
+		.into_iter()
+		.map(|(k, v)| {
+			if !k.is_empty() && !k.starts_with('/') {
+				return Err(serde::de::Error::custom(format!(
+					"invalid JSON pointer '{k}': must start with '/'"
+				)));
+			}
+			cel::Expression::new(v)
+				.map(|e| (strng::new(k), Arc::new(e)))
+				.map_err(|e| serde::de::Error::custom(e.to_string()))
+		})
+		.collect()
 }
 
 #[apply(schema!)]
@@ -48,6 +103,40 @@ pub struct PromptGuard {
 	pub response: Option<ResponseGuard>,
 }
 impl Policy {
+	pub fn expressions(&self) -> impl Iterator<Item = &cel::Expression> {
+		self.request_fields.iter().map(|(_, e)| e.as_ref())
+	}
+
+	/// apply_request_fields sets the configured request fields on the provider-specific request body.
+	pub fn apply_request_fields(
+		&self,
+		exec: &cel::Executor,
+		body: Vec<u8>,
+	) -> Result<Vec<u8>, AIError> {
+		if self.request_fields.is_empty() {
+			return Ok(body);
+		}
+		let mut v: serde_json::Value =
+			serde_json::from_slice(&body).map_err(AIError::RequestMarshal)?;
+		for (pointer, expr) in &self.request_fields {
+			let value = match exec.eval(expr).map(|v| v.json()) {
+				Ok(Ok(value)) => value,
+				Ok(Err(err)) => {
+					debug!(%pointer, "request field is not valid JSON: {err}");
+					continue;
+				},
+				Err(err) => {
+					debug!(%pointer, "request field failed to evaluate: {err}");
+					continue;
+				},
+			};
+			if let Err(err) = json::set_pointer(&mut v, pointer, value) {
+				debug!(%pointer, "failed to set request field: {err}");
+			}
+		}
+		serde_json::to_vec(&v).map_err(AIError::RequestMarshal)
+	}
+
 	pub fn apply_prompt_enrichment(
 		&self,
 		chat: &mut CreateChatCompletionRequest,
@@ -83,6 +172,16 @@ impl Policy {
 		}
 		serde_json::from_value(serde_json::Value::Object(map)).map_err(AIError::RequestParsing)
 	}
+
+	pub fn prompt_guard_mode(&self) -> http::PolicyMode {
+		self
+			.prompt_guard
+			.as_ref()
+			.and_then(|g| g.request.as_ref())
+			.map(|g| g.mode)
+			.unwrap_or_default()
+	}
+
 	pub async fn apply_prompt_guard(
 		&self,
 		client: client::Client,
@@ -229,6 +328,9 @@ pub struct RequestGuard {
 	pub webhook: Option<Webhook>,
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub openai_moderation: Option<Moderation>,
+	/// In monitor mode, prompts the guard would reject are logged and counted. Prompts are never modified.
+	#[serde(default, skip_serializing_if = "is_default")]
+	pub mode: http::PolicyMode,
 }
 
 #[apply(schema!)]
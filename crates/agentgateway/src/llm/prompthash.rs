@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+use crate::llm::SimpleChatCompletionMessage;
+use crate::*;
+
+/// The maximum number of distinct prompts counted per window. Once reached, new prompts are still hashed, but
+/// only prompts already seen in the window are counted.
+const MAX_TRACKED_PROMPTS: usize = 100_000;
+
+/// PromptHashing logs a salted hash of each request's normalized prompt, and counts how often the same prompt is
+/// seen within a window. Agents stuck in a loop, resending the same prompt, can be found from the logs and the
+/// duplicate prompt metric without logging prompt contents.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(with = "PromptHashingSerde"))]
+pub struct PromptHashing {
+	config: PromptHashingSerde,
+	window: Arc<Mutex<Window>>,
+}
+
+impl serde::Serialize for PromptHashing {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		self.config.serialize(serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for PromptHashing {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let config = PromptHashingSerde::deserialize(deserializer)?;
+		if config.window.is_zero() {
+			return Err(serde::de::Error::custom("window must be greater than zero"));
+		}
+		Ok(PromptHashing {
+			config,
+			window: Arc::new(Mutex::new(Window {
+				started: Instant::now(),
+				counts: HashMap::new(),
+			})),
+		})
+	}
+}
+
+#[apply(schema!)]
+pub struct PromptHashingSerde {
+	/// Secret mixed into each hash, so hashes cannot be matched against hashes of guessed prompts.
+	#[serde(serialize_with = "ser_redact")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub salt: SecretString,
+	/// The window duplicate prompts are counted over. Defaults to 1m.
+	#[serde(default = "default_window", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub window: Duration,
+}
+
+fn default_window() -> Duration {
+	Duration::from_secs(60)
+}
+
+#[derive(Debug)]
+struct Window {
+	started: Instant,
+	counts: HashMap<[u8; 16], u64>,
+}
+
+/// PromptHash is the hash of a request's prompt, along with how many times it has been seen in the current window,
+/// including this request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptHash {
+	pub hash: Strng,
+	pub count: u64,
+}
+
+impl PromptHash {
+	pub fn is_duplicate(&self) -> bool {
+		self.count > 1
+	}
+}
+
+impl PromptHashing {
+	pub fn record(&self, messages: &[SimpleChatCompletionMessage]) -> PromptHash {
+		let digest = self.hash(messages);
+		let count = {
+			let mut window = self.window.lock().expect("mutex");
+			if window.started.elapsed() >= self.config.window {
+				window.started = Instant::now();
+				window.counts.clear();
+			}
+			let tracked = window.counts.len();
+			match window.counts.get_mut(&digest) {
+				Some(count) => {
+					*count += 1;
+					*count
+				},
+				None => {
+					if tracked < MAX_TRACKED_PROMPTS {
+						window.counts.insert(digest, 1);
+					}
+					1
+				},
+			}
+		};
+		PromptHash {
+			hash: hex::encode(digest).into(),
+			count,
+		}
+	}
+
+	/// hash returns the salted hash of the prompt. Prompts are normalized by collapsing whitespace, so requests
+	/// that differ only in formatting are treated as the same prompt.
+	fn hash(&self, messages: &[SimpleChatCompletionMessage]) -> [u8; 16] {
+		let mut hasher = Sha256::new();
+		hasher.update(self.config.salt.expose_secret().as_bytes());
+		for m in messages {
+			hasher.update([0]);
+			hasher.update(m.role.as_bytes());
+			hasher.update([0]);
+			for (i, word) in m.content.split_whitespace().enumerate() {
+				if i > 0 {
+					hasher.update(b" ");
+				}
+				hasher.update(word.as_bytes());
+			}
+		}
+		let digest = hasher.finalize();
+		let mut out = [0; 16];
+		out.copy_from_slice(&digest[..16]);
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn hashing(salt: &str, window: &str) -> PromptHashing {
+		serde_json::from_value(serde_json::json!({"salt": salt, "window": window})).unwrap()
+	}
+
+	fn prompt(content: &str) -> Vec<SimpleChatCompletionMessage> {
+		vec![SimpleChatCompletionMessage {
+			role: strng::literal!("user"),
+			content: content.into(),
+		}]
+	}
+
+	#[test]
+	fn counts_duplicates() {
+		let h = hashing("salt", "1m");
+		let first = h.record(&prompt("what is  the weather?"));
+		assert!(!first.is_duplicate());
+		let second = h.record(&prompt(" what is the\nweather? "));
+		assert_eq!(second.hash, first.hash);
+		assert_eq!(second.count, 2);
+		assert_eq!(h.record(&prompt("something else")).count, 1);
+
+		// The salt changes the hash.
+		let other = hashing("pepper", "1m").record(&prompt("what is the weather?"));
+		assert_ne!(other.hash, first.hash);
+	}
+
+	#[test]
+	fn salt_is_redacted() {
+		let h = hashing("salt", "1m");
+		let out = serde_json::to_value(&h).unwrap();
+		assert_ne!(out["salt"], "salt");
+	}
+}
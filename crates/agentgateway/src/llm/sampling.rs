@@ -0,0 +1,186 @@
+use ::http::StatusCode;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::http::Body;
+use crate::llm::{LLMResponse, SimpleChatCompletionMessage};
+use crate::{client, *};
+
+/// ResponseSampling sends a fraction of completed LLM responses, along with their prompts, to an evaluation
+/// webhook. Samples are sent in the background once the response has finished (including streams), so they
+/// never delay the response. Requests are not sampled while too many samples are pending, so a slow webhook
+/// cannot accumulate unbounded work.
+#[apply(schema!)]
+pub struct ResponseSampling {
+	/// URL to POST sampled prompts and completions to.
+	#[serde(serialize_with = "ser_display", deserialize_with = "de_parse")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub webhook: ::http::Uri,
+	/// Fraction of requests to sample, between 0 and 1. Defaults to 0.01.
+	#[serde(default = "default_rate")]
+	pub rate: f64,
+	/// Identities (the JWT `sub` claim) whose requests are never sampled.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub exclude_identities: Vec<String>,
+	#[serde(skip, default = "default_permits")]
+	#[cfg_attr(feature = "schema", schemars(skip))]
+	permits: Arc<Semaphore>,
+}
+
+fn default_rate() -> f64 {
+	0.01
+}
+
+/// Maximum number of samples pending for a policy, from selection until the webhook responds.
+const MAX_PENDING_SAMPLES: usize = 64;
+
+fn default_permits() -> Arc<Semaphore> {
+	Arc::new(Semaphore::new(MAX_PENDING_SAMPLES))
+}
+
+impl ResponseSampling {
+	/// sample decides whether a request is sampled, returning the pending sample if so.
+	pub fn sample(
+		&self,
+		client: client::Client,
+		identity: Option<&str>,
+		prompt: impl FnOnce() -> Vec<SimpleChatCompletionMessage>,
+	) -> Option<Sample> {
+		if !self.should_sample(identity) {
+			return None;
+		}
+		let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+			debug!(webhook=%self.webhook, "too many pending response samples; skipping");
+			return None;
+		};
+		Some(Sample {
+			webhook: self.webhook.clone(),
+			client,
+			prompt: prompt(),
+			permit,
+		})
+	}
+
+	fn should_sample(&self, identity: Option<&str>) -> bool {
+		if identity.is_some_and(|id| self.exclude_identities.iter().any(|e| e == id)) {
+			return false;
+		}
+		rand::random_bool(self.rate.clamp(0.0, 1.0))
+	}
+}
+
+/// Sample is a request that was selected for evaluation; it is sent once the response completes.
+#[derive(Debug)]
+pub struct Sample {
+	webhook: ::http::Uri,
+	client: client::Client,
+	prompt: Vec<SimpleChatCompletionMessage>,
+	// Held until the sample is sent, or dropped with it.
+	permit: OwnedSemaphorePermit,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SampleRequest<'a> {
+	route: Option<&'a str>,
+	identity: Option<&'a str>,
+	provider: &'a str,
+	request_model: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	response_model: Option<&'a str>,
+	status: Option<u16>,
+	latency_ms: u128,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	input_tokens: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	output_tokens: Option<u64>,
+	prompt: &'a [SimpleChatCompletionMessage],
+	#[serde(skip_serializing_if = "Option::is_none")]
+	completion: Option<&'a [String]>,
+}
+
+impl Sample {
+	/// complete sends the sample to the webhook in the background, given the latency of the backend call.
+	/// Requests that did not get an LLM response, such as ones rejected by a prompt guard, are not sent.
+	pub fn complete(
+		self,
+		resp: Option<&LLMResponse>,
+		route: Option<&str>,
+		identity: Option<&str>,
+		status: Option<StatusCode>,
+		latency: Duration,
+	) {
+		let Some(resp) = resp else {
+			return;
+		};
+		let body = serde_json::to_vec(&SampleRequest {
+			route,
+			identity,
+			provider: resp.request.provider.as_str(),
+			request_model: resp.request.request_model.as_str(),
+			response_model: resp.provider_model.as_deref(),
+			status: status.map(|s| s.as_u16()),
+			latency_ms: latency.as_millis(),
+			input_tokens: resp.input_tokens(),
+			output_tokens: resp.output_tokens,
+			prompt: &self.prompt,
+			completion: resp.completion.as_deref(),
+		})
+		.expect("serialization should succeed");
+		let Sample {
+			webhook,
+			client,
+			permit,
+			..
+		} = self;
+		tokio::task::spawn(async move {
+			let req = ::http::Request::builder()
+				.method(::http::Method::POST)
+				.uri(&webhook)
+				.header(::http::header::CONTENT_TYPE, "application/json")
+				.body(Body::from(body))
+				.expect("builder should succeed");
+			match client.simple_call(req).await {
+				Ok(resp) if resp.status().is_success() => {},
+				Ok(resp) => warn!(url=%webhook, status=%resp.status(), "response sampling webhook failed"),
+				Err(err) => warn!(url=%webhook, ?err, "response sampling webhook failed"),
+			}
+			drop(permit);
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sampling(rate: f64) -> ResponseSampling {
+		ResponseSampling {
+			webhook: ::http::Uri::from_static("http://localhost:8080/eval"),
+			rate,
+			exclude_identities: vec!["opted-out".to_string()],
+			permits: default_permits(),
+		}
+	}
+
+	#[test]
+	fn sample_decision() {
+		assert!(sampling(1.0).should_sample(None));
+		assert!(!sampling(0.0).should_sample(None));
+		assert!(!sampling(1.0).should_sample(Some("opted-out")));
+		assert!(sampling(1.0).should_sample(Some("user")));
+	}
+
+	#[tokio::test]
+	async fn pending_samples_bounded() {
+		let s = sampling(1.0);
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		let pending = (0..MAX_PENDING_SAMPLES)
+			.map(|_| s.sample(client.clone(), None, Vec::new).unwrap())
+			.collect::<Vec<_>>();
+		assert!(s.sample(client.clone(), None, Vec::new).is_none());
+		// Samples that complete or are dropped free their slot.
+		drop(pending);
+		assert!(s.sample(client, None, Vec::new).is_some());
+	}
+}
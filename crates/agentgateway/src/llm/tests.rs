@@ -100,3 +100,138 @@ fn test_anthropic() {
 		test_request("anthropic", r, request);
 	}
 }
+
+#[test]
+fn test_request_fields() {
+	let policy: Policy = serde_json::from_value(serde_json::json!({
+		"requestFields": {
+			"/provider/order": "['openai', 'together']",
+			"/metadata/user_id": "request.headers['x-user']",
+			"/user": "'static-user'",
+			"/missing": "request.headers['x-not-set']",
+		}
+	}))
+	.unwrap();
+	let req = ::http::Request::builder()
+		.header("x-user", "alice")
+		.body(crate::http::Body::empty())
+		.unwrap();
+	let mut cb = crate::cel::ContextBuilder::new();
+	for e in policy.expressions() {
+		cb.register_expression(e);
+	}
+	cb.with_request(&req);
+	let exec = cb.build().unwrap();
+
+	let body = serde_json::to_vec(&serde_json::json!({
+		"model": "gpt-4o",
+		"metadata": {"existing": true},
+	}))
+	.unwrap();
+	let body = policy.apply_request_fields(&exec, body).unwrap();
+	let got: Value = serde_json::from_slice(&body).unwrap();
+	assert_eq!(
+		got,
+		serde_json::json!({
+			"model": "gpt-4o",
+			"metadata": {"existing": true, "user_id": "alice"},
+			"provider": {"order": ["openai", "together"]},
+			"user": "static-user",
+		})
+	);
+}
+
+async fn stream_events(body: crate::http::Body) -> Vec<Value> {
+	let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+	std::str::from_utf8(&body)
+		.unwrap()
+		.lines()
+		.filter_map(|l| l.strip_prefix("data: "))
+		.map(|d| serde_json::from_str(d).unwrap_or_else(|_| Value::String(d.to_string())))
+		.collect()
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_error() {
+	let provider = anthropic::Provider { model: None };
+	let input = fs::read("src/llm/tests/stream_anthropic_overloaded.sse").unwrap();
+	let resp = ::http::Response::new(crate::http::Body::from(input));
+	let resp = provider.process_streaming(Default::default(), resp).await;
+	let events = stream_events(resp.into_body()).await;
+	assert_eq!(events.len(), 3, "{events:?}");
+	assert_eq!(events[0]["choices"][0]["delta"]["content"], "Hello");
+	assert_eq!(
+		events[1],
+		serde_json::json!({"error": {"type": "server_error", "message": "Overloaded", "code": "overloaded"}})
+	);
+	assert_eq!(events[2], Value::String("[DONE]".to_string()));
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_interrupted() {
+	let provider = anthropic::Provider { model: None };
+	let delta = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n";
+	let body = crate::http::Body::from_stream(futures_util::stream::iter(vec![
+		Ok(Bytes::from_static(delta.as_bytes())),
+		Err(std::io::Error::other("connection reset")),
+	]));
+	let resp = provider
+		.process_streaming(Default::default(), ::http::Response::new(body))
+		.await;
+	let events = stream_events(resp.into_body()).await;
+	assert_eq!(events.len(), 3, "{events:?}");
+	assert_eq!(events[0]["choices"][0]["delta"]["content"], "Hel");
+	assert_eq!(events[1]["error"]["type"], "server_error");
+	assert_eq!(events[2], Value::String("[DONE]".to_string()));
+}
+
+#[test]
+fn test_error_mapping() {
+	let anthropic = |ty: &str, status: u16| {
+		let resp = serde_json::from_value(serde_json::json!({
+			"type": "error",
+			"error": {"type": ty, "message": "oops"},
+		}))
+		.unwrap();
+		let fallback = universal::ErrorKind::from_status(StatusCode::from_u16(status).unwrap());
+		let err = anthropic::translate_error(resp, fallback).unwrap().error;
+		(err.r#type, err.code)
+	};
+	assert_eq!(
+		anthropic("overloaded_error", 529),
+		("server_error".to_string(), Some("overloaded".to_string()))
+	);
+	assert_eq!(
+		anthropic("rate_limit_error", 429),
+		(
+			"rate_limit_error".to_string(),
+			Some("rate_limit_exceeded".to_string())
+		)
+	);
+	assert_eq!(
+		anthropic("something_new", 400),
+		("invalid_request_error".to_string(), None)
+	);
+
+	let bedrock = |ty: Option<&str>, status: u16| {
+		let resp = bedrock::types::ConverseErrorResponse {
+			message: "oops".to_string(),
+		};
+		let fallback = universal::ErrorKind::from_status(StatusCode::from_u16(status).unwrap());
+		let err = bedrock::translate_error(resp, ty, fallback).unwrap().error;
+		(err.r#type, err.code)
+	};
+	// The header form is capitalized, the streaming form is not
+	assert_eq!(
+		bedrock(Some("ThrottlingException"), 429),
+		(
+			"rate_limit_error".to_string(),
+			Some("rate_limit_exceeded".to_string())
+		)
+	);
+	assert_eq!(
+		bedrock(Some("modelTimeoutException"), 408),
+		("server_error".to_string(), Some("timeout".to_string()))
+	);
+	assert_eq!(bedrock(None, 500), ("server_error".to_string(), None));
+}
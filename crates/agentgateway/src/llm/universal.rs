@@ -23,8 +23,11 @@ pub use async_openai::types::{
 use async_openai::types::{CreateChatCompletionRequest, Stop};
 use serde::{Deserialize, Serialize};
 
+use crate::telemetry::errors::ErrorType;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ChatCompletionErrorResponse {
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub event_id: Option<String>,
 	pub error: ChatCompletionError,
 }
@@ -41,6 +44,100 @@ pub struct ChatCompletionError {
 	pub event_id: Option<String>,
 }
 
+impl ChatCompletionError {
+	/// error_type classifies a provider error, once it has been translated to the OpenAI format.
+	pub fn error_type(&self) -> ErrorType {
+		match (self.r#type.as_str(), self.code.as_deref()) {
+			(_, Some("invalid_api_key" | "permission_denied")) => ErrorType::UpstreamAuthentication,
+			("authentication_error" | "permission_error", _) => ErrorType::UpstreamAuthentication,
+			(_, Some("not_found" | "model_not_found")) => ErrorType::NotFound,
+			(_, Some("request_too_large")) => ErrorType::RequestTooLarge,
+			(_, Some("overloaded")) => ErrorType::UpstreamOverloaded,
+			(_, Some("timeout")) => ErrorType::Timeout,
+			("rate_limit_error", _) => ErrorType::UpstreamRateLimited,
+			("invalid_request_error", _) => ErrorType::InvalidRequest,
+			_ => ErrorType::UpstreamError,
+		}
+	}
+}
+
+/// A single event in an OpenAI-compatible chat completion stream.
+/// Errors that occur after the stream has started are sent as an event with an `error` field.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum StreamEvent {
+	Chunk(StreamResponse),
+	Error(ChatCompletionErrorResponse),
+}
+
+/// ErrorKind is the OpenAI-compatible classification of an error, used to map provider specific errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorKind {
+	pub r#type: &'static str,
+	pub code: Option<&'static str>,
+}
+
+impl ErrorKind {
+	pub const INVALID_REQUEST: ErrorKind = ErrorKind::new("invalid_request_error", None);
+	pub const AUTHENTICATION: ErrorKind =
+		ErrorKind::new("invalid_request_error", Some("invalid_api_key"));
+	pub const PERMISSION: ErrorKind =
+		ErrorKind::new("invalid_request_error", Some("permission_denied"));
+	pub const NOT_FOUND: ErrorKind = ErrorKind::new("invalid_request_error", Some("not_found"));
+	pub const REQUEST_TOO_LARGE: ErrorKind =
+		ErrorKind::new("invalid_request_error", Some("request_too_large"));
+	pub const RATE_LIMIT: ErrorKind = ErrorKind::new("rate_limit_error", Some("rate_limit_exceeded"));
+	pub const SERVER: ErrorKind = ErrorKind::new("server_error", None);
+	pub const OVERLOADED: ErrorKind = ErrorKind::new("server_error", Some("overloaded"));
+	pub const TIMEOUT: ErrorKind = ErrorKind::new("server_error", Some("timeout"));
+
+	const fn new(r#type: &'static str, code: Option<&'static str>) -> Self {
+		ErrorKind { r#type, code }
+	}
+
+	/// lookup finds the kind for a provider error type in a provider's mapping table. Types are matched case
+	/// insensitively, as some providers are inconsistent in their casing.
+	pub fn lookup(table: &[(&str, ErrorKind)], provider_type: &str) -> Option<ErrorKind> {
+		table
+			.iter()
+			.find(|(t, _)| t.eq_ignore_ascii_case(provider_type))
+			.map(|(_, k)| *k)
+	}
+
+	/// from_status classifies an error by its HTTP status, for providers that do not report an error type.
+	pub fn from_status(status: ::http::StatusCode) -> ErrorKind {
+		match status.as_u16() {
+			401 => ErrorKind::AUTHENTICATION,
+			403 => ErrorKind::PERMISSION,
+			404 => ErrorKind::NOT_FOUND,
+			413 => ErrorKind::REQUEST_TOO_LARGE,
+			429 => ErrorKind::RATE_LIMIT,
+			503 | 529 => ErrorKind::OVERLOADED,
+			504 => ErrorKind::TIMEOUT,
+			s if s >= 500 => ErrorKind::SERVER,
+			_ => ErrorKind::INVALID_REQUEST,
+		}
+	}
+
+	pub fn into_error(self, message: String) -> ChatCompletionErrorResponse {
+		ChatCompletionErrorResponse {
+			event_id: None,
+			error: ChatCompletionError {
+				r#type: self.r#type.to_string(),
+				message,
+				param: None,
+				code: self.code.map(ToString::to_string),
+				event_id: None,
+			},
+		}
+	}
+}
+
+/// stream_interrupted is the error sent to clients when the upstream stream fails partway through.
+pub fn stream_interrupted() -> ChatCompletionErrorResponse {
+	ErrorKind::SERVER.into_error("the upstream stream ended unexpectedly".to_string())
+}
+
 pub const SYSTEM_ROLE: &str = "system";
 pub const ASSISTANT_ROLE: &str = "assistant";
 
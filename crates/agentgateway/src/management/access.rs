@@ -0,0 +1,140 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use rustls::ServerConfig;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serializer;
+
+use crate::*;
+
+/// Access controls who can reach a management server, such as the admin or stats server.
+/// With the default, any client that can reach the port is allowed; configured servers use [Access::local]
+/// unless a token or mTLS is set.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Access {
+	/// Bearer token required on every request.
+	#[serde(serialize_with = "ser_redact", skip_serializing_if = "Option::is_none")]
+	pub token: Option<SecretString>,
+	/// TLS configuration, requiring a client certificate signed by the configured CA.
+	#[serde(rename = "mtls", serialize_with = "ser_enabled")]
+	pub tls: Option<Arc<ServerConfig>>,
+	/// Source networks allowed to connect. If empty, all sources are allowed.
+	#[serde(
+		serialize_with = "ser_display_iter",
+		skip_serializing_if = "Vec::is_empty"
+	)]
+	pub allowed_sources: Vec<IpNet>,
+}
+
+fn ser_enabled<S: Serializer, T>(t: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_bool(t.is_some())
+}
+
+impl Access {
+	/// local returns access that only allows connections from loopback addresses.
+	pub fn local() -> Self {
+		Access {
+			allowed_sources: loopback_sources(),
+			..Default::default()
+		}
+	}
+
+	/// is_open reports whether the server accepts unauthenticated requests.
+	pub fn is_open(&self) -> bool {
+		self.token.is_none() && self.tls.is_none()
+	}
+
+	/// is_exposed reports whether the server accepts unauthenticated requests from other hosts.
+	pub fn is_exposed(&self) -> bool {
+		self.is_open()
+			&& (self.allowed_sources.is_empty()
+				|| self
+					.allowed_sources
+					.iter()
+					.any(|n| !n.network().is_loopback()))
+	}
+
+	pub fn allows_source(&self, ip: IpAddr) -> bool {
+		// Normalize IPv4-mapped IPv6 addresses, as seen on dual stack listeners.
+		let ip = ip.to_canonical();
+		self.allowed_sources.is_empty() || self.allowed_sources.iter().any(|n| n.contains(&ip))
+	}
+
+	/// authorized checks the request's bearer token, if one is required.
+	pub fn authorized<B>(&self, req: &::http::Request<B>) -> bool {
+		let Some(token) = &self.token else {
+			return true;
+		};
+		req
+			.headers()
+			.get(::http::header::AUTHORIZATION)
+			.and_then(|h| h.to_str().ok())
+			.and_then(|h| h.strip_prefix("Bearer "))
+			.is_some_and(|got| constant_time_eq(got.as_bytes(), token.expose_secret().as_bytes()))
+	}
+}
+
+fn loopback_sources() -> Vec<IpNet> {
+	vec![
+		Ipv4Net::new(Ipv4Addr::LOCALHOST, 8)
+			.expect("valid prefix")
+			.trunc()
+			.into(),
+		Ipv6Net::from(Ipv6Addr::LOCALHOST).into(),
+	]
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn request(auth: Option<&str>) -> ::http::Request<()> {
+		let mut req = ::http::Request::builder().uri("/config_dump");
+		if let Some(auth) = auth {
+			req = req.header(::http::header::AUTHORIZATION, auth);
+		}
+		req.body(()).unwrap()
+	}
+
+	#[test]
+	fn token() {
+		let access = Access {
+			token: Some(SecretString::from("secret")),
+			..Default::default()
+		};
+		assert!(access.authorized(&request(Some("Bearer secret"))));
+		assert!(!access.authorized(&request(Some("Bearer secre"))));
+		assert!(!access.authorized(&request(Some("secret"))));
+		assert!(!access.authorized(&request(None)));
+		assert!(Access::default().authorized(&request(None)));
+	}
+
+	#[test]
+	fn sources() {
+		let access = Access {
+			allowed_sources: vec!["127.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()],
+			..Default::default()
+		};
+		assert!(access.allows_source("127.0.0.1".parse().unwrap()));
+		assert!(access.allows_source("::ffff:127.0.0.1".parse().unwrap()));
+		assert!(access.allows_source("::1".parse().unwrap()));
+		assert!(!access.allows_source("10.0.0.1".parse().unwrap()));
+		assert!(Access::default().allows_source("10.0.0.1".parse().unwrap()));
+		assert!(Access::default().is_exposed());
+		assert!(!access.is_exposed());
+	}
+
+	#[test]
+	fn local() {
+		let access = Access::local();
+		assert!(access.allows_source("127.0.0.2".parse().unwrap()));
+		assert!(access.allows_source("::1".parse().unwrap()));
+		assert!(!access.allows_source("10.0.0.1".parse().unwrap()));
+		assert!(!access.is_exposed());
+	}
+}
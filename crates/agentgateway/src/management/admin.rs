@@ -16,6 +16,7 @@ use tokio::time;
 use tracing::{info, warn};
 use tracing_subscriber::filter;
 
+use super::diagnostics::{Bundle, Diagnostics};
 use super::hyper_helpers::{Server, empty_response, plaintext_response};
 use crate::Config;
 use crate::http::Response;
@@ -43,10 +44,16 @@ struct State {
 	shutdown_trigger: signal::ShutdownTrigger,
 	config_dump_handlers: Vec<Arc<dyn ConfigDumpHandler>>,
 	admin_fallback: Option<Arc<dyn AdminFallback>>,
+	diagnostics: Option<Diagnostics>,
 }
 
 pub struct Service {
 	s: Server<State>,
+	/// Serves the UI on its own address, when one is configured.
+	ui: Option<Server<()>>,
+	/// Whether the UI may change the configuration. Without access control, the dedicated UI address only
+	/// serves reads.
+	ui_writable: bool,
 }
 
 #[derive(serde::Serialize, Clone)]
@@ -84,7 +91,29 @@ impl Service {
 		shutdown_trigger: signal::ShutdownTrigger,
 		drain_rx: DrainWatcher,
 	) -> anyhow::Result<Self> {
-		Server::<State>::bind(
+		let access = config.admin_access.clone();
+		if access.is_exposed() && !config.admin_addr.is_loopback() {
+			warn!(
+				address=?config.admin_addr,
+				"admin server is reachable from other hosts without authentication; configure adminAccess"
+			);
+		}
+		let ui = match config.ui.address {
+			Some(addr) if config.ui.enabled => {
+				if access.is_exposed() && !addr.is_loopback() {
+					warn!(
+						address=?addr,
+						"UI server is reachable from other hosts without authentication; configuration changes are disabled there until adminAccess is configured"
+					);
+				}
+				let mut ui = Server::<()>::bind("ui", addr, drain_rx.clone(), ()).await?;
+				ui.set_access(access.clone());
+				ui.enable_audit();
+				Some(ui)
+			},
+			_ => None,
+		};
+		let mut s = Server::<State>::bind(
 			"admin",
 			config.admin_addr,
 			drain_rx,
@@ -94,16 +123,25 @@ impl Service {
 				shutdown_trigger,
 				config_dump_handlers: vec![],
 				admin_fallback: None,
+				diagnostics: None,
 			},
 		)
-		.await
-		.map(|s| Service { s })
+		.await?;
+		let ui_writable = !access.is_open();
+		s.set_access(access);
+		s.enable_audit();
+		Ok(Service { s, ui, ui_writable })
 	}
 
 	pub fn address(&self) -> SocketAddr {
 		self.s.address()
 	}
 
+	/// ui_address is the address the UI is served on.
+	pub fn ui_address(&self) -> SocketAddr {
+		self.ui.as_ref().unwrap_or(&self.s).address()
+	}
+
 	pub fn add_config_dump_handler(&mut self, handler: Arc<dyn ConfigDumpHandler>) {
 		self.s.state_mut().config_dump_handlers.push(handler);
 	}
@@ -112,8 +150,38 @@ impl Service {
 		self.s.state_mut().admin_fallback = Some(handler);
 	}
 
+	pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+		self.s.state_mut().diagnostics = Some(diagnostics);
+	}
+
 	pub fn spawn(self) {
-		self.s.spawn(|state, req| async move {
+		let mut s = self.s.map_state(Arc::new);
+		let dedicated_ui = self.ui.is_some();
+		let ui_writable = self.ui_writable;
+		if let Some(ui) = self.ui {
+			let state = s.state_mut().clone();
+			ui.map_state(|()| state)
+				.spawn(move |state, req| async move {
+					if !ui_writable && !req.method().is_safe() {
+						return Ok(plaintext_response(
+							hyper::StatusCode::FORBIDDEN,
+							"configuration changes require adminAccess to be configured\n".to_string(),
+						));
+					}
+					match req.uri().path() {
+						// The UI reads the config dump, so it is served alongside it.
+						"/config_dump" => handle_config_dump(&state).await,
+						_ => {
+							if let Some(h) = &state.admin_fallback {
+								Ok(h.handle(req).await)
+							} else {
+								Ok(empty_response(hyper::StatusCode::NOT_FOUND))
+							}
+						},
+					}
+				});
+		}
+		s.spawn(move |state, req| async move {
 			match req.uri().path() {
 				#[cfg(target_os = "linux")]
 				"/debug/pprof/profile" => handle_pprof(req).await,
@@ -127,20 +195,12 @@ impl Service {
 					)
 					.await,
 				),
-				"/config_dump" => {
-					handle_config_dump(
-						&state.config_dump_handlers,
-						ConfigDump {
-							stores: state.stores.clone(),
-							version: BuildInfo::new(),
-							config: state.config.clone(),
-						},
-					)
-					.await
-				},
+				"/config_dump" => handle_config_dump(&state).await,
+				"/config_version" => handle_config_version(&state).await,
+				"/debug/diagnostics" => handle_diagnostics(&state).await,
 				"/logging" => Ok(handle_logging(req).await),
 				_ => {
-					if let Some(h) = &state.admin_fallback {
+					if let Some(h) = state.admin_fallback.as_ref().filter(|_| !dedicated_ui) {
 						Ok(h.handle(req).await)
 					} else if req.uri().path() == "/" {
 						Ok(handle_dashboard(req).await)
@@ -165,7 +225,15 @@ async fn handle_dashboard(_req: Request<Incoming>) -> Response {
 		),
 		("quitquitquit", "shut down the server"),
 		("config_dump", "dump the current agentgateway configuration"),
+		(
+			"config_version",
+			"show the hash of the current configuration, and when it last changed",
+		),
 		("logging", "query/changing logging levels"),
+		(
+			"debug/diagnostics",
+			"download a support bundle with the config, recent errors, metrics, readiness, and open connections and sessions",
+		),
 	];
 
 	let mut api_rows = String::new();
@@ -233,23 +301,83 @@ async fn handle_server_shutdown(
 	}
 }
 
-async fn handle_config_dump(
-	handlers: &[Arc<dyn ConfigDumpHandler>],
-	dump: ConfigDump,
-) -> anyhow::Result<Response> {
+fn config_dump(state: &State) -> anyhow::Result<String> {
+	let dump = ConfigDump {
+		stores: state.stores.clone(),
+		version: BuildInfo::new(),
+		config: state.config.clone(),
+	};
 	let serde_json::Value::Object(mut kv) = serde_json::to_value(&dump)? else {
 		anyhow::bail!("config dump is not a key-value pair")
 	};
 
-	for h in handlers {
+	for h in &state.config_dump_handlers {
 		let x = h.handle()?;
 		kv.insert(h.key().to_string(), x);
 	}
-	let body = serde_json::to_string_pretty(&kv)?;
+	Ok(serde_json::to_string_pretty(&kv)?)
+}
+
+async fn handle_config_dump(state: &State) -> anyhow::Result<Response> {
+	let body = config_dump(state)?;
+	Ok(
+		::http::Response::builder()
+			.status(hyper::StatusCode::OK)
+			.header(hyper::header::CONTENT_TYPE, "application/json")
+			.body(body.into())
+			.expect("builder with known status code should not fail"),
+	)
+}
+
+async fn handle_config_version(state: &State) -> anyhow::Result<Response> {
+	let Some(version) = state.stores.version.current() else {
+		// The hash is computed shortly after startup.
+		return Ok(empty_response(hyper::StatusCode::SERVICE_UNAVAILABLE));
+	};
 	Ok(
 		::http::Response::builder()
 			.status(hyper::StatusCode::OK)
 			.header(hyper::header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string_pretty(&version)?.into())
+			.expect("builder with known status code should not fail"),
+	)
+}
+
+async fn handle_diagnostics(state: &State) -> anyhow::Result<Response> {
+	let mut bundle = Bundle::default();
+	bundle.add(
+		"version.json",
+		serde_json::to_string_pretty(&BuildInfo::new())?,
+	);
+	// Secrets are redacted when the config is serialized, so the dump is safe to share.
+	bundle.add("config_dump.json", config_dump(state)?);
+	let mut logs = telemetry::recent_logs().join("\n");
+	logs.push('\n');
+	bundle.add("recent_logs.txt", logs);
+	if let Some(d) = &state.diagnostics {
+		bundle.add("metrics.txt", d.metrics()?);
+		bundle.add(
+			"readiness.json",
+			serde_json::to_string_pretty(&d.readiness())?,
+		);
+		bundle.add(
+			"connections.json",
+			serde_json::to_string_pretty(&d.connections.snapshot())?,
+		);
+		bundle.add(
+			"sessions.json",
+			serde_json::to_string_pretty(&d.sessions.snapshot())?,
+		);
+	}
+	let body = bundle.finish().await?;
+	Ok(
+		::http::Response::builder()
+			.status(hyper::StatusCode::OK)
+			.header(hyper::header::CONTENT_TYPE, "application/gzip")
+			.header(
+				hyper::header::CONTENT_DISPOSITION,
+				"attachment; filename=\"agentgateway-diagnostics.tar.gz\"",
+			)
 			.body(body.into())
 			.expect("builder with known status code should not fail"),
 	)
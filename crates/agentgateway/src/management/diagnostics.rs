@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use agent_core::readiness;
+use async_compression::tokio::write::GzipEncoder;
+use prometheus_client::encoding::text::encode;
+use prometheus_client::registry::Registry;
+use tokio::io::AsyncWriteExt;
+
+use crate::mcp::sse::Session;
+use crate::proxy::DownstreamConnection;
+
+/// Diagnostics holds the runtime state captured in a support bundle, beyond what is already part of the
+/// config dump.
+#[derive(Clone)]
+pub struct Diagnostics {
+	pub registry: Arc<Mutex<Registry>>,
+	pub ready: readiness::Ready,
+	pub connections: Table<DownstreamConnection>,
+	pub sessions: Table<Session>,
+}
+
+impl Diagnostics {
+	/// metrics encodes the current metrics in the Prometheus text format.
+	pub fn metrics(&self) -> anyhow::Result<String> {
+		let mut buf = String::new();
+		encode(&mut buf, &self.registry.lock().expect("mutex"))?;
+		Ok(buf)
+	}
+
+	pub fn readiness(&self) -> serde_json::Value {
+		let pending: BTreeSet<String> = self.ready.pending().into_iter().collect();
+		serde_json::json!({
+			"ready": pending.is_empty(),
+			"pending": pending,
+			"degraded": self.ready.degraded(),
+		})
+	}
+}
+
+/// Bundle collects files into a gzipped tarball.
+#[derive(Default)]
+pub struct Bundle {
+	files: Vec<(String, Vec<u8>)>,
+}
+
+impl Bundle {
+	pub fn add(&mut self, name: impl Into<String>, contents: impl Into<Vec<u8>>) {
+		self.files.push((name.into(), contents.into()));
+	}
+
+	pub async fn finish(self) -> anyhow::Result<Vec<u8>> {
+		let mtime = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or_default();
+		let mut tar = tar::Builder::new(Vec::new());
+		for (name, contents) in &self.files {
+			let mut header = tar::Header::new_ustar();
+			header.set_entry_type(tar::EntryType::Regular);
+			header.set_mode(0o644);
+			header.set_mtime(mtime);
+			header.set_size(contents.len() as u64);
+			tar.append_data(&mut header, name, contents.as_slice())?;
+		}
+		let mut enc = GzipEncoder::new(Vec::new());
+		enc.write_all(&tar.into_inner()?).await?;
+		enc.shutdown().await?;
+		Ok(enc.into_inner())
+	}
+}
+
+/// Table tracks live entries, such as downstream connections or MCP sessions, so they can be included in
+/// diagnostics bundles. Each entry is removed when the handle returned by `insert` is dropped.
+pub struct Table<T>(Arc<Mutex<Entries<T>>>);
+
+struct Entries<T> {
+	next: u64,
+	live: BTreeMap<u64, T>,
+}
+
+impl<T> Default for Table<T> {
+	fn default() -> Self {
+		Table(Arc::new(Mutex::new(Entries {
+			next: 0,
+			live: BTreeMap::new(),
+		})))
+	}
+}
+
+impl<T> Clone for Table<T> {
+	fn clone(&self) -> Self {
+		Table(self.0.clone())
+	}
+}
+
+impl<T> std::fmt::Debug for Table<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Table").finish_non_exhaustive()
+	}
+}
+
+impl<T: Clone> Table<T> {
+	pub fn insert(&self, entry: T) -> TableEntry<T> {
+		let mut entries = self.0.lock().expect("mutex");
+		let id = entries.next;
+		entries.next += 1;
+		entries.live.insert(id, entry);
+		TableEntry {
+			table: self.clone(),
+			id,
+		}
+	}
+
+	/// snapshot returns the live entries, oldest first.
+	pub fn snapshot(&self) -> Vec<T> {
+		self
+			.0
+			.lock()
+			.expect("mutex")
+			.live
+			.values()
+			.cloned()
+			.collect()
+	}
+}
+
+/// TableEntry removes its entry from the table when dropped.
+pub struct TableEntry<T> {
+	table: Table<T>,
+	id: u64,
+}
+
+impl<T> Drop for TableEntry<T> {
+	fn drop(&mut self) {
+		self.table.0.lock().expect("mutex").live.remove(&self.id);
+	}
+}
+
+/// fetch downloads a diagnostics bundle from a running agentgateway's admin server.
+pub async fn fetch(admin_address: &str, token: Option<&str>) -> anyhow::Result<bytes::Bytes> {
+	let url = format!("{}/debug/diagnostics", admin_address.trim_end_matches('/'));
+	let mut req = reqwest::Client::new().get(url);
+	if let Some(token) = token {
+		req = req.bearer_auth(token);
+	}
+	let resp = req.send().await?;
+	let status = resp.status();
+	if !status.is_success() {
+		let body = resp.text().await.unwrap_or_default();
+		anyhow::bail!("admin server returned {status}: {body}");
+	}
+	Ok(resp.bytes().await?)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Read;
+
+	use async_compression::tokio::bufread::GzipDecoder;
+	use tokio::io::AsyncReadExt;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn bundle_layout() {
+		let mut bundle = Bundle::default();
+		bundle.add("a.txt", "hello");
+		bundle.add("b.json", vec![b'x'; 512]);
+		let out = bundle.finish().await.unwrap();
+
+		let mut tar = Vec::new();
+		GzipDecoder::new(out.as_slice())
+			.read_to_end(&mut tar)
+			.await
+			.unwrap();
+		let mut archive = tar::Archive::new(tar.as_slice());
+		let files = archive
+			.entries()
+			.unwrap()
+			.map(|e| {
+				let mut e = e.unwrap();
+				let name = e.path().unwrap().to_string_lossy().to_string();
+				assert_eq!(e.header().mode().unwrap(), 0o644);
+				let mut contents = Vec::new();
+				e.read_to_end(&mut contents).unwrap();
+				(name, contents)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			files,
+			vec![
+				("a.txt".to_string(), b"hello".to_vec()),
+				("b.json".to_string(), vec![b'x'; 512]),
+			]
+		);
+	}
+
+	#[test]
+	fn table_entries() {
+		let table = Table::default();
+		let a = table.insert("a");
+		let b = table.insert("b");
+		assert_eq!(table.snapshot(), vec!["a", "b"]);
+		drop(a);
+		assert_eq!(table.snapshot(), vec!["b"]);
+		let c = table.insert("c");
+		assert_eq!(table.snapshot(), vec!["b", "c"]);
+		drop((b, c));
+		assert!(table.snapshot().is_empty());
+	}
+}
@@ -7,15 +7,19 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use agent_core::drain::DrainWatcher;
-use futures_util::TryFutureExt;
 use hyper::Request;
 use hyper::server::conn::http1;
 use hyper_util::rt::TokioTimer;
 use tokio::net::TcpListener;
-use tracing::info;
+use tokio_util::either::Either;
+use tracing::{debug, info};
 
+use super::access::Access;
 use crate::http::{Body, Response};
 
+/// How long a client may take to complete the TLS handshake, so idle connections do not hold a task forever.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub fn http1_server() -> http1::Builder {
 	let mut b = http1::Builder::new();
 	b.timer(TokioTimer::new());
@@ -38,13 +42,16 @@ pub fn plaintext_response(code: hyper::StatusCode, body: String) -> Response {
 }
 
 /// Server implements a generic HTTP server with the follow behavior:
-/// * HTTP/1.1 plaintext only
+/// * HTTP/1.1 only, over plaintext or mTLS
+/// * Access control by source address and bearer token
 /// * Draining
 pub struct Server<S> {
 	name: String,
 	binds: Vec<TcpListener>,
 	drain_rx: DrainWatcher,
 	state: S,
+	access: Access,
+	audit: bool,
 }
 
 impl<S> Server<S> {
@@ -63,9 +70,20 @@ impl<S> Server<S> {
 			binds,
 			drain_rx,
 			state: s,
+			access: Access::default(),
+			audit: false,
 		})
 	}
 
+	pub fn set_access(&mut self, access: Access) {
+		self.access = access;
+	}
+
+	/// enable_audit logs every request that may change state, that is, any request other than GET or HEAD.
+	pub fn enable_audit(&mut self) {
+		self.audit = true;
+	}
+
 	pub fn address(&self) -> SocketAddr {
 		self
 			.binds
@@ -79,6 +97,18 @@ impl<S> Server<S> {
 		&mut self.state
 	}
 
+	/// map_state replaces the server's state, for example to share it with another server.
+	pub fn map_state<T>(self, f: impl FnOnce(S) -> T) -> Server<T> {
+		Server {
+			name: self.name,
+			binds: self.binds,
+			drain_rx: self.drain_rx,
+			state: f(self.state),
+			access: self.access,
+			audit: self.audit,
+		}
+	}
+
 	pub fn spawn<F, R>(self, f: F)
 	where
 		S: Send + Sync + 'static,
@@ -90,6 +120,8 @@ impl<S> Server<S> {
 		let drain = self.drain_rx;
 		let state = Arc::new(self.state);
 		let f = Arc::new(f);
+		let access = Arc::new(self.access);
+		let audit = self.audit;
 		info!(
 				%address,
 				component=self.name,
@@ -101,33 +133,89 @@ impl<S> Server<S> {
 			let state = state.clone();
 			let name = self.name.clone();
 			let f = f.clone();
+			let access = access.clone();
 			tokio::spawn(async move {
 				let stream = tokio_stream::wrappers::TcpListenerStream::new(bind);
 				let mut stream = stream.take_until(Box::pin(drain_stream.wait_for_drain()));
 				while let Some(Ok(socket)) = stream.next().await {
+					let Ok(src) = socket.peer_addr() else {
+						continue;
+					};
+					if !access.allows_source(src.ip()) {
+						debug!(%src, component=name, "connection rejected: source not allowed");
+						continue;
+					}
 					socket.set_nodelay(true).unwrap();
 					let drain = drain_connections.clone();
 					let f = f.clone();
 					let state = state.clone();
+					let access = access.clone();
+					let name = name.clone();
 					tokio::spawn(async move {
+						let io = match &access.tls {
+							Some(tls) => match tokio::time::timeout(
+								TLS_HANDSHAKE_TIMEOUT,
+								tokio_rustls::TlsAcceptor::from(tls.clone()).accept(socket),
+							)
+							.await
+							{
+								Ok(Ok(tls)) => Either::Right(tls),
+								Ok(Err(e)) => {
+									debug!(%src, component=name, "tls handshake failed: {e}");
+									return;
+								},
+								Err(_) => {
+									debug!(%src, component=name, "tls handshake timed out");
+									return;
+								},
+							},
+							None => Either::Left(socket),
+						};
 						let serve = http1_server()
 							.half_close(true)
 							.header_read_timeout(Duration::from_secs(2))
 							.max_buf_size(8 * 1024)
 							.serve_connection(
-								hyper_util::rt::TokioIo::new(socket),
+								hyper_util::rt::TokioIo::new(io),
 								hyper::service::service_fn(move |req| {
 									let state = state.clone();
-
-									// Failures would abort the whole connection; we just want to return an HTTP error
-									f(state, req).or_else(|err| async move {
-										Ok::<_, Infallible>(
-											::http::Response::builder()
-												.status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
-												.body(crate::http::Body::new(err.to_string()))
-												.expect("builder with known status code should not fail"),
-										)
-									})
+									let f = f.clone();
+									let access = access.clone();
+									let name = name.clone();
+									async move {
+										let method = req.method().clone();
+										let path = req.uri().path().to_string();
+										let authorized = access.authorized(&req);
+										let resp = if authorized {
+											// Failures would abort the whole connection; we just want to return an HTTP error
+											f(state, req).await.unwrap_or_else(|err| {
+												::http::Response::builder()
+													.status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+													.body(crate::http::Body::new(err.to_string()))
+													.expect("builder with known status code should not fail")
+											})
+										} else {
+											let mut resp = empty_response(hyper::StatusCode::UNAUTHORIZED);
+											resp.headers_mut().insert(
+												hyper::header::WWW_AUTHENTICATE,
+												hyper::header::HeaderValue::from_static("Bearer"),
+											);
+											resp
+										};
+										if audit && method != hyper::Method::GET && method != hyper::Method::HEAD {
+											info!(
+												target: "audit",
+												component=name,
+												%src,
+												%method,
+												path,
+												authorized,
+												status=resp.status().as_u16(),
+												"management request",
+											);
+										}
+										Ok::<_, Infallible>(resp)
+									}
 								}),
 							);
 						// Wait for drain to signal or connection serving to complete
@@ -9,23 +9,27 @@ use hyper::body::Incoming;
 use prometheus_client::encoding::text::encode;
 use prometheus_client::registry::Registry;
 
+use super::access::Access;
 use super::hyper_helpers;
 use crate::Address;
 use crate::http::Response;
 
 pub struct Server {
-	s: hyper_helpers::Server<Mutex<Registry>>,
+	s: hyper_helpers::Server<Arc<Mutex<Registry>>>,
 }
 
 impl Server {
 	pub async fn new(
 		addr: Address,
+		access: Access,
 		drain_rx: DrainWatcher,
-		registry: Registry,
+		registry: Arc<Mutex<Registry>>,
 	) -> anyhow::Result<Self> {
-		hyper_helpers::Server::<Mutex<Registry>>::bind("stats", addr, drain_rx, Mutex::new(registry))
-			.await
-			.map(|s| Server { s })
+		let mut s =
+			hyper_helpers::Server::<Arc<Mutex<Registry>>>::bind("stats", addr, drain_rx, registry)
+				.await?;
+		s.set_access(access);
+		Ok(Server { s })
 	}
 
 	pub fn address(&self) -> SocketAddr {
@@ -42,7 +46,7 @@ impl Server {
 	}
 }
 
-async fn handle_metrics(reg: Arc<Mutex<Registry>>, req: Request<Incoming>) -> Response {
+async fn handle_metrics(reg: Arc<Arc<Mutex<Registry>>>, req: Request<Incoming>) -> Response {
 	let mut buf = String::new();
 	let reg = reg.lock().expect("mutex");
 	if let Err(err) = encode(&mut buf, &reg) {
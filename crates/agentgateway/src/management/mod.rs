@@ -1,4 +1,6 @@
+pub mod access;
 pub mod admin;
+pub mod diagnostics;
 pub mod metrics_server;
 pub mod readiness_server;
 
@@ -51,7 +51,12 @@ async fn handle_ready(ready: &readiness::Ready, req: Request<Incoming>) -> Respo
 		hyper::Method::GET => {
 			let pending = ready.pending();
 			if pending.is_empty() {
-				return hyper_helpers::plaintext_response(hyper::StatusCode::OK, "ready\n".into());
+				// A degraded process is still ready, but reports why in the body.
+				let body = match ready.degraded() {
+					Some(reason) => format!("ready, degraded: {reason}\n"),
+					None => "ready\n".to_string(),
+				};
+				return hyper_helpers::plaintext_response(hyper::StatusCode::OK, body);
 			}
 			hyper_helpers::plaintext_response(
 				hyper::StatusCode::INTERNAL_SERVER_ERROR,
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use http::{Method, header};
+use rmcp::model::{ClientJsonRpcMessage, GetExtensions, ServerJsonRpcMessage};
+use rmcp::service::serve_server_with_ct;
+use tokio::io;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tokio_util::io::StreamReader;
+use tokio_util::sync::{CancellationToken, PollSender};
+use tracing::{debug, warn};
+
+use crate::http::Request;
+use crate::mcp::relay::Relay;
+
+/// Requests with this content type stream newline-delimited JSON-RPC messages in their body, and receive the
+/// server's messages as server-sent events on the same request, rather than sending a POST per message.
+const DUPLEX_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// The largest message accepted in the request body.
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// The number of messages buffered in each direction. Once a buffer is full, reading the request body or handling
+/// messages waits for the other side to catch up, so a slow client applies backpressure instead of using memory.
+const BUFFERED_MESSAGES: usize = 16;
+
+pub fn is_duplex(req: &Request) -> bool {
+	req.method() == Method::POST
+		&& req
+			.headers()
+			.get(header::CONTENT_TYPE)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| v.split(';').next())
+			.is_some_and(|v| v.trim().eq_ignore_ascii_case(DUPLEX_CONTENT_TYPE))
+}
+
+/// serve runs an MCP session over a single request. Each request is its own session, so no session ID is used.
+/// The session ends once the client has finished sending and every request it sent has been answered, or when
+/// the client disconnects.
+pub fn serve(relay: Relay, req: Request) -> Response {
+	let (from_client_tx, from_client_rx) = mpsc::channel(BUFFERED_MESSAGES);
+	let (to_client_tx, to_client_rx) = mpsc::channel(BUFFERED_MESSAGES);
+	// Requests from the client that have not been answered yet.
+	let pending = Arc::new(watch::channel(0usize).0);
+
+	tokio::spawn(read_messages(req, from_client_tx, pending.clone()));
+	tokio::spawn(async move {
+		let sink = PollSender::new(to_client_tx).sink_map_err(io::Error::other);
+		let stream = ReceiverStream::new(from_client_rx);
+		// The session starts once the client sends the initialize request, so this runs after the response is
+		// returned.
+		match serve_server_with_ct(relay, (sink, stream), CancellationToken::new()).await {
+			Ok(service) => {
+				let _ = service.waiting().await;
+			},
+			Err(e) => warn!(error = ?e, "failed to start duplex MCP session"),
+		}
+	});
+
+	let stream = ReceiverStream::new(to_client_rx).map(move |message| {
+		if matches!(
+			message,
+			ServerJsonRpcMessage::Response(_) | ServerJsonRpcMessage::Error(_)
+		) {
+			pending.send_modify(|n| *n = n.saturating_sub(1));
+		}
+		match serde_json::to_string(&message) {
+			Ok(data) => Ok(Event::default().data(data)),
+			Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+		}
+	});
+	Sse::new(stream).into_response()
+}
+
+/// read_messages parses messages from the request body as they arrive, and sends them to the session.
+async fn read_messages(
+	req: Request,
+	tx: mpsc::Sender<ClientJsonRpcMessage>,
+	pending: Arc<watch::Sender<usize>>,
+) {
+	let (parts, body) = req.into_parts();
+	let reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+	let mut lines = FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_MESSAGE_SIZE));
+	while let Some(line) = lines.next().await {
+		let line = match line {
+			Ok(line) => line,
+			Err(e) => {
+				debug!("failed to read duplex MCP request body: {e}");
+				break;
+			},
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+		let mut message = match serde_json::from_str::<ClientJsonRpcMessage>(&line) {
+			Ok(message) => message,
+			Err(e) => {
+				debug!("skipping invalid message in duplex MCP request body: {e}");
+				continue;
+			},
+		};
+		if let ClientJsonRpcMessage::Request(r) = &mut message {
+			r.request.extensions_mut().insert(parts.clone());
+			pending.send_modify(|n| *n += 1);
+		}
+		if tx.send(message).await.is_err() {
+			// The session ended.
+			return;
+		}
+	}
+	// Closing the input ends the session, so wait for the outstanding responses first.
+	let mut pending = pending.subscribe();
+	tokio::select! {
+		_ = pending.wait_for(|n| *n == 0) => {},
+		_ = tx.closed() => {},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use agent_core::{drain, metrics, strng};
+	use bytes::Bytes;
+	use futures::stream::BoxStream;
+	use prometheus_client::registry::Registry;
+	use serde_json::{Value, json};
+	use sse_stream::SseStream;
+
+	use super::*;
+	use crate::http::Body;
+	use crate::store::Stores;
+	use crate::types::agent::McpBackend;
+	use crate::{ProxyInputs, client, mcp};
+
+	type BodySender = mpsc::Sender<Result<Bytes, io::Error>>;
+
+	struct Session {
+		body: BodySender,
+		events: BoxStream<'static, Value>,
+		_drain: drain::DrainTrigger,
+	}
+
+	impl Session {
+		/// start opens a duplex session through the MCP app, to a backend without targets.
+		async fn start() -> Session {
+			let config = crate::config::parse_config("{}".to_string(), None).unwrap();
+			let stores = Stores::new();
+			let upstream = client::Client::new(&config.dns, None);
+			let (drain_tx, drain_rx) = drain::new();
+			let pi = Arc::new(ProxyInputs {
+				cfg: Arc::new(config),
+				stores: stores.clone(),
+				tracer: None,
+				metrics: Arc::new(crate::metrics::Metrics::new(metrics::sub_registry(
+					&mut Registry::default(),
+				))),
+				upstream,
+				ca: None,
+				state: crate::store::kv::memory(),
+				connections: Default::default(),
+
+				mcp_state: mcp::sse::App::new(
+					stores,
+					Arc::new(crate::mcp::relay::metrics::Metrics::new(
+						&mut Registry::default(),
+						None,
+					)),
+					drain_rx,
+				),
+			});
+
+			let (body, rx) = mpsc::channel(1);
+			let req = ::http::Request::builder()
+				.method(Method::POST)
+				.uri("http://localhost/mcp")
+				.header(header::CONTENT_TYPE, DUPLEX_CONTENT_TYPE)
+				.body(Body::from_stream(ReceiverStream::new(rx)))
+				.unwrap();
+			let backend = McpBackend {
+				targets: vec![],
+				stateful: false,
+				keepalive: None,
+			};
+			let resp = pi
+				.mcp_state
+				.serve(
+					pi.clone(),
+					strng::literal!("mcp"),
+					backend,
+					req,
+					Default::default(),
+				)
+				.await;
+			assert_eq!(resp.status(), 200);
+			let events = SseStream::from_byte_stream(resp.into_body().into_data_stream())
+				.map(|event| serde_json::from_str(&event.unwrap().data.unwrap()).unwrap())
+				.boxed();
+			Session {
+				body,
+				events,
+				_drain: drain_tx,
+			}
+		}
+
+		async fn next(&mut self) -> Value {
+			tokio::time::timeout(Duration::from_secs(5), self.events.next())
+				.await
+				.expect("timed out waiting for a message")
+				.expect("session ended")
+		}
+
+		async fn initialize(&mut self) {
+			send(
+				&self.body,
+				json!({
+					"jsonrpc": "2.0",
+					"id": 0,
+					"method": "initialize",
+					"params": {
+						"protocolVersion": "2025-03-26",
+						"capabilities": {},
+						"clientInfo": {"name": "test", "version": "0.0.0"},
+					},
+				}),
+			)
+			.await;
+			let resp = self.next().await;
+			assert_eq!(resp["id"], 0);
+			assert!(resp["result"]["serverInfo"].is_object(), "{resp}");
+			send(
+				&self.body,
+				json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+			)
+			.await;
+		}
+	}
+
+	async fn send(body: &BodySender, message: Value) {
+		let mut line = serde_json::to_vec(&message).unwrap();
+		line.push(b'\n');
+		body.send(Ok(line.into())).await.unwrap();
+	}
+
+	fn ping(id: usize) -> Value {
+		json!({"jsonrpc": "2.0", "id": id, "method": "ping"})
+	}
+
+	#[tokio::test]
+	async fn ndjson_session() {
+		let mut session = Session::start().await;
+		session.initialize().await;
+		let Session {
+			body,
+			events,
+			_drain,
+		} = session;
+		let responses = tokio::spawn(events.collect::<Vec<_>>());
+
+		// Invalid lines and notifications are not requests, so no response is awaited for them.
+		body
+			.send(Ok(Bytes::from_static(b"not json\n\n")))
+			.await
+			.unwrap();
+		send(
+			&body,
+			json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+		)
+		.await;
+		for id in 2..50 {
+			send(&body, ping(id)).await;
+		}
+		// Closing the request body ends the session, but only once every request has been answered.
+		drop(body);
+		let responses = tokio::time::timeout(Duration::from_secs(5), responses)
+			.await
+			.expect("session should end")
+			.unwrap();
+		let mut ids = responses
+			.iter()
+			.map(|r| r["id"].as_u64().unwrap())
+			.collect::<Vec<_>>();
+		ids.sort();
+		assert_eq!(ids, (1..50).collect::<Vec<_>>());
+		let tools = responses.iter().find(|r| r["id"] == 1).unwrap();
+		assert_eq!(tools["result"]["tools"], json!([]), "{tools}");
+	}
+
+	#[tokio::test]
+	async fn backpressure() {
+		const MESSAGES: usize = 1000;
+		let mut session = Session::start().await;
+		session.initialize().await;
+		let Session {
+			body,
+			events,
+			_drain,
+		} = session;
+		let sender = tokio::spawn(async move {
+			for id in 1..=MESSAGES {
+				send(&body, ping(id)).await;
+			}
+		});
+
+		// Nothing reads the responses, so once the buffers are full the request body is no longer read.
+		tokio::time::sleep(Duration::from_millis(200)).await;
+		assert!(!sender.is_finished());
+
+		let responses = tokio::time::timeout(Duration::from_secs(5), events.count())
+			.await
+			.expect("session should end");
+		assert_eq!(responses, MESSAGES);
+		sender.await.unwrap();
+	}
+
+	fn request(method: Method, content_type: &str) -> Request {
+		::http::Request::builder()
+			.method(method)
+			.header(header::CONTENT_TYPE, content_type)
+			.body(Body::empty())
+			.unwrap()
+	}
+
+	#[test]
+	fn detects_duplex() {
+		assert!(is_duplex(&request(Method::POST, "application/x-ndjson")));
+		assert!(is_duplex(&request(
+			Method::POST,
+			"Application/X-NDJSON; charset=utf-8"
+		)));
+		assert!(!is_duplex(&request(Method::POST, "application/json")));
+		assert!(!is_duplex(&request(Method::GET, "application/x-ndjson")));
+	}
+}
@@ -0,0 +1,284 @@
+//! Records the capabilities negotiated on each MCP session, to debug features that silently do not work
+//! through the relay.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use rmcp::model::{
+	ClientCapabilities, Implementation, InitializeRequestParam, ProtocolVersion, ServerCapabilities,
+	ServerInfo,
+};
+use serde_json::Value;
+
+use crate::management::admin::ConfigDumpHandler;
+use crate::*;
+
+/// Number of recent sessions kept for the admin dump.
+const MAX_HANDSHAKES: usize = 100;
+
+/// Client capabilities that are relayed to targets, when the client's initialize request is forwarded.
+/// See `PeerClientHandler`.
+const FORWARDED_CLIENT_CAPABILITIES: &[&str] = &["roots", "sampling"];
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handshake {
+	pub time: DateTime<Utc>,
+	pub backend: Strng,
+	pub stateful: bool,
+	pub protocol_version: ProtocolVersion,
+	pub client_info: Implementation,
+	pub client_capabilities: ClientCapabilities,
+	/// The capabilities the gateway advertised to the client.
+	pub server_capabilities: ServerCapabilities,
+	pub targets: Vec<TargetHandshake>,
+	/// Capabilities that one side of the session supports, but that cannot be used through the gateway.
+	pub gaps: Vec<CapabilityGap>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetHandshake {
+	pub name: Strng,
+	/// The target's initialize result. Unset for targets that are not MCP servers, such as OpenAPI.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub server: Option<ServerInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityGap {
+	/// The capability, such as `sampling` or `resources.subscribe`.
+	pub capability: String,
+	/// The target that supports the capability, for server capabilities.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub target: Option<Strng>,
+	pub reason: GapReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GapReason {
+	/// The client declared the capability, but targets are not offered it.
+	NotForwarded,
+	/// The target supports the capability, but the gateway does not advertise it to the client.
+	NotExposed,
+}
+
+impl Handshake {
+	pub fn new(
+		backend: Strng,
+		stateful: bool,
+		request: &InitializeRequestParam,
+		server_capabilities: ServerCapabilities,
+		targets: Vec<TargetHandshake>,
+	) -> Handshake {
+		let mut gaps = vec![];
+		// In stateless mode, targets are initialized with the gateway's own capabilities rather than the client's.
+		for capability in capability_paths(&request.capabilities) {
+			let root = capability.split('.').next().unwrap_or_default();
+			if !stateful || capability.contains('.') || !FORWARDED_CLIENT_CAPABILITIES.contains(&root) {
+				gaps.push(CapabilityGap {
+					capability,
+					target: None,
+					reason: GapReason::NotForwarded,
+				});
+			}
+		}
+		let advertised = capability_paths(&server_capabilities);
+		for target in &targets {
+			let Some(server) = &target.server else {
+				continue;
+			};
+			for capability in capability_paths(&server.capabilities) {
+				if !advertised.contains(&capability) {
+					gaps.push(CapabilityGap {
+						capability,
+						target: Some(target.name.clone()),
+						reason: GapReason::NotExposed,
+					});
+				}
+			}
+		}
+		Handshake {
+			time: Utc::now(),
+			backend,
+			stateful,
+			protocol_version: request.protocol_version.clone(),
+			client_info: request.client_info.clone(),
+			client_capabilities: request.capabilities.clone(),
+			server_capabilities,
+			targets,
+			gaps,
+		}
+	}
+}
+
+/// capability_paths flattens capabilities into the set of declared capabilities and their enabled
+/// sub-capabilities, such as `resources` and `resources.subscribe`.
+fn capability_paths(capabilities: &impl serde::Serialize) -> BTreeSet<String> {
+	fn enabled(v: &Value) -> bool {
+		!matches!(v, Value::Null | Value::Bool(false))
+	}
+	let mut paths = BTreeSet::new();
+	let Ok(Value::Object(capabilities)) = serde_json::to_value(capabilities) else {
+		return paths;
+	};
+	for (name, v) in capabilities.iter().filter(|(_, v)| enabled(v)) {
+		if let Value::Object(subs) = v {
+			for (sub, _) in subs.iter().filter(|(_, v)| enabled(v)) {
+				paths.insert(format!("{name}.{sub}"));
+			}
+		}
+		paths.insert(name.clone());
+	}
+	paths
+}
+
+/// Handshakes keeps the most recent session handshakes, and exposes them in the admin config dump.
+#[derive(Debug, Clone, Default)]
+pub struct Handshakes(Arc<Mutex<VecDeque<Handshake>>>);
+
+impl Handshakes {
+	pub fn record(&self, handshake: Handshake) {
+		let gaps = handshake
+			.gaps
+			.iter()
+			.map(|g| match &g.target {
+				Some(t) => format!("{}({t})", g.capability),
+				None => g.capability.clone(),
+			})
+			.join(",");
+		let targets = handshake.targets.iter().map(|t| t.name.as_str()).join(",");
+		if handshake.gaps.is_empty() {
+			debug!(
+				backend=%handshake.backend,
+				client=%handshake.client_info.name,
+				%targets,
+				"mcp session initialized"
+			);
+		} else {
+			info!(
+				backend=%handshake.backend,
+				client=%handshake.client_info.name,
+				%targets,
+				%gaps,
+				"mcp session initialized with unsupported capabilities"
+			);
+		}
+		let mut handshakes = self.0.lock().expect("mutex acquired");
+		if handshakes.len() >= MAX_HANDSHAKES {
+			handshakes.pop_front();
+		}
+		handshakes.push_back(handshake);
+	}
+}
+
+impl ConfigDumpHandler for Handshakes {
+	fn key(&self) -> &'static str {
+		"mcpSessions"
+	}
+
+	fn handle(&self) -> anyhow::Result<serde_json::Value> {
+		let handshakes = self.0.lock().expect("mutex acquired");
+		Ok(serde_json::to_value(&*handshakes)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rmcp::model::{ResourcesCapability, ToolsCapability};
+	use serde_json::json;
+
+	use super::*;
+
+	fn request(capabilities: Value) -> InitializeRequestParam {
+		serde_json::from_value(json!({
+			"protocolVersion": "2025-03-26",
+			"capabilities": capabilities,
+			"clientInfo": {"name": "test", "version": "1.0"},
+		}))
+		.unwrap()
+	}
+
+	fn target(name: &str, capabilities: Value) -> TargetHandshake {
+		TargetHandshake {
+			name: strng::new(name),
+			server: Some(
+				serde_json::from_value(json!({
+					"protocolVersion": "2025-03-26",
+					"capabilities": capabilities,
+					"serverInfo": {"name": name, "version": "1.0"},
+				}))
+				.unwrap(),
+			),
+		}
+	}
+
+	fn gap(capability: &str, target: Option<&str>, reason: GapReason) -> CapabilityGap {
+		CapabilityGap {
+			capability: capability.to_string(),
+			target: target.map(strng::new),
+			reason,
+		}
+	}
+
+	#[test]
+	fn gaps() {
+		let advertised = ServerCapabilities {
+			tools: Some(ToolsCapability::default()),
+			resources: Some(ResourcesCapability::default()),
+			..Default::default()
+		};
+		let req = request(json!({"sampling": {}, "roots": {"listChanged": true}}));
+		let targets = vec![
+			target("a", json!({"tools": {}, "logging": {}})),
+			target(
+				"b",
+				json!({"resources": {"subscribe": true, "listChanged": false}}),
+			),
+		];
+		let h = Handshake::new(
+			strng::new("backend"),
+			true,
+			&req,
+			advertised.clone(),
+			targets.clone(),
+		);
+		assert_eq!(
+			h.gaps,
+			vec![
+				gap("roots.listChanged", None, GapReason::NotForwarded),
+				gap("logging", Some("a"), GapReason::NotExposed),
+				gap("resources.subscribe", Some("b"), GapReason::NotExposed),
+			]
+		);
+
+		// Stateless sessions do not forward any client capabilities
+		let h = Handshake::new(strng::new("backend"), false, &req, advertised, targets);
+		assert_eq!(
+			h.gaps
+				.iter()
+				.filter(|g| g.reason == GapReason::NotForwarded)
+				.map(|g| g.capability.as_str())
+				.collect::<Vec<_>>(),
+			vec!["roots", "roots.listChanged", "sampling"]
+		);
+	}
+
+	#[test]
+	fn bounded() {
+		let handshakes = Handshakes::default();
+		for _ in 0..MAX_HANDSHAKES + 1 {
+			handshakes.record(Handshake::new(
+				strng::new("backend"),
+				true,
+				&request(json!({})),
+				ServerCapabilities::default(),
+				vec![],
+			));
+		}
+		assert_eq!(handshakes.0.lock().unwrap().len(), MAX_HANDSHAKES);
+	}
+}
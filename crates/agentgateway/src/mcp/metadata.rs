@@ -0,0 +1,150 @@
+//! Metadata sent to upstream MCP servers in the `_meta` field of requests.
+
+use rmcp::model::{Implementation, JsonObject, Meta};
+use serde_json::Value;
+
+use crate::mcp::rbac::Identity;
+use crate::*;
+
+/// Prefix of the keys injected by the gateway. Client-supplied keys with this prefix are always removed, so
+/// upstream servers can trust them.
+pub const KEY_PREFIX: &str = "agentgateway.dev/";
+
+#[apply(schema!)]
+pub struct McpMetadata {
+	/// Gateway-derived values to add to `_meta`, each under a key prefixed with `agentgateway.dev/`.
+	#[serde(default)]
+	pub inject: Vec<MetadataField>,
+	/// Keys of client-supplied `_meta` to forward to upstream servers. If unset, client metadata is not forwarded.
+	#[serde(default)]
+	pub client_keys: Option<Vec<String>>,
+}
+
+#[apply(schema!)]
+#[derive(Copy, PartialEq, Eq)]
+pub enum MetadataField {
+	/// The authenticated identity of the client: the JWT subject and issuer, and the mTLS identity.
+	Identity,
+	/// The MCP session ID of the client.
+	SessionId,
+	/// The trace ID of the request.
+	TraceId,
+	/// The name and version the client sent on initialization.
+	ClientInfo,
+}
+
+impl MetadataField {
+	fn key(&self) -> &'static str {
+		match self {
+			MetadataField::Identity => "identity",
+			MetadataField::SessionId => "sessionId",
+			MetadataField::TraceId => "traceId",
+			MetadataField::ClientInfo => "clientInfo",
+		}
+	}
+
+	fn value(&self, values: &Values) -> Option<Value> {
+		match self {
+			MetadataField::Identity => {
+				let mut identity = JsonObject::new();
+				for (key, claim) in [("subject", "sub"), ("issuer", "iss")] {
+					if let Some(v) = values.identity.get_claim(claim, ".") {
+						identity.insert(key.to_string(), Value::String(v.to_string()));
+					}
+				}
+				if let Some(id) = &values.identity.connection_id {
+					identity.insert("connectionId".to_string(), Value::String(id.clone()));
+				}
+				(!identity.is_empty()).then_some(Value::Object(identity))
+			},
+			MetadataField::SessionId => values.session_id.map(|s| Value::String(s.to_string())),
+			MetadataField::TraceId => values.trace_id.clone().map(Value::String),
+			MetadataField::ClientInfo => values
+				.client_info
+				.and_then(|info| serde_json::to_value(info).ok()),
+		}
+	}
+}
+
+/// Values is the request information metadata is derived from.
+pub struct Values<'a> {
+	pub identity: &'a Identity,
+	pub session_id: Option<&'a str>,
+	pub trace_id: Option<String>,
+	pub client_info: Option<&'a Implementation>,
+}
+
+impl McpMetadata {
+	/// build returns the `_meta` to send upstream, given the `_meta` the client sent.
+	pub fn build(&self, client: &Meta, values: &Values) -> Meta {
+		let mut meta = JsonObject::new();
+		if let Some(allowed) = &self.client_keys {
+			for (k, v) in client.0.iter() {
+				if !k.starts_with(KEY_PREFIX) && allowed.contains(k) {
+					meta.insert(k.clone(), v.clone());
+				}
+			}
+		}
+		for field in &self.inject {
+			if let Some(v) = field.value(values) {
+				meta.insert(format!("{KEY_PREFIX}{}", field.key()), v);
+			}
+		}
+		Meta(meta)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use secrecy::SecretString;
+	use serde_json::json;
+
+	use super::*;
+	use crate::http::jwt::Claims;
+
+	#[test]
+	fn build() {
+		let policy: McpMetadata = serde_json::from_value(json!({
+			"inject": ["identity", "sessionId", "traceId"],
+			"clientKeys": ["progressToken", "agentgateway.dev/identity"],
+		}))
+		.unwrap();
+		let identity = Identity::new(
+			Some(Claims {
+				inner: json!({"sub": "alice", "iss": "me"})
+					.as_object()
+					.unwrap()
+					.clone(),
+				jwt: SecretString::from(""),
+			}),
+			None,
+		);
+		let client = Meta(
+			json!({
+				"progressToken": 1,
+				"other": true,
+				"agentgateway.dev/identity": "spoofed",
+			})
+			.as_object()
+			.unwrap()
+			.clone(),
+		);
+		let meta = policy.build(
+			&client,
+			&Values {
+				identity: &identity,
+				session_id: Some("abc"),
+				trace_id: None,
+				client_info: None,
+			},
+		);
+		assert_eq!(
+			Value::Object(meta.0),
+			json!({
+				"progressToken": 1,
+				"agentgateway.dev/identity": {"subject": "alice", "issuer": "me"},
+				"agentgateway.dev/sessionId": "abc",
+			})
+		);
+	}
+}
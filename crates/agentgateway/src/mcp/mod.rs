@@ -1,3 +1,6 @@
+pub mod duplex;
+pub mod handshake;
+pub mod metadata;
 pub mod openapi;
 pub mod rbac;
 pub mod relay;
@@ -4,7 +4,10 @@ use std::sync::Arc;
 
 use http::Method;
 use http::header::{ACCEPT, CONTENT_TYPE};
-use openapiv3::{OpenAPI, Parameter, ReferenceOr, RequestBody, Schema, SchemaKind, Type};
+use openapiv3::{
+	OpenAPI, Operation, Parameter, ReferenceOr, RequestBody, Response, Schema, SchemaKind,
+	StatusCode, Type,
+};
 use reqwest::header::{HeaderName, HeaderValue};
 use rmcp::model::{JsonObject, Tool};
 use serde::{Deserialize, Serialize};
@@ -15,10 +18,15 @@ use crate::proxy::httpproxy::PolicyClient;
 use crate::store::BackendPolicies;
 use crate::types::agent::SimpleBackend;
 
+pub mod validate;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct UpstreamOpenAPICall {
 	pub method: String, /* TODO: Switch to Method, but will require getting rid of Serialize/Deserialize */
 	pub path: String,
+	/// The schema of a successful JSON response, with references resolved.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub response_schema: Option<Schema>,
 	// todo: params
 }
 
@@ -253,6 +261,56 @@ fn resolve_request_body<'a>(
 	}
 }
 
+fn resolve_response<'a>(
+	reference: &'a ReferenceOr<Response>,
+	doc: &'a OpenAPI,
+) -> Result<&'a Response, ParseError> {
+	match reference {
+		ReferenceOr::Reference { reference } => {
+			let reference = reference
+				.strip_prefix("#/components/responses/")
+				.ok_or(ParseError::MissingReference(reference.to_string()))?;
+			let components: &openapiv3::Components = doc
+				.components
+				.as_ref()
+				.ok_or(ParseError::MissingComponents)?;
+			let response = components
+				.responses
+				.get(reference)
+				.ok_or(ParseError::MissingReference(reference.to_string()))?;
+			resolve_response(response, doc)
+		},
+		ReferenceOr::Item(response) => Ok(response),
+	}
+}
+
+/// Returns the JSON schema of the operation's successful response: the first 2xx response, or the default
+/// response if there is none.
+fn success_response_schema(op: &Operation, doc: &OpenAPI) -> Result<Option<Schema>, ParseError> {
+	let response = op
+		.responses
+		.responses
+		.iter()
+		.find(|(code, _)| match code {
+			StatusCode::Code(c) => (200..300).contains(c),
+			StatusCode::Range(r) => *r == 2,
+		})
+		.map(|(_, r)| r)
+		.or(op.responses.default.as_ref());
+	let Some(response) = response else {
+		return Ok(None);
+	};
+	let response = resolve_response(response, doc)?;
+	match response
+		.content
+		.get("application/json")
+		.and_then(|m| m.schema.as_ref())
+	{
+		Some(schema) => Ok(Some(resolve_nested_schema(schema, doc)?)),
+		None => Ok(None),
+	}
+}
+
 /// We need to rework this and I don't want to forget.
 ///
 /// We need to be able to handle data which can end up in multiple destinations:
@@ -398,10 +456,16 @@ pub(crate) fn parse_openapi_schema(
 								// TODO: support output_schema
 								output_schema: None,
 							};
+							// Response schemas are only used for optional validation, so do not fail the tool on them.
+							let response_schema = success_response_schema(op, open_api).unwrap_or_else(|e| {
+								tracing::warn!("failed to resolve response schema for {name}: {e}");
+								None
+							});
 							let upstream = UpstreamOpenAPICall {
 								// method: Method::from_bytes(method.as_ref()).expect("todo"),
 								method: method.to_string(),
 								path: path.clone(),
+								response_schema,
 							};
 							Ok((tool, upstream))
 						},
@@ -504,6 +568,7 @@ pub struct Handler {
 	pub tools: Vec<(Tool, UpstreamOpenAPICall)>,
 	pub default_policies: BackendPolicies,
 	pub backend: SimpleBackend,
+	pub response_validation: Option<validate::ResponseValidation>,
 }
 
 impl Handler {
@@ -683,7 +748,10 @@ impl Handler {
 
 		// Check if the request was successful
 		if status.is_success() {
-			Ok(body)
+			match (&self.response_validation, &info.response_schema) {
+				(Some(validation), Some(schema)) => validation.apply(name, schema, body),
+				_ => Ok(body),
+			}
 		} else {
 			Err(anyhow::anyhow!(
 				"Upstream API call for tool '{}' failed with status {}: {}",
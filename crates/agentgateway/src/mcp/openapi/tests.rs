@@ -39,6 +39,8 @@ async fn setup() -> (MockServer, Handler) {
 		))),
 		upstream: client.clone(),
 		ca: None,
+		state: crate::store::kv::memory(),
+		connections: Default::default(),
 
 		mcp_state: mcp::sse::App::new(
 			stores.clone(),
@@ -91,6 +93,7 @@ async fn setup() -> (MockServer, Handler) {
 	let upstream_call_get = UpstreamOpenAPICall {
 		method: "GET".to_string(),
 		path: "/users/{user_id}".to_string(),
+		response_schema: None,
 	};
 
 	let test_tool_post = Tool {
@@ -133,6 +136,7 @@ async fn setup() -> (MockServer, Handler) {
 	let upstream_call_post = UpstreamOpenAPICall {
 		method: "POST".to_string(),
 		path: "/users".to_string(),
+		response_schema: None,
 	};
 
 	let handler = Handler {
@@ -150,6 +154,7 @@ async fn setup() -> (MockServer, Handler) {
 				parsed.port().unwrap_or(8080),
 			),
 		),
+		response_validation: None,
 	};
 
 	(server, handler)
@@ -322,6 +327,46 @@ async fn test_call_tool_upstream_error() {
 	assert!(err.to_string().contains(&error_response.to_string()));
 }
 
+#[tokio::test]
+async fn test_call_tool_response_validation() {
+	let (server, mut handler) = setup().await;
+	handler.tools[0].1.response_schema = Some(
+		serde_json::from_value(json!({
+			"type": "object",
+			"required": ["id"],
+			"properties": {"id": {"type": "string"}}
+		}))
+		.unwrap(),
+	);
+
+	Mock::given(method("GET"))
+		.and(path("/users/123"))
+		.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": 123, "extra": true })))
+		.mount(&server)
+		.await;
+	let args = json!({ "path": { "user_id": "123" } });
+
+	handler.response_validation = Some(validate::ResponseValidation::Warn);
+	let result = handler
+		.call_tool("get_user", Some(args.as_object().unwrap().clone()))
+		.await;
+	assert_eq!(
+		result.unwrap(),
+		json!({ "id": 123, "extra": true }).to_string()
+	);
+
+	handler.response_validation = Some(validate::ResponseValidation::Fail);
+	let result = handler
+		.call_tool("get_user", Some(args.as_object().unwrap().clone()))
+		.await;
+	assert!(
+		result
+			.unwrap_err()
+			.to_string()
+			.contains("$.id: expected string, got number")
+	);
+}
+
 #[tokio::test]
 async fn test_call_tool_invalid_header_value() {
 	let (server, handler) = setup().await;
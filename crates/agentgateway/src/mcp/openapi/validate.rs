@@ -0,0 +1,292 @@
+use openapiv3::{AdditionalProperties, ReferenceOr, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+use crate::*;
+
+/// ResponseValidation checks successful upstream responses against the operation's response schema, so
+/// drift between an API and its OpenAPI document is caught at the gateway rather than passed on to the
+/// agent.
+#[apply(schema!)]
+#[derive(Copy, PartialEq, Eq)]
+pub enum ResponseValidation {
+	/// Log responses that do not match the schema, and return them unchanged.
+	Warn,
+	/// Fail the tool call if the response does not match the schema.
+	Fail,
+	/// Remove fields that are not defined in the schema, and log any remaining mismatches.
+	Strip,
+}
+
+impl ResponseValidation {
+	/// apply validates a response body, returning the body to send to the client.
+	pub fn apply(&self, tool: &str, schema: &Schema, body: String) -> anyhow::Result<String> {
+		let mut value: Value = match serde_json::from_str(&body) {
+			Ok(v) => v,
+			Err(e) => {
+				return self.report(tool, &[format!("response is not valid JSON: {e}")], body);
+			},
+		};
+		let strip = *self == ResponseValidation::Strip;
+		let mut errors = vec![];
+		validate(schema, &mut value, strip, "$", &mut errors);
+		let body = if strip {
+			serde_json::to_string(&value)?
+		} else {
+			body
+		};
+		if errors.is_empty() {
+			return Ok(body);
+		}
+		self.report(tool, &errors, body)
+	}
+
+	fn report(&self, tool: &str, errors: &[String], body: String) -> anyhow::Result<String> {
+		let errors = errors.join("; ");
+		match self {
+			ResponseValidation::Fail => Err(anyhow::anyhow!(
+				"response for tool '{tool}' does not match its schema: {errors}"
+			)),
+			ResponseValidation::Warn | ResponseValidation::Strip => {
+				warn!(tool, %errors, "response does not match its schema");
+				Ok(body)
+			},
+		}
+	}
+}
+
+/// validate checks a value against a schema, appending a message for each mismatch. References are expected
+/// to be resolved already; any that remain are not checked.
+/// If strip is set, object fields that the schema does not define are removed, rather than reported.
+pub fn validate(
+	schema: &Schema,
+	value: &mut Value,
+	strip: bool,
+	path: &str,
+	errors: &mut Vec<String>,
+) {
+	if value.is_null() && schema.schema_data.nullable {
+		return;
+	}
+	match &schema.schema_kind {
+		SchemaKind::Type(Type::String(s)) => match value {
+			Value::String(v) => {
+				if !s.enumeration.is_empty() && !s.enumeration.iter().flatten().any(|e| e == &*v) {
+					errors.push(format!("{path}: '{v}' is not one of the allowed values"));
+				}
+			},
+			_ => errors.push(mismatch(path, "string", value)),
+		},
+		SchemaKind::Type(Type::Number(_)) => {
+			if !value.is_number() {
+				errors.push(mismatch(path, "number", value));
+			}
+		},
+		SchemaKind::Type(Type::Integer(_)) => {
+			if !value.is_i64() && !value.is_u64() {
+				errors.push(mismatch(path, "integer", value));
+			}
+		},
+		SchemaKind::Type(Type::Boolean(_)) => {
+			if !value.is_boolean() {
+				errors.push(mismatch(path, "boolean", value));
+			}
+		},
+		SchemaKind::Type(Type::Array(a)) => match value {
+			Value::Array(items) => {
+				if let Some(item_schema) = a.items.as_ref().and_then(ReferenceOr::as_item) {
+					for (i, item) in items.iter_mut().enumerate() {
+						validate(item_schema, item, strip, &format!("{path}[{i}]"), errors);
+					}
+				}
+			},
+			_ => errors.push(mismatch(path, "array", value)),
+		},
+		SchemaKind::Type(Type::Object(o)) => match value {
+			Value::Object(fields) => {
+				for required in &o.required {
+					if !fields.contains_key(required) {
+						errors.push(format!("{path}: missing required field '{required}'"));
+					}
+				}
+				// Objects without declared properties are free-form, so there are no unknown fields to strip.
+				let closed = !o.properties.is_empty()
+					&& matches!(
+						o.additional_properties,
+						None | Some(AdditionalProperties::Any(false))
+					);
+				let mut unknown = vec![];
+				for (name, field) in fields.iter_mut() {
+					let field_path = format!("{path}.{name}");
+					match o.properties.get(name) {
+						Some(prop) => {
+							if let Some(prop) = prop.as_item() {
+								validate(prop, field, strip, &field_path, errors);
+							}
+						},
+						None => match &o.additional_properties {
+							Some(AdditionalProperties::Schema(s)) => {
+								if let Some(s) = s.as_item() {
+									validate(s, field, strip, &field_path, errors);
+								}
+							},
+							Some(AdditionalProperties::Any(false)) if !strip => {
+								errors.push(format!("{path}: unexpected field '{name}'"));
+							},
+							_ if strip && closed => unknown.push(name.clone()),
+							_ => {},
+						},
+					}
+				}
+				for name in unknown {
+					fields.remove(&name);
+				}
+			},
+			_ => errors.push(mismatch(path, "object", value)),
+		},
+		// Fields are only stripped at the top level of a combination, as each branch may define different fields.
+		SchemaKind::OneOf { one_of } => {
+			let matched = one_of
+				.iter()
+				.filter_map(ReferenceOr::as_item)
+				.filter(|s| is_valid(s, value))
+				.count();
+			if matched != 1 {
+				errors.push(format!(
+					"{path}: expected exactly one schema in oneOf to match, but {matched} did"
+				));
+			}
+		},
+		SchemaKind::AnyOf { any_of } => {
+			if !any_of
+				.iter()
+				.filter_map(ReferenceOr::as_item)
+				.any(|s| is_valid(s, value))
+			{
+				errors.push(format!("{path}: no schema in anyOf matched"));
+			}
+		},
+		SchemaKind::AllOf { all_of } => {
+			for s in all_of.iter().filter_map(ReferenceOr::as_item) {
+				validate(s, value, false, path, errors);
+			}
+		},
+		SchemaKind::Not { not } => {
+			if let Some(s) = not.as_item()
+				&& is_valid(s, value)
+			{
+				errors.push(format!("{path}: matched a schema it must not match"));
+			}
+		},
+		SchemaKind::Any(a) => {
+			if let Value::Object(fields) = value {
+				for required in &a.required {
+					if !fields.contains_key(required) {
+						errors.push(format!("{path}: missing required field '{required}'"));
+					}
+				}
+				for (name, field) in fields.iter_mut() {
+					if let Some(prop) = a.properties.get(name).and_then(ReferenceOr::as_item) {
+						validate(prop, field, false, &format!("{path}.{name}"), errors);
+					}
+				}
+			}
+		},
+	}
+}
+
+fn is_valid(schema: &Schema, value: &Value) -> bool {
+	let mut errors = vec![];
+	validate(schema, &mut value.clone(), false, "$", &mut errors);
+	errors.is_empty()
+}
+
+fn mismatch(path: &str, expected: &str, got: &Value) -> String {
+	let got = match got {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	};
+	format!("{path}: expected {expected}, got {got}")
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn schema() -> Schema {
+		serde_json::from_value(json!({
+			"type": "object",
+			"required": ["id", "name"],
+			"properties": {
+				"id": {"type": "integer"},
+				"name": {"type": "string"},
+				"status": {"type": "string", "enum": ["active", "disabled"]},
+				"tags": {"type": "array", "items": {"type": "string"}},
+				"manager": {"type": "string", "nullable": true},
+			}
+		}))
+		.unwrap()
+	}
+
+	fn errors(mut value: Value) -> Vec<String> {
+		let mut errors = vec![];
+		validate(&schema(), &mut value, false, "$", &mut errors);
+		errors
+	}
+
+	#[test]
+	fn valid() {
+		assert!(errors(json!({"id": 1, "name": "a", "tags": ["x"], "manager": null})).is_empty());
+		// Undeclared fields are allowed unless additionalProperties is false
+		assert!(errors(json!({"id": 1, "name": "a", "extra": true})).is_empty());
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(
+			errors(json!({"id": "1", "status": "unknown", "tags": [1]})),
+			vec![
+				"$: missing required field 'name'",
+				"$.id: expected integer, got string",
+				"$.status: 'unknown' is not one of the allowed values",
+				"$.tags[0]: expected string, got number",
+			]
+		);
+	}
+
+	#[test]
+	fn strip() {
+		let body = json!({"id": 1, "name": "a", "extra": {"nested": true}}).to_string();
+		let got = ResponseValidation::Strip
+			.apply("tool", &schema(), body.clone())
+			.unwrap();
+		assert_eq!(
+			serde_json::from_str::<Value>(&got).unwrap(),
+			json!({"id": 1, "name": "a"})
+		);
+		assert_eq!(
+			ResponseValidation::Warn
+				.apply("tool", &schema(), body.clone())
+				.unwrap(),
+			body
+		);
+	}
+
+	#[test]
+	fn fail() {
+		let err = ResponseValidation::Fail
+			.apply("tool", &schema(), json!({"id": 1}).to_string())
+			.unwrap_err();
+		assert!(err.to_string().contains("missing required field 'name'"));
+		assert!(
+			ResponseValidation::Fail
+				.apply("tool", &schema(), "not json".to_string())
+				.is_err()
+		);
+	}
+}
@@ -1,8 +1,13 @@
+use std::cell::OnceCell;
+
+use agent_core::bow::OwnedOrBorrowed;
 use serde::{Deserialize, Serialize};
 
 use crate::cel::ContextBuilder;
 use crate::http::authorization::{RuleSet, RuleSets};
 use crate::http::jwt::Claims;
+use crate::mcp::sse::MCPInfo;
+use crate::telemetry::log::AsyncLog;
 use crate::*;
 
 #[apply(schema!)]
@@ -25,14 +30,24 @@ impl McpAuthorizationSet {
 	pub fn new(rs: RuleSets) -> Self {
 		Self(rs)
 	}
-	pub fn validate(&self, res: &ResourceType, cel: &ContextBuilder) -> bool {
+	/// validate reports whether access to the resource is allowed. If rules in monitor mode would deny it, this is
+	/// recorded in the request log.
+	pub fn validate(
+		&self,
+		res: &ResourceType,
+		cel: &ContextBuilder,
+		log: &AsyncLog<MCPInfo>,
+	) -> bool {
 		tracing::debug!("Checking RBAC for resource: {:?}", res);
-		self.0.validate(|| {
-			cel
-				.build_with_mcp(Some(res))
-				.map(agent_core::bow::OwnedOrBorrowed::Owned)
-				.map_err(Into::into)
-		})
+		// The executor is built at most once, and shared by the enforced and monitored checks.
+		let built = OnceCell::new();
+		let exec = || borrow_executor(built.get_or_init(|| cel.build_with_mcp(Some(res))));
+		let allowed = self.0.validate(exec);
+		if allowed && self.0.monitored_denies(exec) {
+			tracing::debug!("RBAC rules in monitor mode would deny resource: {:?}", res);
+			log.non_atomic_mutate(|l| l.monitored_denial = true);
+		}
+		allowed
 	}
 
 	pub fn register(&self, cel: &mut ContextBuilder) {
@@ -40,6 +55,15 @@ impl McpAuthorizationSet {
 	}
 }
 
+fn borrow_executor<'a>(
+	built: &'a Result<crate::cel::Executor<'static>, crate::cel::Error>,
+) -> anyhow::Result<OwnedOrBorrowed<'a, crate::cel::Executor<'a>>> {
+	match built {
+		Ok(exec) => Ok(OwnedOrBorrowed::Borrowed(exec)),
+		Err(e) => Err(anyhow::anyhow!("{e}")),
+	}
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
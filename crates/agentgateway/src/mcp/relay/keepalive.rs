@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use rmcp::RoleClient;
+use rmcp::model::{ClientRequest, PingRequest};
+use rmcp::service::Peer;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::*;
+
+/// Keepalive periodically pings upstream MCP targets, so broken connections (such as an SSE stream that was
+/// silently dropped, or a hung stdio server) are detected before a client request fails on them.
+/// Only applies to stateful backends, as stateless backends connect for each request.
+#[apply(schema!)]
+pub struct Keepalive {
+	/// How often to ping each target. Defaults to 30s.
+	#[serde(default = "default_interval", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub interval: Duration,
+	/// How long to wait for a ping response before counting it as a failure. Defaults to 10s.
+	#[serde(default = "default_timeout", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub timeout: Duration,
+	/// Number of consecutive failed pings before the target is marked unhealthy. Defaults to 3.
+	#[serde(default = "default_failure_threshold")]
+	pub failure_threshold: u32,
+}
+
+fn default_interval() -> Duration {
+	Duration::from_secs(30)
+}
+
+fn default_timeout() -> Duration {
+	Duration::from_secs(10)
+}
+
+fn default_failure_threshold() -> u32 {
+	3
+}
+
+/// Health tracks the result of keepalive pings for a single upstream connection.
+/// Unhealthy targets are left out of fan-out requests, and requests routed directly to them fail fast.
+/// A target becomes healthy again as soon as a ping succeeds, or when the pool replaces its connection.
+#[derive(Debug)]
+pub struct Health {
+	healthy: AtomicBool,
+	failures: AtomicU32,
+}
+
+impl Default for Health {
+	fn default() -> Self {
+		Self {
+			healthy: AtomicBool::new(true),
+			failures: AtomicU32::new(0),
+		}
+	}
+}
+
+impl Health {
+	pub fn is_healthy(&self) -> bool {
+		self.healthy.load(Ordering::Relaxed)
+	}
+
+	fn record_success(&self, target: &str) {
+		self.failures.store(0, Ordering::Relaxed);
+		if !self.healthy.swap(true, Ordering::Relaxed) {
+			info!(target, "mcp target is healthy again");
+		}
+	}
+
+	fn record_failure(&self, target: &str, threshold: u32, reason: &str) {
+		let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+		debug!(target, failures, reason, "mcp keepalive ping failed");
+		if failures >= threshold && self.healthy.swap(false, Ordering::Relaxed) {
+			warn!(
+				target,
+				failures, reason, "mcp target marked unhealthy after failed keepalive pings"
+			);
+		}
+	}
+}
+
+impl Keepalive {
+	/// start pings the peer until the returned guard is dropped.
+	pub fn start(&self, target: Strng, peer: Peer<RoleClient>, health: Arc<Health>) -> DropGuard {
+		let ct = CancellationToken::new();
+		let cancel = ct.clone();
+		let cfg = self.clone();
+		tokio::spawn(async move {
+			let mut interval = tokio::time::interval(cfg.interval);
+			// The first tick completes immediately, and the connection was just established.
+			interval.tick().await;
+			loop {
+				tokio::select! {
+					_ = cancel.cancelled() => return,
+					_ = interval.tick() => {},
+				}
+				let ping = peer.send_request(ClientRequest::PingRequest(PingRequest {
+					method: Default::default(),
+					extensions: Default::default(),
+				}));
+				match tokio::time::timeout(cfg.timeout, ping).await {
+					Ok(Ok(_)) => health.record_success(&target),
+					Ok(Err(e)) => health.record_failure(&target, cfg.failure_threshold, &e.to_string()),
+					Err(_) => health.record_failure(&target, cfg.failure_threshold, "timeout"),
+				}
+			}
+		});
+		ct.drop_guard()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn failure_threshold() {
+		let health = Health::default();
+		health.record_failure("t", 2, "timeout");
+		assert!(health.is_healthy());
+		health.record_failure("t", 2, "timeout");
+		assert!(!health.is_healthy());
+		health.record_success("t");
+		assert!(health.is_healthy());
+		// Failures must be consecutive
+		health.record_failure("t", 2, "timeout");
+		health.record_success("t");
+		health.record_failure("t", 2, "timeout");
+		assert!(health.is_healthy());
+	}
+
+	#[test]
+	fn defaults() {
+		let k: Keepalive = serde_json::from_str("{}").unwrap();
+		assert_eq!(k.interval, Duration::from_secs(30));
+		assert_eq!(k.timeout, Duration::from_secs(10));
+		assert_eq!(k.failure_threshold, 3);
+	}
+}
@@ -6,6 +6,8 @@ use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::Family;
 use prometheus_client::registry::Registry;
 
+use crate::telemetry::errors::ErrorType;
+
 #[derive(Debug)]
 pub struct Metrics {
 	tool_calls: Family<ToolCall, Counter>,
@@ -50,7 +52,7 @@ pub struct ToolCall {
 pub struct ToolCallError {
 	pub server: String,
 	pub name: String,
-	pub error_type: String,
+	pub error_type: ErrorType,
 	#[prometheus(flatten)]
 	pub params: Vec<(String, String)>,
 }
@@ -9,6 +9,7 @@ use agent_core::metrics::Recorder;
 use agent_core::prelude::Strng;
 use agent_core::trcng;
 use agent_core::version::BuildInfo;
+use chrono::Utc;
 use http::HeaderValue;
 use http::request::Parts;
 use itertools::Itertools;
@@ -18,6 +19,7 @@ use opentelemetry::{Context, TraceFlags};
 use rmcp::model::{CallToolRequestParam, Tool, *};
 use rmcp::service::{RequestContext, RunningService};
 use rmcp::transport::child_process::TokioChildProcess;
+use rmcp::transport::common::http_header::HEADER_SESSION_ID;
 use rmcp::{RoleClient, RoleServer, ServerHandler, model};
 use tokio::process::Command;
 use tokio::sync::RwLock;
@@ -26,18 +28,23 @@ use tracing::instrument;
 use crate::ProxyInputs;
 use crate::cel::ContextBuilder;
 use crate::http::jwt::Claims;
+use crate::management::diagnostics::TableEntry;
+use crate::mcp::handshake::{Handshake, Handshakes, TargetHandshake};
+use crate::mcp::metadata::{McpMetadata, Values};
 use crate::mcp::rbac;
 use crate::mcp::rbac::{Identity, McpAuthorizationSet};
 use crate::mcp::relay::pool::ConnectionPool;
 use crate::mcp::relay::upstream::UpstreamTarget;
-use crate::mcp::sse::{MCPInfo, McpBackendGroup};
+use crate::mcp::sse::{MCPInfo, McpBackendGroup, Session};
 use crate::proxy::httpproxy::PolicyClient;
+use crate::telemetry::errors::ErrorType;
 use crate::telemetry::log::AsyncLog;
 use crate::telemetry::trc::TraceParent;
 use crate::transport::stream::TLSConnectionInfo;
 
 type McpError = ErrorData;
 
+pub mod keepalive;
 pub mod metrics;
 mod pool;
 pub mod upstream;
@@ -61,6 +68,8 @@ static AGW_INITIALIZE: LazyLock<InitializeRequestParam> =
 pub struct RqCtx {
 	identity: Identity,
 	context: Context,
+	// The `_meta` to send on upstream requests, if any.
+	meta: Option<Meta>,
 }
 
 impl Default for RqCtx {
@@ -68,13 +77,28 @@ impl Default for RqCtx {
 		Self {
 			identity: Identity::default(),
 			context: Context::new(),
+			meta: None,
 		}
 	}
 }
 
 impl RqCtx {
 	pub fn new(identity: Identity, context: Context) -> Self {
-		Self { identity, context }
+		Self {
+			identity,
+			context,
+			meta: None,
+		}
+	}
+
+	/// extensions returns the extensions for an upstream request.
+	pub(crate) fn extensions(&self) -> model::Extensions {
+		let mut extensions = model::Extensions::new();
+		extensions.insert(self.clone());
+		if let Some(meta) = &self.meta {
+			extensions.insert(meta.clone());
+		}
+		extensions
 	}
 }
 
@@ -83,6 +107,11 @@ pub struct Relay {
 	pool: Arc<RwLock<pool::ConnectionPool>>,
 	metrics: Arc<metrics::Metrics>,
 	policies: McpAuthorizationSet,
+	metadata: Option<McpMetadata>,
+	backend: Strng,
+	handshakes: Handshakes,
+	// Removes the session from the open sessions once the last clone of the relay is dropped.
+	_session: Arc<TableEntry<Session>>,
 	// If we have 1 target only, we don't prefix everything with 'target_'.
 	// Else this is empty
 	default_target_name: Option<String>,
@@ -95,6 +124,7 @@ impl Relay {
 		backend: McpBackendGroup,
 		metrics: Arc<metrics::Metrics>,
 		policies: McpAuthorizationSet,
+		metadata: Option<McpMetadata>,
 		client: PolicyClient,
 		stateful: bool,
 	) -> Self {
@@ -103,12 +133,23 @@ impl Relay {
 		} else {
 			Some(backend.targets[0].name.to_string())
 		};
+		let handshakes = pi.mcp_state.handshakes();
+		let session = pi.mcp_state.sessions().insert(Session {
+			backend: backend.name.clone(),
+			stateful,
+			targets: backend.targets.iter().map(|t| t.name.clone()).collect(),
+			start: Utc::now(),
+		});
 		Self {
+			backend: backend.name.clone(),
 			pool: Arc::new(RwLock::new(pool::ConnectionPool::new(
 				pi, client, backend, stateful,
 			))),
 			metrics,
 			policies,
+			metadata,
+			handshakes,
+			_session: Arc::new(session),
 			default_target_name,
 			stateful,
 		}
@@ -136,17 +177,19 @@ impl Relay {
 	}
 
 	fn setup_request(
-		ext: &model::Extensions,
+		&self,
+		context: &RequestContext<RoleServer>,
 		span_name: &str,
 	) -> Result<(BoxedSpan, RqCtx), McpError> {
-		let (s, rq, _, _) = Self::setup_request_log(ext, span_name)?;
+		let (s, rq, _, _) = self.setup_request_log(context, span_name)?;
 		Ok((s, rq))
 	}
 	fn setup_request_log(
-		ext: &model::Extensions,
+		&self,
+		context: &RequestContext<RoleServer>,
 		span_name: &str,
 	) -> Result<(BoxedSpan, RqCtx, AsyncLog<MCPInfo>, Arc<ContextBuilder>), McpError> {
-		let Some(http) = ext.get::<Parts>() else {
+		let Some(http) = context.extensions.get::<Parts>() else {
 			return Err(McpError::internal_error(
 				"failed to extract parts".to_string(),
 				None,
@@ -181,7 +224,19 @@ impl Relay {
 			.cloned()
 			.expect("CelContextBuilder must be set");
 
-		let rq_ctx = RqCtx::new(Identity::new(claims.cloned(), id), ctx);
+		let mut rq_ctx = RqCtx::new(Identity::new(claims.cloned(), id), ctx);
+		if let Some(metadata) = &self.metadata {
+			let values = Values {
+				identity: &rq_ctx.identity,
+				session_id: http
+					.headers
+					.get(HEADER_SESSION_ID)
+					.and_then(|h| h.to_str().ok()),
+				trace_id: traceparent.map(|tp| tp.trace_id()),
+				client_info: context.peer.peer_info().map(|info| &info.client_info),
+			};
+			rq_ctx.meta = Some(metadata.build(&context.meta, &values));
+		}
 
 		let tracer = trcng::get_tracer();
 		let _span = trcng::start_span(span_name.to_string(), &rq_ctx.identity)
@@ -197,7 +252,7 @@ impl Relay {
 	) -> Result<Vec<(Strng, &'a upstream::UpstreamTarget)>, McpError> {
 		Ok(match self.stateful {
 			true => pool
-				.list()
+				.list(&context.peer)
 				.await
 				.map_err(|e| McpError::internal_error(format!("Failed to list connections: {e}"), None))?,
 			false => {
@@ -303,20 +358,41 @@ impl ServerHandler for Relay {
 		request: InitializeRequestParam,
 		context: RequestContext<RoleServer>,
 	) -> Result<InitializeResult, McpError> {
-		let (_span, _) = Self::setup_request(&context.extensions, "initialize")?;
+		let (_span, _) = self.setup_request(&context, "initialize")?;
 
 		// List servers and initialize the ones that are not initialized
 		let mut pool = self.pool.write().await;
 		// Initialize all targets
-		let _ = pool
-			.initialize(&context.peer, request)
+		let connections = pool
+			.initialize(&context.peer, request.clone())
 			.await
 			.map_err(|e| McpError::internal_error(format!("Failed to list connections: {e}"), None))?;
 
 		// Return static server info about ourselves
 		// TODO: we should actually perform an intersection of what the downstream and we support. The problem
 		// is we may connect to many upstream servers, how do expose what exactly we can and cannot support?
-		Ok(self.get_info())
+		let mut info = self.get_info();
+		// Completions are only advertised if some target can serve them.
+		if connections
+			.iter()
+			.any(|(_, svc)| svc.supports_completions())
+		{
+			info.capabilities.completions = Some(Default::default());
+		}
+		self.handshakes.record(Handshake::new(
+			self.backend.clone(),
+			self.stateful,
+			&request,
+			info.capabilities.clone(),
+			connections
+				.iter()
+				.map(|(name, svc)| TargetHandshake {
+					name: name.clone(),
+					server: svc.peer_info().cloned(),
+				})
+				.collect(),
+		));
+		Ok(info)
 	}
 
 	#[instrument(level = "debug", skip_all)]
@@ -325,7 +401,7 @@ impl ServerHandler for Relay {
 		request: Option<PaginatedRequestParam>,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<ListResourcesResult, McpError> {
-		let (_span, ref rq_ctx) = Self::setup_request(&context.extensions, "list_resources")?;
+		let (_span, ref rq_ctx) = self.setup_request(&context, "list_resources")?;
 		let mut pool = self.pool.write().await;
 		let connections = self.list_conns(&context, pool.deref_mut()).await?;
 		let all = connections.into_iter().map(|(_name, svc)| {
@@ -356,7 +432,7 @@ impl ServerHandler for Relay {
 		request: Option<PaginatedRequestParam>,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<ListResourceTemplatesResult, McpError> {
-		let (_span, ref rq_ctx) = Self::setup_request(&context.extensions, "list_resource_templates")?;
+		let (_span, ref rq_ctx) = self.setup_request(&context, "list_resource_templates")?;
 
 		let mut pool = self.pool.write().await;
 		let connections = self.list_conns(&context, pool.deref_mut()).await?;
@@ -395,7 +471,7 @@ impl ServerHandler for Relay {
 		request: Option<PaginatedRequestParam>,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<ListPromptsResult, McpError> {
-		let (_span, ref rq_ctx) = Self::setup_request(&context.extensions, "list_prompts")?;
+		let (_span, ref rq_ctx) = self.setup_request(&context, "list_prompts")?;
 
 		let mut pool = self.pool.write().await;
 		let connections = self.list_conns(&context, pool.deref_mut()).await?;
@@ -449,8 +525,7 @@ impl ServerHandler for Relay {
 		request: ReadResourceRequestParam,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<ReadResourceResult, McpError> {
-		let (_span, ref rq_ctx, _, cel) =
-			Self::setup_request_log(&context.extensions, "read_resource")?;
+		let (_span, ref rq_ctx, log, cel) = self.setup_request_log(&context, "read_resource")?;
 
 		let uri = request.uri.to_string();
 		let (service_name, resource) = self.parse_resource_name(&uri)?;
@@ -460,6 +535,7 @@ impl ServerHandler for Relay {
 				resource.to_string(),
 			)),
 			cel.as_ref(),
+			&log,
 		) {
 			return Err(McpError::invalid_request("not allowed", None));
 		}
@@ -497,7 +573,7 @@ impl ServerHandler for Relay {
 		request: GetPromptRequestParam,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<GetPromptResult, McpError> {
-		let (_span, ref rq_ctx, _, cel) = Self::setup_request_log(&context.extensions, "get_prompt")?;
+		let (_span, ref rq_ctx, log, cel) = self.setup_request_log(&context, "get_prompt")?;
 
 		let prompt_name = request.name.to_string();
 		let (service_name, prompt) = self.parse_resource_name(&prompt_name)?;
@@ -507,6 +583,7 @@ impl ServerHandler for Relay {
 				prompt.to_string(),
 			)),
 			cel.as_ref(),
+			&log,
 		) {
 			return Err(McpError::invalid_request("not allowed", None));
 		}
@@ -533,18 +610,58 @@ impl ServerHandler for Relay {
 		}
 	}
 
+	#[instrument(level = "debug", skip_all)]
+	async fn complete(
+		&self,
+		request: CompleteRequestParam,
+		context: RequestContext<RoleServer>,
+	) -> std::result::Result<CompleteResult, McpError> {
+		let (_span, ref rq_ctx, log, cel) = self.setup_request_log(&context, "complete")?;
+
+		// The reference is to a prefixed prompt or resource, which determines the target to send to.
+		let mut req = request;
+		let name = match &req.r#ref {
+			Reference::Prompt(p) => p.name.clone(),
+			Reference::Resource(r) => r.uri.clone(),
+		};
+		let (service_name, target_name) = self.parse_resource_name(&name)?;
+		let id = rbac::ResourceId::new(service_name.to_string(), target_name.to_string());
+		let resource = match &mut req.r#ref {
+			Reference::Prompt(p) => {
+				p.name = target_name.to_string();
+				rbac::ResourceType::Prompt(id)
+			},
+			Reference::Resource(r) => {
+				r.uri = target_name.to_string();
+				rbac::ResourceType::Resource(id)
+			},
+		};
+		if !self.policies.validate(&resource, cel.as_ref(), &log) {
+			return Err(McpError::invalid_request("not allowed", None));
+		}
+		let mut pool = self.pool.write().await;
+		let svc = self
+			.get_conn(&context, rq_ctx, pool.deref_mut(), service_name)
+			.await?;
+		match svc.complete(req, rq_ctx).await {
+			Ok(r) => Ok(r),
+			Err(e) => Err(e.into()),
+		}
+	}
+
 	#[instrument(level = "debug", skip_all)]
 	async fn list_tools(
 		&self,
 		request: Option<PaginatedRequestParam>,
 		context: RequestContext<RoleServer>,
 	) -> std::result::Result<ListToolsResult, McpError> {
-		let (_span, ref rq_ctx, _, cel) = Self::setup_request_log(&context.extensions, "list_tools")?;
+		let (_span, ref rq_ctx, log, cel) = self.setup_request_log(&context, "list_tools")?;
 		let mut pool = self.pool.write().await;
 		let connections = self.list_conns(&context, pool.deref_mut()).await?;
 		let all = connections.into_iter().map(|(_name, svc_arc)| {
 			let request = request.clone();
 			let cel = cel.clone();
+			let log = log.clone();
 			async move {
 				match svc_arc.list_tools(request, rq_ctx).await {
 					Ok(r) => Ok(
@@ -557,6 +674,7 @@ impl ServerHandler for Relay {
 										t.name.to_string(),
 									)),
 									cel.as_ref(),
+									&log,
 								)
 							})
 							.map(|t| Tool {
@@ -603,8 +721,7 @@ impl ServerHandler for Relay {
 		context: RequestContext<RoleServer>,
 	) -> impl Future<Output = Result<CallToolResult, McpError>> + Send + '_ {
 		Box::pin(async move {
-			let (_span, ref rq_ctx, log, cel) =
-				Self::setup_request_log(&context.extensions, "call_tool")?;
+			let (_span, ref rq_ctx, log, cel) = self.setup_request_log(&context, "call_tool")?;
 			let tool_name = request.name.to_string();
 			let (service_name, tool) = self.parse_resource_name(&tool_name)?;
 			log.non_atomic_mutate(|l| {
@@ -617,7 +734,9 @@ impl ServerHandler for Relay {
 					tool.to_string(),
 				)),
 				cel.as_ref(),
+				&log,
 			) {
+				log.non_atomic_mutate(|l| l.error_type = Some(ErrorType::Authorization));
 				return Err(McpError::invalid_request("not allowed", None));
 			}
 			let mut pool = self.pool.write().await;
@@ -639,11 +758,13 @@ impl ServerHandler for Relay {
 			match svc.call_tool(req, rq_ctx).await {
 				Ok(r) => Ok(r),
 				Err(e) => {
+					let error_type = e.error_type();
+					log.non_atomic_mutate(|l| l.error_type = Some(error_type));
 					self.metrics.record(
 						metrics::ToolCallError {
 							server: service_name.to_string(),
 							name: tool.to_string(),
-							error_type: e.error_code(),
+							error_type,
 							params: vec![],
 						},
 						(),
@@ -37,6 +37,11 @@ pub(crate) struct ConnectionPool {
 	client: PolicyClient,
 	by_name: HashMap<Strng, upstream::UpstreamTarget>,
 	stateful: bool,
+	/// The downstream client's initialize request, used to re-initialize targets that are reconnected.
+	init_request: Option<InitializeRequestParam>,
+	/// When each unhealthy target was last reconnected, so a target that is down is retried at most once per
+	/// keepalive interval.
+	reconnected_at: HashMap<Strng, Instant>,
 }
 
 impl ConnectionPool {
@@ -52,13 +57,15 @@ impl ConnectionPool {
 			pi,
 			by_name: HashMap::new(),
 			stateful,
+			init_request: None,
+			reconnected_at: HashMap::new(),
 		}
 	}
 
 	pub(crate) async fn get(
 		&mut self,
 		_rq_ctx: &RqCtx,
-		_peer: &Peer<RoleServer>,
+		peer: &Peer<RoleServer>,
 		name: &str,
 	) -> anyhow::Result<&upstream::UpstreamTarget> {
 		if !self.stateful {
@@ -76,11 +83,15 @@ impl ConnectionPool {
 				"requested target {name} is not initialized",
 			));
 		}
-		let target = self.by_name.get(name);
-		Ok(target.ok_or(McpError::invalid_request(
+		self.reconnect_unhealthy(peer).await;
+		let target = self.by_name.get(name).ok_or(McpError::invalid_request(
 			format!("Service {name} not found"),
 			None,
-		))?)
+		))?;
+		if !target.health.is_healthy() {
+			anyhow::bail!("requested target {name} is unhealthy");
+		}
+		Ok(target)
 	}
 
 	pub(crate) async fn remove(&mut self, name: &str) -> Option<upstream::UpstreamTarget> {
@@ -95,6 +106,9 @@ impl ConnectionPool {
 		peer: &Peer<RoleServer>,
 		request: InitializeRequestParam,
 	) -> anyhow::Result<Vec<(Strng, &upstream::UpstreamTarget)>> {
+		if self.stateful {
+			self.init_request = Some(request.clone());
+		}
 		for tgt in self.backend.targets.clone() {
 			if self.stateful && self.by_name.contains_key(&tgt.name) {
 				anyhow::bail!("connection {} already initialized", tgt.name);
@@ -110,7 +124,7 @@ impl ConnectionPool {
 				})?;
 		}
 		if self.stateful {
-			return self.list().await;
+			return self.list(peer).await;
 		}
 
 		// Use list_from_by_name here because, in stateless mode, external calls to list()
@@ -137,20 +151,80 @@ impl ConnectionPool {
 					.get(&tgt.name)
 					.map(|target: &upstream::UpstreamTarget| (tgt.name.clone(), target))
 			})
+			// Skip targets failing keepalive pings, rather than failing the whole request on them.
+			.filter(|(name, target)| {
+				let healthy = target.health.is_healthy();
+				if !healthy {
+					debug!("skipping unhealthy target: {name}");
+				}
+				healthy
+			})
 			.collect();
 		Ok(results)
 	}
 
-	pub(crate) async fn list(&mut self) -> anyhow::Result<Vec<(Strng, &upstream::UpstreamTarget)>> {
+	pub(crate) async fn list(
+		&mut self,
+		peer: &Peer<RoleServer>,
+	) -> anyhow::Result<Vec<(Strng, &upstream::UpstreamTarget)>> {
 		if !self.stateful {
 			return Err(
 				McpError::invalid_request("stateful mode is disabled, cannot list connections", None)
 					.into(),
 			);
 		}
+		self.reconnect_unhealthy(peer).await;
 		self.list_from_by_name()
 	}
 
+	/// reconnect_unhealthy replaces connections that keepalive pings marked unhealthy with new connections,
+	/// which are initialized again with the client's initialize request. A target that fails to reconnect keeps
+	/// its unhealthy connection, so it is still skipped, and is retried after the keepalive interval.
+	async fn reconnect_unhealthy(&mut self, peer: &Peer<RoleServer>) {
+		let (Some(keepalive), Some(init_request)) = (&self.backend.keepalive, &self.init_request)
+		else {
+			return;
+		};
+		let interval = keepalive.interval;
+		let now = Instant::now();
+		let unhealthy = self
+			.backend
+			.targets
+			.iter()
+			.filter(|tgt| {
+				self
+					.by_name
+					.get(&tgt.name)
+					.is_some_and(|t| !t.health.is_healthy())
+			})
+			.filter(|tgt| {
+				self
+					.reconnected_at
+					.get(&tgt.name)
+					.is_none_or(|at| now.duration_since(*at) >= interval)
+			})
+			.cloned()
+			.collect_vec();
+		let init_request = init_request.clone();
+		for tgt in unhealthy {
+			self.reconnected_at.insert(tgt.name.clone(), now);
+			let ct = tokio_util::sync::CancellationToken::new();
+			match self
+				.inner_connect(&ct, &tgt, peer, init_request.clone())
+				.await
+			{
+				Ok(mut transport) => {
+					info!(target = %tgt.name, "reconnected unhealthy mcp target");
+					transport.start_keepalive(tgt.name.clone(), keepalive);
+					self.reconnected_at.remove(&tgt.name);
+					// Dropping the old connection stops its keepalive pings.
+					self.by_name.insert(tgt.name.clone(), transport);
+				},
+				Err(e) => warn!(target = %tgt.name, "failed to reconnect unhealthy mcp target: {e}"),
+			}
+		}
+	}
+
 	#[instrument(
         level = "debug",
         skip_all,
@@ -204,19 +278,17 @@ impl ConnectionPool {
 				.await
 				.context("start sse client")?;
 
-				upstream::UpstreamTarget {
-					spec: upstream::UpstreamTargetSpec::Mcp(
-						serve_client_with_ct(
-							PeerClientHandler {
-								peer: peer.clone(),
-								init_request,
-							},
-							transport,
-							ct.child_token(),
-						)
-						.await?,
-					),
-				}
+				upstream::UpstreamTarget::new(upstream::UpstreamTargetSpec::Mcp(
+					serve_client_with_ct(
+						PeerClientHandler {
+							peer: peer.clone(),
+							init_request,
+						},
+						transport,
+						ct.child_token(),
+					)
+					.await?,
+				))
 			},
 			McpTargetSpec::Mcp(mcp) => {
 				debug!(
@@ -238,19 +310,17 @@ impl ConnectionPool {
 					},
 				);
 
-				upstream::UpstreamTarget {
-					spec: upstream::UpstreamTargetSpec::Mcp(
-						serve_client_with_ct(
-							PeerClientHandler {
-								peer: peer.clone(),
-								init_request,
-							},
-							transport,
-							ct.child_token(),
-						)
-						.await?,
-					),
-				}
+				upstream::UpstreamTarget::new(upstream::UpstreamTargetSpec::Mcp(
+					serve_client_with_ct(
+						PeerClientHandler {
+							peer: peer.clone(),
+							init_request,
+						},
+						transport,
+						ct.child_token(),
+					)
+					.await?,
+				))
 			},
 			McpTargetSpec::Stdio { cmd, args, env } => {
 				debug!("starting stdio transport for target: {}", target.name);
@@ -259,19 +329,17 @@ impl ConnectionPool {
 				for (k, v) in env {
 					c.env(k, v);
 				}
-				upstream::UpstreamTarget {
-					spec: upstream::UpstreamTargetSpec::Mcp(
-						serve_client_with_ct(
-							PeerClientHandler {
-								peer: peer.clone(),
-								init_request,
-							},
-							TokioChildProcess::new(c).context(format!("failed to run command '{cmd}'"))?,
-							ct.child_token(),
-						)
-						.await?,
-					),
-				}
+				upstream::UpstreamTarget::new(upstream::UpstreamTargetSpec::Mcp(
+					serve_client_with_ct(
+						PeerClientHandler {
+							peer: peer.clone(),
+							init_request,
+						},
+						TokioChildProcess::new(c).context(format!("failed to run command '{cmd}'"))?,
+						ct.child_token(),
+					)
+					.await?,
+				))
 			},
 			McpTargetSpec::OpenAPI(open) => {
 				// Renamed for clarity
@@ -293,15 +361,16 @@ impl ConnectionPool {
 					)
 				})?;
 				let be = crate::proxy::resolve_simple_backend(&open.backend, &self.pi)?;
-				upstream::UpstreamTarget {
-					spec: upstream::UpstreamTargetSpec::OpenAPI(Box::new(crate::mcp::openapi::Handler {
+				upstream::UpstreamTarget::new(upstream::UpstreamTargetSpec::OpenAPI(Box::new(
+					crate::mcp::openapi::Handler {
 						backend: be,
 						client: self.client.clone(),
 						default_policies: target.backend_policies.clone(),
 						tools,  // From parse_openapi_schema
 						prefix, // From get_server_prefix
-					})),
-				}
+						response_validation: open.response_validation,
+					},
+				)))
 			},
 		};
 
@@ -322,7 +391,13 @@ impl ConnectionPool {
 			return Ok(());
 		}
 
-		let transport = self.inner_connect(ct, target, peer, init_request).await?;
+		let mut transport = self.inner_connect(ct, target, peer, init_request).await?;
+		// Stateless connections only live for a single request, so there is nothing to keep alive.
+		if self.stateful
+			&& let Some(keepalive) = &self.backend.keepalive
+		{
+			transport.start_keepalive(target.name.clone(), keepalive);
+		}
 
 		// In stateless mode, this just overwrites the existing entry
 		self.by_name.insert(target.name.clone(), transport);
@@ -433,6 +508,11 @@ impl ClientHandler for PeerClientHandler {
 			});
 	}
 
+	async fn ping(&self, _context: RequestContext<RoleClient>) -> Result<(), McpError> {
+		// Upstream keepalives check the connection to us, so answer directly rather than forwarding to the client.
+		Ok(())
+	}
+
 	fn get_info(&self) -> ClientInfo {
 		self.init_request.get_info()
 	}
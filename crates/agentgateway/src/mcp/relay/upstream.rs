@@ -1,6 +1,8 @@
 use serde::Serialize;
+use tokio_util::sync::DropGuard;
 
 use super::*;
+use crate::telemetry::errors::ErrorType;
 #[allow(unused_imports)]
 use crate::*;
 
@@ -10,19 +12,21 @@ pub(crate) enum UpstreamError {
 }
 
 impl UpstreamError {
-	pub(crate) fn error_code(&self) -> String {
+	pub(crate) fn error_type(&self) -> ErrorType {
 		match self {
 			Self::ServiceError(e) => match e {
-				rmcp::ServiceError::McpError(_) => "mcp_error".to_string(),
-				rmcp::ServiceError::Timeout { timeout: _ } => "timeout".to_string(),
-				rmcp::ServiceError::Cancelled { reason } => {
-					reason.clone().unwrap_or("cancelled".to_string())
+				rmcp::ServiceError::McpError(e) => match e.code.0 {
+					// MCP uses this code for a resource that does not exist
+					-32002 => ErrorType::NotFound,
+					code => ErrorType::from_json_rpc(code.into()),
 				},
-				rmcp::ServiceError::UnexpectedResponse => "unexpected_response".to_string(),
-				rmcp::ServiceError::TransportSend(_) => "transport_error".to_string(),
-				_ => "unknown".to_string(),
+				rmcp::ServiceError::Timeout { timeout: _ } => ErrorType::Timeout,
+				rmcp::ServiceError::Cancelled { reason: _ } => ErrorType::Cancelled,
+				rmcp::ServiceError::UnexpectedResponse => ErrorType::InvalidResponse,
+				rmcp::ServiceError::TransportSend(_) => ErrorType::UpstreamConnection,
+				_ => ErrorType::UpstreamError,
 			},
-			Self::OpenAPIError(_) => "openapi_error".to_string(),
+			Self::OpenAPIError(_) => ErrorType::UpstreamError,
 		}
 	}
 }
@@ -111,6 +115,9 @@ impl Filter {
 // UpstreamTarget defines a source for MCP information.
 pub(crate) struct UpstreamTarget {
 	pub(crate) spec: UpstreamTargetSpec,
+	pub(crate) health: Arc<keepalive::Health>,
+	// Stops keepalive pings when the target is dropped.
+	_keepalive: Option<DropGuard>,
 }
 pub(crate) enum UpstreamTargetSpec {
 	Mcp(RunningService<RoleClient, crate::mcp::relay::pool::PeerClientHandler>),
@@ -118,6 +125,68 @@ pub(crate) enum UpstreamTargetSpec {
 }
 
 impl UpstreamTarget {
+	pub(crate) fn new(spec: UpstreamTargetSpec) -> Self {
+		Self {
+			spec,
+			health: Default::default(),
+			_keepalive: None,
+		}
+	}
+
+	/// start_keepalive begins pinging the target, for MCP targets.
+	pub(crate) fn start_keepalive(&mut self, name: Strng, cfg: &keepalive::Keepalive) {
+		if let UpstreamTargetSpec::Mcp(m) = &self.spec {
+			self._keepalive = Some(cfg.start(name, m.peer().clone(), self.health.clone()));
+		}
+	}
+
+	/// peer_info returns the target's initialize result, for MCP targets.
+	pub(crate) fn peer_info(&self) -> Option<&ServerInfo> {
+		match &self.spec {
+			UpstreamTargetSpec::Mcp(m) => m.peer_info(),
+			UpstreamTargetSpec::OpenAPI(_) => None,
+		}
+	}
+
+	/// supports_completions reports whether the target advertised the completions capability.
+	pub(crate) fn supports_completions(&self) -> bool {
+		self
+			.peer_info()
+			.is_some_and(|info| info.capabilities.completions.is_some())
+	}
+
+	pub(crate) async fn complete(
+		&self,
+		request: CompleteRequestParam,
+		rq_ctx: &RqCtx,
+	) -> Result<CompleteResult, UpstreamError> {
+		match &self.spec {
+			UpstreamTargetSpec::Mcp(m) => {
+				let extensions = rq_ctx.extensions();
+				let result = m
+					.send_request(ClientRequest::CompleteRequest(CompleteRequest {
+						method: Default::default(),
+						params: request,
+						extensions,
+					}))
+					.await?;
+				match result {
+					ServerResult::CompleteResult(result) => Ok(result),
+					_ => Err(UpstreamError::ServiceError(
+						rmcp::ServiceError::UnexpectedResponse,
+					)),
+				}
+			},
+			UpstreamTargetSpec::OpenAPI(_) => Ok(CompleteResult {
+				completion: CompletionInfo {
+					values: vec![],
+					total: None,
+					has_more: None,
+				},
+			}),
+		}
+	}
+
 	pub(crate) async fn list_tools(
 		&self,
 		request: Option<PaginatedRequestParam>,
@@ -125,8 +194,7 @@ impl UpstreamTarget {
 	) -> Result<ListToolsResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::ListToolsRequest(ListToolsRequest {
 						method: Default::default(),
@@ -155,8 +223,7 @@ impl UpstreamTarget {
 	) -> Result<GetPromptResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::GetPromptRequest(GetPromptRequest {
 						method: Default::default(),
@@ -185,8 +252,7 @@ impl UpstreamTarget {
 	) -> Result<ListPromptsResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::ListPromptsRequest(ListPromptsRequest {
 						method: Default::default(),
@@ -220,8 +286,7 @@ impl UpstreamTarget {
 	) -> Result<ListResourcesResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::ListResourcesRequest(ListResourcesRequest {
 						method: Default::default(),
@@ -255,8 +320,7 @@ impl UpstreamTarget {
 	) -> Result<ListResourceTemplatesResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::ListResourceTemplatesRequest(
 						ListResourceTemplatesRequest {
@@ -287,8 +351,7 @@ impl UpstreamTarget {
 	) -> Result<ReadResourceResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::ReadResourceRequest(ReadResourceRequest {
 						method: Default::default(),
@@ -314,8 +377,7 @@ impl UpstreamTarget {
 	) -> Result<CallToolResult, UpstreamError> {
 		match &self.spec {
 			UpstreamTargetSpec::Mcp(m) => {
-				let mut extensions = rmcp::model::Extensions::new();
-				extensions.insert(rq_ctx.clone());
+				let extensions = rq_ctx.extensions();
 				let result = m
 					.send_request(ClientRequest::CallToolRequest(CallToolRequest {
 						method: Default::default(),
@@ -11,6 +11,7 @@ use axum::response::sse::{Event, Sse};
 use axum::response::{IntoResponse, Response};
 use axum_core::extract::FromRequest;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures::stream::Stream;
 use futures::{SinkExt, StreamExt};
 use http::Method;
@@ -30,10 +31,14 @@ use crate::cel::ContextBuilder;
 use crate::http::jwt::Claims;
 use crate::http::*;
 use crate::json::from_body;
-use crate::mcp::relay;
+use crate::management::diagnostics::Table;
+use crate::mcp::handshake::Handshakes;
 use crate::mcp::relay::Relay;
+use crate::mcp::relay::keepalive::Keepalive;
+use crate::mcp::{duplex, relay};
 use crate::proxy::httpproxy::PolicyClient;
 use crate::store::{BackendPolicies, Stores};
+use crate::telemetry::errors::ErrorType;
 use crate::telemetry::log::AsyncLog;
 use crate::types::agent::{BackendName, McpAuthentication, McpBackend, McpIDP, PolicyTarget};
 use crate::{ProxyInputs, json};
@@ -45,6 +50,20 @@ type SseTxs =
 pub struct MCPInfo {
 	pub tool_call_name: Option<String>,
 	pub target_name: Option<String>,
+	pub error_type: Option<ErrorType>,
+	/// Set if MCP authorization rules in monitor mode would have denied an operation in the request.
+	pub monitored_denial: bool,
+}
+
+/// Session describes an open MCP session, for diagnostics bundles. In stateless mode, each request is its own
+/// session.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+	pub backend: BackendName,
+	pub stateful: bool,
+	pub targets: Vec<Strng>,
+	pub start: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone)]
@@ -53,6 +72,8 @@ pub struct App {
 	metrics: Arc<relay::metrics::Metrics>,
 	_drain: DrainWatcher,
 	session: Arc<LocalSessionManager>,
+	handshakes: Handshakes,
+	sessions: Table<Session>,
 
 	sse_txs: SseTxs,
 }
@@ -65,10 +86,22 @@ impl App {
 			metrics,
 			_drain: drain,
 			session,
+			handshakes: Default::default(),
+			sessions: Default::default(),
 			sse_txs: Default::default(),
 		}
 	}
 
+	/// handshakes returns the capabilities negotiated on recent sessions.
+	pub fn handshakes(&self) -> Handshakes {
+		self.handshakes.clone()
+	}
+
+	/// sessions returns the open sessions.
+	pub fn sessions(&self) -> Table<Session> {
+		self.sessions.clone()
+	}
+
 	pub async fn serve(
 		&self,
 		pi: Arc<ProxyInputs>,
@@ -77,9 +110,9 @@ impl App {
 		mut req: Request,
 		log: AsyncLog<MCPInfo>,
 	) -> Response {
-		let (backends, authorization_policies, authn) = {
+		let (backends, authorization_policies, authn, metadata) = {
 			let binds = self.state.read_binds();
-			let (authorization_policies, authn) = binds.mcp_policies(name.clone());
+			let (authorization_policies, authn, metadata) = binds.mcp_policies(name.clone());
 			let nt = backend
 				.targets
 				.iter()
@@ -96,9 +129,11 @@ impl App {
 				McpBackendGroup {
 					name: name.clone(),
 					targets: nt,
+					keepalive: backend.keepalive.clone(),
 				},
 				authorization_policies,
 				authn,
+				metadata,
 			)
 		};
 		let metrics = self.metrics.clone();
@@ -138,6 +173,7 @@ impl App {
 					backends.clone(),
 					metrics.clone(),
 					authorization_policies.clone(),
+					metadata.clone(),
 					client.clone(),
 					backend.stateful,
 				),
@@ -165,6 +201,18 @@ impl App {
 					StatusCode::INTERNAL_SERVER_ERROR
 				})
 				.into_response(),
+			_ if duplex::is_duplex(&req) => duplex::serve(
+				Relay::new(
+					pi.clone(),
+					backends.clone(),
+					metrics.clone(),
+					authorization_policies.clone(),
+					metadata.clone(),
+					client.clone(),
+					backend.stateful,
+				),
+				req,
+			),
 			_ => {
 				// Assume this is streamable HTTP otherwise
 				let streamable = StreamableHttpService::new(
@@ -174,6 +222,7 @@ impl App {
 							backends.clone(),
 							metrics.clone(),
 							authorization_policies.clone(),
+							metadata.clone(),
 							client.clone(),
 							backend.stateful,
 						))
@@ -199,6 +248,7 @@ impl App {
 pub struct McpBackendGroup {
 	pub name: BackendName,
 	pub targets: Vec<Arc<McpTarget>>,
+	pub keepalive: Option<Keepalive>,
 }
 
 impl McpBackendGroup {
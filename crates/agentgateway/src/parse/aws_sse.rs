@@ -1,25 +1,32 @@
+use std::sync::atomic::AtomicBool;
+
 use aws_event_stream_parser::{EventStreamCodec, Message};
-use bytes::Bytes;
 use serde::Serialize;
-use tokio_sse_codec::{Event, Frame, SseEncoder};
+use tokio_sse_codec::SseEncoder;
 
-use super::transform::parser as transform_parser;
+use super::sse;
+use super::transform::parser_with_finish;
 use crate::*;
 
+/// transform converts an AWS event stream into an SSE stream, terminated with a `[DONE]` event.
+/// If the upstream stream fails, `on_error` can provide a final event to send before `[DONE]`.
 pub fn transform<O: Serialize>(
 	b: http::Body,
 	mut f: impl FnMut(Message) -> Option<O> + Send + 'static,
+	on_error: impl FnOnce(&http::Error) -> Option<O> + Send + 'static,
 ) -> http::Body {
 	let decoder = EventStreamCodec;
 	let encoder = SseEncoder::new();
 
-	transform_parser(b, decoder, encoder, move |o| {
-		let transformed = f(o)?;
-		let json_bytes = serde_json::to_vec(&transformed).ok()?;
-		Some(Frame::Event(Event::<Bytes> {
-			data: Bytes::from(json_bytes),
-			name: std::borrow::Cow::Borrowed(""),
-			id: None,
-		}))
-	})
+	parser_with_finish(
+		b,
+		decoder,
+		encoder,
+		move |o| {
+			let transformed = f(o)?;
+			sse::json_event(&transformed)
+		},
+		// AWS event streams have no [DONE] event of their own
+		sse::terminate(Arc::new(AtomicBool::new(false)), on_error),
+	)
 }
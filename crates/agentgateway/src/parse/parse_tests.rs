@@ -86,6 +86,53 @@ async fn test_sse_json() {
 	);
 }
 
+#[tokio::test]
+async fn test_sse_resync() {
+	let oversized = "x".repeat(40);
+	let chunks = vec![
+		"data: {\"msg\": 1}\n\n".to_string(),
+		// An oversized event split across chunks, ending in a blank line split between them
+		format!("data: {oversized}"),
+		format!("{oversized}\r"),
+		"\n\r\ndata: {\"msg\": 2}\r\n\r\n".to_string(),
+		// An oversized event followed by a valid one in the same chunk
+		format!("data: {oversized}\n\ndata: {{\"msg\": 3}}\n\n"),
+	];
+	let body = http::Body::from_stream(futures_util::stream::iter(
+		chunks
+			.clone()
+			.into_iter()
+			.map(|c| Ok::<_, std::io::Error>(Bytes::from(c))),
+	));
+
+	let malformed = Arc::new(Mutex::new(vec![]));
+	let malformed_clone = malformed.clone();
+	let decoder = sse::ResyncDecoder::new(32, move |reason| {
+		malformed_clone.lock().unwrap().push(reason)
+	});
+	let events = Arc::new(Mutex::new(vec![]));
+	let ev_clone = events.clone();
+	let body = passthrough::parser(body, decoder, move |o| {
+		if let Some(t) = sse::unwrap_json::<Test>(o).unwrap() {
+			events.lock().unwrap().push(t)
+		}
+	});
+	let got = body.collect().await.map(|col| col.to_bytes()).unwrap();
+	// The stream itself is passed through unmodified
+	assert_eq!(got, Bytes::from(chunks.concat()));
+	assert_eq!(
+		ev_clone.lock().unwrap().clone(),
+		vec![Test { msg: 1 }, Test { msg: 2 }, Test { msg: 3 }]
+	);
+	assert_eq!(
+		malformed.lock().unwrap().clone(),
+		vec![
+			sse::MalformedEvent::Oversized,
+			sse::MalformedEvent::Oversized
+		]
+	);
+}
+
 #[tokio::test]
 async fn test_sse_json_transform() {
 	let msg1 = "data: {\"msg\": 1, \"type\": \"input\"}\n\n";
@@ -1,17 +1,46 @@
-use bytes::Bytes;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bytes::{Buf, Bytes, BytesMut};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use tokio_sse_codec::{Event, Frame, SseDecoder, SseEncoder};
+use tokio_util::codec::Decoder;
 
 use super::passthrough::parser as passthrough_parser;
-use super::transform::parser as transform_parser;
+use super::transform::{parser as transform_parser, parser_with_finish};
 use crate::*;
 
+/// The largest event accepted from a provider stream.
+const MAX_EVENT_SIZE: usize = 2_097_152;
+
+/// MalformedEvent is the reason an event in a provider stream was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MalformedEvent {
+	/// The event was larger than the maximum event size.
+	Oversized,
+	/// The event could not be decoded.
+	Invalid,
+}
+
+impl MalformedEvent {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			MalformedEvent::Oversized => "oversized",
+			MalformedEvent::Invalid => "invalid",
+		}
+	}
+}
+
+/// json_passthrough parses the JSON events in a stream, without modifying the stream itself. Events that are too
+/// large or cannot be decoded are reported to `on_malformed` and skipped, rather than ending the stream.
 pub fn json_passthrough<F: DeserializeOwned>(
 	b: http::Body,
+	on_malformed: impl FnMut(MalformedEvent) + Send + 'static,
 	mut f: impl FnMut(Option<anyhow::Result<F>>) + Send + 'static,
 ) -> http::Body {
-	let decoder = SseDecoder::<Bytes>::with_max_size(2_097_152);
+	let decoder = ResyncDecoder::new(MAX_EVENT_SIZE, on_malformed);
 
 	passthrough_parser(b, decoder, move |o| {
 		let Some(data) = unwrap_sse_data(o) else {
@@ -30,27 +59,217 @@ pub fn json_transform<I: DeserializeOwned, O: Serialize>(
 	b: http::Body,
 	mut f: impl FnMut(anyhow::Result<I>) -> Option<O> + Send + 'static,
 ) -> http::Body {
-	let decoder = SseDecoder::<Bytes>::with_max_size(2_097_152);
+	let decoder = SseDecoder::<Bytes>::with_max_size(MAX_EVENT_SIZE);
 	let encoder = SseEncoder::new();
 
 	transform_parser(b, decoder, encoder, move |o| {
 		let data = unwrap_sse_data(o)?;
 		// Pass through [DONE] events unchanged
 		if data.as_ref() == b"[DONE]" {
-			return Some(Frame::Event(Event::<Bytes> {
-				data: Bytes::copy_from_slice(b"[DONE]"),
-				name: std::borrow::Cow::Borrowed(""),
-				id: None,
-			}));
+			return Some(done_event());
 		}
 		let obj = serde_json::from_slice::<I>(&data);
 		let transformed = f(obj.map_err(anyhow::Error::from))?;
-		let json_bytes = serde_json::to_vec(&transformed).ok()?;
-		Some(Frame::Event(Event::<Bytes> {
-			data: Bytes::from(json_bytes),
-			name: std::borrow::Cow::Borrowed(""),
-			id: None,
-		}))
+		json_event(&transformed)
+	})
+}
+
+/// json_transform_terminated is like json_transform, but guarantees the stream ends with a `[DONE]` event, as
+/// OpenAI-compatible clients expect. If the upstream stream fails, `on_error` can provide a final event to send
+/// before `[DONE]`, rather than abruptly closing the connection.
+pub fn json_transform_terminated<I: DeserializeOwned, O: Serialize>(
+	b: http::Body,
+	mut f: impl FnMut(anyhow::Result<I>) -> Option<O> + Send + 'static,
+	on_error: impl FnOnce(&http::Error) -> Option<O> + Send + 'static,
+) -> http::Body {
+	let decoder = SseDecoder::<Bytes>::with_max_size(MAX_EVENT_SIZE);
+	let encoder = SseEncoder::new();
+	let done = Arc::new(AtomicBool::new(false));
+	let saw_done = done.clone();
+
+	parser_with_finish(
+		b,
+		decoder,
+		encoder,
+		move |o| {
+			let data = unwrap_sse_data(o)?;
+			if data.as_ref() == b"[DONE]" {
+				saw_done.store(true, Ordering::Relaxed);
+				return Some(done_event());
+			}
+			let obj = serde_json::from_slice::<I>(&data);
+			let transformed = f(obj.map_err(anyhow::Error::from))?;
+			json_event(&transformed)
+		},
+		terminate(done, on_error),
+	)
+}
+
+/// terminate builds the final events for a stream: an error event if the stream failed, and `[DONE]` unless it
+/// was already sent.
+pub(super) fn terminate<O: Serialize>(
+	done: Arc<AtomicBool>,
+	on_error: impl FnOnce(&http::Error) -> Option<O> + Send + 'static,
+) -> impl FnOnce(Option<&http::Error>) -> Vec<Frame<Bytes>> + Send + 'static {
+	move |err| {
+		let mut frames = Vec::with_capacity(2);
+		if let Some(err) = err
+			&& let Some(ev) = on_error(err).as_ref().and_then(json_event)
+		{
+			frames.push(ev);
+		}
+		if !done.load(Ordering::Relaxed) {
+			frames.push(done_event());
+		}
+		frames
+	}
+}
+
+pub(super) fn json_event<O: Serialize>(o: &O) -> Option<Frame<Bytes>> {
+	let json_bytes = serde_json::to_vec(o).ok()?;
+	Some(Frame::Event(Event::<Bytes> {
+		data: Bytes::from(json_bytes),
+		name: std::borrow::Cow::Borrowed(""),
+		id: None,
+	}))
+}
+
+/// ResyncDecoder decodes SSE events, skipping events that are too large or fail to decode instead of failing. Every
+/// event ends with a blank line, so after a skipped event, decoding resumes at the next blank line.
+pub struct ResyncDecoder<F> {
+	inner: SseDecoder<Bytes>,
+	max_size: usize,
+	on_malformed: F,
+	/// Frames decoded from the last complete event, which have not been returned yet.
+	decoded: VecDeque<Frame<Bytes>>,
+	/// How much of the buffer was already searched for the end of the event.
+	scanned: usize,
+	/// Whether the searched part of the buffer ends at the start of a line.
+	line_start: bool,
+	/// Set while skipping the rest of an oversized event.
+	skipping: bool,
+}
+
+impl<F: FnMut(MalformedEvent)> ResyncDecoder<F> {
+	pub fn new(max_size: usize, on_malformed: F) -> Self {
+		Self {
+			inner: SseDecoder::new(),
+			max_size,
+			on_malformed,
+			decoded: VecDeque::new(),
+			scanned: 0,
+			line_start: true,
+			skipping: false,
+		}
+	}
+
+	/// event_end searches the buffer for the blank line ending the current event, and returns the position after
+	/// it. The search continues from where the previous one stopped.
+	fn event_end(&mut self, buf: &[u8]) -> Option<usize> {
+		let mut i = self.scanned;
+		while i < buf.len() {
+			let len = match (buf[i], buf.get(i + 1)) {
+				(b'\r', Some(b'\n')) => 2,
+				// A line ending in \r may be followed by \n in the next chunk, so wait for it.
+				(b'\r', None) => break,
+				(b'\r' | b'\n', _) => 1,
+				_ => {
+					self.line_start = false;
+					i += 1;
+					continue;
+				},
+			};
+			i += len;
+			if self.line_start {
+				self.scanned = 0;
+				return Some(i);
+			}
+			self.line_start = true;
+		}
+		self.scanned = i;
+		None
+	}
+
+	fn malformed(&mut self, reason: MalformedEvent) {
+		debug!("skipping {} event in stream", reason.as_str());
+		(self.on_malformed)(reason);
+	}
+
+	/// decode_event decodes a complete event. The inner decoder is replaced if it fails, as it may have kept part
+	/// of the event.
+	fn decode_event(&mut self, mut event: BytesMut, eof: bool) {
+		loop {
+			let res = if eof {
+				self.inner.decode_eof(&mut event)
+			} else {
+				self.inner.decode(&mut event)
+			};
+			match res {
+				Ok(Some(frame)) => self.decoded.push_back(frame),
+				Ok(None) => return,
+				Err(_) => {
+					self.inner = SseDecoder::new();
+					self.malformed(MalformedEvent::Invalid);
+					return;
+				},
+			}
+		}
+	}
+}
+
+impl<F: FnMut(MalformedEvent)> Decoder for ResyncDecoder<F> {
+	type Item = Frame<Bytes>;
+	type Error = io::Error;
+
+	fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+		loop {
+			if let Some(frame) = self.decoded.pop_front() {
+				return Ok(Some(frame));
+			}
+			let Some(end) = self.event_end(buf) else {
+				if !self.skipping && buf.len() > self.max_size {
+					self.skipping = true;
+					self.malformed(MalformedEvent::Oversized);
+				}
+				if self.skipping {
+					// Only the searched part can be dropped, as a trailing \r may be part of the blank line.
+					buf.advance(self.scanned);
+					self.scanned = 0;
+				}
+				return Ok(None);
+			};
+			let event = buf.split_to(end);
+			if self.skipping {
+				self.skipping = false;
+			} else if event.len() > self.max_size {
+				self.malformed(MalformedEvent::Oversized);
+			} else {
+				self.decode_event(event, false);
+			}
+		}
+	}
+
+	fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+		if let Some(frame) = self.decode(buf)? {
+			return Ok(Some(frame));
+		}
+		// The stream ended without a blank line after the last event.
+		let event = buf.split();
+		self.scanned = 0;
+		self.line_start = true;
+		if !self.skipping && !event.is_empty() {
+			self.decode_event(event, true);
+		}
+		self.skipping = false;
+		Ok(self.decoded.pop_front())
+	}
+}
+
+fn done_event() -> Frame<Bytes> {
+	Frame::Event(Event::<Bytes> {
+		data: Bytes::copy_from_slice(b"[DONE]"),
+		name: std::borrow::Cow::Borrowed(""),
+		id: None,
 	})
 }
 
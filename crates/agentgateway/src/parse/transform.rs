@@ -18,12 +18,54 @@ pin_project! {
 		buffered_trailers: Option<HeaderMap>,
 		encoder: E,
 		handler: F,
+		finish: Option<FinishFn<T>>,
 		finished: bool,
 		_phantom: std::marker::PhantomData<T>,
 	}
 }
 
+/// FinishFn is called once when the stream ends, to produce any final items. If the stream ended due to an
+/// error, it is provided, and the error is replaced by the final items rather than returned to the client.
+type FinishFn<T> = Box<dyn FnOnce(Option<&http::Error>) -> Vec<T> + Send>;
+
 pub fn parser<D, E, F, T>(body: http::Body, decoder: D, encoder: E, handler: F) -> http::Body
+where
+	D: Decoder + Send + 'static,
+	D::Error: Send + Into<axum_core::BoxError> + 'static,
+	F: FnMut(D::Item) -> Option<T> + Send + 'static,
+	E: Encoder<T> + Send + 'static,
+	E::Error: Send + Into<axum_core::BoxError> + 'static,
+	T: Send + 'static,
+{
+	new_body(body, decoder, encoder, handler, None)
+}
+
+/// parser_with_finish is like parser, but calls `finish` when the stream ends; see FinishFn.
+pub fn parser_with_finish<D, E, F, T>(
+	body: http::Body,
+	decoder: D,
+	encoder: E,
+	handler: F,
+	finish: impl FnOnce(Option<&http::Error>) -> Vec<T> + Send + 'static,
+) -> http::Body
+where
+	D: Decoder + Send + 'static,
+	D::Error: Send + Into<axum_core::BoxError> + 'static,
+	F: FnMut(D::Item) -> Option<T> + Send + 'static,
+	E: Encoder<T> + Send + 'static,
+	E::Error: Send + Into<axum_core::BoxError> + 'static,
+	T: Send + 'static,
+{
+	new_body(body, decoder, encoder, handler, Some(Box::new(finish)))
+}
+
+fn new_body<D, E, F, T>(
+	body: http::Body,
+	decoder: D,
+	encoder: E,
+	handler: F,
+	finish: Option<FinishFn<T>>,
+) -> http::Body
 where
 	D: Decoder + Send + 'static,
 	D::Error: Send + Into<axum_core::BoxError> + 'static,
@@ -39,11 +81,39 @@ where
 		decode_buffer: BytesMut::new(),
 		buffered_trailers: None,
 		encoder,
+		finish,
 		finished: false,
 		_phantom: std::marker::PhantomData,
 	})
 }
 
+/// finish_stream runs the finish function, if any, encoding its items. If there is no finish function, or it
+/// produced nothing, the error (if any) is returned.
+fn finish_stream<E: Encoder<T>, T>(
+	finish: &mut Option<FinishFn<T>>,
+	encoder: &mut E,
+	encode_buf: &mut BytesMut,
+	err: Option<http::Error>,
+) -> Result<(), http::Error>
+where
+	E::Error: Send + Into<axum_core::BoxError> + 'static,
+{
+	let Some(finish) = finish.take() else {
+		return err.map_or(Ok(()), Err);
+	};
+	let items = finish(err.as_ref());
+	if items.is_empty() {
+		return err.map_or(Ok(()), Err);
+	}
+	if let Some(err) = err {
+		tracing::debug!(%err, "stream ended with error, sending final events");
+	}
+	for item in items {
+		encoder.encode(item, encode_buf).map_err(http::Error::new)?;
+	}
+	Ok(())
+}
+
 impl<D, E, F, T> Body for TransformedBody<D, E, F, T>
 where
 	D: Decoder + Send + 'static,
@@ -111,7 +181,11 @@ where
 			&mut *this.encoder,
 			&mut encode_buffer,
 		) {
-			return Poll::Ready(Some(Err(e)));
+			*this.finished = true;
+			*this.buffered_trailers = None;
+			if let Err(e) = finish_stream(this.finish, &mut *this.encoder, &mut encode_buffer, Some(e)) {
+				return Poll::Ready(Some(Err(e)));
+			}
 		}
 
 		// If we have encoded data to send, send it
@@ -134,18 +208,26 @@ where
 				cx.waker().wake_by_ref();
 				Poll::Pending
 			},
-			Some(Err(e)) => Poll::Ready(Some(Err(e))),
+			Some(Err(e)) => {
+				*this.finished = true;
+				match finish_stream(this.finish, &mut *this.encoder, &mut encode_buffer, Some(e)) {
+					Ok(()) => Poll::Ready(Some(Ok(http_body::Frame::data(encode_buffer.freeze())))),
+					Err(e) => Poll::Ready(Some(Err(e))),
+				}
+			},
 			None => {
 				*this.finished = true;
 				// Try one more decode/encode cycle
-				match (try_decode)(
+				let res = (try_decode)(
 					*this.finished,
 					this.decode_buffer,
 					&mut *this.decoder,
 					this.handler,
 					&mut *this.encoder,
 					&mut encode_buffer,
-				) {
+				)
+				.and_then(|_| finish_stream(this.finish, &mut *this.encoder, &mut encode_buffer, None));
+				match res {
 					Ok(_) => {
 						if !encode_buffer.is_empty() {
 							// If there is more data to encode, send it
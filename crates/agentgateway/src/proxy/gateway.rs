@@ -8,6 +8,7 @@ use agent_core::drain;
 use agent_core::drain::{DrainUpgrader, DrainWatcher};
 use anyhow::anyhow;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures_util::FutureExt;
 use http::StatusCode;
 use hyper_util::rt::TokioIo;
@@ -19,9 +20,16 @@ use tokio::task::{AbortHandle, JoinSet};
 use tokio_stream::StreamExt;
 use tracing::{Instrument, debug, event, info, info_span, warn};
 
+use crate::proxy::ProxyError;
+use crate::proxy::socks5;
+use crate::proxy::socks5::{Destination, Handshake, Reply};
 use crate::store::Event;
+use crate::telemetry::log;
+use crate::telemetry::log::{DropOnLog, RequestLog};
 use crate::transport::stream::{Extension, LoggingMode, Socket};
-use crate::types::agent::{Bind, BindName, BindProtocol, Listener, ListenerProtocol};
+use crate::types::agent::{
+	Bind, BindName, BindProtocol, HostnameMatch, Listener, ListenerProtocol, Target,
+};
 use crate::{ProxyInputs, client};
 
 #[cfg(test)]
@@ -33,6 +41,17 @@ pub struct Gateway {
 	drain: drain::DrainWatcher,
 }
 
+/// DownstreamConnection describes an open downstream connection, for diagnostics bundles.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownstreamConnection {
+	pub bind: BindName,
+	pub protocol: BindProtocol,
+	pub peer: SocketAddr,
+	pub local: SocketAddr,
+	pub start: DateTime<Utc>,
+}
+
 impl Gateway {
 	pub fn new(pi: Arc<ProxyInputs>, drain: DrainWatcher) -> Gateway {
 		Gateway { drain, pi }
@@ -243,6 +262,13 @@ impl Gateway {
 		drain: DrainWatcher,
 	) {
 		let bind_protocol = bind_protocol(inputs.clone(), bind_name.clone());
+		let _connection = inputs.connections.insert(DownstreamConnection {
+			bind: bind_name.clone(),
+			protocol: bind_protocol,
+			peer: raw_stream.tcp().peer_addr,
+			local: raw_stream.tcp().local_addr,
+			start: Utc::now(),
+		});
 		event!(
 			target: "downstream connection",
 			parent: None,
@@ -285,7 +311,166 @@ impl Gateway {
 			BindProtocol::hbone => {
 				let _ = Self::terminate_hbone(bind_name, inputs, raw_stream, drain).await;
 			},
+			BindProtocol::socks5 => {
+				if let Err(e) = Self::proxy_socks5(bind_name, inputs, raw_stream, drain).await {
+					warn!("forward proxy error: {e}");
+				}
+			},
+		}
+	}
+
+	/// proxy_socks5 serves a forward proxy connection. Destinations matching a route are served by the HTTP proxy,
+	/// so its policies and logging apply; anything else is tunneled to the destination as-is, if the listener
+	/// allows it.
+	async fn proxy_socks5(
+		bind_name: BindName,
+		inputs: Arc<ProxyInputs>,
+		mut stream: Socket,
+		drain: DrainWatcher,
+	) -> anyhow::Result<()> {
+		let listeners = inputs
+			.stores
+			.read_binds()
+			.listeners(bind_name.clone())
+			.ok_or(anyhow!("bind not found"))?;
+		let listener = listeners
+			.get_exactly_one()
+			.map_err(|_| anyhow!("SOCKS5 binds must have exactly one listener"))?;
+
+		let mut first = [0u8; 8];
+		let n = tokio::time::timeout(socks5::HANDSHAKE_TIMEOUT, stream.peek(&mut first))
+			.await
+			.map_err(|_| anyhow!("forward proxy client sent nothing"))??;
+		let Some(handshake) = Handshake::detect(&first[..n]) else {
+			// A regular HTTP request, such as an absolute-form request from a client using HTTP_PROXY. Each request
+			// is checked for proxy credentials by the HTTP proxy.
+			return Self::proxy(bind_name, inputs, Some(listener), stream, drain).await;
+		};
+		let ListenerProtocol::SOCKS5(_, forward_proxy) = &listener.protocol else {
+			anyhow::bail!("SOCKS5 binds must have a SOCKS5 listener");
+		};
+		let dest = handshake
+			.read_destination(&mut stream, forward_proxy)
+			.await?;
+		stream.insert_ext(socks5::Authenticated);
+		let intercept = HostnameMatch::all_matches(&dest.host)
+			.any(|m| listener.routes.get_hostname(&m).next().is_some());
+		event!(
+			target: "downstream connection",
+			parent: None,
+			tracing::Level::DEBUG,
+
+			src.addr = %stream.tcp().peer_addr,
+			destination = %dest,
+			handshake = ?handshake,
+			intercept,
+
+			"forward proxy",
+		);
+
+		if !intercept {
+			let mut log: DropOnLog = RequestLog::new(
+				log::CelLogging::new(inputs.cfg.logging.clone(), inputs.cfg.tracing.clone()),
+				inputs.metrics.clone(),
+				stream.tcp().start,
+				stream.tcp().clone(),
+			)
+			.into();
+			log.with(|l| {
+				l.bind_name = Some(bind_name.clone());
+				l.gateway_name = Some(listener.gateway_name.clone());
+				l.listener_name = Some(listener.name.clone());
+				l.host = Some(dest.to_string());
+				l.config_version = inputs.stores.version.hash();
+			});
+			let res = Self::tunnel(
+				forward_proxy,
+				&inputs.upstream,
+				handshake,
+				stream,
+				&dest,
+				log.as_mut().expect("log is set"),
+			)
+			.await;
+			if let Err(e) = &res {
+				log.with(|l| {
+					l.error = Some(e.to_string());
+					l.error_type.store(Some(e.error_type()));
+				});
+			}
+			return Ok(());
 		}
+
+		handshake.reply(&mut stream, Reply::Success).await?;
+		let mut first = [0u8; 1];
+		let n = tokio::time::timeout(socks5::HANDSHAKE_TIMEOUT, stream.peek(&mut first))
+			.await
+			.map_err(|_| anyhow!("forward proxy client sent nothing to {dest}"))??;
+		let stream = if n == 1 && first[0] == socks5::TLS_HANDSHAKE {
+			let Some(cfg) = listener.protocol.tls() else {
+				anyhow::bail!(
+					"cannot apply routes to TLS traffic for {dest}: the listener has no 'tls' set"
+				);
+			};
+			tokio::time::timeout(
+				socks5::HANDSHAKE_TIMEOUT,
+				crate::transport::tls::accept(stream, cfg),
+			)
+			.await
+			.map_err(|_| anyhow!("TLS handshake for {dest} timed out"))??
+		} else {
+			stream
+		};
+		Self::proxy(bind_name, inputs, Some(listener), stream, drain).await
+	}
+
+	/// tunnel connects the client to a destination that did not match a route.
+	async fn tunnel(
+		forward_proxy: &socks5::ForwardProxy,
+		client: &client::Client,
+		handshake: Handshake,
+		mut stream: Socket,
+		dest: &Destination,
+		log: &mut RequestLog,
+	) -> Result<(), ProxyError> {
+		let io_error = |e: std::io::Error| ProxyError::Processing(e.into());
+		let addr = match forward_proxy.resolve(dest, client).await {
+			Ok(addr) => addr,
+			Err(e) => {
+				let reply = match e {
+					ProxyError::AuthorizationFailed => Reply::NotAllowed,
+					_ => Reply::Unreachable,
+				};
+				handshake
+					.reply(&mut stream, reply)
+					.await
+					.map_err(io_error)?;
+				return Err(e);
+			},
+		};
+		log.endpoint = Some(Target::Address(addr));
+		let upstream = match Socket::dial(addr).await {
+			Ok(upstream) => upstream,
+			Err(e) => {
+				handshake
+					.reply(&mut stream, Reply::Unreachable)
+					.await
+					.map_err(io_error)?;
+				return Err(ProxyError::Processing(e));
+			},
+		};
+		handshake
+			.reply(&mut stream, Reply::Success)
+			.await
+			.map_err(io_error)?;
+		agent_core::copy::copy_bidirectional(
+			stream,
+			upstream,
+			&agent_core::copy::ConnectionResult::default(),
+		)
+		.await
+		.map_err(|e| ProxyError::Processing(e.into()))?;
+		Ok(())
 	}
 
 	async fn proxy(
@@ -453,6 +638,12 @@ fn bind_protocol(inp: Arc<ProxyInputs>, bind: BindName) -> BindProtocol {
 	{
 		return BindProtocol::hbone;
 	}
+	if listeners
+		.iter()
+		.any(|l| matches!(l.protocol, ListenerProtocol::SOCKS5(..)))
+	{
+		return BindProtocol::socks5;
+	}
 	if listeners
 		.iter()
 		.any(|l| matches!(l.protocol, ListenerProtocol::HTTPS(_)))
@@ -16,18 +16,23 @@ use types::agent::*;
 use types::discovery::*;
 
 use crate::client::Transport;
+use crate::http::backendoverride::{BackendOverride, Override};
 use crate::http::backendtls::BackendTLS;
+use crate::http::bandit::BanditOutcome;
+use crate::http::failover::FailoverOutcome;
 use crate::http::transformation_cel::Transformation;
+use crate::http::upstreamerrors::UpstreamErrors;
 use crate::http::{
 	Authority, HeaderName, HeaderValue, PolicyResponse, Request, Response, Scheme, StatusCode, Uri,
-	auth, filters, get_host, merge_in_headers, retry,
+	auth, filters, get_host, idempotency, merge_in_headers, retry,
 };
 use crate::llm::{LLMRequest, RequestResult};
-use crate::proxy::{ProxyError, ProxyResponse, resolve_simple_backend};
-use crate::store::{BackendPolicies, LLMRequestPolicies, LLMResponsePolicies};
+use crate::proxy::{ProxyError, ProxyResponse, resolve_simple_backend, socks5};
+use crate::store::{BackendPolicies, LLMRequestPolicies, LLMResponsePolicies, RequestPolicy};
+use crate::telemetry::errors::ErrorType;
 use crate::telemetry::log;
 use crate::telemetry::log::{AsyncLog, DropOnLog, LogBody, RequestLog};
-use crate::telemetry::metrics::TCPLabels;
+use crate::telemetry::metrics::{IdempotencyLabels, TCPLabels};
 use crate::telemetry::trc::TraceParent;
 use crate::transport::stream::{Extension, TCPConnectionInfo, TLSConnectionInfo};
 use crate::{ProxyInputs, store, *};
@@ -47,46 +52,150 @@ async fn apply_request_policies(
 	req: &mut Request,
 	response_policies: &mut ResponsePolicies,
 ) -> Result<(), ProxyResponse> {
-	if let Some(j) = &policies.jwt {
-		j.apply(log, req)
-			.await
-			.map_err(|e| ProxyResponse::from(ProxyError::JwtAuthenticationFailure(e)))?;
-	}
-	if let Some(x) = &policies.ext_authz {
-		x.check(client.clone(), req).await?
-	} else {
-		http::PolicyResponse::default()
-	}
-	.apply(response_policies.headers())?;
-
-	let exec = log
-		.cel
-		.ctx()
-		.build()
-		.map_err(|_| ProxyError::ProcessingString("failed to build cel context".to_string()))?;
-
-	if let Some(j) = &policies.authorization {
-		j.apply(&exec)
-			.map_err(|_| ProxyResponse::from(ProxyError::AuthorizationFailed))?;
+	// Built when the first policy that evaluates expressions is reached, after the variables are set.
+	let mut exec = None;
+	for p in policies.request_policies.iter() {
+		match p {
+			RequestPolicy::LoopDetection => {
+				if let Some(ld) = &policies.loop_detection {
+					match ld.apply(req.headers_mut()) {
+						Ok(path) => log.gateway_hops = Some(path),
+						Err(path) => {
+							debug!(%path, "rejecting request that looped between gateways");
+							log.gateway_hops = Some(path);
+							return Err(ProxyError::LoopDetected.into());
+						},
+					}
+				}
+			},
+			RequestPolicy::SecurityHeaders => {
+				if let Some(sh) = &policies.security_headers {
+					sh.apply(req)
+						.map_err(ProxyError::from)?
+						.apply(response_policies.headers())?;
+				}
+			},
+			RequestPolicy::Jwt => {
+				if let Some(j) = &policies.jwt {
+					j.apply(log, req)
+						.await
+						.map_err(|e| ProxyResponse::from(ProxyError::JwtAuthenticationFailure(e)))?;
+				}
+			},
+			RequestPolicy::ExtAuthz => {
+				if let Some(x) = &policies.ext_authz {
+					x.check(client.clone(), req)
+						.await?
+						.apply(response_policies.headers())?;
+				}
+			},
+			RequestPolicy::Variables => {
+				if let Some(vars) = &policies.variables {
+					vars.apply(log.cel.ctx()).map_err(|_| {
+						ProxyError::ProcessingString("failed to evaluate variables".to_string())
+					})?;
+				}
+			},
+			RequestPolicy::Authorization => {
+				if let Some(j) = &policies.authorization {
+					let exec = executor(&mut exec, log)?;
+					j.apply(exec)
+						.map_err(|_| ProxyResponse::from(ProxyError::AuthorizationFailed))?;
+					if j.monitored_denies(exec) {
+						log.record_monitored("authorization");
+					}
+				}
+			},
+			RequestPolicy::LocalRateLimit => {
+				for lrl in &policies.local_rate_limit {
+					lrl
+						.mode
+						.enforce("localRateLimit", Some(&mut *log), lrl.check_request())?;
+				}
+			},
+			RequestPolicy::RemoteRateLimit => {
+				if let Some(rrl) = &policies.remote_rate_limit {
+					let exec = executor(&mut exec, log)?;
+					let resp = rrl.check(client.clone(), req, exec).await?;
+					rrl
+						.mode
+						.enforce_response("remoteRateLimit", Some(&mut *log), resp)
+						.apply(response_policies.headers())?;
+				}
+			},
+			RequestPolicy::Transformation => {
+				if let Some(j) = &policies.transformation {
+					let exec = executor(&mut exec, log)?;
+					j.apply_request(req, exec)
+						.map_err(|_| ProxyError::TransformationFailure)?;
+				}
+			},
+			RequestPolicy::PoolPartition => {
+				if let Some(pp) = &policies.pool_partition {
+					let route = log.route_name.clone();
+					pp.apply(req, executor(&mut exec, log)?, route);
+				}
+			},
+			RequestPolicy::HeaderSizeLimit => {
+				if let Some(hsl) = &policies.header_size_limit {
+					let removed = hsl.apply(req.headers_mut());
+					if !removed.is_empty() {
+						debug!(headers=?removed, "removed oversized request headers");
+					}
+				}
+			},
+		}
 	}
 
-	for lrl in &policies.local_rate_limit {
-		lrl.check_request()?;
-	}
+	Ok(())
+}
 
-	if let Some(rrl) = &policies.remote_rate_limit {
-		rrl.check(client, req, &exec).await?
-	} else {
-		http::PolicyResponse::default()
+/// executor returns the CEL executor for the request, building it on first use.
+fn executor<'a>(
+	exec: &'a mut Option<cel::Executor<'static>>,
+	log: &mut RequestLog,
+) -> Result<&'a cel::Executor<'static>, ProxyError> {
+	if exec.is_none() {
+		*exec = Some(
+			log
+				.cel
+				.ctx()
+				.build()
+				.map_err(|_| ProxyError::ProcessingString("failed to build cel context".to_string()))?,
+		);
 	}
-	.apply(response_policies.headers())?;
+	Ok(exec.as_ref().expect("executor was just built"))
+}
 
-	if let Some(j) = &policies.transformation {
-		j.apply_request(req, &exec)
-			.map_err(|_| ProxyError::TransformationFailure)?;
+/// apply_backend_override returns the backend the request selected with the override header, if it is allowed to.
+/// Every use of the header is logged, as it bypasses the route's backends.
+fn apply_backend_override(
+	bo: &BackendOverride,
+	log: &mut RequestLog,
+	req: &mut Request,
+) -> Result<Option<RouteBackendReference>, ProxyError> {
+	let Some(name) = bo.requested(req) else {
+		return Ok(None);
+	};
+	let mut exec = None;
+	let exec = executor(&mut exec, log)?;
+	let route = log.route_name.as_ref();
+	let sub = log.jwt_sub.as_ref();
+	match bo.select(&name, exec) {
+		Override::Allowed(backend) => {
+			warn!(route=?route, jwt.sub=?sub, backend=%name, target=%backend.backend.name(), "request overrode backend");
+			log.backend_override = Some(name);
+			Ok(Some(backend.clone()))
+		},
+		Override::Untrusted => {
+			warn!(route=?route, jwt.sub=?sub, backend=%name, "ignoring backend override from untrusted request");
+			Ok(None)
+		},
+		Override::NotAllowed => {
+			warn!(route=?route, jwt.sub=?sub, backend=%name, "rejecting backend override to backend that is not allowed");
+			Err(ProxyError::BackendOverrideNotAllowed(name))
+		},
 	}
-
-	Ok(())
 }
 
 async fn apply_llm_request_policies(
@@ -98,7 +207,11 @@ async fn apply_llm_request_policies(
 	response_headers: &mut HeaderMap,
 ) -> Result<store::LLMResponsePolicies, ProxyResponse> {
 	for lrl in &policies.local_rate_limit {
-		lrl.check_llm_request(llm_req)?;
+		lrl.mode.enforce(
+			"localRateLimit",
+			log.as_deref_mut(),
+			lrl.check_llm_request(llm_req),
+		)?;
 	}
 	let (rl_resp, response) = if let Some(rrl) = &policies.remote_rate_limit
 		&& let Some(log) = log
@@ -111,9 +224,15 @@ async fn apply_llm_request_policies(
 		// For the LLM request side, request either the count of the input tokens (if tokenization was done)
 		// or 0.
 		// Either way, we will 'true up' on the response side.
-		rrl
+		let (resp, amend) = rrl
 			.check_llm(client, req, &exec, llm_req.input_tokens.unwrap_or_default())
-			.await?
+			.await?;
+		(
+			rrl
+				.mode
+				.enforce_response("remoteRateLimit", Some(&mut **log), resp),
+			amend,
+		)
 	} else {
 		(http::PolicyResponse::default(), None)
 	};
@@ -249,6 +368,7 @@ impl HTTPProxy {
 		mut req: ::http::Request<Incoming>,
 	) -> Response {
 		let start = Instant::now();
+		let version = req.version();
 
 		// Copy connection level attributes into request level attributes
 		connection.copy::<TCPConnectionInfo>(req.extensions_mut());
@@ -267,29 +387,43 @@ impl HTTPProxy {
 			tcp.clone(),
 		)
 		.into();
+		let config_version = self.inputs.stores.version.hash();
+		log.with(|l| l.config_version = config_version.clone());
 		let ret = self
 			.proxy_internal(connection, req, log.as_mut().unwrap())
 			.await;
 
 		log.with(|l| {
-			l.error = ret.as_ref().err().and_then(|e| {
-				if let ProxyResponse::Error(e) = e {
-					Some(e.to_string())
-				} else {
-					None
-				}
-			})
+			if let Err(ProxyResponse::Error(e)) = &ret {
+				l.error = Some(e.to_string());
+				l.error_type.store(Some(e.error_type()));
+			}
 		});
-		let resp = ret.unwrap_or_else(|err| match err {
+		let mut resp = ret.unwrap_or_else(|err| match err {
 			ProxyResponse::Error(e) => e.into_response(),
 			ProxyResponse::DirectResponse(dr) => *dr,
 		});
+		self
+			.inputs
+			.cfg
+			.xds
+			.unreachable
+			.apply_stale_header(&self.inputs.stores.control_plane, &mut resp);
+		if let Some(name) = &self.inputs.cfg.config_version_header
+			&& let Some(v) = config_version.and_then(|v| HeaderValue::from_str(&v).ok())
+		{
+			resp.headers_mut().insert(name.clone(), v);
+		}
+		if version == ::http::Version::HTTP_10 {
+			http10_response(&mut resp);
+		}
 
 		// Pass the log into the body so it finishes once the stream is entirely complete.
 		// We will also record trailer info there.
 		log.with(|l| {
 			l.status = Some(resp.status());
-			l.cel.ctx().with_response(&resp)
+			l.cel.ctx().with_response(&resp);
+			l.capture_response_body(&mut resp);
 		});
 
 		resp.map(move |b| http::Body::new(LogBody::new(b, log)))
@@ -305,6 +439,24 @@ impl HTTPProxy {
 			.cel
 			.ctx()
 			.with_source(&log.tcp_info, log.tls_info.as_ref());
+		if self
+			.inputs
+			.cfg
+			.xds
+			.unreachable
+			.rejects(&self.inputs.stores.control_plane)
+		{
+			return Err(ProxyError::ControlPlaneUnreachable.into());
+		}
+		// Forward proxy clients that did not authenticate in a SOCKS5 or CONNECT handshake must send credentials
+		// with every request.
+		if let Some(l) = &self.selected_listener
+			&& let ListenerProtocol::SOCKS5(_, forward_proxy) = &l.protocol
+			&& connection.get::<socks5::Authenticated>().is_none()
+			&& !forward_proxy.authorize(req.headers())
+		{
+			return Err(ProxyError::ProxyAuthenticationRequired.into());
+		}
 		let selected_listener = self.selected_listener.clone();
 		let inputs = self.inputs.clone();
 		let bind_name = self.bind_name.clone();
@@ -335,6 +487,10 @@ impl HTTPProxy {
 		let mut req = req.map(http::Body::new);
 
 		normalize_uri(&connection, &mut req).map_err(ProxyError::Processing)?;
+		// We always speak at least HTTP/1.1 to the upstream, regardless of the client's version.
+		if req.version() == ::http::Version::HTTP_10 {
+			*req.version_mut() = ::http::Version::HTTP_11;
+		}
 		sensitive_headers(&mut req);
 		let mut req_upgrade = hop_by_hop_headers(&mut req);
 
@@ -397,12 +553,10 @@ impl HTTPProxy {
 
 		debug!(bind=%bind_name, listener=%selected_listener.key, route=%selected_route.key, "selected route");
 
-		let route_policies = inputs.stores.read_binds().route_policies(
-			selected_route.rule_name.clone(),
-			selected_route.route_name.clone(),
-			selected_listener.key.clone(),
-			selected_listener.gateway_name.clone(),
-		);
+		let route_policies = inputs
+			.stores
+			.read_binds()
+			.route_policies(&selected_listener, &selected_route);
 		// Register all expressions
 		route_policies.register_cel_expressions(log.cel.ctx());
 		// This is unfortunate but we record the request twice possibly; we want to record it as early as possible
@@ -413,8 +567,12 @@ impl HTTPProxy {
 		if needs_body && let Ok(body) = crate::http::inspect_body(req.body_mut()).await {
 			log.cel.ctx().with_request_body(body);
 		}
+		log.capture_request_body(&mut req);
 
-		let mut response_policies = ResponsePolicies::from(route_policies.transformation.clone());
+		let mut response_policies = ResponsePolicies::from(
+			route_policies.transformation.clone(),
+			route_policies.upstream_errors.clone(),
+		);
 
 		apply_request_policies(
 			&route_policies,
@@ -425,6 +583,30 @@ impl HTTPProxy {
 		)
 		.await?;
 
+		let lookup = match route_policies.idempotency.as_ref() {
+			Some(i) => {
+				i.lookup(&mut req, &selected_route.key, &self.inputs.state)
+					.await?
+			},
+			None => idempotency::Lookup::Skip,
+		};
+		let idempotency = match lookup {
+			idempotency::Lookup::Skip => None,
+			idempotency::Lookup::Proceed(guard) => Some(guard),
+			idempotency::Lookup::Replay(resp) => {
+				self.record_idempotency_dedupe(log, "replayed");
+				return Ok(resp);
+			},
+			idempotency::Lookup::Conflict => {
+				self.record_idempotency_dedupe(log, "conflict");
+				return Err(ProxyError::IdempotencyConflict.into());
+			},
+			idempotency::Lookup::Mismatch => {
+				self.record_idempotency_dedupe(log, "mismatch");
+				return Err(ProxyError::IdempotencyKeyReused.into());
+			},
+		};
+
 		apply_request_filters(
 			selected_route.as_ref().filters.as_slice(),
 			&path_match,
@@ -433,8 +615,38 @@ impl HTTPProxy {
 		.map_err(ProxyError::from)?
 		.apply(response_policies.headers())?;
 
-		let selected_backend =
-			select_backend(selected_route.as_ref(), &req).ok_or(ProxyError::NoValidBackends)?;
+		let overridden = match route_policies.backend_override.as_ref() {
+			Some(bo) => apply_backend_override(bo, log, &mut req)?,
+			None => None,
+		};
+		let selected_backend = if let Some(backend) = overridden {
+			backend
+		} else if let Some(bandit) = route_policies.bandit.as_ref() {
+			let (backend, decision) = bandit
+				.select(&selected_route.backends)
+				.ok_or(ProxyError::NoValidBackends)?;
+			debug!(backend=%backend.backend.name(), decision=decision.as_str(), "bandit selected backend");
+			log.bandit = Some(BanditOutcome {
+				bandit: bandit.clone(),
+				backend: backend.backend.name(),
+				decision,
+				route: log.route_name.clone(),
+				client: self.inputs.upstream.clone(),
+			});
+			backend.clone()
+		} else if let Some(failover) = route_policies.failover.as_ref() {
+			let backend = failover
+				.select(&selected_route.key, &selected_route.backends)
+				.ok_or(ProxyError::NoValidBackends)?;
+			log.failover = Some(FailoverOutcome {
+				failover: failover.clone(),
+				route: selected_route.key.clone(),
+				priority: backend.priority,
+			});
+			backend.clone()
+		} else {
+			select_backend(selected_route.as_ref(), &req).ok_or(ProxyError::NoValidBackends)?
+		};
 		let selected_backend = resolve_backend(selected_backend, self.inputs.as_ref())?;
 
 		apply_request_filters(selected_backend.filters.as_slice(), &path_match, &mut req)
@@ -468,7 +680,7 @@ impl HTTPProxy {
 			Some(TrafficPolicy { retry, .. }) => retry,
 			_ => &None,
 		};
-		let late_route_policies: Arc<LLMRequestPolicies> = Arc::new(route_policies.into());
+		let late_route_policies: Arc<LLMRequestPolicies> = Arc::new(route_policies.as_ref().into());
 		// attempts is the total number of attempts, not the retries
 		let attempts = retries.as_ref().map(|r| r.attempts.get() + 1).unwrap_or(1);
 		let body = if attempts > 1 {
@@ -487,7 +699,7 @@ impl HTTPProxy {
 				trace!("no retries");
 				// no retries at all, just send the request as normal
 				let req = Request::from_parts(head, http::Body::new(body));
-				return self
+				let res = self
 					.attempt_upstream(
 						log,
 						&mut req_upgrade,
@@ -498,6 +710,7 @@ impl HTTPProxy {
 						req,
 					)
 					.await;
+				return idempotency::complete(idempotency, res);
 			},
 		};
 		let mut last_res: Option<Result<Response, ProxyResponse>> = None;
@@ -508,7 +721,8 @@ impl HTTPProxy {
 			if matches!(this.is_capped(), None | Some(true)) {
 				// This could be either too much buffered, or it could mean we got a response before we read the request body.
 				debug!("buffered too much to attempt a retry");
-				return last_res.expect("should only be capped if we had a previous attempt");
+				let res = last_res.expect("should only be capped if we had a previous attempt");
+				return idempotency::complete(idempotency, res);
 			}
 			if !last {
 				// Stop cloning on our last
@@ -539,7 +753,7 @@ impl HTTPProxy {
 				if !last {
 					debug!("response not retry-able");
 				}
-				return res;
+				return idempotency::complete(idempotency, res);
 			}
 			debug!(
 				"attempting another retry, last result was {} {:?}",
@@ -573,14 +787,20 @@ impl HTTPProxy {
 		)
 		.await?;
 
-		let timeout = match &selected_route.policies {
-			Some(TrafficPolicy { timeout, .. }) => timeout.effective_timeout(),
-			_ => None,
-		};
+		let backend_name = selected_backend.backend.name();
 
 		// Setup timeout
-		let call_result = if let Some(timeout) = timeout {
-			let deadline = tokio::time::Instant::from_std(log.start + timeout);
+		let upstream_start = Instant::now();
+		log.upstream_start = Some(upstream_start);
+		let (deadline, adaptive) = match &selected_route.policies {
+			Some(TrafficPolicy { timeout, .. }) => (
+				timeout.deadline(&backend_name, log.start, upstream_start),
+				timeout.adaptive.as_ref(),
+			),
+			_ => (None, None),
+		};
+		let call_result = if let Some(deadline) = deadline {
+			let deadline = tokio::time::Instant::from_std(deadline);
 			let fut = tokio::time::timeout_at(deadline, call);
 			fut.await
 		} else {
@@ -589,24 +809,34 @@ impl HTTPProxy {
 
 		// Run the actual call
 		let mut resp = match call_result {
-			Ok(Ok(resp)) => resp,
+			Ok(Ok(resp)) => {
+				if let Some(adaptive) = adaptive {
+					adaptive.record(&backend_name, upstream_start.elapsed());
+				}
+				resp
+			},
 			Ok(Err(e)) => {
 				return Err(e.into());
 			},
 			Err(_) => {
+				if let Some(adaptive) = adaptive {
+					// Record the time the backend was given, so the timeout can grow if the backend has become slower.
+					adaptive.record(&backend_name, upstream_start.elapsed());
+				}
 				return Err(ProxyError::RequestTimeout.into());
 			},
 		};
 		if resp.status() == StatusCode::SWITCHING_PROTOCOLS {
 			return handle_upgrade(req_upgrade, resp).await.map_err(Into::into);
 		}
+		strip_hop_by_hop(resp.headers_mut());
 
 		// Handle response filters
 		apply_response_filters(selected_route.filters.as_slice(), &mut resp)
 			.map_err(ProxyError::from)?;
 		apply_response_filters(selected_backend.filters.as_slice(), &mut resp)
 			.map_err(ProxyError::from)?;
-		response_policies.apply(&mut resp, log)?;
+		response_policies.apply(&mut resp, log).await?;
 
 		// for now we do not have any body timeout. Maybe we should add it
 		// let resp = body_timeout.apply(resp);
@@ -617,6 +847,18 @@ impl HTTPProxy {
 		Ok(resp)
 	}
 
+	fn record_idempotency_dedupe(&self, log: &RequestLog, result: &'static str) {
+		self
+			.inputs
+			.metrics
+			.idempotency_dedupes
+			.get_or_create(&IdempotencyLabels {
+				route: (&log.route_name).into(),
+				result: strng::new(result).into(),
+			})
+			.inc();
+	}
+
 	fn policy_client(&self) -> PolicyClient {
 		PolicyClient {
 			inputs: self.inputs.clone(),
@@ -668,7 +910,7 @@ async fn handle_upgrade(
 		let _ = agent_core::copy::copy_bidirectional(
 			&mut TokioIo::new(req),
 			&mut TokioIo::new(response_upgraded),
-			&agent_core::copy::ConnectionResult {},
+			&agent_core::copy::ConnectionResult::default(),
 		)
 		.await;
 	});
@@ -888,6 +1130,22 @@ async fn make_backend_call(
 	// Some auth types (AWS) need to be applied after all request processing
 	auth::apply_late_backend_auth(policies.backend_auth.as_ref(), &mut req).await?;
 	let transport = build_transport(&inputs, &backend_call, policies.backend_tls.clone()).await?;
+	if let a2a::RequestType::Call(method) = &a2a_type
+		&& let Some(cards) = policies.a2a.as_ref().and_then(|p| p.agent_card.as_ref())
+	{
+		let fetch = a2a::CardFetch {
+			client: inputs.upstream.clone(),
+			target: backend_call.target.clone(),
+			transport: transport.clone(),
+			backend_auth: policies.backend_auth.clone(),
+			uri: req.uri().clone(),
+			version: req.version(),
+		};
+		if let Some(resp) = cards.check(method, &mut req, fetch).await {
+			log.add(|l| l.error_type.store(Some(ErrorType::Unsupported)));
+			return Ok(Box::pin(async move { Ok(resp) }));
+		}
+	}
 	let call = client::Call {
 		req,
 		target: backend_call.target,
@@ -895,15 +1153,25 @@ async fn make_backend_call(
 	};
 	let upstream = inputs.upstream.clone();
 	let llm_response_log = log.as_ref().map(|l| l.llm_response.clone());
+	let error_type_log = log.as_ref().map(|l| l.error_type.clone());
 	let include_completion_in_log = log
 		.as_ref()
-		.map(|l| l.cel.cel_context.needs_llm_completion())
+		.map(|l| {
+			l.cel.cel_context.needs_llm_completion()
+				|| l.llm_sample.is_some()
+				|| l.bandit.as_ref().is_some_and(|b| b.needs_completion())
+		})
 		.unwrap_or_default();
 	Ok(Box::pin(async move {
 		let mut resp = upstream.call(call).await?;
-		a2a::apply_to_response(policies.a2a.as_ref(), a2a_type, &mut resp)
+		let a2a_error = a2a::apply_to_response(policies.a2a.as_ref(), a2a_type, &mut resp)
 			.await
 			.map_err(ProxyError::Processing)?;
+		if let Some(log) = &error_type_log
+			&& a2a_error.is_some()
+		{
+			log.store(a2a_error);
+		}
 		let mut resp =
 			if let (Some((llm, _, _)), Some(llm_request)) = (policies.llm_provider, llm_request) {
 				llm
@@ -953,12 +1221,14 @@ async fn send_mirror(
 	Ok(())
 }
 
-// Hop-by-hop headers. These are removed when sent to the backend.
+// Hop-by-hop headers. These are removed in both directions.
 // As of RFC 7230, hop-by-hop headers are required to appear in the
-// Connection header field. These are the headers defined by the
+// Connection header field; those are removed as well. These are the headers defined by the
 // obsoleted RFC 2616 (section 13.5.1) and are used for backward
-// compatibility.
-static HOP_HEADERS: [HeaderName; 9] = [
+// compatibility. Any other `Proxy-*` header is also treated as hop-by-hop.
+// Note: `Trailer` is not hop-by-hop (RFC 9110 §6.6.2); it must be kept so HTTP/1.1 upstreams accept request
+// trailers.
+static HOP_HEADERS: [HeaderName; 8] = [
 	header::CONNECTION,
 	// non-standard but still sent by libcurl and rejected by e.g. google
 	HeaderName::from_static("proxy-connection"),
@@ -966,11 +1236,44 @@ static HOP_HEADERS: [HeaderName; 9] = [
 	header::PROXY_AUTHENTICATE,
 	header::PROXY_AUTHORIZATION,
 	header::TE,
-	header::TRAILER,
 	header::TRANSFER_ENCODING,
 	header::UPGRADE,
 ];
 
+/// HTTP/1.0 connections are only kept alive if the client asks for it, and the response agrees with a
+/// `Connection: keep-alive` header. The server negotiates this from the client's request, so make sure the
+/// upstream response does not interfere with it.
+fn http10_response(resp: &mut Response) {
+	resp.headers_mut().remove(header::CONNECTION);
+	resp
+		.headers_mut()
+		.remove(HeaderName::from_static("keep-alive"));
+	*resp.version_mut() = ::http::Version::HTTP_11;
+}
+
+/// strip_hop_by_hop removes the hop-by-hop headers, including any listed in the `Connection` header
+/// (RFC 7230 section 6.1).
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+	let listed = headers
+		.get_all(header::CONNECTION)
+		.iter()
+		.filter_map(|v| v.to_str().ok())
+		.flat_map(|v| v.split(','))
+		.filter_map(|t| HeaderName::from_bytes(t.trim().as_bytes()).ok())
+		.collect_vec();
+	for h in HOP_HEADERS.iter().chain(listed.iter()) {
+		headers.remove(h);
+	}
+	let proxy = headers
+		.keys()
+		.filter(|k| k.as_str().starts_with("proxy-"))
+		.cloned()
+		.collect_vec();
+	for h in proxy {
+		headers.remove(h);
+	}
+}
+
 struct RequestUpgrade {
 	upgade_type: HeaderValue,
 	upgrade: OnUpgrade,
@@ -984,9 +1287,7 @@ fn hop_by_hop_headers(req: &mut Request) -> Option<RequestUpgrade> {
 		.map(|s| s.contains("trailers"))
 		.unwrap_or(false);
 	let upgrade_type = upgrade_type(req.headers());
-	for h in HOP_HEADERS.iter() {
-		req.headers_mut().remove(h);
-	}
+	strip_hop_by_hop(req.headers_mut());
 	// If the incoming request supports trailers, the downstream one will as well
 	if trailers {
 		req.headers_mut().typed_insert(headers::Te::trailers());
@@ -1036,18 +1337,33 @@ fn sensitive_headers(req: &mut Request) {
 // the rest of the code doesn't need to worry about it
 fn normalize_uri(connection: &Extension, req: &mut Request) -> anyhow::Result<()> {
 	debug!("request before normalization: {req:?}");
-	if let ::http::Version::HTTP_10 | ::http::Version::HTTP_11 = req.version()
-		&& req.uri().authority().is_none()
-	{
+	let version = req.version();
+	if !matches!(version, ::http::Version::HTTP_10 | ::http::Version::HTTP_11) {
+		return Ok(());
+	}
+	let mut hosts = req.headers().get_all(http::header::HOST).iter();
+	let host = hosts.next().cloned();
+	if hosts.next().is_some() {
+		anyhow::bail!("multiple host headers");
+	}
+	req.headers_mut().remove(http::header::HOST);
+	// For absolute-form requests (`GET http://example.com/ HTTP/1.1`), the authority is already set. In this case,
+	// the Host header must be ignored in favor of the request target (RFC 9112 section 3.2.2).
+	if req.uri().authority().is_none() {
 		let mut parts = std::mem::take(req.uri_mut()).into_parts();
-		// TODO: handle absolute HTTP/1.1 form
-		let host = req
-			.headers()
-			.get(http::header::HOST)
-			.and_then(|h| h.to_str().ok())
-			.and_then(|h| h.parse::<Authority>().ok())
-			.ok_or_else(|| anyhow::anyhow!("no authority or host"))?;
-		req.headers_mut().remove(http::header::HOST);
+		let host = match host {
+			Some(host) => host
+				.to_str()
+				.ok()
+				.and_then(|h| h.parse::<Authority>().ok())
+				.ok_or_else(|| anyhow::anyhow!("invalid host"))?,
+			// HTTP/1.0 clients are not required to send a Host, so use the address they connected to.
+			None if version == ::http::Version::HTTP_10 => connection
+				.get::<TCPConnectionInfo>()
+				.and_then(|tcp| tcp.local_addr.to_string().parse::<Authority>().ok())
+				.ok_or_else(|| anyhow::anyhow!("no authority or host"))?,
+			None => anyhow::bail!("no authority or host"),
+		};
 
 		parts.authority = Some(host);
 		if parts.path_and_query.is_some() {
@@ -1074,20 +1390,29 @@ struct BackendCall {
 #[derive(Debug, Default)]
 struct ResponsePolicies {
 	transformation: Option<Transformation>,
+	upstream_errors: Option<UpstreamErrors>,
 	response_headers: HeaderMap,
 }
 
 impl ResponsePolicies {
-	pub fn from(transformation: Option<Transformation>) -> ResponsePolicies {
+	pub fn from(
+		transformation: Option<Transformation>,
+		upstream_errors: Option<UpstreamErrors>,
+	) -> ResponsePolicies {
 		Self {
 			transformation,
+			upstream_errors,
 			response_headers: HeaderMap::new(),
 		}
 	}
 	pub fn headers(&mut self) -> &mut HeaderMap {
 		&mut self.response_headers
 	}
-	pub fn apply(&self, resp: &mut Response, log: &mut RequestLog) -> Result<(), ProxyError> {
+	pub async fn apply(&self, resp: &mut Response, log: &mut RequestLog) -> Result<(), ProxyError> {
+		// Applied first, so transformations cannot reintroduce details the upstream error body is stripped of.
+		if let Some(ue) = &self.upstream_errors {
+			ue.apply(resp, log.listener_name.as_ref()).await;
+		}
 		if let Some(j) = &self.transformation {
 			j.apply_response(resp, log.cel.ctx())
 				.map_err(|_| ProxyError::TransformationFailure)?;
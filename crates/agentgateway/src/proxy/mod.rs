@@ -2,12 +2,15 @@ mod gateway;
 pub mod httpproxy;
 #[cfg(test)]
 pub mod request_builder;
+pub mod socks5;
 pub mod tcpproxy;
 
-pub use gateway::Gateway;
+pub use gateway::{DownstreamConnection, Gateway};
 use hyper_util_fork::client::legacy::Error as HyperError;
 
 use crate::http::{HeaderValue, Response, StatusCode};
+use crate::llm::AIError;
+use crate::telemetry::errors::ErrorType;
 use crate::types::agent::{Backend, BackendReference, SimpleBackend, SimpleBackendReference};
 use crate::*;
 
@@ -60,6 +63,8 @@ pub enum ProxyError {
 	NoHealthyEndpoints,
 	#[error("authorization failed")]
 	AuthorizationFailed,
+	#[error("proxy authentication required")]
+	ProxyAuthenticationRequired,
 	#[error("backend authentication failed: {0}")]
 	BackendAuthenticationFailed(anyhow::Error),
 	#[error("upstream call failed: {0:?}")]
@@ -80,6 +85,16 @@ pub enum ProxyError {
 	RateLimitFailed,
 	#[error("invalid request")]
 	InvalidRequest,
+	#[error("a request with the same idempotency key is in progress")]
+	IdempotencyConflict,
+	#[error("the idempotency key was already used for a different request")]
+	IdempotencyKeyReused,
+	#[error("request loop detected")]
+	LoopDetected,
+	#[error("backend override {0} is not allowed")]
+	BackendOverrideNotAllowed(Strng),
+	#[error("control plane is unreachable")]
+	ControlPlaneUnreachable,
 	#[error("request upgrade failed, backend tried {1:?} but {0:?} was requested")]
 	UpgradeFailed(Option<HeaderValue>, Option<HeaderValue>),
 }
@@ -94,6 +109,44 @@ impl ProxyError {
 			_ => false,
 		}
 	}
+	pub fn error_type(&self) -> ErrorType {
+		match self {
+			ProxyError::BindNotFound => ErrorType::RouteNotFound,
+			ProxyError::ListenerNotFound => ErrorType::RouteNotFound,
+			ProxyError::RouteNotFound => ErrorType::RouteNotFound,
+			ProxyError::NoValidBackends => ErrorType::BackendNotFound,
+			ProxyError::BackendDoesNotExist => ErrorType::BackendNotFound,
+			ProxyError::BackendUnsupportedMirror => ErrorType::BackendNotFound,
+			ProxyError::ServiceNotFound => ErrorType::BackendNotFound,
+			ProxyError::InvalidBackendType => ErrorType::BackendNotFound,
+			ProxyError::DnsResolution => ErrorType::DnsResolution,
+			ProxyError::NoHealthyEndpoints => ErrorType::NoHealthyBackends,
+			ProxyError::JwtAuthenticationFailure(_) => ErrorType::Authentication,
+			ProxyError::AuthorizationFailed => ErrorType::Authorization,
+			ProxyError::ProxyAuthenticationRequired => ErrorType::Authentication,
+			ProxyError::BackendAuthenticationFailed(_) => ErrorType::BackendAuthentication,
+			ProxyError::UpstreamCallFailed(_) => ErrorType::UpstreamConnection,
+			ProxyError::UpgradeFailed(_, _) => ErrorType::UpgradeFailed,
+			ProxyError::RequestTimeout => ErrorType::Timeout,
+			ProxyError::RateLimitExceeded { .. } => ErrorType::RateLimited,
+			ProxyError::RateLimitFailed => ErrorType::RateLimited,
+			ProxyError::InvalidRequest => ErrorType::InvalidRequest,
+			ProxyError::IdempotencyConflict => ErrorType::IdempotencyConflict,
+			ProxyError::IdempotencyKeyReused => ErrorType::IdempotencyConflict,
+			ProxyError::LoopDetected => ErrorType::LoopDetected,
+			ProxyError::BackendOverrideNotAllowed(_) => ErrorType::InvalidRequest,
+			ProxyError::ControlPlaneUnreachable => ErrorType::ControlPlaneUnreachable,
+			// LLM errors are wrapped as processing errors, but have a more specific classification.
+			ProxyError::Processing(e) => e
+				.downcast_ref::<AIError>()
+				.map(AIError::error_type)
+				.unwrap_or(ErrorType::Processing),
+			ProxyError::ProcessingString(_) => ErrorType::Processing,
+			ProxyError::FilterError(_) => ErrorType::Processing,
+			ProxyError::TransformationFailure => ErrorType::Processing,
+		}
+	}
+
 	pub fn into_response(self) -> Response {
 		let code = match self {
 			ProxyError::BindNotFound => StatusCode::NOT_FOUND,
@@ -112,12 +165,18 @@ impl ProxyError {
 			// Should it be 4xx?
 			ProxyError::FilterError(_) => StatusCode::INTERNAL_SERVER_ERROR,
 			ProxyError::InvalidRequest => StatusCode::BAD_REQUEST,
+			ProxyError::IdempotencyConflict => StatusCode::CONFLICT,
+			ProxyError::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+			ProxyError::LoopDetected => StatusCode::LOOP_DETECTED,
+			ProxyError::BackendOverrideNotAllowed(_) => StatusCode::BAD_REQUEST,
 
 			ProxyError::JwtAuthenticationFailure(_) => StatusCode::FORBIDDEN,
 			ProxyError::AuthorizationFailed => StatusCode::FORBIDDEN,
+			ProxyError::ProxyAuthenticationRequired => StatusCode::PROXY_AUTHENTICATION_REQUIRED,
 
 			ProxyError::DnsResolution => StatusCode::SERVICE_UNAVAILABLE,
 			ProxyError::NoHealthyEndpoints => StatusCode::SERVICE_UNAVAILABLE,
+			ProxyError::ControlPlaneUnreachable => StatusCode::SERVICE_UNAVAILABLE,
 			ProxyError::UpstreamCallFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
 
 			ProxyError::RequestTimeout => StatusCode::GATEWAY_TIMEOUT,
@@ -132,6 +191,12 @@ impl ProxyError {
 			.header(hyper::header::CONTENT_TYPE, "text/plain");
 
 		// Apply per-error headers
+		if let ProxyError::ProxyAuthenticationRequired = self {
+			rb = rb.header(
+				hyper::header::PROXY_AUTHENTICATE,
+				r#"Basic realm="agentgateway""#,
+			);
+		}
 		if let ProxyError::RateLimitExceeded {
 			limit,
 			remaining,
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use agent_core::copy::{ConnectionResult, TcpStreamSplitter, copy_bidirectional};
 use itertools::Itertools;
 use rand::prelude::IndexedRandom;
 
@@ -43,9 +44,13 @@ impl TCPProxy {
 			tcp.clone(),
 		)
 		.into();
+		log.with(|l| l.config_version = self.inputs.stores.version.hash());
 		let ret = self.proxy_internal(connection, log.as_mut().unwrap()).await;
 		if let Err(e) = ret {
-			log.with(|l| l.error = Some(e.to_string()));
+			log.with(|l| {
+				l.error = Some(e.to_string());
+				l.error_type.store(Some(e.error_type()));
+			});
 		}
 	}
 
@@ -84,6 +89,14 @@ impl TCPProxy {
 		log.gateway_name = Some(selected_listener.gateway_name.clone());
 		log.listener_name = Some(selected_listener.name.clone());
 		debug!(bind=%bind_name, listener=%selected_listener.key, "selected listener");
+		if inputs
+			.cfg
+			.xds
+			.unreachable
+			.rejects(&inputs.stores.control_plane)
+		{
+			return Err(ProxyError::ControlPlaneUnreachable);
+		}
 
 		let selected_route =
 			select_best_route(sni, selected_listener.clone()).ok_or(ProxyError::RouteNotFound)?;
@@ -134,12 +147,25 @@ impl TCPProxy {
 		let upstream = stream::Socket::dial(addr)
 			.await
 			.map_err(ProxyError::Processing)?;
-		agent_core::copy::copy_bidirectional(
-			connection,
-			upstream,
-			&agent_core::copy::ConnectionResult {},
-		)
-		.await
+		let stats = ConnectionResult::default();
+		if connection.is_tcp() && upstream.is_tcp() {
+			// Neither side terminates TLS, so we can skip the Socket wrappers and split the streams without locking.
+			// The wrappers no longer see the traffic, so their byte counts are recorded once the copy completes.
+			let (downstream, downstream_metrics) = connection.into_tcp().expect("checked tcp");
+			let (upstream, upstream_metrics) = upstream.into_tcp().expect("checked tcp");
+			let res = copy_bidirectional(
+				TcpStreamSplitter(downstream),
+				TcpStreamSplitter(upstream),
+				&stats,
+			)
+			.await;
+			let (sent, received) = stats.load();
+			downstream_metrics.record(sent, received);
+			upstream_metrics.record(received, sent);
+			res
+		} else {
+			copy_bidirectional(connection, upstream, &stats).await
+		}
 		.map_err(|e| ProxyError::Processing(e.into()))?;
 		Ok(())
 	}
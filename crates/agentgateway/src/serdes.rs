@@ -1,6 +1,7 @@
 use std::fmt::{Debug, Display};
 use std::io;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Context;
 #[cfg(feature = "schema")]
@@ -33,6 +34,23 @@ pub mod yamlviajson {
 		Ok(serde_json_path_to_error::from_slice(&buf)?)
 	}
 
+	/// from_str_documents parses each document of a multi-document YAML stream.
+	pub fn from_str_documents<T>(s: &str) -> anyhow::Result<Vec<T>>
+	where
+		T: for<'de> de::Deserialize<'de>,
+	{
+		serde_yaml::Deserializer::from_str(s)
+			.map(|de_yaml| {
+				let mut buf = Vec::with_capacity(128);
+				{
+					let mut se_json = serde_json::Serializer::new(&mut buf);
+					serde_transcode::transcode(de_yaml, &mut se_json)?;
+				}
+				Ok(serde_json_path_to_error::from_slice(&buf)?)
+			})
+			.collect()
+	}
+
 	pub fn to_string<T>(value: &T) -> anyhow::Result<String>
 	where
 		T: ?Sized + ser::Serialize,
@@ -226,6 +244,16 @@ where
 	}
 }
 
+pub fn de_expression<'de, D>(deserializer: D) -> Result<Arc<crate::cel::Expression>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let s = String::deserialize(deserializer)?;
+	crate::cel::Expression::new(s)
+		.map(Arc::new)
+		.map_err(|e| serde::de::Error::custom(e.to_string()))
+}
+
 #[derive(Debug, Clone, serde::Deserialize)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(untagged)]
@@ -1,10 +1,12 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use agent_core::prelude::*;
+use agent_core::readiness;
 use notify::{EventKind, RecursiveMode};
 
 use crate::client::Client;
+use crate::control::unreachable;
 use crate::store::Stores;
 use crate::types::proto::agent::Resource as ADPResource;
 use crate::types::proto::workload::Address as XdsAddress;
@@ -32,21 +34,48 @@ impl StateManager {
 		client: client::Client,
 		xds_metrics: agent_xds::Metrics,
 		awaiting_ready: tokio::sync::watch::Sender<()>,
+		ready: readiness::Ready,
 	) -> anyhow::Result<Self> {
 		let stores = Stores::new();
 
 		let xds_client = if config.address.is_some() {
-			Some(
-				agent_xds::Config::new(
-					config.address.as_ref().unwrap().clone(),
-					config.gateway.clone(),
-					config.namespace.clone(),
-				)
-				.with_watched_handler::<XdsAddress>(ADDRESS_TYPE, stores.clone().discovery.clone())
-				.with_watched_handler::<ADPResource>(ADP_TYPE, stores.clone().binds.clone())
-				// .with_watched_handler::<XdsAuthorization>(AUTHORIZATION_TYPE, state)
-				.build(xds_metrics, awaiting_ready),
+			// Startup waits for the initial XDS response, or for the fallback configuration to be loaded if the
+			// control plane is unreachable.
+			let (xds_ready, mut xds_ready_rx) = tokio::sync::watch::channel(());
+			let (fallback_tx, fallback_rx) = tokio::sync::oneshot::channel();
+			tokio::spawn(async move {
+				tokio::select! {
+					_ = xds_ready_rx.changed() => {},
+					Ok(()) = fallback_rx => {},
+				}
+				drop(awaiting_ready);
+			});
+			let xds = agent_xds::Config::new(
+				config.address.as_ref().unwrap().clone(),
+				config.gateway.clone(),
+				config.namespace.clone(),
 			)
+			.with_watched_handler::<XdsAddress>(ADDRESS_TYPE, stores.clone().discovery.clone())
+			.with_watched_handler::<ADPResource>(ADP_TYPE, stores.clone().binds.clone())
+			// .with_watched_handler::<XdsAuthorization>(AUTHORIZATION_TYPE, state)
+			.build(xds_metrics, xds_ready);
+			let monitor = unreachable::Monitor {
+				cfg: config.unreachable.clone(),
+				status: stores.control_plane.clone(),
+				ready,
+				fallback: config
+					.unreachable
+					.fallback_config
+					.clone()
+					.map(|path| LocalClient {
+						cfg: ConfigSource::File(path),
+						stores: stores.clone(),
+						client: client.clone(),
+					}),
+				on_fallback: Some(fallback_tx),
+			};
+			tokio::spawn(monitor.run(xds.status()));
+			Some(xds)
 		} else {
 			None
 		};
@@ -115,6 +144,9 @@ impl LocalClient {
 
 		let lc: LocalClient = self.to_owned();
 		let mut next_state = lc.reload_config(PreviousState::default()).await?;
+		// Files referenced by the config, such as trust bundles, are watched as well.
+		let mut watched = vec![];
+		watch_files(&mut watcher, &mut watched, &next_state.watch_files);
 		tokio::task::spawn(async move {
 			// Handle file change events
 			while let Some(Ok(events)) = rx.recv().await {
@@ -127,6 +159,7 @@ impl LocalClient {
 					match lc.reload_config(next_state.clone()).await {
 						Ok(nxt) => {
 							next_state = nxt;
+							watch_files(&mut watcher, &mut watched, &next_state.watch_files);
 							info!("Config reloaded successfully")
 						},
 						Err(e) => {
@@ -141,7 +174,7 @@ impl LocalClient {
 		Ok(())
 	}
 
-	async fn reload_config(&self, prev: PreviousState) -> anyhow::Result<PreviousState> {
+	pub(crate) async fn reload_config(&self, prev: PreviousState) -> anyhow::Result<PreviousState> {
 		let config_content = self.cfg.read_to_string().await?;
 		let config = crate::types::local::NormalizedLocalConfig::from(
 			self.client.clone(),
@@ -165,12 +198,51 @@ impl LocalClient {
 		Ok(PreviousState {
 			binds: next_binds,
 			discovery: next_discovery,
+			watch_files: config.watch_files,
 		})
 	}
 }
 
+impl LocalClient {
+	/// clear removes the resources previously loaded by reload_config.
+	pub(crate) fn clear(&self, prev: PreviousState) -> anyhow::Result<()> {
+		self
+			.stores
+			.binds
+			.sync_local(vec![], vec![], vec![], prev.binds);
+		self
+			.stores
+			.discovery
+			.sync_local(vec![], vec![], prev.discovery)?;
+		Ok(())
+	}
+}
+
+fn watch_files(
+	watcher: &mut notify_debouncer_full::Debouncer<
+		notify::RecommendedWatcher,
+		notify_debouncer_full::RecommendedCache,
+	>,
+	watched: &mut Vec<PathBuf>,
+	files: &[PathBuf],
+) {
+	for file in files {
+		if watched.contains(file) {
+			continue;
+		}
+		match watcher.watch(file, RecursiveMode::NonRecursive) {
+			Ok(()) => {
+				info!("Watching file: {}", file.display());
+				watched.push(file.clone());
+			},
+			Err(e) => warn!("Failed to watch file {}: {}", file.display(), e),
+		}
+	}
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct PreviousState {
 	pub binds: store::BindPreviousState,
 	pub discovery: store::DiscoveryPreviousState,
+	pub watch_files: Vec<PathBuf>,
 }
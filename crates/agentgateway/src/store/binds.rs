@@ -13,13 +13,13 @@ use crate::http::authorization::{HTTPAuthorizationSet, RuleSets};
 use crate::http::backendtls::BackendTLS;
 use crate::http::ext_proc::InferenceRouting;
 use crate::http::{ext_authz, ext_proc, remoteratelimit};
+use crate::mcp::metadata::McpMetadata;
 use crate::mcp::rbac::McpAuthorizationSet;
 use crate::proxy::httpproxy::PolicyClient;
-use crate::store::Event;
+use crate::store::{ConfigVersion, Event};
 use crate::types::agent::{
-	A2aPolicy, Backend, BackendName, Bind, BindName, GatewayName, Listener, ListenerKey, ListenerSet,
-	McpAuthentication, Policy, PolicyName, PolicyTarget, Route, RouteKey, RouteName, RouteRuleName,
-	TCPRoute, TargetedPolicy,
+	A2aPolicy, Backend, BackendName, Bind, BindName, Listener, ListenerKey, ListenerSet,
+	McpAuthentication, Policy, PolicyName, PolicyTarget, Route, RouteKey, TCPRoute, TargetedPolicy,
 };
 use crate::types::proto::agent::resource::Kind as XdsKind;
 use crate::types::proto::agent::{
@@ -43,6 +43,10 @@ pub struct Store {
 	staged_routes: HashMap<ListenerKey, HashMap<RouteKey, Route>>,
 	staged_tcp_routes: HashMap<ListenerKey, HashMap<RouteKey, TCPRoute>>,
 
+	/// Policies that apply to each route, by listener and route key. Kept up to date as binds and policies
+	/// change, so requests do not need to resolve them.
+	resolved_route_policies: HashMap<ListenerKey, HashMap<RouteKey, Arc<RoutePolicies>>>,
+
 	tx: tokio::sync::broadcast::Sender<Event<Arc<Bind>>>,
 }
 
@@ -87,9 +91,120 @@ pub struct RoutePolicies {
 	pub ext_authz: Option<ext_authz::ExtAuthz>,
 	pub transformation: Option<http::transformation_cel::Transformation>,
 	pub llm: Option<Arc<llm::Policy>>,
+	pub pool_partition: Option<http::poolpartition::PoolPartition>,
+	pub idempotency: Option<http::idempotency::Idempotency>,
+	pub bandit: Option<http::bandit::Bandit>,
+	pub security_headers: Option<http::securityheaders::SecurityHeaders>,
+	pub failover: Option<http::failover::Failover>,
+	pub header_size_limit: Option<http::headersizelimit::HeaderSizeLimit>,
+	pub variables: Option<http::variables::Variables>,
+	pub loop_detection: Option<http::loopdetection::LoopDetection>,
+	pub upstream_errors: Option<http::upstreamerrors::UpstreamErrors>,
+	pub backend_override: Option<http::backendoverride::BackendOverride>,
+	/// The request policies that are configured, so requests only visit those.
+	pub request_policies: RequestPolicySet,
+}
+
+/// RequestPolicy is a kind of policy applied to requests, declared in the order the policies are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestPolicy {
+	LoopDetection,
+	SecurityHeaders,
+	Jwt,
+	ExtAuthz,
+	// Evaluated once authentication has run, so the variables can use the JWT claims.
+	Variables,
+	Authorization,
+	LocalRateLimit,
+	RemoteRateLimit,
+	Transformation,
+	PoolPartition,
+	// Applied last, so authentication and transformations can still read the headers.
+	HeaderSizeLimit,
+}
+
+impl RequestPolicy {
+	const ALL: [RequestPolicy; 11] = [
+		RequestPolicy::LoopDetection,
+		RequestPolicy::SecurityHeaders,
+		RequestPolicy::Jwt,
+		RequestPolicy::ExtAuthz,
+		RequestPolicy::Variables,
+		RequestPolicy::Authorization,
+		RequestPolicy::LocalRateLimit,
+		RequestPolicy::RemoteRateLimit,
+		RequestPolicy::Transformation,
+		RequestPolicy::PoolPartition,
+		RequestPolicy::HeaderSizeLimit,
+	];
+}
+
+/// RequestPolicySet is a bitset of request policies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestPolicySet(u16);
+
+impl RequestPolicySet {
+	pub fn insert(&mut self, p: RequestPolicy) {
+		self.0 |= 1 << p as u16;
+	}
+
+	pub fn contains(&self, p: RequestPolicy) -> bool {
+		self.0 & (1 << p as u16) != 0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+
+	/// iter returns the policies in the set, in the order they are applied.
+	pub fn iter(&self) -> impl Iterator<Item = RequestPolicy> + use<> {
+		let mut bits = self.0;
+		std::iter::from_fn(move || {
+			if bits == 0 {
+				return None;
+			}
+			let next = bits.trailing_zeros() as usize;
+			bits &= bits - 1;
+			Some(RequestPolicy::ALL[next])
+		})
+	}
 }
 
 impl RoutePolicies {
+	fn configured_request_policies(&self) -> RequestPolicySet {
+		let mut set = RequestPolicySet::default();
+		for (configured, p) in [
+			(self.loop_detection.is_some(), RequestPolicy::LoopDetection),
+			(
+				self.security_headers.is_some(),
+				RequestPolicy::SecurityHeaders,
+			),
+			(self.jwt.is_some(), RequestPolicy::Jwt),
+			(self.ext_authz.is_some(), RequestPolicy::ExtAuthz),
+			(self.variables.is_some(), RequestPolicy::Variables),
+			(self.authorization.is_some(), RequestPolicy::Authorization),
+			(
+				!self.local_rate_limit.is_empty(),
+				RequestPolicy::LocalRateLimit,
+			),
+			(
+				self.remote_rate_limit.is_some(),
+				RequestPolicy::RemoteRateLimit,
+			),
+			(self.transformation.is_some(), RequestPolicy::Transformation),
+			(self.pool_partition.is_some(), RequestPolicy::PoolPartition),
+			(
+				self.header_size_limit.is_some(),
+				RequestPolicy::HeaderSizeLimit,
+			),
+		] {
+			if configured {
+				set.insert(p);
+			}
+		}
+		set
+	}
+
 	pub fn register_cel_expressions(&self, ctx: &mut ContextBuilder) {
 		if let Some(xfm) = &self.transformation {
 			for expr in xfm.expressions() {
@@ -104,11 +219,31 @@ impl RoutePolicies {
 		if let Some(rrl) = &self.authorization {
 			rrl.register(ctx)
 		};
+		if let Some(llm) = &self.llm {
+			for expr in llm.expressions() {
+				ctx.register_expression(expr)
+			}
+		};
+		if let Some(pp) = &self.pool_partition {
+			for expr in pp.expressions() {
+				ctx.register_expression(expr)
+			}
+		};
+		if let Some(vars) = &self.variables {
+			for expr in vars.expressions() {
+				ctx.register_expression(expr)
+			}
+		};
+		if let Some(bo) = &self.backend_override {
+			for expr in bo.expressions() {
+				ctx.register_expression(expr)
+			}
+		};
 	}
 }
 
-impl From<RoutePolicies> for LLMRequestPolicies {
-	fn from(value: RoutePolicies) -> Self {
+impl From<&RoutePolicies> for LLMRequestPolicies {
+	fn from(value: &RoutePolicies) -> Self {
 		LLMRequestPolicies {
 			remote_rate_limit: value.remote_rate_limit.clone(),
 			local_rate_limit: value
@@ -151,6 +286,7 @@ impl Store {
 			staged_routes: Default::default(),
 			staged_listeners: Default::default(),
 			staged_tcp_routes: Default::default(),
+			resolved_route_policies: Default::default(),
 			tx,
 		}
 	}
@@ -161,13 +297,24 @@ impl Store {
 		tokio_stream::wrappers::BroadcastStream::new(sub)
 	}
 
-	pub fn route_policies(
-		&self,
-		route_rule: Option<RouteRuleName>,
-		route: RouteName,
-		listener: ListenerKey,
-		gateway: GatewayName,
-	) -> RoutePolicies {
+	/// route_policies returns the policies that apply to a route.
+	pub fn route_policies(&self, listener: &Listener, route: &Route) -> Arc<RoutePolicies> {
+		if let Some(pol) = self
+			.resolved_route_policies
+			.get(&listener.key)
+			.and_then(|routes| routes.get(&route.key))
+		{
+			return pol.clone();
+		}
+		// The route was removed since it was selected; resolve it directly.
+		Arc::new(self.resolve_route_policies(listener, route))
+	}
+
+	fn resolve_route_policies(&self, listener: &Listener, route: &Route) -> RoutePolicies {
+		let route_rule = route.rule_name.clone();
+		let gateway = listener.gateway_name.clone();
+		let listener = listener.key.clone();
+		let route = route.route_name.clone();
 		// Changes we must do:
 		// * Index the store by the target
 		// * Avoid the N lookups, or at least the boilerplate, for each type
@@ -199,6 +346,17 @@ impl Store {
 			transformation: None,
 			authorization: None,
 			llm: None,
+			pool_partition: None,
+			idempotency: None,
+			bandit: None,
+			security_headers: None,
+			failover: None,
+			header_size_limit: None,
+			variables: None,
+			loop_detection: None,
+			upstream_errors: None,
+			backend_override: None,
+			request_policies: Default::default(),
 		};
 		for rule in rules {
 			match &rule.policy {
@@ -219,6 +377,27 @@ impl Store {
 				Policy::Transformation(p) => {
 					pol.transformation.get_or_insert_with(|| p.clone());
 				},
+				Policy::SecurityHeaders(p) => {
+					pol.security_headers.get_or_insert_with(|| p.clone());
+				},
+				Policy::Failover(p) => {
+					pol.failover.get_or_insert_with(|| p.clone());
+				},
+				Policy::HeaderSizeLimit(p) => {
+					pol.header_size_limit.get_or_insert_with(|| p.clone());
+				},
+				Policy::Variables(p) => {
+					pol.variables.get_or_insert_with(|| p.clone());
+				},
+				Policy::LoopDetection(p) => {
+					pol.loop_detection.get_or_insert_with(|| p.clone());
+				},
+				Policy::UpstreamErrors(p) => {
+					pol.upstream_errors.get_or_insert_with(|| p.clone());
+				},
+				Policy::BackendOverride(p) => {
+					pol.backend_override.get_or_insert_with(|| p.clone());
+				},
 				Policy::Authorization(p) => {
 					// Authorization policies merge, unlike others
 					authz.push(p.clone().0);
@@ -226,12 +405,22 @@ impl Store {
 				Policy::AI(p) => {
 					pol.llm.get_or_insert_with(|| p.clone());
 				},
+				Policy::PoolPartition(p) => {
+					pol.pool_partition.get_or_insert_with(|| p.clone());
+				},
+				Policy::Idempotency(p) => {
+					pol.idempotency.get_or_insert_with(|| p.clone());
+				},
+				Policy::Bandit(p) => {
+					pol.bandit.get_or_insert_with(|| p.clone());
+				},
 				_ => {}, // others are not route policies
 			}
 		}
 		if !authz.is_empty() {
 			pol.authorization = Some(HTTPAuthorizationSet::new(authz.into()));
 		}
+		pol.request_policies = pol.configured_request_policies();
 
 		pol
 	}
@@ -275,7 +464,11 @@ impl Store {
 	pub fn mcp_policies(
 		&self,
 		backend: BackendName,
-	) -> (McpAuthorizationSet, Option<McpAuthentication>) {
+	) -> (
+		McpAuthorizationSet,
+		Option<McpAuthentication>,
+		Option<McpMetadata>,
+	) {
 		let t = PolicyTarget::Backend(backend);
 		let rs = McpAuthorizationSet::new(RuleSets::from(
 			self
@@ -306,7 +499,20 @@ impl Store {
 				}
 			})
 			.next();
-		(rs, auth)
+		let metadata = self
+			.policies_by_name
+			.values()
+			.filter_map(|p| {
+				if p.target != t {
+					return None;
+				};
+				match &p.policy {
+					Policy::McpMetadata(md) => Some(md.clone()),
+					_ => None,
+				}
+			})
+			.next();
+		(rs, auth, metadata)
 	}
 
 	pub fn listeners(&self, bind: BindName) -> Option<ListenerSet> {
@@ -330,9 +536,45 @@ impl Store {
     )]
 	pub fn remove_bind(&mut self, bind: BindName) {
 		if let Some(old) = self.by_name.remove(&bind) {
+			for l in old.listeners.iter() {
+				self.resolved_route_policies.remove(&l.key);
+			}
 			let _ = self.tx.send(Event::Remove(old));
 		}
 	}
+
+	/// Re-resolve the policies of every route that a policy targeting `target` may apply to.
+	fn refresh_route_policies(&mut self, target: &PolicyTarget) {
+		let applies = |l: &Listener, r: &Route| match target {
+			PolicyTarget::Gateway(g) => &l.gateway_name == g,
+			PolicyTarget::Listener(k) => &l.key == k,
+			PolicyTarget::Route(n) => &r.route_name == n,
+			PolicyTarget::RouteRule(n) => r.rule_name.as_ref() == Some(n),
+			PolicyTarget::Backend(_) => false,
+		};
+		let resolved = self
+			.by_name
+			.values()
+			.flat_map(|b| b.listeners.iter())
+			.flat_map(|l| l.routes.iter().map(move |r| (l, r)))
+			.filter(|&(l, r)| applies(l, r))
+			.map(|(l, r)| {
+				(
+					l.key.clone(),
+					r.key.clone(),
+					Arc::new(self.resolve_route_policies(l, r)),
+				)
+			})
+			.collect_vec();
+		for (l, r, pol) in resolved {
+			self
+				.resolved_route_policies
+				.entry(l)
+				.or_default()
+				.insert(r, pol);
+		}
+	}
+
 	#[instrument(
         level = Level::INFO,
         name="remove_policy",
@@ -340,10 +582,11 @@ impl Store {
         fields(bind),
     )]
 	pub fn remove_policy(&mut self, pol: PolicyName) {
-		if let Some(old) = self.policies_by_name.remove(&pol)
-			&& let Some(o) = self.policies_by_target.get_mut(&old.target)
-		{
-			o.remove(&pol);
+		if let Some(old) = self.policies_by_name.remove(&pol) {
+			if let Some(o) = self.policies_by_target.get_mut(&old.target) {
+				o.remove(&pol);
+			}
+			self.refresh_route_policies(&old.target);
 		}
 	}
 	#[instrument(
@@ -446,7 +689,19 @@ impl Store {
 			bind.listeners.insert(v)
 		}
 		let arc = Arc::new(bind);
-		self.by_name.insert(arc.key.clone(), arc.clone());
+		if let Some(old) = self.by_name.insert(arc.key.clone(), arc.clone()) {
+			for l in old.listeners.iter() {
+				self.resolved_route_policies.remove(&l.key);
+			}
+		}
+		for l in arc.listeners.iter() {
+			let routes = l
+				.routes
+				.iter()
+				.map(|r| (r.key.clone(), Arc::new(self.resolve_route_policies(l, r))))
+				.collect();
+			self.resolved_route_policies.insert(l.key.clone(), routes);
+		}
 		// ok to have no subs
 		let _ = self.tx.send(Event::Add(arc));
 	}
@@ -470,12 +725,16 @@ impl Store {
 			if let Some(o) = self.policies_by_target.get_mut(&old.target) {
 				o.remove(&pol.name);
 			}
+			if old.target != pol.target {
+				self.refresh_route_policies(&old.target);
+			}
 		}
 		self
 			.policies_by_target
 			.entry(pol.target.clone())
 			.or_default()
 			.insert(pol.name.clone());
+		self.refresh_route_policies(&pol.target);
 	}
 
 	pub fn insert_listener(&mut self, mut lis: Listener, bind_name: BindName) {
@@ -656,6 +915,7 @@ impl Store {
 #[derive(Clone, Debug)]
 pub struct StoreUpdater {
 	state: Arc<RwLock<Store>>,
+	version: ConfigVersion,
 }
 
 #[derive(serde::Serialize)]
@@ -666,8 +926,8 @@ pub struct Dump {
 }
 
 impl StoreUpdater {
-	pub fn new(state: Arc<RwLock<Store>>) -> StoreUpdater {
-		Self { state }
+	pub fn new(state: Arc<RwLock<Store>>, version: ConfigVersion) -> StoreUpdater {
+		Self { state, version }
 	}
 	pub fn read(&self) -> std::sync::RwLockReadGuard<'_, Store> {
 		self.state.read().expect("mutex acquired")
@@ -710,6 +970,7 @@ impl StoreUpdater {
 		prev: PreviousState,
 	) -> PreviousState {
 		let mut s = self.state.write().expect("mutex acquired");
+		self.version.mark_changed();
 		let mut old_binds = prev.binds;
 		let mut old_pols = prev.policies;
 		let mut old_backends = prev.backends;
@@ -759,6 +1020,7 @@ impl agent_xds::Handler<ADPResource> for StoreUpdater {
 		updates: Box<&mut dyn Iterator<Item = XdsUpdate<ADPResource>>>,
 	) -> Result<(), Vec<RejectedConfig>> {
 		let mut state = self.state.write().unwrap();
+		self.version.mark_changed();
 		let handle = |res: XdsUpdate<ADPResource>| {
 			match res {
 				XdsUpdate::Update(w) => state.insert_xds(w.resource)?,
@@ -772,3 +1034,165 @@ impl agent_xds::Handler<ADPResource> for StoreUpdater {
 		agent_xds::handle_single_resource(updates, handle)
 	}
 }
+
+#[cfg(any(test, feature = "internal_benches"))]
+mod tests {
+	use divan::Bencher;
+
+	use super::*;
+
+	fn header_size_limit(name: &str, target: PolicyTarget, max_size: usize) -> TargetedPolicy {
+		TargetedPolicy {
+			name: name.into(),
+			target,
+			policy: Policy::HeaderSizeLimit(http::headersizelimit::HeaderSizeLimit {
+				max_size,
+				headers: vec![],
+			}),
+		}
+	}
+
+	fn route() -> Route {
+		Route {
+			key: strng::literal!("route-key"),
+			route_name: strng::literal!("route"),
+			rule_name: Some(strng::literal!("rule")),
+			hostnames: vec![],
+			matches: vec![],
+			filters: vec![],
+			backends: vec![],
+			policies: None,
+		}
+	}
+
+	fn store_with_route() -> Store {
+		let mut store = Store::new();
+		store.insert_bind(Bind {
+			key: strng::literal!("bind"),
+			address: "127.0.0.1:8080".parse().unwrap(),
+			listeners: ListenerSet::from_list([Listener {
+				key: strng::literal!("listener"),
+				name: strng::literal!("listener"),
+				gateway_name: strng::literal!("gateway"),
+				hostname: Default::default(),
+				protocol: crate::types::agent::ListenerProtocol::HTTP,
+				routes: crate::types::agent::RouteSet::from_list(vec![route()]),
+				tcp_routes: Default::default(),
+			}]),
+		});
+		store
+	}
+
+	fn lookup(store: &Store) -> Arc<RoutePolicies> {
+		let bind = store.by_name.get(&strng::literal!("bind")).unwrap();
+		let listener = bind.listeners.iter().next().unwrap();
+		store.route_policies(listener, &route())
+	}
+
+	#[test]
+	fn route_policies_resolved_on_change() {
+		let mut store = store_with_route();
+		assert!(lookup(&store).request_policies.is_empty());
+		store.insert_policy(header_size_limit(
+			"gw",
+			PolicyTarget::Gateway(strng::literal!("gateway")),
+			10,
+		));
+		let first = lookup(&store);
+		assert!(Arc::ptr_eq(&first, &lookup(&store)));
+		assert_eq!(first.header_size_limit.as_ref().unwrap().max_size, 10);
+		assert!(
+			first
+				.request_policies
+				.contains(RequestPolicy::HeaderSizeLimit)
+		);
+
+		// A policy for another gateway leaves the route alone.
+		store.insert_policy(header_size_limit(
+			"other",
+			PolicyTarget::Gateway(strng::literal!("other")),
+			30,
+		));
+		assert!(Arc::ptr_eq(&first, &lookup(&store)));
+
+		// A more specific policy replaces the resolved policies.
+		store.insert_policy(header_size_limit(
+			"rule",
+			PolicyTarget::RouteRule(strng::literal!("rule")),
+			20,
+		));
+		let second = lookup(&store);
+		assert!(!Arc::ptr_eq(&first, &second));
+		assert_eq!(second.header_size_limit.as_ref().unwrap().max_size, 20);
+
+		store.remove_policy(strng::literal!("rule"));
+		assert_eq!(
+			lookup(&store).header_size_limit.as_ref().unwrap().max_size,
+			10
+		);
+		store.remove_policy(strng::literal!("gw"));
+		assert!(lookup(&store).request_policies.is_empty());
+
+		// Removed routes are dropped.
+		store.remove_route(strng::literal!("route-key"));
+		assert!(
+			store
+				.resolved_route_policies
+				.get(&strng::literal!("listener"))
+				.unwrap()
+				.is_empty()
+		);
+		store.remove_bind(strng::literal!("bind"));
+		assert!(store.resolved_route_policies.is_empty());
+	}
+
+	#[divan::bench(args = [false, true])]
+	fn bench_route_policies(b: Bencher, cached: bool) {
+		let mut store = store_with_route();
+		for (i, target) in [
+			PolicyTarget::Gateway(strng::literal!("gateway")),
+			PolicyTarget::Listener(strng::literal!("listener")),
+			PolicyTarget::Route(strng::literal!("route")),
+			PolicyTarget::RouteRule(strng::literal!("rule")),
+		]
+		.into_iter()
+		.enumerate()
+		{
+			store.insert_policy(header_size_limit(&format!("p{i}"), target, i));
+		}
+		b.bench_local(|| {
+			if cached {
+				divan::black_box(lookup(&store));
+			} else {
+				let bind = store.by_name.get(&strng::literal!("bind")).unwrap();
+				let listener = bind.listeners.iter().next().unwrap();
+				divan::black_box(store.resolve_route_policies(listener, &route()));
+			}
+		});
+	}
+
+	#[test]
+	fn request_policy_set() {
+		let mut set = RequestPolicySet::default();
+		assert!(set.is_empty());
+		assert_eq!(set.iter().count(), 0);
+		set.insert(RequestPolicy::HeaderSizeLimit);
+		set.insert(RequestPolicy::Jwt);
+		set.insert(RequestPolicy::LoopDetection);
+		set.insert(RequestPolicy::Jwt);
+		assert!(set.contains(RequestPolicy::Jwt));
+		assert!(!set.contains(RequestPolicy::ExtAuthz));
+		assert_eq!(
+			set.iter().collect::<Vec<_>>(),
+			vec![
+				RequestPolicy::LoopDetection,
+				RequestPolicy::Jwt,
+				RequestPolicy::HeaderSizeLimit
+			]
+		);
+		// Each policy has its own bit, in declaration order.
+		for (i, p) in RequestPolicy::ALL.into_iter().enumerate() {
+			assert_eq!(p as usize, i);
+		}
+	}
+}
@@ -0,0 +1,721 @@
+//! Key-value storage for state that outlives a single request, such as idempotency records.
+//!
+//! The memory store is local to the process. The file store keeps state across restarts, and the Redis
+//! store shares it between replicas.
+//!
+//! Only idempotency records are kept here. MCP sessions are not: each session owns a live connection to its
+//! upstream servers, which cannot be serialized, so a session is always served by the replica that created it.
+//! Load balancing keeps no per-client affinity state to share.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use rustls::pki_types::ServerName;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::*;
+
+#[apply(schema!)]
+#[derive(Default)]
+pub enum Config {
+	/// Keep state in memory. State is lost on restart, and is not shared between replicas.
+	#[default]
+	Memory,
+	/// Keep state in files under a directory.
+	File(FileConfig),
+	/// Keep state in Redis, so it is shared between replicas.
+	Redis(RedisConfig),
+}
+
+#[apply(schema!)]
+pub struct FileConfig {
+	/// Directory to store state in. It is created if it does not exist.
+	pub path: PathBuf,
+}
+
+#[apply(schema!)]
+pub struct RedisConfig {
+	/// Redis server address, in the format "host:port".
+	pub address: String,
+	/// Username to authenticate as. Requires `password`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub username: Option<String>,
+	/// Password to authenticate with, sent with the AUTH command when connecting.
+	#[serde(
+		default,
+		serialize_with = "ser_redact",
+		skip_serializing_if = "Option::is_none"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub password: Option<SecretString>,
+	/// Connect over TLS, verifying the server's certificate against the system root CAs.
+	#[serde(default)]
+	pub tls: bool,
+	/// Prefix added to every key. Defaults to `agentgateway/`.
+	#[serde(default = "default_key_prefix")]
+	pub key_prefix: String,
+	/// Timeout for each request to Redis. Defaults to 1s.
+	#[serde(default = "default_timeout", with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub timeout: Duration,
+}
+
+fn default_key_prefix() -> String {
+	"agentgateway/".to_string()
+}
+
+fn default_timeout() -> Duration {
+	Duration::from_secs(1)
+}
+
+impl Config {
+	pub fn build(&self) -> anyhow::Result<Store> {
+		Ok(match self {
+			Config::Memory => memory(),
+			Config::File(f) => Arc::new(FileStore::new(f.path.clone())?),
+			Config::Redis(r) => Arc::new(RedisStore::new(r.clone())?),
+		})
+	}
+}
+
+/// KvStore stores values with an expiry. Expired values are never returned.
+#[async_trait]
+pub trait KvStore: Send + Sync + std::fmt::Debug {
+	async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>>;
+	async fn set(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<()>;
+	/// set_if_absent stores the value only if the key is not already set, returning whether it was stored.
+	async fn set_if_absent(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<bool>;
+	async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+	/// is_local reports whether state is only visible to this process. Consumers may prefer their own
+	/// in-process structures in that case, which can enforce limits the generic store cannot.
+	fn is_local(&self) -> bool {
+		false
+	}
+}
+
+pub type Store = Arc<dyn KvStore>;
+
+pub fn memory() -> Store {
+	Arc::new(MemoryStore::default())
+}
+
+/// Expired entries are removed when read, and swept once the store has doubled in size since the last sweep.
+const MIN_SWEEP: usize = 1024;
+
+#[derive(Debug)]
+pub struct MemoryStore {
+	entries: Mutex<MemoryEntries>,
+}
+
+#[derive(Debug)]
+struct MemoryEntries {
+	values: HashMap<String, (Bytes, Instant)>,
+	sweep_at: usize,
+}
+
+impl Default for MemoryStore {
+	fn default() -> Self {
+		Self {
+			entries: Mutex::new(MemoryEntries {
+				values: HashMap::new(),
+				sweep_at: MIN_SWEEP,
+			}),
+		}
+	}
+}
+
+impl MemoryEntries {
+	fn live(&mut self, key: &str, now: Instant) -> Option<Bytes> {
+		match self.values.get(key) {
+			Some((v, expires)) if *expires > now => Some(v.clone()),
+			Some(_) => {
+				self.values.remove(key);
+				None
+			},
+			None => None,
+		}
+	}
+
+	fn insert(&mut self, key: &str, value: Bytes, expires: Instant, now: Instant) {
+		if self.values.len() >= self.sweep_at {
+			self.values.retain(|_, (_, expires)| *expires > now);
+			self.sweep_at = (self.values.len() * 2).max(MIN_SWEEP);
+		}
+		self.values.insert(key.to_string(), (value, expires));
+	}
+}
+
+#[async_trait]
+impl KvStore for MemoryStore {
+	async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+		let mut entries = self.entries.lock().expect("mutex acquired");
+		Ok(entries.live(key, Instant::now()))
+	}
+
+	async fn set(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<()> {
+		let now = Instant::now();
+		let mut entries = self.entries.lock().expect("mutex acquired");
+		entries.insert(key, value, now + ttl, now);
+		Ok(())
+	}
+
+	async fn set_if_absent(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<bool> {
+		let now = Instant::now();
+		let mut entries = self.entries.lock().expect("mutex acquired");
+		if entries.live(key, now).is_some() {
+			return Ok(false);
+		}
+		entries.insert(key, value, now + ttl, now);
+		Ok(true)
+	}
+
+	async fn delete(&self, key: &str) -> anyhow::Result<()> {
+		let mut entries = self.entries.lock().expect("mutex acquired");
+		entries.values.remove(key);
+		Ok(())
+	}
+
+	fn is_local(&self) -> bool {
+		true
+	}
+}
+
+/// How often the file store removes expired files. Temporary files older than this are left over from an
+/// interrupted write, and are removed too.
+const FILE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// FileStore keeps each key in its own file, named by the hex encoded SHA-256 hash of the key, so names
+/// have a fixed length however long the key is. Each file holds the expiry time, in milliseconds since the
+/// Unix epoch, followed by the value. Expired files are removed when read, and by a periodic sweep.
+#[derive(Debug)]
+pub struct FileStore {
+	dir: PathBuf,
+	_sweeper: Option<DropGuard>,
+}
+
+impl FileStore {
+	pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+		fs_err::create_dir_all(&dir)?;
+		let sweeper = match tokio::runtime::Handle::try_current() {
+			Ok(rt) => {
+				let cancel = CancellationToken::new();
+				rt.spawn(sweep_periodically(dir.clone(), cancel.clone()));
+				Some(cancel.drop_guard())
+			},
+			Err(_) => {
+				warn!("no runtime available, expired state files will only be removed when read");
+				None
+			},
+		};
+		Ok(Self {
+			dir,
+			_sweeper: sweeper,
+		})
+	}
+
+	fn path(&self, key: &str) -> PathBuf {
+		self.dir.join(hex::encode(Sha256::digest(key)))
+	}
+
+	async fn read(&self, path: &Path) -> anyhow::Result<Option<Bytes>> {
+		let contents = match tokio::fs::read(path).await {
+			Ok(c) => Bytes::from(c),
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+		};
+		let expires = contents
+			.get(..8)
+			.and_then(|b| b.try_into().ok())
+			.map(u64::from_be_bytes)
+			.with_context(|| format!("malformed state file {}", path.display()))?;
+		if expires <= unix_millis(Duration::ZERO) {
+			remove(path).await?;
+			return Ok(None);
+		}
+		Ok(Some(contents.slice(8..)))
+	}
+
+	/// write_temp writes a record to a new temporary file, so it can be moved into place atomically.
+	async fn write_temp(&self, value: &[u8], ttl: Duration) -> anyhow::Result<PathBuf> {
+		let tmp = self
+			.dir
+			.join(format!(".tmp-{:016x}", rand::random::<u64>()));
+		let mut contents = Vec::with_capacity(8 + value.len());
+		contents.extend_from_slice(&unix_millis(ttl).to_be_bytes());
+		contents.extend_from_slice(value);
+		tokio::fs::write(&tmp, contents)
+			.await
+			.with_context(|| format!("write {}", tmp.display()))?;
+		Ok(tmp)
+	}
+}
+
+fn unix_millis(offset: Duration) -> u64 {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default();
+	(now + offset).as_millis() as u64
+}
+
+async fn sweep_periodically(dir: PathBuf, cancel: CancellationToken) {
+	loop {
+		tokio::select! {
+			_ = cancel.cancelled() => return,
+			_ = tokio::time::sleep(FILE_SWEEP_INTERVAL) => {},
+		}
+		if let Err(e) = sweep(&dir).await {
+			warn!(dir = %dir.display(), "failed to remove expired state: {e}");
+		}
+	}
+}
+
+/// sweep removes expired files, and temporary files left over from interrupted writes.
+async fn sweep(dir: &Path) -> anyhow::Result<()> {
+	let now = unix_millis(Duration::ZERO);
+	let mut entries = tokio::fs::read_dir(dir)
+		.await
+		.with_context(|| format!("read {}", dir.display()))?;
+	while let Some(entry) = entries.next_entry().await? {
+		let path = entry.path();
+		let expired = if entry.file_name().to_string_lossy().starts_with(".tmp-") {
+			entry
+				.metadata()
+				.await
+				.and_then(|m| m.modified())
+				.is_ok_and(|t| t.elapsed().is_ok_and(|age| age > FILE_SWEEP_INTERVAL))
+		} else {
+			let mut expires = [0; 8];
+			match tokio::fs::File::open(&path).await {
+				Ok(mut f) => {
+					let read = f.read_exact(&mut expires).await.is_ok();
+					read && u64::from_be_bytes(expires) <= now
+				},
+				// Removed since the directory was listed.
+				Err(_) => false,
+			}
+		};
+		if expired {
+			remove(&path).await?;
+		}
+	}
+	Ok(())
+}
+
+async fn remove(path: &Path) -> anyhow::Result<()> {
+	match tokio::fs::remove_file(path).await {
+		Err(e) if e.kind() != ErrorKind::NotFound => {
+			Err(e).with_context(|| format!("remove {}", path.display()))
+		},
+		_ => Ok(()),
+	}
+}
+
+#[async_trait]
+impl KvStore for FileStore {
+	async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+		self.read(&self.path(key)).await
+	}
+
+	async fn set(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<()> {
+		let tmp = self.write_temp(&value, ttl).await?;
+		let res = tokio::fs::rename(&tmp, self.path(key)).await;
+		if res.is_err() {
+			let _ = remove(&tmp).await;
+		}
+		Ok(res?)
+	}
+
+	async fn set_if_absent(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<bool> {
+		let path = self.path(key);
+		// Clears the file if it has expired.
+		if self.read(&path).await?.is_some() {
+			return Ok(false);
+		}
+		// Linking fails if the file already exists, so only one writer can win.
+		let tmp = self.write_temp(&value, ttl).await?;
+		let res = tokio::fs::hard_link(&tmp, &path).await;
+		remove(&tmp).await?;
+		match res {
+			Ok(()) => Ok(true),
+			Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+			Err(e) => Err(e).with_context(|| format!("write {}", path.display())),
+		}
+	}
+
+	async fn delete(&self, key: &str) -> anyhow::Result<()> {
+		remove(&self.path(key)).await
+	}
+}
+
+/// RedisStore sends commands to Redis over a single multiplexed connection, which pipelines concurrent
+/// requests. The connection is opened on first use, and replaced after it fails or a request times out.
+pub struct RedisStore {
+	cfg: RedisConfig,
+	tls: Option<(TlsConnector, ServerName<'static>)>,
+	conn: Mutex<Option<MultiplexedConnection>>,
+	/// Held while opening a connection, so concurrent requests share one.
+	connecting: tokio::sync::Mutex<()>,
+}
+
+impl fmt::Debug for RedisStore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RedisStore")
+			.field("cfg", &self.cfg)
+			.finish_non_exhaustive()
+	}
+}
+
+trait Io: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin {}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin> Io for T {}
+
+impl RedisStore {
+	pub fn new(cfg: RedisConfig) -> anyhow::Result<Self> {
+		if cfg.username.is_some() && cfg.password.is_none() {
+			anyhow::bail!("redis username requires a password");
+		}
+		let tls = if cfg.tls {
+			let host = redis_host(&cfg.address);
+			let server_name = ServerName::try_from(host.to_string())
+				.with_context(|| format!("invalid redis host {host}"))?;
+			let mut cc = (*crate::http::backendtls::SYSTEM_TRUST.config).clone();
+			cc.alpn_protocols.clear();
+			Some((TlsConnector::from(Arc::new(cc)), server_name))
+		} else {
+			None
+		};
+		Ok(Self {
+			cfg,
+			tls,
+			conn: Default::default(),
+			connecting: Default::default(),
+		})
+	}
+
+	async fn query<T: redis::FromRedisValue>(&self, cmd: &redis::Cmd) -> anyhow::Result<T> {
+		let res = tokio::time::timeout(self.cfg.timeout, async {
+			let mut conn = self.connection().await?;
+			cmd.query_async::<T>(&mut conn).await.map_err(|e| {
+				if e.is_io_error() || e.is_unrecoverable_error() {
+					self.reset();
+				}
+				anyhow::anyhow!("redis error: {e}")
+			})
+		})
+		.await;
+		match res {
+			Ok(res) => res,
+			Err(_) => {
+				// The connection may be stuck; the next request opens a new one.
+				self.reset();
+				anyhow::bail!("redis request timed out")
+			},
+		}
+	}
+
+	/// connection returns the current connection, opening one if there is none.
+	async fn connection(&self) -> anyhow::Result<MultiplexedConnection> {
+		if let Some(conn) = self.current() {
+			return Ok(conn);
+		}
+		let _connecting = self.connecting.lock().await;
+		// Another request may have connected while this one waited.
+		if let Some(conn) = self.current() {
+			return Ok(conn);
+		}
+		let conn = self.connect().await?;
+		*self.conn.lock().expect("mutex acquired") = Some(conn.clone());
+		Ok(conn)
+	}
+
+	fn current(&self) -> Option<MultiplexedConnection> {
+		self.conn.lock().expect("mutex acquired").clone()
+	}
+
+	fn reset(&self) {
+		*self.conn.lock().expect("mutex acquired") = None;
+	}
+
+	async fn connect(&self) -> anyhow::Result<MultiplexedConnection> {
+		let address = &self.cfg.address;
+		let tcp = TcpStream::connect(address)
+			.await
+			.with_context(|| format!("connect to redis at {address}"))?;
+		let info = redis::RedisConnectionInfo {
+			username: self.cfg.username.clone(),
+			password: self
+				.cfg
+				.password
+				.as_ref()
+				.map(|p| p.expose_secret().to_string()),
+			..Default::default()
+		};
+		let io: Box<dyn Io> = match &self.tls {
+			Some((connector, server_name)) => Box::new(
+				connector
+					.connect(server_name.clone(), tcp)
+					.await
+					.with_context(|| format!("tls handshake with redis at {address}"))?,
+			),
+			None => Box::new(tcp),
+		};
+		// The driver reads replies and writes requests for the connection, until every handle is dropped.
+		let (conn, driver) =
+			MultiplexedConnection::new(&info, io)
+				.await
+				.map_err(|e| match e.kind() {
+					redis::ErrorKind::AuthenticationFailed => {
+						anyhow::anyhow!("redis authentication failed: {e}")
+					},
+					_ => anyhow::Error::from(e).context(format!("connect to redis at {address}")),
+				})?;
+		tokio::spawn(driver);
+		Ok(conn)
+	}
+
+	fn key(&self, key: &str) -> String {
+		format!("{}{key}", self.cfg.key_prefix)
+	}
+}
+
+/// redis_host returns the host part of a "host:port" address, without the brackets around an IPv6 address.
+fn redis_host(address: &str) -> &str {
+	let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+	host
+		.strip_prefix('[')
+		.and_then(|h| h.strip_suffix(']'))
+		.unwrap_or(host)
+}
+
+#[async_trait]
+impl KvStore for RedisStore {
+	async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+		let value: Option<Vec<u8>> = self.query(redis::cmd("GET").arg(self.key(key))).await?;
+		Ok(value.map(Bytes::from))
+	}
+
+	async fn set(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<()> {
+		self
+			.query::<()>(
+				redis::cmd("SET")
+					.arg(self.key(key))
+					.arg(value.as_ref())
+					.arg("PX")
+					.arg(ttl.as_millis().max(1) as u64),
+			)
+			.await
+	}
+
+	async fn set_if_absent(&self, key: &str, value: Bytes, ttl: Duration) -> anyhow::Result<bool> {
+		// Redis replies with nil if the key was already set.
+		let reply: Option<String> = self
+			.query(
+				redis::cmd("SET")
+					.arg(self.key(key))
+					.arg(value.as_ref())
+					.arg("PX")
+					.arg(ttl.as_millis().max(1) as u64)
+					.arg("NX"),
+			)
+			.await?;
+		Ok(reply.is_some())
+	}
+
+	async fn delete(&self, key: &str) -> anyhow::Result<()> {
+		self.query::<()>(redis::cmd("DEL").arg(self.key(key))).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufStream};
+
+	use super::*;
+
+	async fn check_store(store: &dyn KvStore) {
+		let ttl = Duration::from_secs(60);
+		assert_eq!(store.get("a").await.unwrap(), None);
+		store.set("a", Bytes::from("1"), ttl).await.unwrap();
+		assert_eq!(store.get("a").await.unwrap(), Some(Bytes::from("1")));
+
+		assert!(
+			!store
+				.set_if_absent("a", Bytes::from("2"), ttl)
+				.await
+				.unwrap()
+		);
+		assert!(
+			store
+				.set_if_absent("b", Bytes::from("2"), ttl)
+				.await
+				.unwrap()
+		);
+		assert_eq!(store.get("b").await.unwrap(), Some(Bytes::from("2")));
+
+		store.delete("a").await.unwrap();
+		assert_eq!(store.get("a").await.unwrap(), None);
+		store.delete("a").await.unwrap();
+
+		// Expired values are treated as absent
+		store
+			.set("c", Bytes::from("3"), Duration::ZERO)
+			.await
+			.unwrap();
+		assert_eq!(store.get("c").await.unwrap(), None);
+		assert!(
+			store
+				.set_if_absent("c", Bytes::from("4"), ttl)
+				.await
+				.unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn memory_store() {
+		check_store(&MemoryStore::default()).await;
+	}
+
+	#[tokio::test]
+	async fn file_store() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = FileStore::new(dir.path().join("state")).unwrap();
+		check_store(&store).await;
+		// Keys are encoded, so they cannot escape the directory
+		store
+			.set("../x", Bytes::from("1"), Duration::from_secs(60))
+			.await
+			.unwrap();
+		assert!(!dir.path().join("x").exists());
+		// Long keys are hashed to a fixed length file name
+		let long = "k".repeat(1000);
+		store
+			.set(&long, Bytes::from("1"), Duration::from_secs(60))
+			.await
+			.unwrap();
+		assert_eq!(store.get(&long).await.unwrap(), Some(Bytes::from("1")));
+	}
+
+	#[tokio::test]
+	async fn file_store_sweep() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = FileStore::new(dir.path().to_path_buf()).unwrap();
+		store
+			.set("a", Bytes::from("1"), Duration::ZERO)
+			.await
+			.unwrap();
+		store
+			.set("b", Bytes::from("2"), Duration::from_secs(60))
+			.await
+			.unwrap();
+		sweep(dir.path()).await.unwrap();
+		assert!(!store.path("a").exists());
+		assert!(store.path("b").exists());
+	}
+
+	/// fake_redis serves GET on a local port, requiring AUTH as user/secret. Getting the key "close" closes the
+	/// connection. It returns the address, and a counter of accepted connections.
+	async fn fake_redis() -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+		async fn read_command(r: &mut (impl AsyncBufRead + Unpin)) -> Option<Vec<Vec<u8>>> {
+			let mut line = String::new();
+			r.read_line(&mut line).await.ok()?;
+			let n: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+			let mut args = Vec::with_capacity(n);
+			for _ in 0..n {
+				line.clear();
+				r.read_line(&mut line).await.ok()?;
+				let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+				let mut arg = vec![0; len + 2];
+				r.read_exact(&mut arg).await.ok()?;
+				arg.truncate(len);
+				args.push(arg);
+			}
+			Some(args)
+		}
+
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap().to_string();
+		let conns = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		let accepted = conns.clone();
+		tokio::spawn(async move {
+			loop {
+				let (stream, _) = listener.accept().await.unwrap();
+				accepted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+				tokio::spawn(async move {
+					let mut stream = BufStream::new(stream);
+					let mut authenticated = false;
+					while let Some(cmd) = read_command(&mut stream).await {
+						let reply: &[u8] = match cmd[0].as_slice() {
+							b"AUTH" if cmd[1..] == [b"user".to_vec(), b"secret".to_vec()] => {
+								authenticated = true;
+								b"+OK\r\n"
+							},
+							b"AUTH" => b"-WRONGPASS invalid username-password pair\r\n",
+							_ if !authenticated => b"-NOAUTH Authentication required.\r\n",
+							b"CLIENT" => b"+OK\r\n",
+							b"GET" if cmd[1] == b"agentgateway/close" => return,
+							b"GET" => {
+								tokio::time::sleep(Duration::from_millis(10)).await;
+								b"$-1\r\n"
+							},
+							_ => b"-ERR unknown command\r\n",
+						};
+						stream.write_all(reply).await.unwrap();
+						stream.flush().await.unwrap();
+					}
+				});
+			}
+		});
+		(address, conns)
+	}
+
+	fn redis_config(address: String, password: &str) -> RedisConfig {
+		RedisConfig {
+			address,
+			username: Some("user".to_string()),
+			password: Some(SecretString::from(password)),
+			tls: false,
+			key_prefix: default_key_prefix(),
+			timeout: Duration::from_secs(5),
+		}
+	}
+
+	#[tokio::test]
+	async fn redis_auth_and_reconnect() {
+		let (address, conns) = fake_redis().await;
+		let store = RedisStore::new(redis_config(address.clone(), "secret")).unwrap();
+		let gets = (0..10).map(|_| store.get("a"));
+		for res in futures::future::join_all(gets).await {
+			assert_eq!(res.unwrap(), None);
+		}
+		// Concurrent requests share one connection
+		assert_eq!(conns.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+		// A closed connection is replaced on the next request
+		assert!(store.get("close").await.is_err());
+		assert_eq!(store.get("a").await.unwrap(), None);
+		assert_eq!(conns.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+		let store = RedisStore::new(redis_config(address, "wrong")).unwrap();
+		let err = store.get("a").await.unwrap_err();
+		assert!(
+			err.to_string().starts_with("redis authentication failed"),
+			"{err}"
+		);
+	}
+
+	#[test]
+	fn redis_hosts() {
+		assert_eq!(redis_host("redis.example.com:6380"), "redis.example.com");
+		assert_eq!(redis_host("[::1]:6379"), "::1");
+		assert_eq!(redis_host("localhost"), "localhost");
+	}
+}
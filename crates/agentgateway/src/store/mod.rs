@@ -3,16 +3,20 @@ mod binds;
 use std::sync::Arc;
 
 pub use binds::{
-	BackendPolicies, LLMRequestPolicies, LLMResponsePolicies, RoutePolicies, Store as BindStore,
+	BackendPolicies, LLMRequestPolicies, LLMResponsePolicies, RequestPolicy, RequestPolicySet,
+	RoutePolicies, Store as BindStore,
 };
 use serde::{Serialize, Serializer};
 mod discovery;
+pub mod kv;
+mod version;
 use std::sync::RwLock;
 
 pub use binds::PreviousState as BindPreviousState;
 pub use discovery::{
 	LocalWorkload, PreviousState as DiscoveryPreviousState, Store as DiscoveryStore,
 };
+pub use version::{ConfigVersion, Version as ConfigVersionInfo};
 
 use crate::store;
 
@@ -26,6 +30,9 @@ pub enum Event<T> {
 pub struct Stores {
 	pub discovery: discovery::StoreUpdater,
 	pub binds: binds::StoreUpdater,
+	pub control_plane: crate::control::unreachable::Status,
+	/// Hash of the active binds, policies, and backends.
+	pub version: ConfigVersion,
 }
 
 impl Default for Stores {
@@ -36,9 +43,12 @@ impl Default for Stores {
 
 impl Stores {
 	pub fn new() -> Stores {
+		let version = ConfigVersion::default();
 		Stores {
 			discovery: discovery::StoreUpdater::new(Arc::new(RwLock::new(discovery::Store::new()))),
-			binds: binds::StoreUpdater::new(Arc::new(RwLock::new(binds::Store::new()))),
+			binds: binds::StoreUpdater::new(Arc::new(RwLock::new(binds::Store::new())), version.clone()),
+			control_plane: Default::default(),
+			version,
 		}
 	}
 	pub fn read_binds(&self) -> std::sync::RwLockReadGuard<'_, store::BindStore> {
@@ -57,6 +67,8 @@ struct StoresDump {
 	discovery: discovery::Dump,
 	#[serde(flatten)]
 	binds: binds::Dump,
+	control_plane: crate::control::unreachable::Status,
+	config_version: Option<ConfigVersionInfo>,
 }
 
 impl Serialize for Stores {
@@ -67,6 +79,8 @@ impl Serialize for Stores {
 		let serializable = StoresDump {
 			discovery: self.discovery.dump(),
 			binds: self.binds.dump(),
+			control_plane: self.control_plane.clone(),
+			config_version: self.version.current(),
 		};
 		serializable.serialize(serializer)
 	}
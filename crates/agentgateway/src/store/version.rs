@@ -0,0 +1,185 @@
+use std::time::SystemTime;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+
+use crate::store::Stores;
+use crate::telemetry::metrics::{ConfigLabels, Metrics};
+use crate::*;
+
+/// How long to wait for further updates before hashing the config. Control plane pushes arrive as a burst of
+/// updates, which should produce one new version rather than one per update.
+const SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// ConfigVersion tracks a hash of the active configuration. The hash only depends on the configuration itself, so
+/// gateways serving the same configuration report the same version, regardless of the order it was received in.
+#[derive(Clone, Debug)]
+pub struct ConfigVersion {
+	changes: Arc<watch::Sender<u64>>,
+	current: Arc<watch::Sender<Option<Version>>>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+	pub hash: Strng,
+	/// When the configuration last changed to this hash.
+	#[serde(serialize_with = "ser_rfc3339")]
+	pub since: SystemTime,
+}
+
+fn ser_rfc3339<S: serde::Serializer>(t: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&chrono::DateTime::<chrono::Utc>::from(*t).to_rfc3339())
+}
+
+impl Default for ConfigVersion {
+	fn default() -> Self {
+		Self {
+			changes: Arc::new(watch::channel(0).0),
+			current: Arc::new(watch::channel(None).0),
+		}
+	}
+}
+
+impl ConfigVersion {
+	/// mark_changed records that the stores were updated, so the hash is recomputed.
+	pub(super) fn mark_changed(&self) {
+		self.changes.send_modify(|c| *c += 1);
+	}
+
+	/// hash returns the hash of the active configuration, once it has been computed.
+	pub fn hash(&self) -> Option<Strng> {
+		self.current.borrow().as_ref().map(|v| v.hash.clone())
+	}
+
+	pub fn current(&self) -> Option<Version> {
+		self.current.borrow().clone()
+	}
+
+	/// run keeps the hash up to date with the stores, recording each change in the metrics.
+	pub async fn run(self, stores: Stores, metrics: Arc<Metrics>) {
+		let mut changes = self.changes.subscribe();
+		loop {
+			changes.borrow_and_update();
+			match hash(&stores) {
+				Ok(hash) => self.update(hash, &metrics),
+				Err(e) => warn!("failed to hash config: {e}"),
+			}
+			if changes.changed().await.is_err() {
+				return;
+			}
+			tokio::time::sleep(SETTLE_DELAY).await;
+		}
+	}
+
+	fn update(&self, hash: Strng, metrics: &Metrics) {
+		let previous = self.hash();
+		if previous.as_ref() == Some(&hash) {
+			return;
+		}
+		if let Some(previous) = previous {
+			metrics.config_info.remove(&ConfigLabels {
+				hash: previous.into(),
+			});
+			metrics.config_changes.inc();
+		}
+		info!(%hash, "config version changed");
+		metrics
+			.config_info
+			.get_or_create(&ConfigLabels {
+				hash: hash.clone().into(),
+			})
+			.set(1);
+		self.current.send_replace(Some(Version {
+			hash,
+			since: SystemTime::now(),
+		}));
+	}
+}
+
+/// hash computes the hash of the binds, policies, and backends in the stores. Services and workloads are left
+/// out, as their endpoints change without any configuration change. Secrets are redacted when the stores are
+/// serialized, so rotating a secret alone does not change the hash.
+fn hash(stores: &Stores) -> anyhow::Result<Strng> {
+	let dump = serde_json::to_value(stores.binds.dump())?;
+	let digest = Sha256::digest(serde_json::to_vec(&canonicalize(dump))?);
+	Ok(hex::encode(&digest[..8]).into())
+}
+
+/// canonicalize sorts the keys of each object, so maps serialize the same way regardless of insertion order.
+fn canonicalize(v: Value) -> Value {
+	match v {
+		Value::Object(m) => {
+			let mut entries: Vec<_> = m.into_iter().collect();
+			entries.sort_by(|a, b| a.0.cmp(&b.0));
+			Value::Object(
+				entries
+					.into_iter()
+					.map(|(k, v)| (k, canonicalize(v)))
+					.collect(),
+			)
+		},
+		Value::Array(a) => Value::Array(a.into_iter().map(canonicalize).collect()),
+		v => v,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn canonical_order() {
+		let a = canonicalize(json!({"b": {"y": 1, "x": [{"d": 1, "c": 2}]}, "a": null}));
+		let b = canonicalize(json!({"a": null, "b": {"x": [{"c": 2, "d": 1}], "y": 1}}));
+		assert_eq!(
+			serde_json::to_string(&a).unwrap(),
+			serde_json::to_string(&b).unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn tracks_changes() {
+		let stores = Stores::new();
+		let mut registry = prometheus_client::registry::Registry::default();
+		let metrics = Arc::new(Metrics::new(&mut registry));
+		let version = stores.version.clone();
+		tokio::spawn(version.clone().run(stores.clone(), metrics.clone()));
+
+		let mut current = version.current.subscribe();
+		current.changed().await.unwrap();
+		let initial = version.hash().unwrap();
+		assert_eq!(initial, hash(&Stores::new()).unwrap());
+
+		stores.binds.sync_local(
+			vec![crate::types::agent::Bind {
+				key: strng::literal!("bind"),
+				address: "127.0.0.1:8080".parse().unwrap(),
+				listeners: Default::default(),
+			}],
+			vec![],
+			vec![],
+			Default::default(),
+		);
+		current.changed().await.unwrap();
+		assert_ne!(version.hash().unwrap(), initial);
+		assert_eq!(metrics.config_changes.get(), 1);
+
+		// Services and their endpoints are not part of the configuration version.
+		let svc = crate::types::discovery::Service::try_from(&crate::types::proto::workload::Service {
+			name: "svc".into(),
+			namespace: "ns".into(),
+			hostname: "svc.ns.svc.cluster.local".into(),
+			..Default::default()
+		})
+		.unwrap();
+		stores
+			.discovery
+			.sync_local(vec![svc], vec![], Default::default())
+			.unwrap();
+		assert_eq!(hash(&stores).unwrap(), version.hash().unwrap());
+	}
+}
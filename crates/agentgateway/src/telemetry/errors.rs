@@ -0,0 +1,144 @@
+use std::fmt::{Display, Write};
+
+use prometheus_client::encoding::{EncodeLabelValue, LabelValueEncoder};
+
+/// ErrorType is a stable, low cardinality classification of why a request failed. It is reported as the
+/// `error_type` metric label and the `error.type` log field, regardless of whether the error came from the
+/// gateway itself or an MCP, A2A, or LLM upstream.
+///
+/// The string values are part of the public interface, so they should not be renamed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorType {
+	/// No bind, listener, or route matched the request.
+	RouteNotFound,
+	/// The route references a backend that does not exist or cannot be used.
+	BackendNotFound,
+	NoHealthyBackends,
+	DnsResolution,
+	/// The client failed to authenticate, for example with an invalid JWT.
+	Authentication,
+	Authorization,
+	/// The request was rejected by a rate limit in the gateway.
+	RateLimited,
+	InvalidRequest,
+	RequestTooLarge,
+	IdempotencyConflict,
+	/// The request already passed through this gateway, or through too many gateways.
+	LoopDetected,
+	ControlPlaneUnreachable,
+	/// A policy or filter failed while processing the request or response.
+	Processing,
+	/// The gateway failed to authenticate to the backend.
+	BackendAuthentication,
+	/// The connection to the upstream failed.
+	UpstreamConnection,
+	UpgradeFailed,
+	Timeout,
+	Cancelled,
+	/// The upstream reported that the requested method, resource, task, or model does not exist.
+	NotFound,
+	/// The upstream does not support the requested operation or content.
+	Unsupported,
+	/// The upstream rejected the gateway's credentials.
+	UpstreamAuthentication,
+	UpstreamRateLimited,
+	UpstreamOverloaded,
+	/// The upstream reported an internal error.
+	UpstreamError,
+	/// The upstream response could not be understood.
+	InvalidResponse,
+}
+
+impl ErrorType {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ErrorType::RouteNotFound => "route_not_found",
+			ErrorType::BackendNotFound => "backend_not_found",
+			ErrorType::NoHealthyBackends => "no_healthy_backends",
+			ErrorType::DnsResolution => "dns_resolution",
+			ErrorType::Authentication => "authentication",
+			ErrorType::Authorization => "authorization",
+			ErrorType::RateLimited => "rate_limited",
+			ErrorType::InvalidRequest => "invalid_request",
+			ErrorType::RequestTooLarge => "request_too_large",
+			ErrorType::IdempotencyConflict => "idempotency_conflict",
+			ErrorType::LoopDetected => "loop_detected",
+			ErrorType::ControlPlaneUnreachable => "control_plane_unreachable",
+			ErrorType::Processing => "processing",
+			ErrorType::BackendAuthentication => "backend_authentication",
+			ErrorType::UpstreamConnection => "upstream_connection",
+			ErrorType::UpgradeFailed => "upgrade_failed",
+			ErrorType::Timeout => "timeout",
+			ErrorType::Cancelled => "cancelled",
+			ErrorType::NotFound => "not_found",
+			ErrorType::Unsupported => "unsupported",
+			ErrorType::UpstreamAuthentication => "upstream_authentication",
+			ErrorType::UpstreamRateLimited => "upstream_rate_limited",
+			ErrorType::UpstreamOverloaded => "upstream_overloaded",
+			ErrorType::UpstreamError => "upstream_error",
+			ErrorType::InvalidResponse => "invalid_response",
+		}
+	}
+
+	/// from_json_rpc classifies the error codes reserved by the JSON-RPC 2.0 specification. Protocols built on
+	/// JSON-RPC, like MCP and A2A, assign their own meaning to the server error range, so they handle those
+	/// codes before falling back to this.
+	pub fn from_json_rpc(code: i64) -> ErrorType {
+		match code {
+			// Parse error, invalid request, and invalid params
+			-32700 | -32600 | -32602 => ErrorType::InvalidRequest,
+			// Method not found
+			-32601 => ErrorType::NotFound,
+			_ => ErrorType::UpstreamError,
+		}
+	}
+}
+
+impl Display for ErrorType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl EncodeLabelValue for ErrorType {
+	fn encode(&self, writer: &mut LabelValueEncoder) -> Result<(), std::fmt::Error> {
+		writer.write_str(self.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::llm::universal::ErrorKind;
+
+	#[test]
+	fn provider_errors() {
+		let cases = [
+			(ErrorKind::INVALID_REQUEST, ErrorType::InvalidRequest),
+			(ErrorKind::AUTHENTICATION, ErrorType::UpstreamAuthentication),
+			(ErrorKind::PERMISSION, ErrorType::UpstreamAuthentication),
+			(ErrorKind::NOT_FOUND, ErrorType::NotFound),
+			(ErrorKind::REQUEST_TOO_LARGE, ErrorType::RequestTooLarge),
+			(ErrorKind::RATE_LIMIT, ErrorType::UpstreamRateLimited),
+			(ErrorKind::SERVER, ErrorType::UpstreamError),
+			(ErrorKind::OVERLOADED, ErrorType::UpstreamOverloaded),
+			(ErrorKind::TIMEOUT, ErrorType::Timeout),
+		];
+		for (kind, want) in cases {
+			let err = kind.into_error("failed".to_string()).error;
+			assert_eq!(err.error_type(), want, "{kind:?}");
+		}
+	}
+
+	#[test]
+	fn json_rpc_errors() {
+		assert_eq!(ErrorType::from_json_rpc(-32700), ErrorType::InvalidRequest);
+		assert_eq!(ErrorType::from_json_rpc(-32601), ErrorType::NotFound);
+		assert_eq!(ErrorType::from_json_rpc(-32603), ErrorType::UpstreamError);
+		assert_eq!(ErrorType::from_json_rpc(-32099), ErrorType::UpstreamError);
+		assert_eq!(
+			ErrorType::NoHealthyBackends.to_string(),
+			"no_healthy_backends"
+		);
+	}
+}
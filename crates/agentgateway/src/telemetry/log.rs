@@ -2,13 +2,14 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::net::SocketAddr;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, ready};
 use std::time::Instant;
 
 use agent_core::metrics::CustomField;
 use agent_core::strng;
 use agent_core::telemetry::{OptionExt, ValueBag, debug, display};
+use bytes::{Bytes, BytesMut};
 use crossbeam::atomic::AtomicCell;
 use frozen_collections::{FzHashSet, FzStringMap};
 use http_body::{Body, Frame, SizeHint};
@@ -18,9 +19,18 @@ use serde_json::Value;
 use tracing::{Level, trace};
 
 use crate::cel::{ContextBuilder, Expression};
-use crate::telemetry::metrics::{GenAILabels, GenAILabelsTokenUsage, HTTPLabels, Metrics};
+use crate::http::bandit::BanditOutcome;
+use crate::http::failover::FailoverOutcome;
+use crate::parse::sse::MalformedEvent;
+use crate::serdes::ser_display_iter;
+use crate::telemetry::errors::ErrorType;
+use crate::telemetry::metrics::{
+	GenAILabels, GenAILabelsTimeToFirstToken, GenAILabelsTokenUsage, HTTPLabels,
+	MalformedEventLabels, Metrics, MonitoredDenialLabels, RouteLabels, TraceExemplar,
+};
 use crate::telemetry::trc;
 use crate::telemetry::trc::TraceParent;
+use crate::telemetry::usage::Usage;
 use crate::transport::stream::{TCPConnectionInfo, TLSConnectionInfo};
 use crate::types::agent::{
 	BackendName, BindName, GatewayName, ListenerName, RouteName, RouteRuleName, Target,
@@ -79,6 +89,126 @@ pub struct Config {
 	pub filter: Option<Arc<cel::Expression>>,
 	pub fields: Arc<LoggingFields>,
 	pub metric_fields: Arc<MetricFields>,
+	pub body_capture: Option<Arc<BodyCapture>>,
+}
+
+/// BodyCapture records a preview of request and response bodies in the access log.
+#[derive(serde::Serialize, Debug)]
+pub struct BodyCapture {
+	pub condition: Option<Arc<cel::Expression>>,
+	pub request: bool,
+	pub response: bool,
+	pub max_bytes: usize,
+	#[serde(serialize_with = "ser_display_iter")]
+	pub redact: Vec<regex::Regex>,
+}
+
+/// Bytes captured beyond max_bytes, so redaction patterns that cross the limit still match in full. Matches
+/// longer than this may be partially shown.
+const REDACTION_SLACK: usize = 1024;
+
+const REDACTED: &str = "<redacted>";
+
+impl BodyCapture {
+	/// capture_limit is the number of bytes of a body to keep for its preview.
+	fn capture_limit(&self) -> usize {
+		self.max_bytes.saturating_add(REDACTION_SLACK)
+	}
+
+	/// preview renders a captured body for the log. Compressed bodies are decompressed, binary bodies are
+	/// summarized, and any matches of the redaction patterns are removed before it is cut to max_bytes.
+	fn preview(&self, captured: &CapturedBody) -> String {
+		let (raw, total) = {
+			let c = captured.inner.lock().expect("mutex acquired");
+			(c.data.clone().freeze(), c.total)
+		};
+		let mut truncated = total > raw.len();
+		let data = match captured
+			.encoding
+			.as_ref()
+			.and_then(|e| e.to_str().ok())
+			.and_then(|e| crate::http::compression::decode_prefix(&raw, e, self.capture_limit()))
+		{
+			Some(decoded) => {
+				truncated |= decoded.len() >= self.capture_limit();
+				decoded
+			},
+			None => raw,
+		};
+		let text = match std::str::from_utf8(&data) {
+			Ok(s) => s,
+			// The body was cut off in the middle of a character
+			Err(e) if e.error_len().is_none() => {
+				std::str::from_utf8(&data[..e.valid_up_to()]).expect("valid up to this point")
+			},
+			Err(_) => return format!("<binary: {total} bytes>"),
+		};
+		if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+			return format!("<binary: {total} bytes>");
+		}
+		let mut text = text.to_string();
+		for r in &self.redact {
+			text = r.replace_all(&text, REDACTED).into_owned();
+		}
+		if text.len() > self.max_bytes {
+			let mut cut = self.max_bytes;
+			while !text.is_char_boundary(cut) {
+				cut -= 1;
+			}
+			// Drop a redaction marker that would be cut off, rather than show part of it.
+			if let Some((start, _)) = text
+				.match_indices(REDACTED)
+				.find(|(start, m)| *start < cut && cut < start + m.len())
+			{
+				cut = start;
+			}
+			text.truncate(cut);
+			truncated = true;
+		}
+		if truncated {
+			text.push_str("...");
+		}
+		text
+	}
+}
+
+/// CapturedBody holds the start of a body, as it is streamed.
+#[derive(Clone, Debug)]
+pub struct CapturedBody {
+	inner: Arc<Mutex<Captured>>,
+	encoding: Option<::http::HeaderValue>,
+	limit: usize,
+}
+
+#[derive(Debug, Default)]
+struct Captured {
+	data: BytesMut,
+	total: usize,
+}
+
+impl CapturedBody {
+	fn new(limit: usize, headers: &::http::HeaderMap) -> Self {
+		Self {
+			inner: Default::default(),
+			encoding: headers.get(::http::header::CONTENT_ENCODING).cloned(),
+			limit,
+		}
+	}
+
+	fn record(&self, data: &[u8]) {
+		let mut c = self.inner.lock().expect("mutex acquired");
+		let remaining = self.limit.saturating_sub(c.data.len());
+		c.data.extend_from_slice(&data[..remaining.min(data.len())]);
+		c.total += data.len();
+	}
+
+	/// wrap returns a body that records its contents into the capture as it is read.
+	pub fn wrap(&self, body: crate::http::Body) -> crate::http::Body {
+		crate::http::Body::new(CaptureBody {
+			body,
+			capture: self.clone(),
+		})
+	}
 }
 
 #[derive(serde::Serialize, Default, Clone, Debug)]
@@ -165,6 +295,7 @@ pub struct CelLogging {
 	pub filter: Option<Arc<cel::Expression>>,
 	pub fields: Arc<LoggingFields>,
 	pub metric_fields: Arc<MetricFields>,
+	pub body_capture: Option<Arc<BodyCapture>>,
 	pub tracing_sampler: TraceSampler,
 }
 
@@ -306,12 +437,16 @@ impl CelLogging {
 		for v in cfg.metric_fields.add.values_unordered() {
 			cel_context.register_expression(v.as_ref());
 		}
+		if let Some(c) = cfg.body_capture.as_ref().and_then(|b| b.condition.as_ref()) {
+			cel_context.register_expression(c.as_ref());
+		}
 
 		Self {
 			cel_context,
 			filter: cfg.filter,
 			fields: cfg.fields,
 			metric_fields: cfg.metric_fields,
+			body_capture: cfg.body_capture,
 			tracing_sampler: TraceSampler {
 				random_sampling: tracing_config.random_sampling,
 				client_sampling: tracing_config.client_sampling,
@@ -335,6 +470,7 @@ impl CelLogging {
 			filter,
 			fields,
 			metric_fields,
+			body_capture: _,
 			tracing_sampler: _,
 		} = self;
 		let executor = cel_context.build()?;
@@ -380,6 +516,7 @@ impl RequestLog {
 			cel,
 			metrics,
 			start,
+			upstream_start: None,
 			tcp_info,
 			tls_info: None,
 			tracer: None,
@@ -398,14 +535,25 @@ impl RequestLog {
 			jwt_sub: None,
 			retry_attempt: None,
 			error: None,
+			error_type: Default::default(),
 			grpc_status: Default::default(),
 			mcp_status: Default::default(),
 			incoming_span: None,
 			outgoing_span: None,
 			llm_request: None,
 			llm_response: Default::default(),
+			llm_sample: None,
+			llm_prompt_hash: None,
 			a2a_method: None,
+			gateway_hops: None,
+			config_version: None,
 			inference_pool: None,
+			request_body: None,
+			response_body: None,
+			bandit: None,
+			failover: None,
+			backend_override: None,
+			monitored_denials: Vec::new(),
 		}
 	}
 }
@@ -414,6 +562,8 @@ pub struct RequestLog {
 	pub cel: CelLogging,
 	pub metrics: Arc<Metrics>,
 	pub start: Instant,
+	// Start of the latest attempt to call the backend, once one is made
+	pub upstream_start: Option<Instant>,
 	pub tcp_info: TCPConnectionInfo,
 
 	// Set only for TLS traffic
@@ -441,6 +591,9 @@ pub struct RequestLog {
 
 	pub retry_attempt: Option<u8>,
 	pub error: Option<String>,
+	/// Classification of the error, set by the gateway or when an upstream response carries an error.
+	/// MCP and LLM errors are recorded on their own logs; see [RequestLog::classify_error].
+	pub error_type: AsyncLog<ErrorType>,
 
 	pub grpc_status: AsyncLog<u8>,
 	pub mcp_status: AsyncLog<mcp::sse::MCPInfo>,
@@ -450,13 +603,113 @@ pub struct RequestLog {
 
 	pub llm_request: Option<llm::LLMRequest>,
 	pub llm_response: AsyncLog<llm::LLMResponse>,
+	// Set only if the request was selected for response sampling
+	pub llm_sample: Option<llm::sampling::Sample>,
+	// Set only if prompt hashing is enabled on the route
+	pub llm_prompt_hash: Option<llm::prompthash::PromptHash>,
 
 	pub a2a_method: Option<&'static str>,
+	// The gateways the request passed through, if loop detection is enabled
+	pub gateway_hops: Option<Strng>,
+	// Hash of the configuration active when the request started
+	pub config_version: Option<Strng>,
 
 	pub inference_pool: Option<SocketAddr>,
+
+	// Set only if body capture is enabled for the request
+	pub request_body: Option<CapturedBody>,
+	pub response_body: Option<CapturedBody>,
+
+	// Set only if the backend was selected by a bandit policy
+	pub bandit: Option<BanditOutcome>,
+	// Set only if the backend was selected by a failover policy
+	pub failover: Option<FailoverOutcome>,
+	// Set only if the backend was overridden by the request, to the name of the backend it selected
+	pub backend_override: Option<Strng>,
+
+	// Policies in monitor mode that would have denied the request
+	pub monitored_denials: Vec<&'static str>,
 }
 
 impl RequestLog {
+	/// classify_error returns the type of error the request failed with, if any. Errors from the gateway take
+	/// precedence, as they determine the response the client received.
+	fn classify_error(&self) -> Option<ErrorType> {
+		let mut error_type = self.error_type.load();
+		if error_type.is_none() {
+			self
+				.mcp_status
+				.non_atomic_mutate(|m| error_type = m.error_type);
+		}
+		if error_type.is_none() {
+			self
+				.llm_response
+				.non_atomic_mutate(|r| error_type = r.error_type);
+		}
+		error_type
+	}
+
+	/// capture_request_body starts capturing the request body, if body capture is enabled for the request.
+	/// The response body will be captured as well, once capture_response_body is called.
+	pub fn capture_request_body(&mut self, req: &mut crate::http::Request) {
+		let Some(bc) = self.cel.body_capture.clone() else {
+			return;
+		};
+		if let Some(condition) = &bc.condition {
+			let Ok(exec) = self.cel.build() else {
+				return;
+			};
+			if !exec.executor.eval_bool(condition) {
+				return;
+			}
+		}
+		if bc.request {
+			let capture = CapturedBody::new(bc.capture_limit(), req.headers());
+			let body = std::mem::take(req.body_mut());
+			*req.body_mut() = capture.wrap(body);
+			self.request_body = Some(capture);
+		}
+		if bc.response {
+			// Placeholder to record the decision; the encoding is set from the response.
+			self.response_body = Some(CapturedBody::new(bc.capture_limit(), &Default::default()));
+		}
+	}
+
+	/// capture_response_body wraps the response body to capture it, if capture_request_body enabled it.
+	pub fn capture_response_body(&mut self, resp: &mut crate::http::Response) {
+		let Some(capture) = self.response_body.as_mut() else {
+			return;
+		};
+		*capture = CapturedBody::new(capture.limit, resp.headers());
+		let body = std::mem::take(resp.body_mut());
+		*resp.body_mut() = capture.wrap(body);
+	}
+
+	/// record_monitored records that a policy in monitor mode would have denied the request.
+	pub fn record_monitored(&mut self, policy: &'static str) {
+		tracing::debug!(policy, "request allowed by policy in monitor mode");
+		self
+			.metrics
+			.monitored_denials
+			.get_or_create(&MonitoredDenialLabels {
+				route: (&self.route_name).into(),
+				policy: strng::new(policy).into(),
+			})
+			.inc();
+		self.monitored_denials.push(policy);
+	}
+
+	/// record_duplicate_prompt records that the request's prompt was already seen in the prompt hashing window.
+	pub fn record_duplicate_prompt(&mut self) {
+		self
+			.metrics
+			.duplicate_prompts
+			.get_or_create(&RouteLabels {
+				route: (&self.route_name).into(),
+			})
+			.inc();
+	}
+
 	pub fn trace_sampled(&self, tp: Option<&TraceParent>) -> bool {
 		let TraceSampler {
 			random_sampling,
@@ -482,12 +735,80 @@ impl RequestLog {
 	}
 }
 
+fn record_malformed_events(metrics: &Metrics, r: &llm::LLMResponse) {
+	for (reason, count) in [
+		(MalformedEvent::Oversized, r.oversized_events),
+		(MalformedEvent::Invalid, r.invalid_events),
+	] {
+		if count > 0 {
+			metrics
+				.llm_stream_malformed_events
+				.get_or_create(&MalformedEventLabels {
+					provider: r.request.provider.clone().into(),
+					reason: strng::new(reason.as_str()).into(),
+				})
+				.inc_by(count);
+		}
+	}
+}
+
 impl Drop for DropOnLog {
 	fn drop(&mut self) {
 		let Some(mut log) = self.log.take() else {
 			return;
 		};
 
+		let mut mcp_monitored_denial = false;
+		log
+			.mcp_status
+			.non_atomic_mutate(|m| mcp_monitored_denial = m.monitored_denial);
+		if mcp_monitored_denial {
+			log.record_monitored("mcpAuthorization");
+		}
+
+		let bandit_decision = log.bandit.as_ref().map(|b| b.decision.as_str());
+		let failover_priority = log.failover.as_ref().map(|f| f.priority);
+		let monitored_denials =
+			(!log.monitored_denials.is_empty()).then(|| log.monitored_denials.iter().join(","));
+		let failed = log.error.is_some()
+			|| log
+				.status
+				.is_none_or(|s| s.is_server_error() || s == ::http::StatusCode::TOO_MANY_REQUESTS);
+		// Latency of the backend call that produced the response. Requests that never reached the backend
+		// have failed, which is penalized on its own.
+		let upstream_latency = log.upstream_start.map(|s| s.elapsed()).unwrap_or_default();
+		if let Some(bandit) = log.bandit.take() {
+			// Put the response back, as it is still needed for logging below.
+			let llm_response = log.llm_response.take();
+			bandit.complete(
+				upstream_latency,
+				log.status,
+				failed,
+				log.outgoing_span.as_ref(),
+				llm_response.as_ref().and_then(|r| r.completion.as_deref()),
+			);
+			log.llm_response.store(llm_response);
+		}
+		if let Some(failover) = log.failover.take() {
+			failover.complete(upstream_latency, failed);
+		}
+		if let Some(sample) = log.llm_sample.take() {
+			// Put the response back, as it is still needed for logging below.
+			let llm_response = log.llm_response.take();
+			sample.complete(
+				llm_response.as_ref(),
+				log.route_name.as_deref(),
+				log.jwt_sub.as_deref(),
+				log.status,
+				upstream_latency,
+			);
+			log.llm_response.store(llm_response);
+		}
+
+		log
+			.llm_response
+			.non_atomic_mutate(|r| record_malformed_events(&log.metrics, r));
+		let error_type = log.classify_error();
 		let mut http_labels = HTTPLabels {
 			bind: (&log.bind_name).into(),
 			gateway: (&log.gateway_name).into(),
@@ -497,15 +818,17 @@ impl Drop for DropOnLog {
 			backend: (&log.backend_name).into(),
 			method: log.method.clone().into(),
 			status: log.status.as_ref().map(|s| s.as_u16()).into(),
+			error_type,
 			custom: CustomField::default(),
 		};
 
 		let enable_custom_metrics = log.cel.metric_fields.add.len() > 0;
+		let enable_usage = log.metrics.usage.is_some() && log.llm_request.is_some();
 
 		let enable_trace = log.tracer.is_some();
 		// We will later check it also matches a filter, but filter is slower
 		let maybe_enable_log = agent_core::telemetry::enabled("request", &Level::INFO);
-		if !maybe_enable_log && !enable_trace && !enable_custom_metrics {
+		if !maybe_enable_log && !enable_trace && !enable_custom_metrics && !enable_usage {
 			// Report our non-customized metrics
 			log.metrics.requests.get_or_create(&http_labels).inc();
 			return;
@@ -525,24 +848,38 @@ impl Drop for DropOnLog {
 			return;
 		};
 
-		let custom_metric_fields = CustomField::new(
-			// For metrics, keep empty values which will become 'unknown'
-			cel_exec
-				.eval_keep_empty(&cel_exec.metric_fields.add, true)
-				.into_iter()
-				.map(|(k, v)| {
-					(
-						strng::new(k),
-						v.and_then(|v| match v {
-							Value::String(s) => Some(strng::new(s)),
-							_ => None,
-						}),
-					)
-				}),
-		);
+		// For metrics, keep empty values which will become 'unknown'
+		let metric_fields = cel_exec
+			.eval_keep_empty(&cel_exec.metric_fields.add, true)
+			.into_iter()
+			.map(|(k, v)| {
+				(
+					strng::new(k),
+					v.and_then(|v| match v {
+						Value::String(s) => Some(strng::new(s)),
+						_ => None,
+					}),
+				)
+			})
+			.collect_vec();
+		let custom_metric_fields = CustomField::new(metric_fields.iter().cloned());
 		http_labels.custom = custom_metric_fields.clone();
 		log.metrics.requests.get_or_create(&http_labels).inc();
 
+		if enable_usage && let Some(usage) = &log.metrics.usage {
+			usage.record(Usage {
+				route: log.route_name.as_deref(),
+				identity: log.jwt_sub.as_deref(),
+				model: llm_response
+					.as_ref()
+					.and_then(|l| l.provider_model.as_deref())
+					.or(log.llm_request.as_ref().map(|l| l.request_model.as_str())),
+				fields: &metric_fields,
+				input_tokens: llm_response.as_ref().and_then(|l| l.input_tokens()),
+				output_tokens: llm_response.as_ref().and_then(|l| l.output_tokens),
+			});
+		}
+
 		if let Some(llm_response) = &llm_response {
 			let gen_ai_labels = Arc::new(GenAILabels {
 				gen_ai_operation_name: strng::literal!("chat").into(),
@@ -581,11 +918,21 @@ impl Drop for DropOnLog {
 				let ttft = ft - log.start;
 				// Duration from start of request to first token
 				// This is the start of when WE got the request, but it should probably be when we SENT the upstream.
+				let exemplar = log
+					.outgoing_span
+					.as_ref()
+					.filter(|tp| enable_trace && tp.is_sampled())
+					.map(|tp| TraceExemplar {
+						trace_id: tp.trace_id(),
+					});
 				log
 					.metrics
 					.gen_ai_time_to_first_token
-					.get_or_create(&gen_ai_labels)
-					.observe(ttft.as_secs_f64());
+					.get_or_create(&GenAILabelsTimeToFirstToken {
+						route: (&log.route_name).into(),
+						common: gen_ai_labels.clone().into(),
+					})
+					.observe(ttft.as_secs_f64(), exemplar, None);
 
 				if let Some(ot) = llm_response.output_tokens {
 					let first_to_last = end_time - ft;
@@ -605,6 +952,12 @@ impl Drop for DropOnLog {
 		}
 
 		let dur = format!("{}ms", duration.as_millis());
+		let rtt = log
+			.tcp_info
+			.rtt
+			.current()
+			.map(|rtt| format!("{}us", rtt.as_micros()));
+		let tls = log.tls_info.as_ref();
 		let grpc = log.grpc_status.load();
 
 		let input_tokens = llm_response.as_ref().and_then(|l| l.input_tokens());
@@ -616,6 +969,14 @@ impl Drop for DropOnLog {
 
 		let fields = cel_exec.fields.as_ref();
 
+		let (request_body, response_body) = match log.cel.body_capture.as_deref() {
+			Some(bc) => (
+				log.request_body.as_ref().map(|c| bc.preview(c)),
+				log.response_body.as_ref().map(|c| bc.preview(c)),
+			),
+			None => (None, None),
+		};
+
 		let mut kv = vec![
 			("gateway", log.gateway_name.display()),
 			("listener", log.listener_name.display()),
@@ -623,6 +984,22 @@ impl Drop for DropOnLog {
 			("route", log.route_name.display()),
 			("endpoint", log.endpoint.display()),
 			("src.addr", Some(display(&log.tcp_info.peer_addr))),
+			("src.rtt", rtt.display()),
+			(
+				"tls.version",
+				tls.and_then(|t| t.version.as_ref()).map(display),
+			),
+			(
+				"tls.cipher",
+				tls.and_then(|t| t.cipher.as_ref()).map(display),
+			),
+			(
+				"tls.alpn",
+				tls
+					.and_then(|t| t.negotiated_alpn)
+					.map(|a| display(a.as_str())),
+			),
+			("tls.resumed", tls.map(|t| t.resumed.into())),
 			("http.method", log.method.display()),
 			("http.host", log.host.display()),
 			("http.path", log.path.display()),
@@ -637,6 +1014,8 @@ impl Drop for DropOnLog {
 			("span.id", span_id.display()),
 			("jwt.sub", log.jwt_sub.display()),
 			("a2a.method", log.a2a_method.display()),
+			("gateway.hops", log.gateway_hops.display()),
+			("config.version", log.config_version.display()),
 			(
 				"mcp.target",
 				mcp
@@ -664,6 +1043,14 @@ impl Drop for DropOnLog {
 				log.llm_request.as_ref().map(|l| display(&l.request_model)),
 			),
 			("llm.request.tokens", input_tokens.map(Into::into)),
+			(
+				"llm.prompt.hash",
+				log.llm_prompt_hash.as_ref().map(|h| display(&h.hash)),
+			),
+			(
+				"llm.prompt.repeats",
+				log.llm_prompt_hash.as_ref().map(|h| h.count.into()),
+			),
 			(
 				"llm.response.model",
 				llm_response
@@ -678,7 +1065,14 @@ impl Drop for DropOnLog {
 					.map(Into::into),
 			),
 			("retry.attempt", log.retry_attempt.display()),
+			("bandit.decision", bandit_decision.display()),
+			("failover.priority", failover_priority.display()),
+			("backend.override", log.backend_override.display()),
+			("policy.monitored", monitored_denials.display()),
 			("error", log.error.display()),
+			("error.type", error_type.map(|e| display(e.as_str()))),
+			("http.request.body", request_body.display()),
+			("http.response.body", response_body.display()),
 			("duration", Some(dur.as_str().into())),
 		];
 		if enable_trace && let Some(t) = &log.tracer {
@@ -739,9 +1133,10 @@ where
 		match result {
 			Some(Ok(frame)) => {
 				if let Some(trailer) = frame.trailers_ref()
-					&& let Some(grpc) = this.log.as_mut().map(|log| log.grpc_status.clone())
+					&& let Some(log) = this.log.as_mut()
 				{
-					crate::proxy::httpproxy::maybe_set_grpc_status(&grpc, trailer);
+					crate::proxy::httpproxy::maybe_set_grpc_status(&log.grpc_status, trailer);
+					log.cel.ctx().with_response_trailers(trailer);
 				}
 				Poll::Ready(Some(Ok(frame)))
 			},
@@ -757,3 +1152,108 @@ where
 		self.body.size_hint()
 	}
 }
+
+pin_project_lite::pin_project! {
+	/// CaptureBody passes through a body, recording its start into a CapturedBody.
+	struct CaptureBody {
+		#[pin]
+		body: crate::http::Body,
+		capture: CapturedBody,
+	}
+}
+
+impl Body for CaptureBody {
+	type Data = Bytes;
+	type Error = crate::http::Error;
+
+	fn poll_frame(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+		let this = self.project();
+		let result = ready!(this.body.poll_frame(cx));
+		if let Some(Ok(frame)) = &result
+			&& let Some(data) = frame.data_ref()
+		{
+			this.capture.record(data);
+		}
+		Poll::Ready(result)
+	}
+
+	fn is_end_stream(&self) -> bool {
+		self.body.is_end_stream()
+	}
+
+	fn size_hint(&self) -> SizeHint {
+		self.body.size_hint()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn capture(max_bytes: usize, redact: &[&str]) -> BodyCapture {
+		BodyCapture {
+			condition: None,
+			request: true,
+			response: true,
+			max_bytes,
+			redact: redact
+				.iter()
+				.map(|r| regex::Regex::new(r).unwrap())
+				.collect(),
+		}
+	}
+
+	fn captured(bc: &BodyCapture, encoding: Option<&'static str>, chunks: &[&[u8]]) -> CapturedBody {
+		let mut headers = ::http::HeaderMap::new();
+		if let Some(e) = encoding {
+			headers.insert(
+				::http::header::CONTENT_ENCODING,
+				::http::HeaderValue::from_static(e),
+			);
+		}
+		let c = CapturedBody::new(bc.capture_limit(), &headers);
+		for chunk in chunks {
+			c.record(chunk);
+		}
+		c
+	}
+
+	#[test]
+	fn preview() {
+		let bc = capture(16, &[r#""token":\s*"[^"]*""#]);
+		let c = captured(&bc, None, &[br#"{"token": "abc"}"#]);
+		assert_eq!(bc.preview(&c), r#"{<redacted>}"#);
+
+		let c = captured(&bc, None, &[b"hello ", b"world, this is long"]);
+		assert_eq!(bc.preview(&c), "hello world, thi...");
+
+		let c = captured(&bc, None, &[&[0x00, 0x01, 0x02, 0xff]]);
+		assert_eq!(bc.preview(&c), "<binary: 4 bytes>");
+	}
+
+	#[test]
+	fn preview_redacts_across_limit() {
+		// The key crosses max_bytes, so it must be matched before the preview is cut.
+		let bc = capture(16, &[r"sk-[a-zA-Z0-9]{20}"]);
+		let c = captured(&bc, None, &[b"key=sk-abcdefghijklmnopqrst&rest"]);
+		assert_eq!(bc.preview(&c), "key=<redacted>&r...");
+
+		// A marker that would be cut off is dropped entirely.
+		let bc = capture(8, &[r"sk-[a-zA-Z0-9]{20}"]);
+		let c = captured(&bc, None, &[b"key=sk-abcdefghijklmnopqrst"]);
+		assert_eq!(bc.preview(&c), "key=...");
+	}
+
+	#[tokio::test]
+	async fn preview_compressed() {
+		let bc = capture(1024, &[]);
+		let body = crate::http::compression::encode_body(b"compressed body", "gzip")
+			.await
+			.unwrap();
+		let c = captured(&bc, Some("gzip"), &[&body]);
+		assert_eq!(bc.preview(&c), "compressed body");
+	}
+}
@@ -1,14 +1,19 @@
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use agent_core::metrics::{CustomField, DefaultedUnknown, EncodeArc, EncodeDisplay};
 use agent_core::strng::RichStrng;
 use agent_core::version;
 use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::exemplar::HistogramWithExemplars;
 use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::histogram::Histogram as PromHistogram;
 use prometheus_client::metrics::info::Info;
 use prometheus_client::registry::Registry;
 
+use crate::telemetry::errors::ErrorType;
+use crate::telemetry::usage::UsageReporter;
 use crate::types::agent::BindProtocol;
 
 #[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
@@ -22,6 +27,8 @@ pub struct HTTPLabels {
 
 	pub method: DefaultedUnknown<EncodeDisplay<http::Method>>,
 	pub status: DefaultedUnknown<EncodeDisplay<u16>>,
+	/// Empty for requests that did not fail.
+	pub error_type: Option<ErrorType>,
 
 	#[prometheus(flatten)]
 	pub custom: CustomField,
@@ -38,6 +45,19 @@ pub struct GenAILabels {
 	pub custom: CustomField,
 }
 
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct GenAILabelsTimeToFirstToken {
+	pub route: DefaultedUnknown<RichStrng>,
+	#[prometheus(flatten)]
+	pub common: EncodeArc<GenAILabels>,
+}
+
+/// TraceExemplar links an observation to the trace of the request it came from.
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct TraceExemplar {
+	pub trace_id: String,
+}
+
 #[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
 pub struct GenAILabelsTokenUsage {
 	pub gen_ai_token_type: DefaultedUnknown<RichStrng>,
@@ -53,6 +73,47 @@ pub struct TCPLabels {
 	pub protocol: BindProtocol,
 }
 
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct PoolPartitionLabels {
+	pub target: DefaultedUnknown<RichStrng>,
+	/// The route whose pool partition policy applied. Partition keys themselves are not exposed, as they are
+	/// derived from requests and have unbounded cardinality.
+	pub route: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct IdempotencyLabels {
+	pub route: DefaultedUnknown<RichStrng>,
+	/// Either 'replayed', when a stored response was returned, 'conflict', when a request with the same key
+	/// was still in progress, or 'mismatch', when the key was reused for a different request.
+	pub result: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct MonitoredDenialLabels {
+	pub route: DefaultedUnknown<RichStrng>,
+	/// The type of policy, such as 'authorization' or 'localRateLimit'.
+	pub policy: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct RouteLabels {
+	pub route: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct MalformedEventLabels {
+	pub provider: DefaultedUnknown<RichStrng>,
+	/// Either 'oversized', for events larger than the maximum event size, or 'invalid', for events that could not
+	/// be decoded.
+	pub reason: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Default, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct ConfigLabels {
+	pub hash: DefaultedUnknown<RichStrng>,
+}
+
 type Counter = Family<HTTPLabels, prometheus_client::metrics::counter::Counter>;
 type Histogram<T> = Family<T, prometheus_client::metrics::histogram::Histogram>;
 type TCPCounter = Family<TCPLabels, prometheus_client::metrics::counter::Counter>;
@@ -70,7 +131,29 @@ pub struct Metrics {
 	pub gen_ai_token_usage: Histogram<GenAILabelsTokenUsage>,
 	pub gen_ai_request_duration: Histogram<GenAILabels>,
 	pub gen_ai_time_per_output_token: Histogram<GenAILabels>,
-	pub gen_ai_time_to_first_token: Histogram<GenAILabels>,
+	/// Time to first token, with the trace of the request as an exemplar when it is sampled.
+	pub gen_ai_time_to_first_token:
+		Family<GenAILabelsTimeToFirstToken, HistogramWithExemplars<TraceExemplar>>,
+
+	pub pool_partition_requests:
+		Family<PoolPartitionLabels, prometheus_client::metrics::counter::Counter>,
+	pub pool_partition_throttled:
+		Family<PoolPartitionLabels, prometheus_client::metrics::counter::Counter>,
+	pub pool_partition_active_requests: Family<PoolPartitionLabels, Gauge>,
+
+	pub idempotency_dedupes: Family<IdempotencyLabels, prometheus_client::metrics::counter::Counter>,
+	pub monitored_denials:
+		Family<MonitoredDenialLabels, prometheus_client::metrics::counter::Counter>,
+	pub duplicate_prompts: Family<RouteLabels, prometheus_client::metrics::counter::Counter>,
+	pub llm_stream_malformed_events:
+		Family<MalformedEventLabels, prometheus_client::metrics::counter::Counter>,
+
+	/// Set to 1 for the hash of the active configuration.
+	pub config_info: Family<ConfigLabels, Gauge>,
+	pub config_changes: prometheus_client::metrics::counter::Counter,
+
+	/// Aggregates token usage into periodic reports, if enabled.
+	pub usage: Option<Arc<UsageReporter>>,
 }
 
 impl Metrics {
@@ -111,15 +194,36 @@ impl Metrics {
 			gen_ai_time_per_output_token.clone(),
 		);
 
-		let gen_ai_time_to_first_token = Family::<GenAILabels, _>::new_with_constructor(move || {
-			PromHistogram::new(FIRST_TOKEN_BUCKET)
-		});
+		let gen_ai_time_to_first_token =
+			Family::<GenAILabelsTimeToFirstToken, _>::new_with_constructor(move || {
+				HistogramWithExemplars::new(FIRST_TOKEN_BUCKET.into_iter())
+			});
 		registry.register(
 			"gen_ai_server_time_to_first_token",
 			"Time to generate the first token for a given request",
 			gen_ai_time_to_first_token.clone(),
 		);
 
+		let pool_partition_active_requests = Family::<PoolPartitionLabels, Gauge>::default();
+		registry.register(
+			"upstream_pool_partition_active_requests",
+			"The number of in-flight upstream requests per connection pool partition",
+			pool_partition_active_requests.clone(),
+		);
+
+		let config_info = Family::<ConfigLabels, Gauge>::default();
+		registry.register(
+			"config_info",
+			"The hash of the active configuration",
+			config_info.clone(),
+		);
+		let config_changes = prometheus_client::metrics::counter::Counter::default();
+		registry.register(
+			"config_changes",
+			"The total number of times the active configuration changed",
+			config_changes.clone(),
+		);
+
 		Metrics {
 			requests: build(
 				registry,
@@ -135,6 +239,47 @@ impl Metrics {
 			gen_ai_request_duration,
 			gen_ai_time_per_output_token,
 			gen_ai_time_to_first_token,
+			pool_partition_requests: build(
+				registry,
+				"upstream_pool_partition_requests",
+				"The total number of upstream requests sent through a partitioned connection pool",
+			),
+			pool_partition_throttled: build(
+				registry,
+				"upstream_pool_partition_throttled",
+				"The total number of upstream requests that waited for the partition's max streams limit",
+			),
+			pool_partition_active_requests,
+			idempotency_dedupes: build(
+				registry,
+				"idempotency_dedupes",
+				"The total number of requests deduplicated by their idempotency key",
+			),
+			monitored_denials: build(
+				registry,
+				"policy_monitored_denials",
+				"The total number of requests that a policy in monitor mode would have denied",
+			),
+			duplicate_prompts: build(
+				registry,
+				"llm_duplicate_prompts",
+				"The total number of LLM requests whose prompt was already seen in the prompt hashing window",
+			),
+			llm_stream_malformed_events: build(
+				registry,
+				"llm_stream_malformed_events",
+				"The total number of events in LLM provider streams that were skipped because they were oversized or malformed",
+			),
+			config_info,
+			config_changes,
+			usage: None,
+		}
+	}
+
+	pub fn with_usage_reporter(self, usage: Arc<UsageReporter>) -> Self {
+		Metrics {
+			usage: Some(usage),
+			..self
 		}
 	}
 }
@@ -166,3 +311,63 @@ const OUTPUT_TOKEN_BUCKET: [f64; 13] = [
 const FIRST_TOKEN_BUCKET: [f64; 16] = [
 	0.001, 0.005, 0.01, 0.02, 0.04, 0.06, 0.08, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 7.5, 10.0,
 ];
+
+#[cfg(test)]
+mod tests {
+	use agent_core::strng::{self, Strng};
+	use prometheus_client::encoding::text::encode;
+
+	use super::*;
+
+	#[test]
+	fn time_to_first_token() {
+		let mut registry = Registry::default();
+		let metrics = Metrics::new(&mut registry);
+		let common: EncodeArc<GenAILabels> = Arc::new(GenAILabels {
+			gen_ai_system: strng::literal!("openai").into(),
+			..Default::default()
+		})
+		.into();
+		metrics
+			.gen_ai_time_to_first_token
+			.get_or_create(&GenAILabelsTimeToFirstToken {
+				route: Some(strng::literal!("chat")).into(),
+				common: common.clone(),
+			})
+			.observe(
+				0.03,
+				Some(TraceExemplar {
+					trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+				}),
+				None,
+			);
+		metrics
+			.gen_ai_time_to_first_token
+			.get_or_create(&GenAILabelsTimeToFirstToken {
+				route: None::<Strng>.into(),
+				common,
+			})
+			.observe(0.5, None, None);
+
+		let mut out = String::new();
+		encode(&mut out, &registry).unwrap();
+		let ttft: Vec<_> = out
+			.lines()
+			.filter(|l| l.starts_with("gen_ai_server_time_to_first_token"))
+			.collect();
+		assert!(
+			ttft.iter().any(|l| l.contains(r#"route="chat""#)
+				&& l.contains(r#"gen_ai_system="openai""#)
+				&& l.contains(r#"le="0.04""#)
+				&& l.contains(r#"# {trace_id="4bf92f3577b34da6a3ce929d0e0e4736"} 0.03"#)),
+			"{out}"
+		);
+		assert!(
+			ttft
+				.iter()
+				.any(|l| l.contains(r#"route="unknown""#) && l.contains(r#"le="0.5""#)),
+			"{out}"
+		);
+		assert!(!out.contains("llm_streaming_time_to_first_token"), "{out}");
+	}
+}
@@ -1,3 +1,5 @@
+pub mod errors;
 pub mod log;
 pub mod metrics;
 pub mod trc;
+pub mod usage;
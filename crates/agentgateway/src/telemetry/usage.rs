@@ -0,0 +1,438 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use agent_core::drain::DrainWatcher;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+
+use crate::client::Client;
+use crate::http::Body;
+use crate::*;
+
+/// The label used in place of dimension values for groups that are too small to be reported individually.
+const SUPPRESSED: &str = "other";
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+	#[serde(with = "serde_dur")]
+	pub interval: Duration,
+	pub group_by: Vec<Dimension>,
+	/// Groups with fewer requests than this in a reporting window are merged into a single 'other' group,
+	/// so reports cannot be used to single out individual callers.
+	pub min_group_requests: u64,
+	pub pricing: HashMap<Strng, ModelPricing>,
+	pub directory: Option<PathBuf>,
+	pub format: Format,
+	#[serde(serialize_with = "ser_display_option")]
+	pub webhook: Option<::http::Uri>,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Format {
+	#[default]
+	Json,
+	Csv,
+}
+
+impl Format {
+	fn extension(&self) -> &'static str {
+		match self {
+			Format::Json => "json",
+			Format::Csv => "csv",
+		}
+	}
+
+	fn content_type(&self) -> &'static str {
+		match self {
+			Format::Json => "application/json",
+			Format::Csv => "text/csv",
+		}
+	}
+}
+
+/// Cost of a model, in an arbitrary currency, per million tokens.
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ModelPricing {
+	#[serde(default)]
+	pub input: f64,
+	#[serde(default)]
+	pub output: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dimension {
+	Route,
+	Identity,
+	Model,
+	/// A custom metric field, from `config.metrics.fields.add`.
+	Field(Strng),
+}
+
+impl Dimension {
+	pub fn parse(s: &str) -> Dimension {
+		match s {
+			"route" => Dimension::Route,
+			"identity" => Dimension::Identity,
+			"model" => Dimension::Model,
+			other => Dimension::Field(strng::new(other)),
+		}
+	}
+
+	fn name(&self) -> &str {
+		match self {
+			Dimension::Route => "route",
+			Dimension::Identity => "identity",
+			Dimension::Model => "model",
+			Dimension::Field(f) => f.as_str(),
+		}
+	}
+}
+
+impl Serialize for Dimension {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.name())
+	}
+}
+
+/// A single completed LLM request, as seen by the usage reporter.
+#[derive(Debug, Default)]
+pub struct Usage<'a> {
+	pub route: Option<&'a str>,
+	pub identity: Option<&'a str>,
+	pub model: Option<&'a str>,
+	pub fields: &'a [(Strng, Option<Strng>)],
+	pub input_tokens: Option<u64>,
+	pub output_tokens: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Totals {
+	requests: u64,
+	input_tokens: u64,
+	output_tokens: u64,
+	cost: f64,
+}
+
+impl Totals {
+	fn merge(&mut self, other: &Totals) {
+		self.requests += other.requests;
+		self.input_tokens += other.input_tokens;
+		self.output_tokens += other.output_tokens;
+		self.cost += other.cost;
+	}
+}
+
+#[derive(Debug)]
+struct Window {
+	start: SystemTime,
+	groups: HashMap<Vec<Strng>, Totals>,
+}
+
+impl Window {
+	fn new() -> Self {
+		Window {
+			start: SystemTime::now(),
+			groups: Default::default(),
+		}
+	}
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+	start: DateTime<Utc>,
+	end: DateTime<Utc>,
+	groups: Vec<ReportGroup>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportGroup {
+	labels: IndexMap<String, Strng>,
+	#[serde(flatten)]
+	totals: Totals,
+}
+
+/// UsageReporter aggregates token usage from completed requests, and periodically exports a report of the
+/// totals for each group, resetting the totals for the next window.
+#[derive(Debug)]
+pub struct UsageReporter {
+	cfg: Config,
+	window: Mutex<Window>,
+}
+
+impl UsageReporter {
+	pub fn new(cfg: Config) -> Self {
+		UsageReporter {
+			cfg,
+			window: Mutex::new(Window::new()),
+		}
+	}
+
+	pub fn record(&self, usage: Usage) {
+		let key = self
+			.cfg
+			.group_by
+			.iter()
+			.map(|d| {
+				let v = match d {
+					Dimension::Route => usage.route,
+					Dimension::Identity => usage.identity,
+					Dimension::Model => usage.model,
+					Dimension::Field(f) => usage
+						.fields
+						.iter()
+						.find(|(k, _)| k == f)
+						.and_then(|(_, v)| v.as_deref()),
+				};
+				v.map(strng::new)
+					.unwrap_or_else(|| strng::literal!("unknown"))
+			})
+			.collect_vec();
+		let input_tokens = usage.input_tokens.unwrap_or_default();
+		let output_tokens = usage.output_tokens.unwrap_or_default();
+		let cost = usage
+			.model
+			.and_then(|m| self.cfg.pricing.get(m))
+			.map(|p| (input_tokens as f64 * p.input + output_tokens as f64 * p.output) / 1_000_000.0)
+			.unwrap_or_default();
+		let mut window = self.window.lock().expect("mutex acquired");
+		window.groups.entry(key).or_default().merge(&Totals {
+			requests: 1,
+			input_tokens,
+			output_tokens,
+			cost,
+		});
+	}
+
+	/// take_report returns the report for the current window, and starts a new one.
+	pub fn take_report(&self) -> Report {
+		let window = std::mem::replace(
+			&mut *self.window.lock().expect("mutex acquired"),
+			Window::new(),
+		);
+		let mut suppressed: Option<Totals> = None;
+		let mut groups = Vec::with_capacity(window.groups.len());
+		for (key, totals) in window.groups {
+			if totals.requests < self.cfg.min_group_requests {
+				suppressed.get_or_insert_default().merge(&totals);
+				continue;
+			}
+			groups.push(ReportGroup {
+				labels: self.labels(key),
+				totals,
+			});
+		}
+		groups.sort_by(|a, b| a.labels.values().cmp(b.labels.values()));
+		if let Some(totals) = suppressed {
+			let key = self
+				.cfg
+				.group_by
+				.iter()
+				.map(|_| strng::new(SUPPRESSED))
+				.collect();
+			groups.push(ReportGroup {
+				labels: self.labels(key),
+				totals,
+			});
+		}
+		Report {
+			start: window.start.into(),
+			end: SystemTime::now().into(),
+			groups,
+		}
+	}
+
+	fn labels(&self, key: Vec<Strng>) -> IndexMap<String, Strng> {
+		self
+			.cfg
+			.group_by
+			.iter()
+			.map(|d| d.name().to_string())
+			.zip(key)
+			.collect()
+	}
+
+	fn encode(&self, report: &Report) -> anyhow::Result<Vec<u8>> {
+		match self.cfg.format {
+			Format::Json => Ok(serde_json::to_vec(report)?),
+			Format::Csv => {
+				let mut out = String::new();
+				let header = ["start", "end"]
+					.into_iter()
+					.chain(self.cfg.group_by.iter().map(|d| d.name()))
+					.chain(["requests", "input_tokens", "output_tokens", "cost"])
+					.map(csv_escape)
+					.join(",");
+				writeln!(out, "{header}")?;
+				let start = report.start.to_rfc3339();
+				let end = report.end.to_rfc3339();
+				for g in &report.groups {
+					writeln!(
+						out,
+						"{start},{end},{},{},{},{},{}",
+						g.labels.values().map(|v| csv_escape(v.as_str())).join(","),
+						g.totals.requests,
+						g.totals.input_tokens,
+						g.totals.output_tokens,
+						g.totals.cost,
+					)?;
+				}
+				Ok(out.into_bytes())
+			},
+		}
+	}
+
+	async fn export(&self, client: &Client) {
+		let report = self.take_report();
+		if report.groups.is_empty() {
+			return;
+		}
+		let body = match self.encode(&report) {
+			Ok(b) => b,
+			Err(err) => {
+				warn!(?err, "failed to encode usage report");
+				return;
+			},
+		};
+		if let Some(dir) = &self.cfg.directory {
+			let path = dir.join(format!(
+				"usage-{}.{}",
+				report.end.format("%Y%m%dT%H%M%SZ"),
+				self.cfg.format.extension()
+			));
+			if let Err(err) = fs_err::tokio::write(&path, &body).await {
+				warn!(?err, "failed to write usage report");
+			} else {
+				debug!(path=%path.display(), "wrote usage report");
+			}
+		}
+		if let Some(url) = &self.cfg.webhook {
+			let req = ::http::Request::builder()
+				.method(::http::Method::POST)
+				.uri(url)
+				.header(::http::header::CONTENT_TYPE, self.cfg.format.content_type())
+				.body(Body::from(body))
+				.expect("builder should succeed");
+			match client.simple_call(req).await {
+				Ok(resp) if resp.status().is_success() => {
+					debug!(%url, "sent usage report");
+				},
+				Ok(resp) => warn!(%url, status=%resp.status(), "usage report webhook rejected report"),
+				Err(err) => warn!(%url, ?err, "failed to send usage report"),
+			}
+		}
+	}
+
+	/// run exports a report every interval. On drain, a final report is exported for the partial window.
+	pub async fn run(self: Arc<Self>, client: Client, drain: DrainWatcher) {
+		let mut ticker = tokio::time::interval_at(
+			tokio::time::Instant::now() + self.cfg.interval,
+			self.cfg.interval,
+		);
+		let drained = drain.wait_for_drain();
+		tokio::pin!(drained);
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => self.export(&client).await,
+				_release = &mut drained => {
+					self.export(&client).await;
+					return;
+				}
+			}
+		}
+	}
+}
+
+fn csv_escape(s: &str) -> Cow<'_, str> {
+	if s.contains([',', '"', '\n', '\r']) {
+		Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+	} else {
+		Cow::Borrowed(s)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn reporter(min_group_requests: u64) -> UsageReporter {
+		UsageReporter::new(Config {
+			interval: Duration::from_secs(60),
+			group_by: vec![Dimension::Route, Dimension::parse("team")],
+			min_group_requests,
+			pricing: HashMap::from([(
+				strng::literal!("gpt-4o"),
+				ModelPricing {
+					input: 2.0,
+					output: 10.0,
+				},
+			)]),
+			directory: None,
+			format: Format::Csv,
+			webhook: None,
+		})
+	}
+
+	fn record(r: &UsageReporter, route: &str, team: &str) {
+		r.record(Usage {
+			route: Some(route),
+			identity: None,
+			model: Some("gpt-4o"),
+			fields: &[(strng::literal!("team"), Some(strng::new(team)))],
+			input_tokens: Some(1_000_000),
+			output_tokens: Some(100_000),
+		});
+	}
+
+	#[test]
+	fn aggregates_and_suppresses() {
+		let r = reporter(2);
+		record(&r, "chat", "a");
+		record(&r, "chat", "a");
+		record(&r, "chat", "b");
+		record(&r, "embed", "c");
+		let report = r.take_report();
+		assert_eq!(report.groups.len(), 2);
+		let a = &report.groups[0];
+		assert_eq!(a.labels["route"], "chat");
+		assert_eq!(a.labels["team"], "a");
+		assert_eq!(a.totals.requests, 2);
+		assert_eq!(a.totals.input_tokens, 2_000_000);
+		assert_eq!(a.totals.cost, 6.0);
+		let other = &report.groups[1];
+		assert_eq!(other.labels["route"], SUPPRESSED);
+		assert_eq!(other.labels["team"], SUPPRESSED);
+		assert_eq!(other.totals.requests, 2);
+
+		// The window is reset after a report is taken
+		assert!(r.take_report().groups.is_empty());
+	}
+
+	#[test]
+	fn csv() {
+		let r = reporter(0);
+		record(&r, "chat,v1", "a");
+		let report = r.take_report();
+		let out = String::from_utf8(r.encode(&report).unwrap()).unwrap();
+		let mut lines = out.lines();
+		assert_eq!(
+			lines.next(),
+			Some("start,end,route,team,requests,input_tokens,output_tokens,cost")
+		);
+		assert!(
+			lines
+				.next()
+				.unwrap()
+				.ends_with(",\"chat,v1\",a,1,1000000,100000,3")
+		);
+	}
+}
@@ -2,11 +2,12 @@ use std::fmt::Display;
 use std::io::{Error, IoSlice};
 use std::net::SocketAddr;
 use std::pin::Pin;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use agent_core::strng::{self, Strng};
 use agent_hbone::RWStream;
 use hyper_util::client::legacy::connect::{Connected, Connection};
 use prometheus_client::metrics::counter::Atomic;
@@ -22,6 +23,60 @@ pub struct TCPConnectionInfo {
 	pub peer_addr: SocketAddr,
 	pub local_addr: SocketAddr,
 	pub start: Instant,
+	pub rtt: Rtt,
+}
+
+/// Rtt is the kernel's smoothed round trip time estimate for a TCP connection. Only available on Linux.
+#[derive(Default, Debug, Clone)]
+pub struct Rtt {
+	established: Option<Duration>,
+	// The connection's fd, until the connection is closed.
+	fd: Arc<Mutex<Option<i32>>>,
+}
+
+impl Rtt {
+	fn new(stream: &TcpStream) -> (Rtt, RttGuard) {
+		let fd = raw_fd(stream);
+		let rtt = Rtt {
+			established: fd.and_then(tcp_rtt),
+			fd: Arc::new(Mutex::new(fd)),
+		};
+		let guard = RttGuard(rtt.fd.clone());
+		(rtt, guard)
+	}
+
+	/// established returns the estimate sampled once the connection, and its TLS handshake if any, completed.
+	pub fn established(&self) -> Option<Duration> {
+		self.established
+	}
+
+	/// current samples the estimate now, or returns the established estimate once the connection is closed.
+	pub fn current(&self) -> Option<Duration> {
+		// The lock is held while sampling, so the connection cannot close the fd in the meantime.
+		let fd = self.fd.lock().expect("mutex acquired");
+		fd.and_then(tcp_rtt).or(self.established)
+	}
+
+	fn resample(&mut self) {
+		let sampled = self.fd.lock().expect("mutex acquired").and_then(tcp_rtt);
+		self.established = sampled.or(self.established);
+	}
+}
+
+/// RttGuard stops RTT sampling when dropped, before the connection's fd is closed.
+struct RttGuard(Arc<Mutex<Option<i32>>>);
+
+impl Drop for RttGuard {
+	fn drop(&mut self) {
+		*self.0.lock().expect("mutex acquired") = None;
+	}
+}
+
+/// TcpSocket is a TCP stream whose RTT may be sampled while it is open.
+pub struct TcpSocket {
+	// Declared before `stream`, so sampling stops before the stream is closed.
+	_rtt: RttGuard,
+	stream: TcpStream,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -31,6 +86,16 @@ pub enum Alpn {
 	Other,
 }
 
+impl Alpn {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Alpn::Http11 => "http/1.1",
+			Alpn::H2 => "h2",
+			Alpn::Other => "other",
+		}
+	}
+}
+
 impl From<&[u8]> for Alpn {
 	fn from(value: &[u8]) -> Self {
 		if value == b"h2" {
@@ -48,6 +113,12 @@ pub struct TLSConnectionInfo {
 	pub src_identity: Option<Identity>,
 	pub server_name: Option<String>,
 	pub negotiated_alpn: Option<Alpn>,
+	/// The negotiated protocol version, such as `TLSv1_3`.
+	pub version: Option<Strng>,
+	/// The negotiated cipher suite, such as `TLS13_AES_128_GCM_SHA256`.
+	pub cipher: Option<Strng>,
+	/// Whether the session was resumed rather than established with a full handshake.
+	pub resumed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +139,14 @@ impl Metrics {
 			logging: LoggingMode::default(),
 		}
 	}
+
+	/// record adds bytes transferred outside of the Socket, such as after `Socket::into_tcp`, to the counter.
+	pub fn record(&self, sent: u64, recv: u64) {
+		if let Some(c) = &self.counter {
+			c.counts.0.inc_by(sent);
+			c.counts.1.inc_by(recv);
+		}
+	}
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
@@ -133,14 +212,19 @@ impl Socket {
 	pub fn from_tcp(stream: TcpStream) -> anyhow::Result<Self> {
 		let mut ext = Extension::new();
 		stream.set_nodelay(true)?;
+		let (rtt, guard) = Rtt::new(&stream);
 		ext.insert(TCPConnectionInfo {
 			peer_addr: to_canonical(stream.peer_addr()?),
 			local_addr: to_canonical(stream.local_addr()?),
 			start: Instant::now(),
+			rtt,
 		});
 		Ok(Socket {
 			ext,
-			inner: SocketType::Tcp(stream),
+			inner: SocketType::Tcp(TcpSocket {
+				_rtt: guard,
+				stream,
+			}),
 			metrics: Metrics::with_counter(),
 		})
 	}
@@ -163,9 +247,21 @@ impl Socket {
 				src_identity: crate::transport::tls::identity_from_connection(ssl),
 				negotiated_alpn: ssl.alpn_protocol().map(Alpn::from),
 				server_name,
+				version: ssl.protocol_version().map(|v| strng::new(format!("{v:?}"))),
+				cipher: ssl
+					.negotiated_cipher_suite()
+					.map(|c| strng::new(format!("{:?}", c.suite()))),
+				resumed: ssl.handshake_kind() == Some(rustls::HandshakeKind::Resumed),
 			}
 		};
 		ext.insert(info);
+		// The handshake gave the kernel more round trips to estimate the RTT from.
+		if let SocketType::Tcp(_) = &**tls.get_ref().0
+			&& let Some(mut tcp) = ext.get::<TCPConnectionInfo>().cloned()
+		{
+			tcp.rtt.resample();
+			ext.insert(tcp);
+		}
 		Ok(Socket {
 			ext,
 			inner: SocketType::Tls(Box::new(tls)),
@@ -189,6 +285,10 @@ impl Socket {
 		self.metrics.logging = l;
 	}
 
+	pub fn insert_ext<T: Clone + Send + Sync + 'static>(&mut self, val: T) {
+		self.ext.insert(val);
+	}
+
 	pub fn get_ext(&self) -> Extension {
 		self.ext.clone()
 	}
@@ -219,13 +319,37 @@ impl Socket {
 		Socket::from_tcp(res)
 	}
 
+	/// peek reads data without consuming it. This is only supported for TCP sockets.
+	pub async fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		match &self.inner {
+			SocketType::Tcp(tcp) => tcp.stream.peek(buf).await,
+			_ => Err(std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"peek is only supported for TCP sockets",
+			)),
+		}
+	}
+
 	pub fn counter(&self) -> Option<BytesCounter> {
 		self.metrics.counter.clone()
 	}
+
+	pub fn is_tcp(&self) -> bool {
+		matches!(self.inner, SocketType::Tcp(_))
+	}
+
+	/// into_tcp unwraps a plain TCP socket, so it can be used without the overhead of the Socket wrapper.
+	/// Bytes transferred on the stream are no longer counted; callers should `record` them on the returned Metrics.
+	pub fn into_tcp(self) -> Option<(TcpStream, Metrics)> {
+		match self.inner {
+			SocketType::Tcp(tcp) => Some((tcp.stream, self.metrics)),
+			_ => None,
+		}
+	}
 }
 
 pub enum SocketType {
-	Tcp(TcpStream),
+	Tcp(TcpSocket),
 	Tls(Box<TlsStream<Box<SocketType>>>),
 	Hbone(RWStream),
 	Memory(DuplexStream),
@@ -239,7 +363,7 @@ impl AsyncRead for SocketType {
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<std::io::Result<()>> {
 		match self.get_mut() {
-			SocketType::Tcp(inner) => Pin::new(inner).poll_read(cx, buf),
+			SocketType::Tcp(inner) => Pin::new(&mut inner.stream).poll_read(cx, buf),
 			SocketType::Tls(inner) => Pin::new(inner).poll_read(cx, buf),
 			SocketType::Hbone(inner) => Pin::new(inner).poll_read(cx, buf),
 			SocketType::Memory(inner) => Pin::new(inner).poll_read(cx, buf),
@@ -254,7 +378,7 @@ impl AsyncWrite for SocketType {
 		buf: &[u8],
 	) -> Poll<Result<usize, std::io::Error>> {
 		match self.get_mut() {
-			SocketType::Tcp(inner) => Pin::new(inner).poll_write(cx, buf),
+			SocketType::Tcp(inner) => Pin::new(&mut inner.stream).poll_write(cx, buf),
 			SocketType::Tls(inner) => Pin::new(inner).poll_write(cx, buf),
 			SocketType::Hbone(inner) => Pin::new(inner).poll_write(cx, buf),
 			SocketType::Memory(inner) => Pin::new(inner).poll_write(cx, buf),
@@ -264,7 +388,7 @@ impl AsyncWrite for SocketType {
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
 		match self.get_mut() {
-			SocketType::Tcp(inner) => Pin::new(inner).poll_flush(cx),
+			SocketType::Tcp(inner) => Pin::new(&mut inner.stream).poll_flush(cx),
 			SocketType::Tls(inner) => Pin::new(inner).poll_flush(cx),
 			SocketType::Hbone(inner) => Pin::new(inner).poll_flush(cx),
 			SocketType::Memory(inner) => Pin::new(inner).poll_flush(cx),
@@ -274,7 +398,7 @@ impl AsyncWrite for SocketType {
 
 	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
 		match self.get_mut() {
-			SocketType::Tcp(inner) => Pin::new(inner).poll_shutdown(cx),
+			SocketType::Tcp(inner) => Pin::new(&mut inner.stream).poll_shutdown(cx),
 			SocketType::Tls(inner) => Pin::new(inner).poll_shutdown(cx),
 			SocketType::Hbone(inner) => Pin::new(inner).poll_shutdown(cx),
 			SocketType::Memory(inner) => Pin::new(inner).poll_shutdown(cx),
@@ -288,7 +412,7 @@ impl AsyncWrite for SocketType {
 		bufs: &[IoSlice<'_>],
 	) -> Poll<Result<usize, std::io::Error>> {
 		match self.get_mut() {
-			SocketType::Tcp(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
+			SocketType::Tcp(inner) => Pin::new(&mut inner.stream).poll_write_vectored(cx, bufs),
 			SocketType::Tls(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
 			SocketType::Hbone(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
 			SocketType::Memory(inner) => Pin::new(inner).poll_write_vectored(cx, bufs),
@@ -298,7 +422,7 @@ impl AsyncWrite for SocketType {
 
 	fn is_write_vectored(&self) -> bool {
 		match &self {
-			SocketType::Tcp(inner) => inner.is_write_vectored(),
+			SocketType::Tcp(inner) => inner.stream.is_write_vectored(),
 			SocketType::Tls(inner) => inner.is_write_vectored(),
 			SocketType::Hbone(inner) => inner.is_write_vectored(),
 			SocketType::Memory(inner) => inner.is_write_vectored(),
@@ -411,6 +535,47 @@ impl Extension {
 	}
 }
 
+#[cfg(target_os = "linux")]
+fn raw_fd(stream: &TcpStream) -> Option<i32> {
+	use std::os::fd::AsRawFd;
+	Some(stream.as_raw_fd())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn raw_fd(_stream: &TcpStream) -> Option<i32> {
+	None
+}
+
+#[cfg(target_os = "linux")]
+fn tcp_rtt(fd: i32) -> Option<Duration> {
+	// SAFETY: tcp_info only contains integers, so the all-zero value is valid.
+	let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+	// SAFETY: `info` is a writable buffer of `len` bytes. The kernel writes at most `len` bytes and stores the
+	// number written back into `len`. Callers only pass fds of open connections.
+	let res = unsafe {
+		libc::getsockopt(
+			fd,
+			libc::IPPROTO_TCP,
+			libc::TCP_INFO,
+			(&mut info as *mut libc::tcp_info).cast(),
+			&mut len,
+		)
+	};
+	// Older kernels may fill in less than the full struct; only trust the RTT if it was written.
+	let rtt_end =
+		std::mem::offset_of!(libc::tcp_info, tcpi_rtt) + std::mem::size_of_val(&info.tcpi_rtt);
+	if res != 0 || (len as usize) < rtt_end || info.tcpi_rtt == 0 {
+		return None;
+	}
+	Some(Duration::from_micros(info.tcpi_rtt as u64))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn tcp_rtt(_fd: i32) -> Option<Duration> {
+	None
+}
+
 fn to_canonical(addr: SocketAddr) -> SocketAddr {
 	// another match has to be used for IPv4 and IPv6 support
 	let ip = addr.ip().to_canonical();
@@ -477,3 +642,25 @@ impl Drop for Metrics {
 		}
 	}
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn loopback_rtt() {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let stream = TcpStream::connect(listener.local_addr().unwrap())
+			.await
+			.unwrap();
+		let (rtt, guard) = Rtt::new(&stream);
+		let established = rtt.established().expect("rtt is reported once connected");
+		assert!(established < Duration::from_secs(1));
+		assert!(rtt.current().is_some());
+
+		// Once the connection is closed, the established estimate is reported.
+		drop(guard);
+		drop(stream);
+		assert_eq!(rtt.current(), Some(established));
+	}
+}
@@ -104,19 +104,22 @@ pub enum ListenerProtocol {
 	TLS(TLSConfig),
 	TCP,
 	HBONE,
+	/// A forward proxy accepting SOCKS5 and HTTP CONNECT. Tunneled TLS is terminated with the config, if set.
+	SOCKS5(Option<TLSConfig>, Arc<crate::proxy::socks5::ForwardProxy>),
 }
 
 impl ListenerProtocol {
 	pub fn tls(&self) -> Option<Arc<rustls::ServerConfig>> {
 		match self {
 			ListenerProtocol::HTTPS(t) | ListenerProtocol::TLS(t) => Some(t.config.clone()),
+			ListenerProtocol::SOCKS5(Some(t), _) => Some(t.config.clone()),
 			_ => None,
 		}
 	}
 }
 
 // Protocol of the entire bind. TODO: we should make this a property of the API
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, EncodeLabelValue, serde::Serialize)]
 #[allow(non_camel_case_types)]
 pub enum BindProtocol {
 	http,
@@ -124,6 +127,7 @@ pub enum BindProtocol {
 	hbone,
 	tcp,
 	tls,
+	socks5,
 }
 
 pub type ListenerKey = Strng;
@@ -291,6 +295,13 @@ pub enum RouteFilter {
 pub struct TrafficPolicy {
 	pub timeout: timeout::Policy,
 	pub retry: Option<retry::Policy>,
+	/// Where the timeout was inherited from, such as `defaults` or a listener, if the route does not set one.
+	/// An inherited policy is used as a whole; it is not merged with the route's policy.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub timeout_inherited_from: Option<Strng>,
+	/// Where the retry policy was inherited from, if the route does not set one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub retry_inherited_from: Option<Strng>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
@@ -315,6 +326,9 @@ pub enum PathRedirect {
 pub struct RouteBackendReference {
 	#[serde(default = "default_weight")]
 	pub weight: usize,
+	/// Priority group of the backend, used by the failover policy. Lower priorities are preferred.
+	#[serde(default, skip_serializing_if = "is_default")]
+	pub priority: u32,
 	#[serde(flatten)]
 	pub backend: BackendReference,
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -480,6 +494,8 @@ pub type BackendName = Strng;
 pub struct McpBackend {
 	pub targets: Vec<Arc<McpTarget>>,
 	pub stateful: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub keepalive: Option<crate::mcp::relay::keepalive::Keepalive>,
 }
 
 impl McpBackend {
@@ -547,6 +563,9 @@ pub struct OpenAPITarget {
 	#[serde(deserialize_with = "de_openapi")]
 	#[cfg_attr(feature = "schema", schemars(with = "serde_json::value::RawValue"))]
 	pub schema: Arc<OpenAPI>,
+	/// Validate successful responses against the operation's response schema.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub response_validation: Option<crate::mcp::openapi::validate::ResponseValidation>,
 }
 
 pub fn de_openapi<'a, D>(deserializer: D) -> Result<Arc<OpenAPI>, D::Error>
@@ -734,6 +753,10 @@ impl RouteSet {
 		})
 	}
 
+	pub fn iter(&self) -> impl Iterator<Item = &Route> {
+		self.all.values()
+	}
+
 	pub fn insert(&mut self, r: Route) {
 		// Insert the route into all HashMap first so it's available during binary search
 		self.all.insert(r.key.clone(), r.clone());
@@ -988,6 +1011,8 @@ pub enum Policy {
 	McpAuthorization(McpAuthorization),
 	// Supported targets: Backend, only when Backend type is MCP
 	McpAuthentication(McpAuthentication),
+	// Supported targets: Backend, only when Backend type is MCP
+	McpMetadata(crate::mcp::metadata::McpMetadata),
 	// Support targets: Backend; single policy allowed
 	A2a(A2aPolicy),
 	// Supported targets: Backend; single policy allowed
@@ -1019,10 +1044,36 @@ pub enum Policy {
 	// ExtProc(),
 	// Supported targets: Gateway < Route < RouteRule; single policy allowed
 	Transformation(crate::http::transformation_cel::Transformation),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	PoolPartition(crate::http::poolpartition::PoolPartition),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	Idempotency(crate::http::idempotency::Idempotency),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	Bandit(crate::http::bandit::Bandit),
+	// Supported targets: Gateway < Listener < Route < RouteRule; single policy allowed
+	SecurityHeaders(crate::http::securityheaders::SecurityHeaders),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	Failover(crate::http::failover::Failover),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	HeaderSizeLimit(crate::http::headersizelimit::HeaderSizeLimit),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	Variables(crate::http::variables::Variables),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	LoopDetection(crate::http::loopdetection::LoopDetection),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	UpstreamErrors(crate::http::upstreamerrors::UpstreamErrors),
+	// Supported targets: Gateway < Route < RouteRule; single policy allowed
+	BackendOverride(crate::http::backendoverride::BackendOverride),
 }
 
 #[apply(schema!)]
-pub struct A2aPolicy {}
+#[derive(Default)]
+pub struct A2aPolicy {
+	/// Cache the agent card of the upstream, and reject calls for capabilities it does not advertise before
+	/// they are sent to it.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub agent_card: Option<crate::a2a::AgentCardCache>,
+}
 
 #[apply(schema!)]
 pub struct Authorization(pub RuleSet);
@@ -48,6 +48,7 @@ impl TryFrom<&proto::agent::RouteBackend> for RouteBackendReference {
 			.collect::<Result<Vec<_>, _>>()?;
 		Ok(Self {
 			weight: s.weight as usize,
+			priority: 0,
 			backend: kind,
 			filters,
 		})
@@ -116,8 +117,11 @@ impl TryFrom<proto::agent::TrafficPolicy> for TrafficPolicy {
 			timeout: crate::http::timeout::Policy {
 				request_timeout: req,
 				backend_request_timeout: backend,
+				adaptive: None,
 			},
 			retry,
+			timeout_inherited_from: None,
+			retry_inherited_from: None,
 		})
 	}
 }
@@ -321,6 +325,7 @@ impl TryFrom<&proto::agent::Backend> for Backend {
 						proto::agent::mcp_backend::StatefulMode::Stateful => true,
 						proto::agent::mcp_backend::StatefulMode::Stateless => false,
 					},
+					keepalive: None,
 				},
 			),
 			_ => {
@@ -622,6 +627,7 @@ impl TryFrom<&proto::agent::Policy> for TargetedPolicy {
 							Type::Request => localratelimit::RateLimitType::Requests,
 							Type::Token => localratelimit::RateLimitType::Tokens,
 						},
+						mode: Default::default(),
 					}
 					.try_into()
 					.map_err(|e| ProtoError::Generic(format!("invalid rate limit: {e}")))?,
@@ -634,7 +640,7 @@ impl TryFrom<&proto::agent::Policy> for TargetedPolicy {
 					context: Some(ea.context.clone()),
 				})
 			},
-			Some(proto::agent::policy_spec::Kind::A2a(_)) => Policy::A2a(A2aPolicy {}),
+			Some(proto::agent::policy_spec::Kind::A2a(_)) => Policy::A2a(A2aPolicy::default()),
 			Some(proto::agent::policy_spec::Kind::BackendTls(btls)) => {
 				let tls = backendtls::ResolvedBackendTLS {
 					cert: btls.cert.clone(),
@@ -642,6 +648,9 @@ impl TryFrom<&proto::agent::Policy> for TargetedPolicy {
 					root: btls.root.clone(),
 					insecure: btls.insecure.unwrap_or_default(),
 					insecure_host: false,
+					revocation: None,
+					bundles: vec![],
+					system_trust: None,
 				}
 				.try_into()
 				.map_err(|e| ProtoError::Generic(e.to_string()))?;
@@ -715,6 +724,7 @@ impl TryFrom<&proto::agent::Policy> for TargetedPolicy {
 							regex,
 							webhook,
 							openai_moderation,
+							mode: Default::default(),
 						}),
 						response: pg
 							.response
@@ -741,6 +751,9 @@ impl TryFrom<&proto::agent::Policy> for TargetedPolicy {
 							.collect(),
 					),
 					prompts: ai.prompts.as_ref().map(convert_prompt_enrichment),
+					request_fields: vec![],
+					sampling: None,
+					prompt_hashing: None,
 				}))
 			},
 			_ => return Err(ProtoError::EnumParse("unknown spec kind".to_string())),
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
@@ -11,8 +11,9 @@ use rustls::ServerConfig;
 use serde_with::{TryFromInto, serde_as};
 
 use crate::http::auth::BackendAuth;
-use crate::http::backendtls::LocalBackendTLS;
+use crate::http::backendtls::{LocalBackendTLS, LocalTrustBundle, TrustBundles};
 use crate::http::{filters, retry, timeout};
+use crate::mcp::metadata::McpMetadata;
 use crate::mcp::rbac::McpAuthorization;
 use crate::store::LocalWorkload;
 use crate::types::agent::PolicyTarget::RouteRule;
@@ -47,6 +48,9 @@ pub struct NormalizedLocalConfig {
 	// for now
 	pub workloads: Vec<LocalWorkload>,
 	pub services: Vec<Service>,
+	/// Files the configuration depends on, other than the configuration itself, which should be watched for
+	/// changes.
+	pub watch_files: Vec<PathBuf>,
 }
 
 #[apply(schema_de!)]
@@ -57,6 +61,13 @@ pub struct LocalConfig {
 	config: Arc<Option<serde_json::value::Value>>,
 	#[serde(default)]
 	binds: Vec<LocalBind>,
+	/// Policies applied to the routes of every listener. Policies set on a listener or route take precedence.
+	/// Logging fields are not set here, as they already apply to every route, from `config.logging.fields`.
+	#[serde(default)]
+	defaults: Option<LocalDefaultPolicies>,
+	/// Named sets of CA certificates, which backend TLS policies can trust with 'trustBundles'.
+	#[serde(default)]
+	trust_bundles: IndexMap<Strng, LocalTrustBundle>,
 	#[serde(default)]
 	#[cfg_attr(feature = "schema", schemars(with = "serde_json::value::RawValue"))]
 	workloads: Vec<LocalWorkload>,
@@ -84,6 +95,138 @@ struct LocalListener {
 	tls: Option<LocalTLSServerConfig>,
 	routes: Option<Vec<LocalRoute>>,
 	tcp_routes: Option<Vec<LocalTCPRoute>>,
+	/// Policies applied to all routes of the listener. Policies set on a route take precedence.
+	#[serde(default)]
+	policies: Option<LocalDefaultPolicies>,
+	/// Who may use a SOCKS5 listener, and which destinations that do not match a route may be tunneled to.
+	#[serde(default)]
+	forward_proxy: Option<crate::proxy::socks5::ForwardProxy>,
+}
+
+/// Policies declared once, rather than on every route, and inherited by routes that do not set the same policy.
+/// Authorization is the exception: rules from every level apply.
+#[apply(schema_de!)]
+struct LocalDefaultPolicies {
+	/// Add security headers, such as HSTS, to responses, and optionally redirect plaintext requests to HTTPS.
+	#[serde(default)]
+	security_headers: Option<crate::http::securityheaders::SecurityHeaders>,
+	/// Authenticate incoming JWT requests.
+	#[serde(default)]
+	jwt_auth: Option<crate::http::jwt::LocalJwtConfig>,
+	/// Authenticate incoming requests by calling an external authorization server.
+	#[serde(default)]
+	ext_authz: Option<LocalExtAuthz>,
+	/// Authorization policies for HTTP access.
+	#[serde(default)]
+	authorization: Option<Authorization>,
+	/// Rate limit incoming requests. State is kept local. Set on a listener, the listener's routes share one bucket;
+	/// set in 'defaults', each bind has its own bucket, shared by its listeners.
+	#[serde(default)]
+	local_rate_limit: Vec<crate::http::localratelimit::RateLimitSerde>,
+	/// Timeout requests that exceed the configured duration.
+	#[serde(default)]
+	timeout: Option<timeout::Policy>,
+	/// Retry matching requests.
+	#[serde(default)]
+	retry: Option<retry::Policy>,
+}
+
+/// DefaultPolicies are the policies of a defaults block, ready to be attached to the listener or gateway they
+/// apply to.
+#[derive(Default)]
+struct DefaultPolicies {
+	/// Policies, by the kind of policy.
+	policies: Vec<(&'static str, Policy)>,
+	/// Rate limits are built for each target they are attached to, so targets do not share a bucket.
+	local_rate_limit: Vec<crate::http::localratelimit::RateLimitSerde>,
+	backends: Vec<Backend>,
+	/// Timeouts and retries are part of the route, rather than attached to it, so they are inherited when routes
+	/// are converted.
+	traffic: TrafficDefaults,
+}
+
+impl DefaultPolicies {
+	/// instantiate returns the policies to attach to a single target.
+	fn instantiate(&self) -> anyhow::Result<Vec<(&'static str, Policy)>> {
+		let mut policies = self.policies.clone();
+		if !self.local_rate_limit.is_empty() {
+			let limits = self
+				.local_rate_limit
+				.iter()
+				.cloned()
+				.map(crate::http::localratelimit::RateLimit::try_from)
+				.collect::<Result<Vec<_>, _>>()?;
+			policies.push(("localRateLimit", Policy::LocalRateLimit(limits)));
+		}
+		Ok(policies)
+	}
+}
+
+/// TrafficDefaults are the inherited timeout and retry policies, with where each was declared.
+#[derive(Clone, Default)]
+struct TrafficDefaults {
+	timeout: Option<(timeout::Policy, Strng)>,
+	retry: Option<(retry::Policy, Strng)>,
+}
+
+impl TrafficDefaults {
+	/// or returns these defaults, with the unset ones inherited from `parent`.
+	fn or(self, parent: &TrafficDefaults) -> TrafficDefaults {
+		TrafficDefaults {
+			timeout: self.timeout.or_else(|| parent.timeout.clone()),
+			retry: self.retry.or_else(|| parent.retry.clone()),
+		}
+	}
+}
+
+impl LocalDefaultPolicies {
+	/// convert converts the policies. `name` prefixes the names of any backends the policies create.
+	async fn convert(self, client: client::Client, name: &str) -> anyhow::Result<DefaultPolicies> {
+		let LocalDefaultPolicies {
+			security_headers,
+			jwt_auth,
+			ext_authz,
+			authorization,
+			local_rate_limit,
+			timeout,
+			retry,
+		} = self;
+		let mut out = DefaultPolicies {
+			local_rate_limit,
+			traffic: TrafficDefaults {
+				timeout: timeout.map(|p| (p, name.into())),
+				retry: retry.map(|p| (p, name.into())),
+			},
+			..Default::default()
+		};
+		if let Some(p) = security_headers {
+			out
+				.policies
+				.push(("securityHeaders", Policy::SecurityHeaders(p)));
+		}
+		if let Some(p) = jwt_auth {
+			out
+				.policies
+				.push(("jwtAuth", Policy::JwtAuth(p.try_into(client).await?)));
+		}
+		if let Some(p) = ext_authz {
+			let (bref, backend) = to_simple_backend_and_ref(strng::format!("{name}/extauthz"), &p.target);
+			out.backends.extend(backend);
+			out.policies.push((
+				"extAuthz",
+				Policy::ExtAuthz(http::ext_authz::ExtAuthz {
+					target: Arc::new(bref),
+					context: p.context,
+				}),
+			));
+		}
+		if let Some(p) = authorization {
+			out
+				.policies
+				.push(("authorization", Policy::Authorization(p)));
+		}
+		Ok(out)
+	}
 }
 
 #[derive(Debug, Clone, Default, serde::Deserialize)]
@@ -97,6 +240,10 @@ enum LocalListenerProtocol {
 	TLS,
 	TCP,
 	HBONE,
+	/// A forward proxy for outbound traffic, accepting SOCKS5 and HTTP CONNECT. Destinations that match a route are
+	/// handled by it; others are only tunneled directly if 'forwardProxy' allows them. To apply routes to HTTPS
+	/// traffic, 'tls' must be set with a certificate for the destinations that clients trust.
+	SOCKS5,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -130,6 +277,9 @@ struct LocalRoute {
 pub struct LocalRouteBackend {
 	#[serde(default = "default_weight")]
 	pub weight: usize,
+	/// Priority group of the backend, used by the 'failover' policy. Lower priorities are preferred.
+	#[serde(default)]
+	pub priority: u32,
 	#[serde(flatten)]
 	pub backend: LocalBackend,
 	// TODO: add back per-backend filters
@@ -202,7 +352,11 @@ impl LocalBackend {
 						LocalMcpTargetSpec::Stdio { cmd, args, env } => {
 							(McpTargetSpec::Stdio { cmd, args, env }, false)
 						},
-						LocalMcpTargetSpec::OpenAPI { backend, schema } => {
+						LocalMcpTargetSpec::OpenAPI {
+							backend,
+							schema,
+							response_validation,
+						} => {
 							let (backend, _, tls) = backend.process()?;
 							let (bref, be) = to_simple_backend_and_ref(name.clone(), &backend);
 							be.into_iter().for_each(|b| backends.push(b));
@@ -210,6 +364,7 @@ impl LocalBackend {
 								McpTargetSpec::OpenAPI(OpenAPITarget {
 									backend: bref,
 									schema,
+									response_validation,
 								}),
 								tls,
 							)
@@ -219,7 +374,7 @@ impl LocalBackend {
 						policies.push(TargetedPolicy {
 							name: strng::format!("implicit-tls/{}", name),
 							target: PolicyTarget::Backend(name.clone()),
-							policy: Policy::BackendTLS(LocalBackendTLS::default().try_into()?),
+							policy: Policy::BackendTLS(http::backendtls::SYSTEM_TRUST.clone()),
 						});
 					}
 					let t = McpTarget {
@@ -232,7 +387,11 @@ impl LocalBackend {
 					McpStatefulMode::Stateless => false,
 					McpStatefulMode::Stateful => true,
 				};
-				let m = McpBackend { targets, stateful };
+				let m = McpBackend {
+					targets,
+					stateful,
+					keepalive: tgt.keepalive.clone(),
+				};
 				backends.push(Backend::MCP(name, m));
 				(backends, policies)
 			},
@@ -255,6 +414,10 @@ pub struct LocalMcpBackend {
 	pub targets: Vec<Arc<LocalMcpTarget>>,
 	#[serde(default)]
 	pub stateful_mode: McpStatefulMode,
+	/// Periodically ping targets to detect broken connections. Unhealthy targets are skipped until they
+	/// respond again.
+	#[serde(default)]
+	pub keepalive: Option<crate::mcp::relay::keepalive::Keepalive>,
 }
 
 #[apply(schema_de!)]
@@ -334,6 +497,10 @@ pub enum LocalMcpTargetSpec {
 		#[serde(deserialize_with = "types::agent::de_openapi")]
 		#[cfg_attr(feature = "schema", schemars(with = "serde_json::value::RawValue"))]
 		schema: Arc<OpenAPI>,
+		/// Validate successful responses against the operation's response schema. Responses that do not
+		/// match can be logged (`warn`), rejected (`fail`), or have undefined fields removed (`strip`).
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		response_validation: Option<crate::mcp::openapi::validate::ResponseValidation>,
 	},
 }
 
@@ -432,6 +599,9 @@ struct FilterOrPolicy {
 	/// Authentication for MCP clients.
 	#[serde(default)]
 	mcp_authentication: Option<McpAuthentication>,
+	/// Metadata to add to the `_meta` of upstream MCP requests, and which client `_meta` keys to forward.
+	#[serde(default)]
+	mcp_metadata: Option<McpMetadata>,
 	/// Mark this traffic as A2A to enable A2A processing and telemetry.
 	#[serde(default)]
 	a2a: Option<A2aPolicy>,
@@ -469,6 +639,41 @@ struct FilterOrPolicy {
 		)
 	)]
 	transformations: Option<crate::http::transformation_cel::Transformation>,
+	/// Partition the upstream connection pool by a key derived from the request.
+	#[serde(default)]
+	pool_partition: Option<crate::http::poolpartition::PoolPartition>,
+	/// Deduplicate retried POST requests using their Idempotency-Key header.
+	#[serde(default)]
+	idempotency: Option<crate::http::idempotency::Idempotency>,
+	/// Experimental: select among the route's backends based on their observed latency, errors, and quality,
+	/// instead of their weights.
+	#[serde(default)]
+	bandit: Option<crate::http::bandit::Bandit>,
+	/// Send traffic to the backends with the lowest 'priority', failing over to the next priority when their
+	/// error rate or latency exceeds a threshold. Ignored if a 'bandit' policy also applies to the route.
+	#[serde(default)]
+	failover: Option<crate::http::failover::Failover>,
+	/// Add security headers, such as HSTS, to responses, and optionally redirect plaintext requests to HTTPS.
+	#[serde(default)]
+	security_headers: Option<crate::http::securityheaders::SecurityHeaders>,
+	/// Remove oversized request headers, such as large cookies, before forwarding the request.
+	#[serde(default)]
+	header_size_limit: Option<crate::http::headersizelimit::HeaderSizeLimit>,
+	/// Named CEL expressions, evaluated once per request, that other policies on the route can reference as
+	/// `vars.<name>`.
+	#[serde(default)]
+	variables: Option<crate::http::variables::Variables>,
+	/// Reject requests that loop between gateways, or pass through too many of them, such as agents calling each
+	/// other in a cycle.
+	#[serde(default)]
+	loop_detection: Option<crate::http::loopdetection::LoopDetection>,
+	/// Control whether error bodies from the upstream are returned as is, sanitized, or replaced.
+	#[serde(default)]
+	upstream_errors: Option<crate::http::upstreamerrors::UpstreamErrors>,
+	/// Allow trusted requests to select one of a fixed set of backends with a header, such as to send a single
+	/// request to a staging model.
+	#[serde(default)]
+	backend_override: Option<LocalBackendOverride>,
 
 	// TrafficPolicy
 	/// Timeout requests that exceed the configured duration.
@@ -489,19 +694,45 @@ async fn convert(client: client::Client, i: LocalConfig) -> anyhow::Result<Norma
 	let LocalConfig {
 		config: _,
 		binds,
+		defaults,
+		trust_bundles,
 		workloads,
 		services,
 	} = i;
+	let trust_bundles = TrustBundles::load(trust_bundles)?;
+	let defaults = match defaults {
+		Some(d) => d.convert(client.clone(), "defaults").await?,
+		None => DefaultPolicies::default(),
+	};
 	let mut all_policies = vec![];
-	let mut all_backends = vec![];
+	let mut all_backends = defaults.backends.clone();
 	let mut all_binds = vec![];
+	// Defaults are attached to each gateway, so they apply to all of its listeners.
+	let mut gateways = HashSet::new();
 	for b in binds {
 		let bind_name = strng::format!("bind/{}", b.port);
 		let mut ls = ListenerSet::default();
 		for (idx, l) in b.listeners.into_iter().enumerate() {
-			let (l, pol, backends) = convert_listener(client.clone(), bind_name.clone(), idx, l).await?;
+			let (l, pol, backends) = convert_listener(
+				client.clone(),
+				&trust_bundles,
+				&defaults.traffic,
+				bind_name.clone(),
+				idx,
+				l,
+			)
+			.await?;
 			all_policies.extend_from_slice(&pol);
 			all_backends.extend_from_slice(&backends);
+			if gateways.insert(l.gateway_name.clone()) {
+				for (kind, policy) in defaults.instantiate()? {
+					all_policies.push(TargetedPolicy {
+						name: strng::format!("{}/defaults/{kind}", l.gateway_name),
+						target: PolicyTarget::Gateway(l.gateway_name.clone()),
+						policy,
+					});
+				}
+			}
 			ls.insert(l)
 		}
 		let b = Bind {
@@ -517,11 +748,14 @@ async fn convert(client: client::Client, i: LocalConfig) -> anyhow::Result<Norma
 		backends: all_backends,
 		workloads,
 		services,
+		watch_files: trust_bundles.files,
 	})
 }
 
 async fn convert_listener(
 	client: client::Client,
+	trust_bundles: &TrustBundles,
+	traffic_defaults: &TrafficDefaults,
 	bind_name: BindName,
 	idx: usize,
 	l: LocalListener,
@@ -534,8 +768,13 @@ async fn convert_listener(
 		tls,
 		routes,
 		tcp_routes,
+		policies,
+		forward_proxy,
 	} = l;
 
+	if forward_proxy.is_some() && !matches!(protocol, LocalListenerProtocol::SOCKS5) {
+		bail!("'forwardProxy' requires protocol SOCKS5");
+	}
 	let protocol = match protocol {
 		LocalListenerProtocol::HTTP => {
 			if routes.is_none() {
@@ -566,6 +805,15 @@ async fn convert_listener(
 			ListenerProtocol::TCP
 		},
 		LocalListenerProtocol::HBONE => ListenerProtocol::HBONE,
+		LocalListenerProtocol::SOCKS5 => {
+			if routes.is_none() {
+				bail!("protocol SOCKS5 requires 'routes'")
+			}
+			ListenerProtocol::SOCKS5(
+				tls.map(convert_tls_server).transpose()?,
+				Arc::new(forward_proxy.unwrap_or_default()),
+			)
+		},
 	};
 	if tcp_routes.is_some() && routes.is_some() {
 		bail!("only 'routes' or 'tcpRoutes' may be set");
@@ -577,9 +825,40 @@ async fn convert_listener(
 	let mut all_policies = vec![];
 	let mut all_backends = vec![];
 
+	let mut traffic_defaults = traffic_defaults.clone();
+	if let Some(policies) = policies {
+		if !matches!(
+			protocol,
+			ListenerProtocol::HTTP | ListenerProtocol::HTTPS(_) | ListenerProtocol::SOCKS5(..)
+		) {
+			bail!("listener 'policies' require an HTTP listener");
+		}
+		let defaults = policies.convert(client.clone(), &key).await?;
+		all_policies.extend(
+			defaults
+				.instantiate()?
+				.into_iter()
+				.map(|(kind, policy)| TargetedPolicy {
+					name: strng::format!("{key}/{kind}"),
+					target: PolicyTarget::Listener(key.clone()),
+					policy,
+				}),
+		);
+		all_backends.extend(defaults.backends);
+		traffic_defaults = defaults.traffic.or(&traffic_defaults);
+	}
+
 	let mut rs = RouteSet::default();
 	for (idx, l) in routes.into_iter().flatten().enumerate() {
-		let (route, policies, backends) = convert_route(client.clone(), l, idx, key.clone()).await?;
+		let (route, policies, backends) = convert_route(
+			client.clone(),
+			trust_bundles,
+			&traffic_defaults,
+			l,
+			idx,
+			key.clone(),
+		)
+		.await?;
 		all_policies.extend_from_slice(&policies);
 		all_backends.extend_from_slice(&backends);
 		rs.insert(route)
@@ -587,7 +866,8 @@ async fn convert_listener(
 
 	let mut trs = TCPRouteSet::default();
 	for (idx, l) in tcp_routes.into_iter().flatten().enumerate() {
-		let (route, policies) = convert_tcp_route(l, idx, key.clone()).await?;
+		let (route, policies) =
+			convert_tcp_route(client.clone(), trust_bundles, l, idx, key.clone()).await?;
 		all_policies.extend_from_slice(&policies);
 		trs.insert(route)
 	}
@@ -606,6 +886,8 @@ async fn convert_listener(
 
 async fn convert_route(
 	client: client::Client,
+	trust_bundles: &TrustBundles,
+	traffic_defaults: &TrafficDefaults,
 	lr: LocalRoute,
 	idx: usize,
 	listener_key: ListenerKey,
@@ -652,6 +934,7 @@ async fn convert_route(
 		let (backends, policies_from_backends) = b.backend.as_backends(bref.name())?;
 		let bref = RouteBackendReference {
 			weight: b.weight,
+			priority: b.priority,
 			backend: bref,
 			filters: vec![],
 			// filters: b.filters,
@@ -674,9 +957,13 @@ async fn convert_route(
 		})
 	};
 
+	let (timeout_default, timeout_inherited_from) = traffic_defaults.timeout.clone().unzip();
+	let (retry_default, retry_inherited_from) = traffic_defaults.retry.clone().unzip();
 	let mut traffic_policy = TrafficPolicy {
-		timeout: timeout::Policy::default(),
-		retry: None,
+		timeout: timeout_default.unwrap_or_default(),
+		retry: retry_default,
+		timeout_inherited_from,
+		retry_inherited_from,
 	};
 	if let Some(pol) = policies {
 		let FilterOrPolicy {
@@ -689,6 +976,7 @@ async fn convert_route(
 			cors,
 			mcp_authorization,
 			mcp_authentication,
+			mcp_metadata,
 			a2a,
 			ai,
 			backend_tls,
@@ -698,6 +986,16 @@ async fn convert_route(
 			remote_rate_limit,
 			jwt_auth,
 			transformations,
+			pool_partition,
+			idempotency,
+			bandit,
+			failover,
+			security_headers,
+			header_size_limit,
+			variables,
+			loop_detection,
+			upstream_errors,
+			backend_override,
 			ext_authz,
 			timeout,
 			retry,
@@ -740,14 +1038,21 @@ async fn convert_route(
 			external_policies.push(backend_tgt(Policy::McpAuthentication(p))?);
 			external_policies.push(tgt(Policy::JwtAuth(jp.try_into(client.clone()).await?)));
 		}
+		if let Some(p) = mcp_metadata {
+			external_policies.push(backend_tgt(Policy::McpMetadata(p))?)
+		}
 		if let Some(p) = a2a {
 			external_policies.push(backend_tgt(Policy::A2a(p))?)
 		}
 		if let Some(p) = ai {
-			external_policies.push(backend_tgt(Policy::AI(Arc::new(p)))?)
+			// The AI policy is applied from the route policies, so it must target the route rule; a backend
+			// target is never looked up.
+			external_policies.push(tgt(Policy::AI(Arc::new(p))))
 		}
 		if let Some(p) = backend_tls {
-			external_policies.push(backend_tgt(Policy::BackendTLS(p.try_into()?))?)
+			external_policies.push(backend_tgt(Policy::BackendTLS(
+				p.try_into(client.clone(), trust_bundles)?,
+			))?)
 		}
 		if let Some(p) = backend_auth {
 			external_policies.push(backend_tgt(Policy::BackendAuth(p))?)
@@ -758,6 +1063,65 @@ async fn convert_route(
 		if let Some(p) = transformations {
 			external_policies.push(tgt(Policy::Transformation(p)))
 		}
+		if let Some(p) = pool_partition {
+			external_policies.push(tgt(Policy::PoolPartition(p)))
+		}
+		if let Some(p) = idempotency {
+			external_policies.push(tgt(Policy::Idempotency(p)))
+		}
+		if let Some(p) = bandit {
+			external_policies.push(tgt(Policy::Bandit(p)))
+		}
+		if let Some(p) = failover {
+			external_policies.push(tgt(Policy::Failover(p)))
+		}
+		if let Some(p) = security_headers {
+			external_policies.push(tgt(Policy::SecurityHeaders(p)))
+		}
+		if let Some(p) = header_size_limit {
+			external_policies.push(tgt(Policy::HeaderSizeLimit(p)))
+		}
+		if let Some(p) = variables {
+			external_policies.push(tgt(Policy::Variables(p)))
+		}
+		if let Some(p) = loop_detection {
+			external_policies.push(tgt(Policy::LoopDetection(p)))
+		}
+		if let Some(p) = upstream_errors {
+			external_policies.push(tgt(Policy::UpstreamErrors(p)))
+		}
+		if let Some(p) = backend_override {
+			let mut backends = IndexMap::with_capacity(p.backends.len());
+			for (name, b) in p.backends {
+				let bref = match &b {
+					LocalBackend::Service { name, port } => BackendReference::Service {
+						name: name.clone(),
+						port: *port,
+					},
+					LocalBackend::Invalid => BackendReference::Invalid,
+					_ => BackendReference::Backend(strng::format!("{key}/override/{name}")),
+				};
+				let (be, policies_from_backends) = b.as_backends(bref.name())?;
+				external_backends.extend(be);
+				external_policies.extend(policies_from_backends);
+				backends.insert(
+					name,
+					RouteBackendReference {
+						weight: default_weight(),
+						priority: 0,
+						backend: bref,
+						filters: vec![],
+					},
+				);
+			}
+			external_policies.push(tgt(Policy::BackendOverride(
+				http::backendoverride::BackendOverride {
+					header: p.header,
+					condition: p.condition,
+					backends,
+				},
+			)))
+		}
 		if let Some(p) = authorization {
 			external_policies.push(tgt(Policy::Authorization(p)))
 		}
@@ -783,6 +1147,7 @@ async fn convert_route(
 				domain: p.domain,
 				target: Arc::new(bref),
 				descriptors: Arc::new(p.descriptors),
+				mode: p.mode,
 			};
 			backend
 				.into_iter()
@@ -792,9 +1157,11 @@ async fn convert_route(
 
 		if let Some(p) = timeout {
 			traffic_policy.timeout = p;
+			traffic_policy.timeout_inherited_from = None;
 		}
 		if let Some(p) = retry {
 			traffic_policy.retry = Some(p);
+			traffic_policy.retry_inherited_from = None;
 		}
 	}
 	let route = Route {
@@ -811,6 +1178,8 @@ async fn convert_route(
 }
 
 async fn convert_tcp_route(
+	client: client::Client,
+	trust_bundles: &TrustBundles,
 	lr: LocalTCPRoute,
 	idx: usize,
 	listener_key: ListenerKey,
@@ -869,7 +1238,9 @@ async fn convert_tcp_route(
 	if let Some(pol) = policies {
 		let TCPFilterOrPolicy { backend_tls } = pol;
 		if let Some(p) = backend_tls {
-			external_policies.push(backend_tgt(Policy::BackendTLS(p.try_into()?))?)
+			external_policies.push(backend_tgt(Policy::BackendTLS(
+				p.try_into(client.clone(), trust_bundles)?,
+			))?)
 		}
 	}
 	#[allow(unreachable_code)]
@@ -931,10 +1302,223 @@ pub struct LocalExtAuthz {
 	pub context: Option<HashMap<String, String>>, // TODO: gRPC vs HTTP, fail open, include body,
 }
 
+#[apply(schema_de!)]
+pub struct LocalBackendOverride {
+	/// Header naming the backend to send the request to. Defaults to `x-agentgateway-backend-override`.
+	#[serde(
+		default = "default_backend_override_header",
+		deserialize_with = "de_parse"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub header: ::http::HeaderName,
+	/// CEL expression that must evaluate to true for the header to be honored, such as a check of the caller's
+	/// JWT claims. Requests that do not match are sent to the route's backends, as if the header was not set.
+	#[serde(deserialize_with = "de_expression")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	pub condition: Arc<crate::cel::Expression>,
+	/// The backends requests may select, by the name used in the header. Requests naming any other backend are
+	/// rejected.
+	pub backends: IndexMap<Strng, LocalBackend>,
+}
+
+fn default_backend_override_header() -> ::http::HeaderName {
+	::http::HeaderName::from_static("x-agentgateway-backend-override")
+}
+
 #[apply(schema_de!)]
 pub struct LocalRemoteRateLimit {
 	pub domain: String,
 	#[serde(flatten)]
 	pub target: SimpleLocalBackend,
 	pub descriptors: crate::http::remoteratelimit::DescriptorSet,
+	/// In monitor mode, requests the rate limit service rejects are logged and counted, but allowed.
+	#[serde(default)]
+	pub mode: crate::http::PolicyMode,
+}
+
+#[cfg(test)]
+mod tests {
+	use itertools::Itertools;
+	use serde_json::json;
+
+	use super::*;
+
+	const CONFIG: &str = r#"
+defaults:
+  timeout:
+    requestTimeout: 10s
+  retry:
+    codes: [503]
+  authorization:
+    rules: ['jwt.sub != ""']
+binds:
+- port: 3000
+  listeners:
+  - name: a
+    protocol: HTTP
+    policies:
+      timeout:
+        requestTimeout: 5s
+    routes:
+    - name: inherited
+      backends:
+      - host: 127.0.0.1:8080
+    - name: overridden
+      policies:
+        timeout:
+          requestTimeout: 1s
+      backends:
+      - host: 127.0.0.1:8080
+  - name: b
+    hostname: b.example.com
+    protocol: HTTP
+    routes:
+    - name: global
+      backends:
+      - host: 127.0.0.1:8080
+"#;
+
+	#[tokio::test]
+	async fn inherits_defaults() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		let config = NormalizedLocalConfig::from(client, CONFIG).await.unwrap();
+
+		// Attached policies are inherited through their target, once per gateway.
+		let policies = config
+			.policies
+			.iter()
+			.map(|p| (p.name.as_str(), &p.target))
+			.collect_vec();
+		assert_eq!(
+			policies,
+			vec![(
+				"bind/3000/defaults/authorization",
+				&PolicyTarget::Gateway(strng::literal!("bind/3000"))
+			)]
+		);
+
+		let listeners = serde_json::to_value(&config.binds[0].listeners).unwrap();
+		let traffic = |listener: &str, route: &str| {
+			listeners[format!("{listener}/bind/3000")]["routes"]
+				[format!("{listener}/bind/3000/{route}/default")]["policies"]
+				.clone()
+		};
+		assert_eq!(
+			traffic("a", "inherited")["timeout"]["requestTimeout"],
+			json!("5s")
+		);
+		assert_eq!(
+			traffic("a", "overridden")["timeout"]["requestTimeout"],
+			json!("1s")
+		);
+		assert_eq!(
+			traffic("b", "global")["timeout"]["requestTimeout"],
+			json!("10s")
+		);
+		assert!(traffic("a", "inherited")["retry"].is_object());
+		assert!(traffic("b", "global")["retry"].is_object());
+
+		// The config dump shows where each inherited policy was declared.
+		assert_eq!(
+			traffic("a", "inherited")["timeoutInheritedFrom"],
+			json!("a/bind/3000")
+		);
+		assert_eq!(
+			traffic("a", "inherited")["retryInheritedFrom"],
+			json!("defaults")
+		);
+		assert!(traffic("a", "overridden")["timeoutInheritedFrom"].is_null());
+		assert_eq!(
+			traffic("b", "global")["timeoutInheritedFrom"],
+			json!("defaults")
+		);
+	}
+
+	#[tokio::test]
+	async fn default_rate_limits_are_per_bind() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		let config = NormalizedLocalConfig::from(
+			client,
+			r#"
+defaults:
+  localRateLimit:
+  - maxTokens: 1
+    tokensPerFill: 1
+    fillInterval: 1h
+binds:
+- port: 3000
+  listeners:
+  - routes:
+    - backends:
+      - host: 127.0.0.1:8080
+- port: 3001
+  listeners:
+  - routes:
+    - backends:
+      - host: 127.0.0.1:8080
+"#,
+		)
+		.await
+		.unwrap();
+		let limits = config
+			.policies
+			.iter()
+			.filter_map(|p| match &p.policy {
+				Policy::LocalRateLimit(l) => Some(l[0].clone()),
+				_ => None,
+			})
+			.collect_vec();
+		assert_eq!(limits.len(), 2);
+		assert!(limits[0].check_request().is_ok());
+		assert!(limits[0].check_request().is_err());
+		// The other bind has its own bucket
+		assert!(limits[1].check_request().is_ok());
+	}
+
+	#[tokio::test]
+	async fn ai_policy_applies_to_route() {
+		let cfg = crate::config::parse_config("{}".to_string(), None).unwrap();
+		let client = client::Client::new(&cfg.dns, None);
+		let config = NormalizedLocalConfig::from(
+			client,
+			r#"
+binds:
+- port: 3000
+  listeners:
+  - name: a
+    protocol: HTTP
+    routes:
+    - name: llm
+      policies:
+        ai:
+          requestFields:
+            /metadata/user_id: jwt.sub
+      backends:
+      - host: 127.0.0.1:8080
+      - host: 127.0.0.1:8081
+"#,
+		)
+		.await
+		.unwrap();
+
+		let mut store = crate::store::BindStore::new();
+		for b in config.binds {
+			store.insert_bind(b);
+		}
+		for p in config.policies {
+			store.insert_policy(p);
+		}
+		let bind = store.all().pop().unwrap();
+		let listener = bind.listeners.iter().next().unwrap();
+		let route = listener.routes.iter().next().unwrap();
+		assert_eq!(route.rule_name.as_deref(), Some("a/bind/3000/llm/default"));
+		let policies = store.route_policies(listener, route);
+		let llm = policies
+			.llm
+			.as_ref()
+			.expect("AI policy should apply to the route");
+		assert_eq!(llm.request_fields.len(), 1);
+	}
 }
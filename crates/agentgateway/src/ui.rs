@@ -1,8 +1,10 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use axum::body::Body;
 use axum::extract::State;
 use axum::http::StatusCode;
+use axum::middleware;
 use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::get;
 use axum::{Json, Router};
@@ -20,6 +22,7 @@ use crate::management::admin::{AdminFallback, AdminResponse};
 use crate::{Config, ConfigSource, client, yamlviajson};
 pub struct UiHandler {
 	router: Router,
+	base_path: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -46,20 +49,112 @@ lazy_static::lazy_static! {
 impl UiHandler {
 	pub fn new(cfg: Arc<Config>) -> Self {
 		let ui_service = ServeDir::new(&ASSETS_DIR);
+		let ui_path = format!("{}/ui", cfg.ui.base_path.as_deref().unwrap_or_default());
+		let ui_router = Router::new().nest_service("/ui", ui_service);
+		let ui_router = match cfg.ui.base_path.clone() {
+			Some(base) => {
+				let base = Arc::new(BasePath(base));
+				ui_router.layer(middleware::map_response(move |resp: Response| {
+					let base = base.clone();
+					async move { base.apply(resp).await }
+				}))
+			},
+			None => ui_router,
+		};
 		let router = Router::new()
 			// Redirect to the UI
 			.route("/config", get(get_config).post(write_config))
-			.nest_service("/ui", ui_service)
-			.route("/", get(|| async { Redirect::permanent("/ui") }))
+			.route("/ui-settings", get(get_ui_settings))
+			.merge(ui_router)
+			.route(
+				"/",
+				get(move || {
+					let ui_path = ui_path.clone();
+					async move { Redirect::permanent(&ui_path) }
+				}),
+			)
 			.layer(add_cors_layer())
 			.with_state(App {
 				state: cfg.clone(),
 				client: client::Client::new(&cfg.dns, None),
 			});
-		Self { router }
+		Self {
+			router,
+			base_path: cfg.ui.base_path.clone(),
+		}
+	}
+
+	/// strip_base_path removes the base path from a request, for proxies that forward it unchanged.
+	fn strip_base_path(&self, req: &mut http::Request<Incoming>) {
+		let Some(base) = &self.base_path else {
+			return;
+		};
+		let Some(rest) = req.uri().path().strip_prefix(base.as_str()) else {
+			return;
+		};
+		if !rest.is_empty() && !rest.starts_with('/') {
+			return;
+		}
+		let path = if rest.is_empty() { "/" } else { rest };
+		let pq = match req.uri().query() {
+			Some(q) => format!("{path}?{q}"),
+			None => path.to_string(),
+		};
+		let mut parts = req.uri().clone().into_parts();
+		if let Ok(pq) = pq.parse() {
+			parts.path_and_query = Some(pq);
+			if let Ok(uri) = http::Uri::from_parts(parts) {
+				*req.uri_mut() = uri;
+			}
+		}
 	}
 }
 
+/// BasePath rewrites the absolute `/ui` paths the bundled UI assets are built with, so they resolve under the
+/// configured prefix. The asset prefix is fixed when the UI is built, so it cannot be set at runtime.
+struct BasePath(String);
+
+impl BasePath {
+	async fn apply(&self, resp: Response) -> Response {
+		let is_text = resp
+			.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|v| v.to_str().ok())
+			.is_some_and(|ct| ct.starts_with("text/") || ct.contains("javascript"));
+		if !is_text {
+			return resp;
+		}
+		let (mut parts, body) = resp.into_parts();
+		let body = match axum::body::to_bytes(body, usize::MAX).await {
+			Ok(b) => b,
+			Err(e) => {
+				return ErrorResponse::Anyhow(anyhow::anyhow!("failed to read asset: {e}")).into_response();
+			},
+		};
+		let Ok(text) = std::str::from_utf8(&body) else {
+			return Response::from_parts(parts, Body::from(body));
+		};
+		parts.headers.remove(CONTENT_LENGTH);
+		Response::from_parts(parts, Body::from(self.rewrite(text)))
+	}
+
+	fn rewrite(&self, text: &str) -> String {
+		let base = &self.0;
+		text
+			.replace("\"/ui/", &format!("\"{base}/ui/"))
+			.replace("\"/ui\"", &format!("\"{base}/ui\""))
+			.replace("url(/ui/", &format!("url({base}/ui/"))
+	}
+}
+
+/// UiSettings is served to the UI, which applies the configured title and logo itself.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UiSettings {
+	title: Option<String>,
+	logo_url: Option<String>,
+}
+
 #[derive(Debug, thiserror::Error)]
 enum ErrorResponse {
 	#[error("{0}")]
@@ -83,6 +178,13 @@ impl IntoResponse for ErrorResponse {
 	}
 }
 
+async fn get_ui_settings(State(app): State<App>) -> Json<UiSettings> {
+	Json(UiSettings {
+		title: app.state.ui.title.clone(),
+		logo_url: app.state.ui.logo_url.clone(),
+	})
+}
+
 async fn get_config(State(app): State<App>) -> Result<Json<Value>, ErrorResponse> {
 	let s = app.cfg()?.read_to_string().await?;
 	let v: Value = yamlviajson::from_str(&s).map_err(|e| ErrorResponse::Anyhow(e.into()))?;
@@ -155,8 +257,59 @@ pub fn add_cors_layer() -> CorsLayer {
 }
 
 impl AdminFallback for UiHandler {
-	fn handle(&self, req: http::Request<Incoming>) -> AdminResponse {
+	fn handle(&self, mut req: http::Request<Incoming>) -> AdminResponse {
+		self.strip_base_path(&mut req);
 		let router = self.router.clone();
 		Box::pin(async { router.oneshot(req).await.unwrap() })
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn base_path_rewrite() {
+		let base = BasePath("/portal/gateway".to_string());
+		let html = r#"<html><head><title>Agentgateway Dashboard</title><link href="/ui/_next/a.css"/></head></html>"#;
+		assert_eq!(
+			base.rewrite(html),
+			r#"<html><head><title>Agentgateway Dashboard</title><link href="/portal/gateway/ui/_next/a.css"/></head></html>"#
+		);
+		let js = r#"basePath:"/ui",src:"/uix/a.js",bg:url(/ui/a.svg)"#;
+		assert_eq!(
+			base.rewrite(js),
+			r#"basePath:"/portal/gateway/ui",src:"/uix/a.js",bg:url(/portal/gateway/ui/a.svg)"#
+		);
+	}
+
+	#[tokio::test]
+	async fn ui_settings() {
+		let cfg = crate::config::parse_config(
+			r#"{"config":{"ui":{"basePath":"/portal/gateway/","title":"Acme <AI> Gateway"}}}"#
+				.to_string(),
+			None,
+		)
+		.unwrap();
+		let ui = UiHandler::new(Arc::new(cfg));
+		let resp = ui
+			.router
+			.clone()
+			.oneshot(
+				http::Request::get("/ui-settings")
+					.body(Body::empty())
+					.unwrap(),
+			)
+			.await
+			.unwrap();
+		assert_eq!(resp.status(), StatusCode::OK);
+		let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+			.await
+			.unwrap();
+		let settings: Value = serde_json::from_slice(&body).unwrap();
+		assert_eq!(
+			settings,
+			serde_json::json!({"title": "Acme <AI> Gateway", "logoUrl": null})
+		);
+	}
+}
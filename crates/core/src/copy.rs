@@ -4,6 +4,7 @@ use std::marker::PhantomPinned;
 use std::mem::MaybeUninit;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::task::{Context, Poll, ready};
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
@@ -164,14 +165,27 @@ const RESIZE_THRESHOLD_LARGE: u64 = 128 * 1024;
 // After 10Mb of data we will trigger a resize from LARGE to JUMBO
 const RESIZE_THRESHOLD_JUMBO: u64 = 10 * 1024 * 1024;
 
-pub struct ConnectionResult {}
+/// ConnectionResult counts the bytes copied by copy_bidirectional, from the downstream's point of view: bytes
+/// sent to the downstream, and bytes received from it.
+#[derive(Debug, Default)]
+pub struct ConnectionResult {
+	sent: AtomicU64,
+	received: AtomicU64,
+}
 
 impl ConnectionResult {
-	pub fn increment_recv(&self, _: u64) {
-		// TODO
+	pub fn increment_recv(&self, amt: u64) {
+		self.received.fetch_add(amt, Ordering::Relaxed);
+	}
+	pub fn increment_send(&self, amt: u64) {
+		self.sent.fetch_add(amt, Ordering::Relaxed);
 	}
-	pub fn increment_send(&self, _: u64) {
-		// TODO
+	/// load returns the (sent, received) byte counts.
+	pub fn load(&self) -> (u64, u64) {
+		(
+			self.sent.load(Ordering::Relaxed),
+			self.received.load(Ordering::Relaxed),
+		)
 	}
 }
 
@@ -487,3 +501,46 @@ pub fn poll_read_buf<T: AsyncRead + ?Sized, B: BufMut>(
 
 	Poll::Ready(Ok(n))
 }
+
+#[cfg(test)]
+mod tests {
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpListener;
+
+	use super::*;
+
+	async fn tcp_pair() -> (TcpStream, TcpStream) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let client = TcpStream::connect(listener.local_addr().unwrap());
+		let (client, server) = tokio::join!(client, listener.accept());
+		(client.unwrap(), server.unwrap().0)
+	}
+
+	#[tokio::test]
+	async fn counts_bytes() {
+		let (mut client, downstream) = tcp_pair().await;
+		let (upstream, mut server) = tcp_pair().await;
+		let stats = ConnectionResult::default();
+		let proxy = copy_bidirectional(
+			TcpStreamSplitter(downstream),
+			TcpStreamSplitter(upstream),
+			&stats,
+		);
+		let peers = async {
+			client.write_all(b"hello").await.unwrap();
+			client.shutdown().await.unwrap();
+			let mut req = Vec::new();
+			server.read_to_end(&mut req).await.unwrap();
+			server.write_all(b"hello world").await.unwrap();
+			server.shutdown().await.unwrap();
+			let mut resp = Vec::new();
+			client.read_to_end(&mut resp).await.unwrap();
+			(req, resp)
+		};
+		let (res, (req, resp)) = tokio::join!(proxy, peers);
+		res.unwrap();
+		assert_eq!(req, b"hello");
+		assert_eq!(resp, b"hello world");
+		assert_eq!(stats.load(), (11, 5));
+	}
+}
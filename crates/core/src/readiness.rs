@@ -8,17 +8,21 @@ use tracing::info;
 use crate::telemetry;
 
 /// Ready tracks whether the process is ready.
+/// A ready process may also be degraded: still serving, but with a known problem that should be surfaced.
 #[derive(Clone, Debug, Default)]
-pub struct Ready(Arc<Mutex<HashSet<String>>>);
+pub struct Ready {
+	pending: Arc<Mutex<HashSet<String>>>,
+	degraded: Arc<Mutex<Option<String>>>,
+}
 
 impl Ready {
 	pub fn new() -> Ready {
-		Ready(Default::default())
+		Ready::default()
 	}
 
 	/// register_task allows a caller to add a dependency to be marked "ready".
 	pub fn register_task(&self, name: &str) -> BlockReady {
-		self.0.lock().unwrap().insert(name.to_string());
+		self.pending.lock().unwrap().insert(name.to_string());
 		BlockReady {
 			parent: self.to_owned(),
 			name: name.to_string(),
@@ -26,7 +30,16 @@ impl Ready {
 	}
 
 	pub fn pending(&self) -> HashSet<String> {
-		self.0.lock().unwrap().clone()
+		self.pending.lock().unwrap().clone()
+	}
+
+	/// set_degraded marks the process as degraded with the given reason, or clears it.
+	pub fn set_degraded(&self, reason: Option<String>) {
+		*self.degraded.lock().unwrap() = reason;
+	}
+
+	pub fn degraded(&self) -> Option<String> {
+		self.degraded.lock().unwrap().clone()
 	}
 }
 
@@ -44,7 +57,7 @@ impl BlockReady {
 
 impl Drop for BlockReady {
 	fn drop(&mut self) {
-		let mut pending = self.parent.0.lock().unwrap();
+		let mut pending = self.parent.pending.lock().unwrap();
 		let removed = pending.remove(&self.name);
 		debug_assert!(removed); // It is a bug to somehow remove something twice
 		let left = pending.len();
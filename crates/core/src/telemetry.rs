@@ -9,8 +9,10 @@ mod nonblocking;
 mod worker;
 
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Write as FmtWrite};
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::time::Instant;
 use std::{env, fmt, io};
 
@@ -29,6 +31,7 @@ use tracing_subscriber::field::RecordFields;
 use tracing_subscriber::fmt::format::{JsonVisitor, Writer};
 use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
 use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::Context;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{Layer, Registry, filter, reload};
@@ -38,6 +41,10 @@ pub static APPLICATION_START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
 static LOG_HANDLE: OnceCell<LogHandle> = OnceCell::new();
 static NON_BLOCKING: OnceCell<(NonBlocking, bool)> = OnceCell::new();
 
+/// Number of recent warning and error logs kept in memory, for diagnostics bundles.
+const RECENT_LOGS: usize = 500;
+static RECENT_LOGS_BUFFER: Lazy<Mutex<VecDeque<String>>> = Lazy::new(Default::default);
+
 pub trait OptionExt<T>: Sized {
 	fn display(&self) -> Option<ValueBag>
 	where
@@ -153,10 +160,51 @@ pub fn setup_logging() -> nonblocking::WorkerGuard {
 	let _ = NON_BLOCKING.set((non_blocking.clone(), use_json));
 	tracing_subscriber::registry()
 		.with(fmt_layer(non_blocking, use_json))
+		.with(RecentLogs.with_filter(filter::LevelFilter::WARN))
 		.init();
 	_guard
 }
 
+/// recent_logs returns the most recent warning and error logs, oldest first.
+pub fn recent_logs() -> Vec<String> {
+	RECENT_LOGS_BUFFER
+		.lock()
+		.expect("mutex acquired")
+		.iter()
+		.cloned()
+		.collect()
+}
+
+/// RecentLogs keeps the most recent log lines in memory, independent of the configured log level.
+struct RecentLogs;
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+	fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+		let normalized_meta = event.normalized_metadata();
+		let meta = normalized_meta.as_ref().unwrap_or_else(|| event.metadata());
+		let target = meta.target();
+		let target = target.strip_prefix("agentgateway::").unwrap_or(target);
+		let mut line = date::build();
+		let _ = write!(
+			line,
+			"\t{}\t{target}\t",
+			meta.level().to_string().to_ascii_lowercase()
+		);
+		let mut visitor = Visitor {
+			writer: Writer::new(&mut line),
+			res: Ok(()),
+			is_empty: true,
+		};
+		event.record(&mut visitor);
+
+		let mut recent = RECENT_LOGS_BUFFER.lock().expect("mutex acquired");
+		if recent.len() >= RECENT_LOGS {
+			recent.pop_front();
+		}
+		recent.push_back(line);
+	}
+}
+
 fn json_fmt(writer: NonBlocking) -> Box<dyn Layer<Registry> + Send + Sync + 'static> {
 	let format = tracing_subscriber::fmt::layer()
 		.with_writer(writer)
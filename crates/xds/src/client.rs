@@ -1,6 +1,6 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, mem};
 
 use agent_core::metrics::{IncrementRecorder, Recorder};
@@ -11,7 +11,7 @@ use prost_types::value::Kind;
 use prost_types::{Struct, Value};
 use split_iter::Splittable;
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 use tracing::{Instrument, debug, error, info, info_span, warn};
 
 use super::Error;
@@ -364,6 +364,7 @@ pub struct AdsClient {
 
 	pub(crate) metrics: Metrics,
 	block_ready: Option<tokio::sync::watch::Sender<()>>,
+	status: watch::Sender<ConnectionStatus>,
 
 	connection_id: u32,
 	types_to_expect: HashSet<String>,
@@ -418,6 +419,16 @@ impl Demander {
 	}
 }
 
+/// ConnectionStatus reports whether the client currently has a stream to the control plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+	/// No stream has been established yet.
+	Connecting,
+	Connected,
+	/// The stream was lost; the client is retrying.
+	Disconnected,
+}
+
 const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
 const MAX_BACKOFF: Duration = Duration::from_secs(15);
 
@@ -445,6 +456,7 @@ impl AdsClient {
 			state,
 			metrics,
 			block_ready: Some(block_ready),
+			status: watch::channel(ConnectionStatus::Connecting).0,
 			connection_id: 0,
 			types_to_expect,
 		}
@@ -455,6 +467,19 @@ impl AdsClient {
 		&self.config
 	}
 
+	/// status allows watching the connection to the control plane.
+	pub fn status(&self) -> watch::Receiver<ConnectionStatus> {
+		self.status.subscribe()
+	}
+
+	fn set_status(&self, status: ConnectionStatus) {
+		self
+			.metrics
+			.connected
+			.set((status == ConnectionStatus::Connected) as i64);
+		self.status.send_replace(status);
+	}
+
 	/// demander returns a Demander instance which can be used to request resources on-demand
 	pub fn demander(&self) -> Option<Demander> {
 		if self.config.on_demand {
@@ -467,7 +492,9 @@ impl AdsClient {
 	}
 
 	async fn run_loop(&mut self, backoff: Duration) -> Duration {
-		match self.run_internal().await {
+		let res = self.run_internal().await;
+		self.set_status(ConnectionStatus::Disconnected);
+		match res {
 			Err(e @ Error::Connection(_, _)) => {
 				// For connection errors, we add backoff
 				let backoff = std::cmp::min(MAX_BACKOFF, backoff * 2);
@@ -621,6 +648,7 @@ impl AdsClient {
 		debug!("connected established");
 
 		info!("Stream established");
+		self.set_status(ConnectionStatus::Connected);
 		loop {
 			tokio::select! {
 				_demand_event = self.state.demand.recv() => {
@@ -637,6 +665,8 @@ impl AdsClient {
 						received_type = Some(msg.type_url.clone())
 					}
 					if let XdsSignal::Ack = self.handle_stream_event(msg, &discovery_req_tx).await? {
+						let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+						self.metrics.last_update.set(now.as_secs() as i64);
 						if let Some(received_type) = received_type {
 							self.types_to_expect.remove(&received_type);
 							if self.types_to_expect.is_empty() {
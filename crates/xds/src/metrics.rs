@@ -2,6 +2,7 @@ use agent_core::metrics::Recorder;
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::registry::{Registry, Unit};
 
 use super::service::discovery::v3::DeltaDiscoveryResponse;
@@ -10,6 +11,8 @@ pub struct Metrics {
 	pub connection_terminations: Family<ConnectionTermination, Counter>,
 	pub message_types: Family<TypeUrl, Counter>,
 	pub total_messages_size: Family<TypeUrl, Counter>,
+	pub connected: Gauge,
+	pub last_update: Gauge,
 }
 
 #[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
@@ -56,10 +59,27 @@ impl Metrics {
 			total_messages_size.clone(),
 		);
 
+		let connected = Gauge::default();
+		registry.register(
+			"xds_connected",
+			"Whether there is currently a stream to the xds server (unstable)",
+			connected.clone(),
+		);
+
+		let last_update = Gauge::default();
+		registry.register(
+			"xds_last_update_timestamp_seconds",
+			"Unix time of the last accepted xds response; the difference to the current time is the age of the \
+			 configuration (unstable)",
+			last_update.clone(),
+		);
+
 		Self {
 			connection_terminations,
 			message_types: message_count,
 			total_messages_size,
+			connected,
+			last_update,
 		}
 	}
 }
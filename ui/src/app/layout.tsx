@@ -10,6 +10,7 @@ import { SidebarWrapper } from "@/components/sidebar-wrapper";
 import { WizardProvider } from "@/lib/wizard-context";
 import { ConfigErrorWrapper } from "@/components/config-error-wrapper";
 import { XdsModeNotification } from "@/components/xds-mode-notification";
+import { DocumentTitle } from "@/components/document-title";
 
 const geistSans = Geist({
   variable: "--font-geist-sans",
@@ -39,6 +40,7 @@ export default function RootLayout({
       <body
         className={`${geistSans.variable} ${geistMono.variable} antialiased h-full flex flex-col`}
       >
+        <DocumentTitle />
         <ServerProvider>
           <ThemeProvider
             attribute="class"
@@ -1,4 +1,13 @@
+"use client";
+
+import { useUiSettings } from "@/lib/branding";
+
 export function AgentgatewayLogo({ className }: { className?: string }) {
+  const { logoUrl } = useUiSettings();
+  if (logoUrl) {
+    // eslint-disable-next-line @next/next/no-img-element
+    return <img src={logoUrl} alt="Logo" className={className} />;
+  }
   return (
     <svg viewBox="0 0 100 100" fill="none" xmlns="http://www.w3.org/2000/svg" className={className}>
       <path
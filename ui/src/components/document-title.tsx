@@ -0,0 +1,15 @@
+"use client";
+
+import { useEffect } from "react";
+import { useUiSettings } from "@/lib/branding";
+
+// Applies the title configured on the gateway, if any, in place of the default one.
+export function DocumentTitle() {
+  const { title } = useUiSettings();
+  useEffect(() => {
+    if (title) {
+      document.title = title;
+    }
+  }, [title]);
+  return null;
+}
@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
+import { gatewayUrl } from "@/lib/branding";
 
-const API_URL = process.env.NODE_ENV === "production" ? "" : "http://localhost:15000";
+const API_URL = gatewayUrl();
 
 type XdsSubscriber = (val: boolean) => void;
 const xdsSubscribers: XdsSubscriber[] = [];
@@ -8,8 +8,9 @@ import {
   fetchConfigDump,
   subscribeXdsMode,
 } from "@/hooks/use-xds-mode";
+import { gatewayUrl } from "./branding";
 
-const API_URL = process.env.NODE_ENV === "production" ? "" : "http://localhost:15000";
+const API_URL = gatewayUrl();
 
 let currentXdsMode = isXdsMode();
 subscribeXdsMode((xdsMode) => {
@@ -0,0 +1,43 @@
+import { useEffect, useState } from "react";
+
+// Settings served by the gateway at `/ui-settings`; see the `ui` section of the gateway config.
+export interface UiSettings {
+  title?: string | null;
+  logoUrl?: string | null;
+}
+
+// Path prefix the gateway is served under, for proxies that forward it unchanged. The UI always lives at
+// `<prefix>/ui`, so the prefix is whatever precedes that segment in the current location.
+export function basePath(): string {
+  if (typeof window === "undefined") {
+    return "";
+  }
+  const match = window.location.pathname.match(/^(.*?)\/ui(\/|$)/);
+  return match ? match[1] : "";
+}
+
+// Base URL of the gateway APIs the UI calls. In production the UI is served by the gateway itself, possibly
+// behind a path prefix.
+export function gatewayUrl(): string {
+  return process.env.NODE_ENV === "production" ? basePath() : "http://localhost:15000";
+}
+
+let settings: Promise<UiSettings> | null = null;
+
+export function loadUiSettings(): Promise<UiSettings> {
+  if (!settings) {
+    settings = fetch(`${gatewayUrl()}/ui-settings`)
+      .then((resp) => (resp.ok ? resp.json() : {}))
+      .catch(() => ({}));
+  }
+  return settings;
+}
+
+// Read after mount, so the statically rendered markup matches the first client render.
+export function useUiSettings(): UiSettings {
+  const [value, setValue] = useState<UiSettings>({});
+  useEffect(() => {
+    loadUiSettings().then(setValue);
+  }, []);
+  return value;
+}